@@ -2,7 +2,7 @@ pub(crate) mod attachment {
     
     use std::sync::Arc;
 
-    use crate::attachment_page::attachment_page::AttachmentPage;
+    use crate::{attachment_page::attachment_page::AttachmentPage, tag::tag::Tag};
 
 
     #[derive(Debug, Clone)]
@@ -13,10 +13,13 @@ pub(crate) mod attachment {
         comment: Arc<String>,
         date_added: i64,
         document_id: u32,
+        tags: Vec<Tag>,
+        /// Raised on every change to the row, so a save can tell whether someone else changed it in between.
+        version: u32
     }
 
     impl Attachment {
-        pub(crate) fn new(attachment_id: u32, pages: Vec<AttachmentPage>, reference_number: Arc<String>, comment: Arc<String>, date_added: i64, document_id: u32) -> Attachment {
+        pub(crate) fn new(attachment_id: u32, pages: Vec<AttachmentPage>, reference_number: Arc<String>, comment: Arc<String>, date_added: i64, document_id: u32, tags: Vec<Tag>) -> Attachment {
             Attachment {
                 attachment_id: attachment_id,
                 pages: pages,
@@ -24,9 +27,17 @@ pub(crate) mod attachment {
                 comment: comment,
                 date_added: date_added,
                 document_id: document_id,
+                tags: tags,
+                version: 0
             }
         }
 
+        /// The attachment with the version of its row, as read from the library.
+        pub(crate) fn with_version(mut self, version: u32) -> Attachment {
+            self.version = version;
+            return self
+        }
+
         pub(crate) fn get_attachment_id(&self) -> u32 {
             return self.attachment_id
         }
@@ -54,5 +65,13 @@ pub(crate) mod attachment {
         pub(crate) fn get_document_id(&self) -> u32 {
             return self.document_id
         }
+
+        pub(crate) fn get_tags(&self) -> &Vec<Tag> {
+            return &self.tags
+        }
+
+        pub(crate) fn get_version(&self) -> u32 {
+            return self.version
+        }
     }
 }
\ No newline at end of file
@@ -1,19 +1,22 @@
 pub(crate) mod attachment_page {
-    use std::{fs, sync::Arc};
+    use std::sync::Arc;
+
+    use crate::storage::storage::read_page_file;
 
     #[derive(Debug, Clone)]
     pub(crate) struct AttachmentPage {
         page_id: u32,
         image: Vec<u8>,
         file_path: Arc<String>,
-        attachment_id: u32
+        attachment_id: u32,
+        checksum: Option<Arc<String>>
     }
 
     impl AttachmentPage {
-        pub(crate) fn new(page_id: u32, file_path: String, attachment_id: u32) -> AttachmentPage {
+        pub(crate) fn new(page_id: u32, file_path: String, attachment_id: u32, checksum: Option<String>) -> AttachmentPage {
             AttachmentPage {
                 page_id: page_id,
-                image: match fs::read(&file_path) {
+                image: match read_page_file(&file_path) {
                     Ok(image) => image,
                     Err(err) => {
                         println!("Error reading image file: {}", err);
@@ -21,7 +24,8 @@ pub(crate) mod attachment_page {
                     }
                 },
                 file_path: file_path.into(),
-                attachment_id: attachment_id
+                attachment_id: attachment_id,
+                checksum: checksum.map(Arc::new)
             }
         }
 
@@ -32,5 +36,9 @@ pub(crate) mod attachment_page {
         pub(crate) fn file_path(&self) -> Arc<String> {
             self.file_path.clone()
         }
+
+        pub(crate) fn checksum(&self) -> Option<Arc<String>> {
+            self.checksum.clone()
+        }
     }
 }
\ No newline at end of file
@@ -0,0 +1,117 @@
+pub(crate) mod background {
+    use std::{panic::{self, AssertUnwindSafe}, sync::{Arc, atomic::{AtomicBool, Ordering}}, thread};
+
+    use iced::{Task, futures::channel::mpsc};
+
+    /// What a background job reports to the screen that started it.
+    #[derive(Debug, Clone)]
+    pub(crate) enum JobEvent<T> {
+        /// Items done so far and items in total.
+        Progress(usize, usize),
+        Finished(T),
+        /// The work panicked; the message says where.
+        Failed(String)
+    }
+
+    /// A running background job as the screen sees it: what it does, how far it got, and a way to stop it.
+    #[derive(Debug, Clone)]
+    pub(crate) struct Job {
+        label: String,
+        done: usize,
+        total: usize,
+        cancelled: Arc<AtomicBool>
+    }
+
+    impl Job {
+        pub(crate) fn label(&self) -> &str {
+            return &self.label
+        }
+
+        pub(crate) fn set_progress(&mut self, done: usize, total: usize) {
+            self.done = done;
+            self.total = total;
+        }
+
+        pub(crate) fn progress(&self) -> f32 {
+            if self.total == 0 {
+                return 0.0
+            }
+            return self.done as f32 / self.total as f32
+        }
+
+        pub(crate) fn status(&self) -> String {
+            if self.is_cancelled() {
+                return format!("{}: cancelling...", self.label)
+            }
+            if self.total == 0 {
+                return format!("{}...", self.label)
+            }
+            return format!("{}: {} of {}", self.label, self.done, self.total)
+        }
+
+        /// Asks the work to stop at its next check. Whatever it already finished stays done.
+        pub(crate) fn cancel(&self) {
+            self.cancelled.store(true, Ordering::Relaxed);
+        }
+
+        pub(crate) fn is_cancelled(&self) -> bool {
+            return self.cancelled.load(Ordering::Relaxed)
+        }
+    }
+
+    /// Handed to the work on the background thread to report how far it got and to check for cancellation.
+    pub(crate) struct Progress {
+        report: Box<dyn Fn(usize, usize) + Send>,
+        cancelled: Arc<AtomicBool>
+    }
+
+    impl Progress {
+        pub(crate) fn report(&self, done: usize, total: usize) {
+            (self.report)(done, total);
+        }
+
+        pub(crate) fn is_cancelled(&self) -> bool {
+            return self.cancelled.load(Ordering::Relaxed)
+        }
+    }
+
+    /// Runs `work` on its own thread, so file, image and database work does not block the window. The returned
+    /// task delivers the job's progress and result as messages; the `Job` is kept by the screen to show and cancel it.
+    pub(crate) fn run<T, M>(label: &str, work: impl FnOnce(&Progress) -> T + Send + 'static, to_message: impl Fn(JobEvent<T>) -> M + Send + 'static) -> (Job, Task<M>)
+    where
+        T: Send + 'static,
+        M: Send + 'static
+    {
+        let (sender, receiver) = mpsc::unbounded();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let progress_sender = sender.clone();
+        let progress = Progress {
+            report: Box::new(move |done, total| {
+                let _ = progress_sender.unbounded_send(JobEvent::Progress(done, total));
+            }),
+            cancelled: cancelled.clone()
+        };
+
+        thread::spawn(move || {
+            let event = match panic::catch_unwind(AssertUnwindSafe(|| work(&progress))) {
+                Ok(result) => JobEvent::Finished(result),
+                Err(err) => {
+                    let message = err.downcast_ref::<&str>().map(|message| message.to_string())
+                        .or_else(|| err.downcast_ref::<String>().cloned())
+                        .unwrap_or_else(|| String::from("unknown error"));
+                    println!("Error in background job: {}", message);
+                    JobEvent::Failed(message)
+                }
+            };
+            let _ = sender.unbounded_send(event);
+        });
+
+        let job = Job {
+            label: label.to_string(),
+            done: 0,
+            total: 0,
+            cancelled: cancelled
+        };
+        return (job, Task::run(receiver, to_message))
+    }
+}
@@ -0,0 +1,303 @@
+pub(crate) mod bulk {
+    use std::{fs::{File, OpenOptions}, io::Write, path::{Path, PathBuf}, sync::Arc};
+
+    use zip::{ZipWriter, write::SimpleFileOptions};
+
+    use crate::{attachment::attachment::Attachment, db::db_module::DbConnection, document::document::Document, document_type::document_type::DocumentType, edit_history::edit_history::{DocumentDetails, Edit}, screen::document_list::document_list::{export_to_pdf, pdf_bytes}};
+
+    /// What a bulk job does with each selected document or attachment.
+    #[derive(Debug, Clone, PartialEq)]
+    pub(crate) enum BulkAction {
+        DeleteDocuments,
+        TagDocuments(String),
+        /// Custom field values of the old type are cleared, as its fields do not belong to the new one.
+        ChangeDocumentType(DocumentType),
+        /// Writes one PDF per document.
+        ExportDocuments(ExportTarget),
+        DeleteAttachments,
+        TagAttachments(String),
+        /// Moves the attachments to the document with this id, keeping their numbers.
+        MoveAttachments(u32),
+        /// Writes one PDF per attachment.
+        ExportAttachments(ExportTarget)
+    }
+
+    /// Where bulk exports write their PDFs.
+    #[derive(Debug, Clone, PartialEq)]
+    pub(crate) enum ExportTarget {
+        /// One file each into the folder.
+        Folder(PathBuf),
+        /// All files into one ZIP archive, which is created anew.
+        Archive(PathBuf)
+    }
+
+    impl BulkAction {
+        fn verb(&self) -> &'static str {
+            match self {
+                BulkAction::DeleteDocuments | BulkAction::DeleteAttachments => "Deleting",
+                BulkAction::TagDocuments(_) | BulkAction::TagAttachments(_) => "Tagging",
+                BulkAction::ChangeDocumentType(_) => "Changing type of",
+                BulkAction::ExportDocuments(_) | BulkAction::ExportAttachments(_) => "Exporting",
+                BulkAction::MoveAttachments(_) => "Moving"
+            }
+        }
+
+        /// Names the action for the busy bar, such as "Deleting documents".
+        pub(crate) fn label(&self) -> String {
+            let items = match self {
+                BulkAction::DeleteDocuments | BulkAction::TagDocuments(_) | BulkAction::ChangeDocumentType(_) | BulkAction::ExportDocuments(_) => "documents",
+                _ => "attachments"
+            };
+            return format!("{} {}", self.verb(), items)
+        }
+    }
+
+    /// A bulk action worked through one item per step, so the list can show its progress in between.
+    #[derive(Debug, Clone)]
+    pub(crate) struct BulkJob {
+        action: BulkAction,
+        pending: Vec<u32>,
+        total: usize,
+        done: usize,
+        /// Items left out before the job started, such as locked documents.
+        skipped: usize,
+        errors: Vec<String>,
+        /// The undoable edits made so far, in order.
+        edits: Vec<Edit>
+    }
+
+    impl BulkJob {
+        pub(crate) fn new(action: BulkAction, ids: Vec<u32>, skipped: usize) -> BulkJob {
+            let mut pending = ids;
+            pending.reverse();
+            BulkJob {
+                action: action,
+                total: pending.len(),
+                pending: pending,
+                done: 0,
+                skipped: skipped,
+                errors: Vec::new(),
+                edits: Vec::new()
+            }
+        }
+
+        /// Items done so far and items in total.
+        pub(crate) fn counts(&self) -> (usize, usize) {
+            return (self.done, self.total)
+        }
+
+        pub(crate) fn is_finished(&self) -> bool {
+            return self.pending.is_empty()
+        }
+
+        /// Drops the items that have not been worked on yet.
+        pub(crate) fn cancel(&mut self) {
+            self.pending.clear();
+        }
+
+        pub(crate) fn take_edits(&mut self) -> Vec<Edit> {
+            return std::mem::take(&mut self.edits)
+        }
+
+        pub(crate) fn status(&self) -> String {
+            if !self.is_finished() {
+                return format!("{} {} of {}...", self.action.verb(), self.done + 1, self.total)
+            }
+            let mut status = format!("Done: {} of {}", self.done - self.errors.len(), self.total);
+            if self.skipped > 0 {
+                status.push_str(&format!(", {} locked skipped", self.skipped));
+            }
+            if !self.errors.is_empty() {
+                status.push_str(&format!(", {} failed: {}", self.errors.len(), self.errors.join("; ")));
+            }
+            return status
+        }
+
+        /// Applies the action to the next item. For attachment actions `documents` has to hold their document with
+        /// its attachments loaded; document actions read the documents from the library.
+        pub(crate) fn step(&mut self, conn: &mut DbConnection, documents: &[Arc<Document>]) {
+            let id = match self.pending.pop() {
+                Some(id) => id,
+                None => return
+            };
+            if let Err(err) = self.apply(conn, documents, id) {
+                println!("Error in bulk action: {}", err);
+                self.errors.push(err);
+            }
+            self.done += 1;
+        }
+
+        fn apply(&mut self, conn: &mut DbConnection, documents: &[Arc<Document>], id: u32) -> Result<(), String> {
+            match &self.action {
+                BulkAction::DeleteDocuments => {
+                    conn.delete_document(id).map_err(|err| err.to_string())?;
+                    self.edits.push(Edit::DeleteDocument { document_id: id });
+                },
+                BulkAction::TagDocuments(name) => conn.add_document_tag(id, name.clone()).map_err(|err| err.to_string())?,
+                BulkAction::ChangeDocumentType(document_type) => {
+                    // The selection may include documents the list has not loaded.
+                    let document = conn.read_document_summary(id).map_err(|err| err.to_string())?.ok_or_else(|| format!("document #{} not found", id))?;
+                    let mut old_values: Vec<(u32, String)> = document.get_field_values().iter()
+                        .filter(|(field_id, _)| !document_type.get_fields().iter().any(|field| field.field_id == **field_id))
+                        .map(|(field_id, value)| (*field_id, value.clone()))
+                        .collect();
+                    old_values.sort();
+                    let before = DocumentDetails {
+                        document_number: document.get_document_number().to_string(),
+                        document_type: document.get_document_type().to_string(),
+                        comment: document.get_comment().to_string(),
+                        field_values: old_values.clone(),
+                        document_date: document.get_document_date(),
+                        due_date: document.get_due_date()
+                    };
+                    let after = DocumentDetails {
+                        document_type: document_type.get_name().to_string(),
+                        field_values: old_values.into_iter().map(|(field_id, _)| (field_id, String::new())).collect(),
+                        ..before.clone()
+                    };
+                    conn.edit_document_details(id, &after, None)
+                        .map_err(|err| format!("{}: {}", before.document_number, err))?;
+                    self.edits.push(Edit::ChangeDocument { document_id: id, before: before, after: after });
+                },
+                BulkAction::ExportDocuments(target) => {
+                    // The list only holds summaries, so the pages are read here, one document at a time.
+                    let document = conn.read_document(id).map_err(|err| err.to_string())?.ok_or_else(|| format!("document #{} not found", id))?;
+                    let pages: Vec<Vec<u8>> = document.get_attachments().unwrap_or_default().iter()
+                        .flat_map(|attachment| attachment.pages().iter().map(|page| page.image().to_vec()).collect::<Vec<_>>())
+                        .collect();
+                    if pages.is_empty() {
+                        return Err(format!("{} has no pages", document.get_document_number()))
+                    }
+                    self.export(target, format!("{}.pdf", file_stem(&document.get_document_number())), pages)?;
+                },
+                BulkAction::DeleteAttachments => {
+                    let attachment = find_attachment(documents, id)?;
+                    conn.delete_attachment(id).map_err(|err| format!("{}: {}", attachment.get_reference_number(), err))?;
+                    self.edits.push(Edit::DeleteAttachment { document_id: attachment.get_document_id(), attachment_id: id });
+                },
+                BulkAction::TagAttachments(name) => conn.add_attachment_tag(id, name.clone()).map_err(|err| err.to_string())?,
+                BulkAction::MoveAttachments(document_id) => {
+                    let attachment = find_attachment(documents, id)?;
+                    let reference_number = attachment.get_reference_number().to_string();
+                    conn.move_attachment(id, *document_id, reference_number.clone(), None).map_err(|err| format!("{}: {}", reference_number, err))?;
+                    self.edits.push(Edit::MoveAttachment {
+                        document_id: *document_id,
+                        attachment_id: id,
+                        from_document_id: attachment.get_document_id(),
+                        before_reference_number: reference_number.clone(),
+                        after_reference_number: reference_number
+                    });
+                },
+                BulkAction::ExportAttachments(target) => {
+                    let attachment = find_attachment(documents, id)?;
+                    let document = find_document(documents, attachment.get_document_id())?;
+                    let pages: Vec<Vec<u8>> = attachment.pages().iter().map(|page| page.image().to_vec()).collect();
+                    if pages.is_empty() {
+                        return Err(format!("{} has no pages", attachment.get_reference_number()))
+                    }
+                    let file_name = format!("{}_{}.pdf", file_stem(&document.get_document_number()), file_stem(&attachment.get_reference_number()));
+                    self.export(target, file_name, pages)?;
+                }
+            }
+            return Ok(())
+        }
+
+        fn export(&self, target: &ExportTarget, file_name: String, pages: Vec<Vec<u8>>) -> Result<(), String> {
+            match target {
+                ExportTarget::Folder(folder) => return export_to_pdf(pages, folder.join(file_name), None),
+                // The archive is started by the first item exported, so an archive picked to be replaced starts out empty.
+                ExportTarget::Archive(path) => return add_to_archive(path, self.done == self.errors.len(), &file_name, &pdf_bytes(pages, None)?)
+            }
+        }
+    }
+
+    /// Adds a file to the ZIP archive at `path`, creating the archive first when `create` is set.
+    fn add_to_archive(path: &Path, create: bool, file_name: &str, bytes: &[u8]) -> Result<(), String> {
+        let mut archive = match create {
+            true => ZipWriter::new(File::create(path).map_err(|err| err.to_string())?),
+            false => ZipWriter::new_append(OpenOptions::new().read(true).write(true).open(path).map_err(|err| err.to_string())?).map_err(|err| err.to_string())?
+        };
+        archive.start_file(file_name, SimpleFileOptions::default()).map_err(|err| err.to_string())?;
+        archive.write_all(bytes).map_err(|err| err.to_string())?;
+        archive.finish().map_err(|err| err.to_string())?;
+        return Ok(())
+    }
+
+    fn find_document(documents: &[Arc<Document>], document_id: u32) -> Result<Arc<Document>, String> {
+        return documents.iter()
+            .find(|document| document.get_document_id() == document_id)
+            .cloned()
+            .ok_or_else(|| format!("document #{} not found", document_id))
+    }
+
+    fn find_attachment(documents: &[Arc<Document>], attachment_id: u32) -> Result<Arc<Attachment>, String> {
+        return documents.iter()
+            .flat_map(|document| document.get_attachments().unwrap_or_default())
+            .find(|attachment| attachment.get_attachment_id() == attachment_id)
+            .ok_or_else(|| format!("attachment #{} not found", attachment_id))
+    }
+
+    /// The number with characters that are not allowed in file names replaced, e.g. `INV/2025/1` becomes `INV_2025_1`.
+    fn file_stem(number: &str) -> String {
+        return number.chars().map(|c| if c.is_alphanumeric() || c == '-' || c == '.' { c } else { '_' }).collect()
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use std::io::Read;
+
+        use zip::ZipArchive;
+
+        use crate::document_type::document_type::{FieldKind, TypeField};
+
+        use super::*;
+
+        fn add_type(conn: &mut DbConnection, name: &str) -> DocumentType {
+            conn.new_document_type(String::from(name)).unwrap();
+            let type_id = conn.last_rowid().unwrap() as u32;
+            conn.new_type_field(type_id, &TypeField { field_id: 0, name: String::from("Amount"), kind: FieldKind::Text, required: false, options: Vec::new() }).unwrap();
+            return conn.read_document_types().unwrap().into_iter().find(|document_type| document_type.get_type_id() == type_id).unwrap()
+        }
+
+        #[test]
+        fn test_change_type_clears_old_fields() {
+            let mut conn = DbConnection::in_memory();
+            let invoice = add_type(&mut conn, "Invoice");
+            let receipt = add_type(&mut conn, "Receipt");
+            let field_id = invoice.get_fields()[0].field_id;
+            conn.new_document(String::from("D-1"), String::from("Invoice"), String::new(), vec![(field_id, String::from("12.50"))], None, None).unwrap();
+            let document_id = conn.last_rowid().unwrap() as u32;
+
+            let documents = conn.read_document_table().unwrap();
+            let mut job = BulkJob::new(BulkAction::ChangeDocumentType(receipt), vec![document_id], 0);
+            job.step(&mut conn, &documents);
+            let document = conn.read_document_summary(document_id).unwrap().unwrap();
+            assert_eq!(document.get_document_type().as_str(), "Receipt");
+            assert!(document.get_field_values().is_empty());
+
+            // The edit brings the values back on undo.
+            match &job.take_edits()[..] {
+                [Edit::ChangeDocument { before, .. }] => assert_eq!(before.field_values, vec![(field_id, String::from("12.50"))]),
+                edits => panic!("unexpected edits {:?}", edits)
+            }
+        }
+
+        #[test]
+        fn test_add_to_archive() {
+            let path = std::env::temp_dir().join(format!("bulk_archive_{}.zip", std::process::id()));
+            add_to_archive(&path, true, "a.pdf", b"first").unwrap();
+            add_to_archive(&path, false, "b.pdf", b"second").unwrap();
+
+            let mut archive = ZipArchive::new(File::open(&path).unwrap()).unwrap();
+            assert_eq!(archive.file_names().collect::<Vec<_>>().len(), 2);
+            let mut content = String::new();
+            archive.by_name("b.pdf").unwrap().read_to_string(&mut content).unwrap();
+            assert_eq!(content, "second");
+
+            // Creating the archive again replaces it.
+            add_to_archive(&path, true, "c.pdf", b"third").unwrap();
+            assert_eq!(ZipArchive::new(File::open(&path).unwrap()).unwrap().len(), 1);
+            std::fs::remove_file(&path).unwrap();
+        }
+    }
+}
@@ -0,0 +1,180 @@
+pub(crate) mod crypto {
+    use std::{io, sync::Mutex};
+
+    use argon2::{Algorithm, Argon2, Params, PasswordHash, PasswordHasher, PasswordVerifier, Version, password_hash::SaltString};
+    use chacha20poly1305::{KeyInit, XChaCha20Poly1305, XNonce, aead::{Aead, Payload}};
+    use hmac::{Hmac, Mac};
+    use rand_core::{OsRng, RngCore};
+    use sha2::Sha256;
+
+    /// Marks a page file as encrypted; files without it are read as they are.
+    const MAGIC: &[u8; 8] = b"DMPAGE1\0";
+    /// XChaCha20's nonce is long enough to pick at random for every file.
+    const NONCE_LEN: usize = 24;
+    const TAG_LEN: usize = 16;
+    const HEADER_LEN: usize = MAGIC.len() + NONCE_LEN;
+    /// Memory Argon2 uses per PIN hash, so guessing PINs from the config file takes a while.
+    #[cfg(not(test))]
+    const PIN_MEMORY_KIB: u32 = Params::DEFAULT_M_COST;
+    /// Unoptimized test builds would spend seconds on every hash.
+    #[cfg(test)]
+    const PIN_MEMORY_KIB: u32 = Params::MIN_M_COST;
+
+    /// The key page files are encrypted with while an encrypted library is unlocked. It is random and kept inside
+    /// the encrypted database, so changing the passphrase does not touch the page files.
+    static PAGE_KEY: Mutex<Option<[u8; 32]>> = Mutex::new(None);
+
+    pub(crate) fn set_page_key(key: Option<[u8; 32]>) {
+        *PAGE_KEY.lock().unwrap_or_else(|err| err.into_inner()) = key;
+    }
+
+    fn page_key() -> Option<[u8; 32]> {
+        return *PAGE_KEY.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    pub(crate) fn random_key() -> [u8; 32] {
+        let mut key = [0u8; 32];
+        OsRng.fill_bytes(&mut key);
+        return key
+    }
+
+    /// Encrypts the page bytes for storing when the library is encrypted, and leaves them as they are otherwise.
+    ///
+    /// Files are sealed with XChaCha20-Poly1305 under a random nonce, with the header as associated data, so a
+    /// changed or swapped file is rejected instead of shown as a broken image.
+    pub(crate) fn seal(bytes: &[u8]) -> Vec<u8> {
+        return match page_key() {
+            Some(key) => seal_with(&key, bytes),
+            None => bytes.to_vec()
+        }
+    }
+
+    fn seal_with(key: &[u8; 32], bytes: &[u8]) -> Vec<u8> {
+        let mut nonce = [0u8; NONCE_LEN];
+        OsRng.fill_bytes(&mut nonce);
+        let ciphertext = XChaCha20Poly1305::new(key.into())
+            .encrypt(XNonce::from_slice(&nonce), Payload { msg: bytes, aad: MAGIC })
+            .expect("Page files are far below the cipher's length limit.");
+
+        let mut sealed = Vec::with_capacity(HEADER_LEN + ciphertext.len());
+        sealed.extend_from_slice(MAGIC);
+        sealed.extend_from_slice(&nonce);
+        sealed.extend_from_slice(&ciphertext);
+        return sealed
+    }
+
+    /// The page bytes of a stored file, decrypting it if it was sealed.
+    pub(crate) fn open(data: Vec<u8>) -> Result<Vec<u8>, io::Error> {
+        if !is_sealed(&data) {
+            return Ok(data)
+        }
+        let key = page_key().ok_or_else(|| io::Error::new(io::ErrorKind::PermissionDenied, "The library is locked."))?;
+        return open_with(&key, data)
+    }
+
+    fn open_with(key: &[u8; 32], data: Vec<u8>) -> Result<Vec<u8>, io::Error> {
+        if data.len() < HEADER_LEN + TAG_LEN {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Encrypted page file is truncated."))
+        }
+        let nonce = XNonce::from_slice(&data[MAGIC.len()..HEADER_LEN]);
+        return XChaCha20Poly1305::new(key.into())
+            .decrypt(nonce, Payload { msg: &data[HEADER_LEN..], aad: MAGIC })
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "Encrypted page file was changed or belongs to another library."))
+    }
+
+    pub(crate) fn is_sealed(data: &[u8]) -> bool {
+        return data.starts_with(MAGIC)
+    }
+
+    /// The name a page is stored under while an encrypted library is unlocked: an HMAC-SHA256 of its content, so
+    /// file names do not give away which pages the library holds. `None` for a plain library.
+    pub(crate) fn keyed_checksum(bytes: &[u8]) -> Option<String> {
+        return page_key().map(|key| keyed_checksum_with(&key, bytes))
+    }
+
+    fn keyed_checksum_with(key: &[u8; 32], bytes: &[u8]) -> String {
+        // A key of its own for names, so the page key is only ever used by the cipher.
+        let name_key = hmac_sha256(key, b"object names");
+        return to_hex(&hmac_sha256(&name_key, bytes))
+    }
+
+    /// An Argon2id hash of the application lock PIN for the config file. The PHC string keeps the salt and the
+    /// parameters, so stored PINs still verify if the parameters are raised later.
+    pub(crate) fn hash_pin(pin: &str) -> String {
+        let params = Params::new(PIN_MEMORY_KIB, Params::DEFAULT_T_COST, Params::DEFAULT_P_COST, None).expect("The PIN hash parameters are valid.");
+        let hasher = Argon2::new(Algorithm::Argon2id, Version::V0x13, params);
+        let salt = SaltString::generate(&mut OsRng);
+        return hasher.hash_password(pin.as_bytes(), &salt).expect("Argon2 hashes PINs of any length.").to_string()
+    }
+
+    pub(crate) fn verify_pin(pin: &str, stored: &str) -> bool {
+        return match PasswordHash::new(stored) {
+            Ok(hash) => Argon2::default().verify_password(pin.as_bytes(), &hash).is_ok(),
+            Err(_) => false
+        }
+    }
+
+    fn to_hex(bytes: &[u8]) -> String {
+        let mut hex = String::with_capacity(bytes.len() * 2);
+        for byte in bytes {
+            hex.push_str(&format!("{:02x}", byte));
+        }
+        return hex
+    }
+
+    fn hmac_sha256(key: &[u8], bytes: &[u8]) -> [u8; 32] {
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(key).expect("HMAC takes keys of any length.");
+        mac.update(bytes);
+        return mac.finalize().into_bytes().into()
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_seal_and_open() {
+            let key = random_key();
+            let sealed = seal_with(&key, b"page bytes");
+            assert!(is_sealed(&sealed));
+            assert_ne!(&sealed[HEADER_LEN..], b"page bytes");
+            assert_eq!(open_with(&key, sealed.clone()).unwrap(), b"page bytes");
+            // Each file gets its own nonce.
+            assert_ne!(seal_with(&key, b"page bytes"), sealed);
+        }
+
+        #[test]
+        fn test_open_rejects_changed_files() {
+            let key = random_key();
+            let mut sealed = seal_with(&key, b"page bytes");
+            assert!(open_with(&random_key(), sealed.clone()).is_err());
+            *sealed.last_mut().unwrap() ^= 1;
+            assert!(open_with(&key, sealed.clone()).is_err());
+            *sealed.last_mut().unwrap() ^= 1;
+            // The header is authenticated too.
+            sealed[MAGIC.len()] ^= 1;
+            assert!(open_with(&key, sealed).is_err());
+            assert!(open_with(&key, MAGIC.to_vec()).is_err());
+        }
+
+        #[test]
+        fn test_pin_hash() {
+            let stored = hash_pin("1234");
+            assert!(verify_pin("1234", &stored));
+            assert!(!verify_pin("4321", &stored));
+            assert!(!verify_pin("1234", "not a hash"));
+            assert!(stored.starts_with("$argon2id$"));
+            // Each hash gets its own salt.
+            assert_ne!(stored, hash_pin("1234"));
+        }
+
+        #[test]
+        fn test_keyed_checksum() {
+            let key = random_key();
+            assert_eq!(keyed_checksum_with(&key, b"page bytes"), keyed_checksum_with(&key, b"page bytes"));
+            assert_eq!(keyed_checksum_with(&key, b"page bytes").len(), 64);
+            // Other libraries name the same page differently.
+            assert_ne!(keyed_checksum_with(&random_key(), b"page bytes"), keyed_checksum_with(&key, b"page bytes"));
+        }
+    }
+}
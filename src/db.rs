@@ -1,107 +1,635 @@
 pub(crate) mod db_module {
-    use std::fs;
+    use std::collections::HashMap;
+    use std::fs::{self, File};
+    use std::io::Read;
     use std::path::Path;
+    use std::ops::{Deref, DerefMut};
     use std::path::PathBuf;
-    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex, OnceLock};
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    use time::Date;
 
     use rusqlite::Connection;
+    use rusqlite::ErrorCode;
+    use rusqlite::OptionalExtension;
     use rusqlite::Result;
+    use rusqlite::TransactionBehavior;
     use rusqlite::config::DbConfig;
+    use rusqlite::types::Value;
     use rusqlite::ffi::SQLITE_DBCONFIG_ENABLE_FKEY;
+    use rusqlite::ffi::SQLITE_CONSTRAINT_UNIQUE;
 
     use crate::attachment::attachment::Attachment;
+    use crate::crypto::crypto::{random_key, set_page_key};
     use crate::attachment_page::attachment_page::AttachmentPage;
-    use crate::document::document::Document;
+    use crate::document::document::{Document, DocumentMetadata, DocumentStatus, format_date, parse_date};
+    use crate::document_link::document_link::{DocumentLink, LinkKind};
+    use crate::document_type::document_type::{DocumentType, FieldKind, TypeField};
+    use crate::document_merge::document_merge::MergedDetails;
+    use crate::edit_history::edit_history::DocumentDetails;
+    use crate::saved_view::saved_view::{GroupBy, ListQuery, SavedView, SortKey, natural_cmp};
+    use crate::tag::tag::{TAG_COLORS, Tag};
+
+    /// How many idle connections are kept open for reuse.
+    const POOL_SIZE: usize = 4;
+    /// How long a statement waits for another connection's write lock before failing with "database is locked".
+    const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+    /// Open connections not in use, handed out again by `DbConnection::new`.
+    static POOL: Mutex<Vec<Connection>> = Mutex::new(Vec::new());
+    /// Whether the database file was created and migrated by this process, which only has to happen once.
+    static INITIALIZED: AtomicBool = AtomicBool::new(false);
+    /// Raised whenever the library file is replaced, so connections to the old file are not returned to the pool.
+    static POOL_GENERATION: AtomicUsize = AtomicUsize::new(0);
+    /// The passphrase of the unlocked encrypted library, given to every connection as it opens.
+    static PASSPHRASE: Mutex<Option<String>> = Mutex::new(None);
+    /// Seconds an attachment lock counts without being renewed, so a crashed session does not hold it forever.
+    const LOCK_TIMEOUT: i64 = 60;
+    /// How often a running session renews its `live_session` heartbeat.
+    pub(crate) const SESSION_HEARTBEAT: Duration = Duration::from_secs(20);
+    /// Seconds a session counts as running without renewing its heartbeat, so a crashed one is noticed.
+    const SESSION_TIMEOUT: i64 = 60;
+
+    /// A connection borrowed from the pool, which goes back to the pool when dropped unless the library file was
+    /// replaced since it was opened.
+    #[derive(Debug)]
+    struct PooledConnection(Option<Connection>, usize);
+
+    impl PooledConnection {
+        fn new(conn: Connection) -> PooledConnection {
+            return PooledConnection(Some(conn), POOL_GENERATION.load(Ordering::Relaxed))
+        }
+    }
+
+    impl Deref for PooledConnection {
+        type Target = Connection;
+
+        fn deref(&self) -> &Connection {
+            return self.0.as_ref().unwrap()
+        }
+    }
+
+    impl DerefMut for PooledConnection {
+        fn deref_mut(&mut self) -> &mut Connection {
+            return self.0.as_mut().unwrap()
+        }
+    }
+
+    impl Drop for PooledConnection {
+        fn drop(&mut self) {
+            // Only connections to the library file go back to the pool, not the in-memory ones of tests.
+            if let Some(conn) = self.0.take().filter(|conn| conn.path().is_some_and(|path| !path.is_empty())) {
+                let mut pool = POOL.lock().unwrap_or_else(|err| err.into_inner());
+                if pool.len() < POOL_SIZE && self.1 == POOL_GENERATION.load(Ordering::Relaxed) {
+                    pool.push(conn);
+                }
+            }
+        }
+    }
 
     #[derive(Debug)]
     pub(crate) struct DbConnection {
-        conn: Connection,
+        conn: PooledConnection,
         last_rowid: Option<i64>,
     }
 
     impl DbConnection {
         pub(crate) fn new() -> DbConnection {
             DbConnection { 
-                conn: Result::expect(Self::db_init(), "Error connecting to database."),
+                conn: PooledConnection::new(Result::expect(Self::take_connection(), "Error connecting to database.")),
                 last_rowid: None,
             }
         }
 
-        pub(crate) fn db_init() -> Result<Connection, rusqlite::Error> {
+        /// A connection to a new database in memory, for tests.
+        #[cfg(test)]
+        pub(crate) fn in_memory() -> DbConnection {
+            let conn = Connection::open_in_memory().unwrap();
+            conn.create_collation("NATURAL_SORT", natural_cmp).unwrap();
+            Self::create_schema(&conn).unwrap();
+            Self::migrate(&conn).unwrap();
+            DbConnection {
+                conn: PooledConnection::new(conn),
+                last_rowid: None,
+            }
+        }
+
+        /// Reuses an idle connection if there is one and opens a new one otherwise. The first connection of the
+        /// process creates or migrates the database.
+        fn take_connection() -> Result<Connection, rusqlite::Error> {
+            let mut pool = POOL.lock().unwrap_or_else(|err| err.into_inner());
             if !Path::new("./data.db").exists() {
-                let conn = Connection::open("./data.db")?;
-                conn.set_db_config(DbConfig::SQLITE_DBCONFIG_ENABLE_FKEY, true);
-                conn.execute(
-                "CREATE TABLE document (
-                        document_id INTEGER PRIMARY KEY,
-                        document_number TEXT NOT NULL UNIQUE,
-                        document_type TEXT,
-                        comment TEXT,
-                        date_added INTEGER NOT NULL DEFAULT (unixepoch('now'))
-                    )", ()
-                )?;
+                // Idle connections still point at the removed file, so start over with a new database.
+                pool.clear();
+                POOL_GENERATION.fetch_add(1, Ordering::Relaxed);
+                INITIALIZED.store(false, Ordering::Relaxed);
+            }
+            if let Some(conn) = pool.pop() {
+                return Ok(conn);
+            }
+            if !INITIALIZED.load(Ordering::Relaxed) {
+                let conn = Self::db_init()?;
+                INITIALIZED.store(true, Ordering::Relaxed);
+                return Ok(conn);
+            }
+            return Self::connect();
+        }
 
-                conn.execute("CREATE TABLE attachment (
-                        attachment_id INTEGER PRIMARY KEY,
-                        reference_number TEXT NOT NULL UNIQUE,
-                        comment TEXT,
-                        date_added INTEGER NOT NULL DEFAULT (unixepoch('now')),
-                        document_id INTEGER NOT NULL,
-                        FOREIGN KEY(document_id) REFERENCES document(document_id) ON DELETE CASCADE
-                    )", ()
-                )?;
+        pub(crate) fn db_init() -> Result<Connection, rusqlite::Error> {
+            let conn = Self::connect()?;
+            let has_schema: bool = conn.query_row("SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'document')", [], |row| row.get(0))?;
+            if !has_schema {
+                Self::create_schema(&conn)?;
+                fs::create_dir("data").unwrap_or_else(|err| {
+                    println!("Error creating data folder: {}", err);
+                });
+            }
+            Self::migrate(&conn)?;
+            println!("DB initialized.");
+            return Ok(conn);
+        }
 
-                conn.execute("CREATE TABLE page (
-                        page_id INTEGER PRIMARY KEY,
-                        file_path TEXT NOT NULL,
-                        attachment_id INTEGER NOT NULL,
-                        FOREIGN KEY(attachment_id) REFERENCES attachment(attachment_id) ON DELETE CASCADE
+        /// The tables of the first release, which `migrate` brings up to date. They are created in one transaction,
+        /// so a first start that is interrupted leaves an empty file that is set up again on the next start.
+        fn create_schema(conn: &Connection) -> Result<(), rusqlite::Error> {
+            let transaction = conn.unchecked_transaction()?;
+            transaction.execute(
+            "CREATE TABLE document (
+                    document_id INTEGER PRIMARY KEY,
+                    document_number TEXT NOT NULL UNIQUE,
+                    document_type TEXT,
+                    comment TEXT,
+                    date_added INTEGER NOT NULL DEFAULT (unixepoch('now'))
                 )", ()
-                )?;
+            )?;
 
-                    fs::create_dir("data").unwrap_or_else(|err| {
-                        println!("Error creating data folder: {}", err);
-                    });
-                    println!("DB initialized.");
+            transaction.execute("CREATE TABLE attachment (
+                    attachment_id INTEGER PRIMARY KEY,
+                    reference_number TEXT NOT NULL UNIQUE,
+                    comment TEXT,
+                    date_added INTEGER NOT NULL DEFAULT (unixepoch('now')),
+                    document_id INTEGER NOT NULL,
+                    FOREIGN KEY(document_id) REFERENCES document(document_id) ON DELETE CASCADE
+                )", ()
+            )?;
 
-                return Ok(conn);
-                
+            transaction.execute("CREATE TABLE page (
+                    page_id INTEGER PRIMARY KEY,
+                    file_path TEXT NOT NULL,
+                    attachment_id INTEGER NOT NULL,
+                    FOREIGN KEY(attachment_id) REFERENCES attachment(attachment_id) ON DELETE CASCADE
+            )", ()
+            )?;
+            return transaction.commit()
+        }
+
+        // Schema changes made after the initial release, applied in order and tracked with user_version. Each step
+        // commits together with its version, so a step that fails is tried again in full on the next start.
+        fn migrate(conn: &Connection) -> Result<(), rusqlite::Error> {
+            let version: i64 = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+
+            if version < 1 {
+                migrate_step(conn, 1, |conn| {
+                    conn.execute("ALTER TABLE page ADD COLUMN checksum TEXT", ())?;
+                    return Ok(())
+                })?;
             }
-            else {
-                println!("DB initialized.");
-                return Self::connect();
+
+            if version < 2 {
+                migrate_step(conn, 2, |conn| {
+                    conn.execute("ALTER TABLE document ADD COLUMN deleted_at INTEGER", ())?;
+                    conn.execute("ALTER TABLE attachment ADD COLUMN deleted_at INTEGER", ())?;
+                    return Ok(())
+                })?;
+            }
+
+            // No foreign keys, so the history of purged documents and attachments is kept.
+            if version < 3 {
+                migrate_step(conn, 3, |conn| {
+                    conn.execute("CREATE TABLE audit_log (
+                            audit_id INTEGER PRIMARY KEY,
+                            document_id INTEGER NOT NULL,
+                            attachment_id INTEGER,
+                            action TEXT NOT NULL,
+                            field TEXT,
+                            old_value TEXT,
+                            new_value TEXT,
+                            changed_at INTEGER NOT NULL DEFAULT (unixepoch('now')),
+                            user_name TEXT NOT NULL
+                        )", ()
+                    )?;
+                    conn.execute("CREATE INDEX audit_log_document_id ON audit_log (document_id)", ())?;
+                    return Ok(())
+                })?;
+            }
+
+            if version < 4 {
+                migrate_step(conn, 4, |conn| {
+                    conn.execute("CREATE TABLE attachment_version (
+                            version_id INTEGER PRIMARY KEY,
+                            attachment_id INTEGER NOT NULL,
+                            replaced_at INTEGER NOT NULL DEFAULT (unixepoch('now'))
+                        )", ()
+                    )?;
+                    conn.execute("CREATE TABLE version_page (
+                            version_id INTEGER NOT NULL,
+                            position INTEGER NOT NULL,
+                            file_path TEXT NOT NULL,
+                            checksum TEXT,
+                            PRIMARY KEY (version_id, position)
+                        )", ()
+                    )?;
+                    return Ok(())
+                })?;
+            }
+
+            if version < 5 {
+                migrate_step(conn, 5, |conn| {
+                    conn.execute("CREATE TABLE tag (
+                            tag_id INTEGER PRIMARY KEY,
+                            name TEXT NOT NULL UNIQUE COLLATE NOCASE,
+                            color TEXT NOT NULL
+                        )", ()
+                    )?;
+                    conn.execute("CREATE TABLE document_tag (
+                            document_id INTEGER NOT NULL,
+                            tag_id INTEGER NOT NULL,
+                            PRIMARY KEY (document_id, tag_id)
+                        )", ()
+                    )?;
+                    conn.execute("CREATE TABLE attachment_tag (
+                            attachment_id INTEGER NOT NULL,
+                            tag_id INTEGER NOT NULL,
+                            PRIMARY KEY (attachment_id, tag_id)
+                        )", ()
+                    )?;
+                    return Ok(())
+                })?;
+            }
+
+            // Document types used to be free text; the distinct values become the initial managed types,
+            // and documents are renamed to the stored spelling so "Invoice" and "invoice" end up as one type.
+            if version < 6 {
+                migrate_step(conn, 6, |conn| {
+                    conn.execute("CREATE TABLE document_type (
+                            type_id INTEGER PRIMARY KEY,
+                            name TEXT NOT NULL UNIQUE COLLATE NOCASE
+                        )", ()
+                    )?;
+                    conn.execute("CREATE TABLE type_field (
+                            field_id INTEGER PRIMARY KEY,
+                            type_id INTEGER NOT NULL,
+                            name TEXT NOT NULL,
+                            kind TEXT NOT NULL,
+                            required INTEGER NOT NULL DEFAULT 0,
+                            options TEXT NOT NULL DEFAULT ''
+                        )", ()
+                    )?;
+                    conn.execute("CREATE TABLE field_value (
+                            document_id INTEGER NOT NULL,
+                            field_id INTEGER NOT NULL,
+                            value TEXT NOT NULL,
+                            PRIMARY KEY (document_id, field_id)
+                        )", ()
+                    )?;
+                    conn.execute("INSERT OR IGNORE INTO document_type (name)
+                        SELECT TRIM(document_type) FROM document WHERE TRIM(COALESCE(document_type, '')) != '' ORDER BY document_id", ())?;
+                    conn.execute("UPDATE document SET document_type = (SELECT name FROM document_type WHERE name = TRIM(document.document_type))
+                        WHERE TRIM(COALESCE(document_type, '')) != ''", ())?;
+                    return Ok(())
+                })?;
+            }
+
+            if version < 7 {
+                migrate_step(conn, 7, |conn| {
+                    conn.execute("ALTER TABLE document_type ADD COLUMN number_pattern TEXT NOT NULL DEFAULT ''", ())?;
+                    conn.execute("ALTER TABLE document_type ADD COLUMN auto_number INTEGER NOT NULL DEFAULT 0", ())?;
+                    return Ok(())
+                })?;
+            }
+
+            if version < 8 {
+                migrate_step(conn, 8, |conn| {
+                    conn.execute("ALTER TABLE document ADD COLUMN document_date TEXT", ())?;
+                    conn.execute("ALTER TABLE document ADD COLUMN due_date TEXT", ())?;
+                    conn.execute("ALTER TABLE document ADD COLUMN status TEXT NOT NULL DEFAULT 'draft'", ())?;
+                    return Ok(())
+                })?;
+            }
+
+            if version < 9 {
+                migrate_step(conn, 9, |conn| {
+                    conn.execute("ALTER TABLE document ADD COLUMN status_changed_at INTEGER", ())?;
+                    return Ok(())
+                })?;
             }
-            
-            
+
+            if version < 10 {
+                migrate_step(conn, 10, |conn| {
+                    conn.execute("CREATE TABLE document_link (
+                            link_id INTEGER PRIMARY KEY,
+                            source_id INTEGER NOT NULL,
+                            target_id INTEGER NOT NULL,
+                            kind TEXT NOT NULL,
+                            created_at INTEGER NOT NULL DEFAULT (unixepoch('now')),
+                            UNIQUE (source_id, target_id, kind)
+                        )", ()
+                    )?;
+                    conn.execute("CREATE INDEX document_link_target_id ON document_link (target_id)", ())?;
+                    return Ok(())
+                })?;
+            }
+
+            if version < 11 {
+                migrate_step(conn, 11, |conn| {
+                    conn.execute("CREATE TABLE saved_view (
+                            view_id INTEGER PRIMARY KEY,
+                            name TEXT NOT NULL UNIQUE,
+                            query TEXT NOT NULL DEFAULT '',
+                            sort_key TEXT NOT NULL DEFAULT 'document_number',
+                            descending INTEGER NOT NULL DEFAULT 0,
+                            group_by TEXT NOT NULL DEFAULT ''
+                        )", ()
+                    )?;
+                    return Ok(())
+                })?;
+            }
+
+            if version < 12 {
+                migrate_step(conn, 12, |conn| {
+                    // Foreign keys used to be enabled on the first connection only, so deletes left rows behind.
+                    conn.execute("DELETE FROM attachment WHERE document_id NOT IN (SELECT document_id FROM document)", ())?;
+                    conn.execute("DELETE FROM page WHERE attachment_id NOT IN (SELECT attachment_id FROM attachment)", ())?;
+                    return Ok(())
+                })?;
+            }
+
+            // Versions for optimistic concurrency when several people share the library, raised by triggers so
+            // every write counts, whichever statement made it.
+            if version < 13 {
+                migrate_step(conn, 13, |conn| {
+                    conn.execute("ALTER TABLE document ADD COLUMN version INTEGER NOT NULL DEFAULT 0", ())?;
+                    conn.execute("ALTER TABLE attachment ADD COLUMN version INTEGER NOT NULL DEFAULT 0", ())?;
+                    conn.execute("CREATE TRIGGER document_version_bump AFTER UPDATE ON document WHEN NEW.version = OLD.version
+                        BEGIN UPDATE document SET version = OLD.version + 1 WHERE document_id = NEW.document_id; END", ()
+                    )?;
+                    conn.execute("CREATE TRIGGER attachment_version_bump AFTER UPDATE ON attachment WHEN NEW.version = OLD.version
+                        BEGIN UPDATE attachment SET version = OLD.version + 1 WHERE attachment_id = NEW.attachment_id; END", ()
+                    )?;
+                    conn.execute("CREATE TABLE attachment_lock (
+                            attachment_id INTEGER PRIMARY KEY,
+                            session TEXT NOT NULL,
+                            user_name TEXT NOT NULL,
+                            heartbeat INTEGER NOT NULL DEFAULT (unixepoch('now'))
+                        )", ()
+                    )?;
+                    return Ok(())
+                })?;
+            }
+
+            // Numbers only have to be unique among rows outside the trash, so a deleted item does not block its
+            // number until it is purged. A UNIQUE column constraint cannot be dropped, so both tables are rebuilt.
+            // Foreign keys are off meanwhile, or dropping the old tables would cascade to attachments and pages.
+            if version < 14 {
+                conn.set_db_config(DbConfig::SQLITE_DBCONFIG_ENABLE_FKEY, false)?;
+                let result = migrate_step(conn, 14, |conn| {
+                    // Attachments of documents in the trash are trashed with them, so their numbers are freed too.
+                    conn.execute("UPDATE attachment SET deleted_at = (SELECT deleted_at FROM document WHERE document.document_id = attachment.document_id)
+                        WHERE deleted_at IS NULL AND document_id IN (SELECT document_id FROM document WHERE deleted_at IS NOT NULL)", ()
+                    )?;
+                    conn.execute_batch("CREATE TABLE new_document (
+                            document_id INTEGER PRIMARY KEY,
+                            document_number TEXT NOT NULL,
+                            document_type TEXT,
+                            comment TEXT,
+                            date_added INTEGER NOT NULL DEFAULT (unixepoch('now')),
+                            deleted_at INTEGER,
+                            document_date TEXT,
+                            due_date TEXT,
+                            status TEXT NOT NULL DEFAULT 'draft',
+                            status_changed_at INTEGER,
+                            version INTEGER NOT NULL DEFAULT 0
+                        );
+                        INSERT INTO new_document (document_id, document_number, document_type, comment, date_added, deleted_at, document_date, due_date, status, status_changed_at, version)
+                            SELECT document_id, document_number, document_type, comment, date_added, deleted_at, document_date, due_date, status, status_changed_at, version FROM document;
+                        DROP TABLE document;
+                        ALTER TABLE new_document RENAME TO document;
+                        CREATE UNIQUE INDEX document_number_unique ON document (document_number) WHERE deleted_at IS NULL;
+                        CREATE TRIGGER document_version_bump AFTER UPDATE ON document WHEN NEW.version = OLD.version
+                            BEGIN UPDATE document SET version = OLD.version + 1 WHERE document_id = NEW.document_id; END;
+
+                        CREATE TABLE new_attachment (
+                            attachment_id INTEGER PRIMARY KEY,
+                            reference_number TEXT NOT NULL,
+                            comment TEXT,
+                            date_added INTEGER NOT NULL DEFAULT (unixepoch('now')),
+                            document_id INTEGER NOT NULL,
+                            deleted_at INTEGER,
+                            version INTEGER NOT NULL DEFAULT 0,
+                            FOREIGN KEY(document_id) REFERENCES document(document_id) ON DELETE CASCADE
+                        );
+                        INSERT INTO new_attachment (attachment_id, reference_number, comment, date_added, document_id, deleted_at, version)
+                            SELECT attachment_id, reference_number, comment, date_added, document_id, deleted_at, version FROM attachment;
+                        DROP TABLE attachment;
+                        ALTER TABLE new_attachment RENAME TO attachment;
+                        CREATE UNIQUE INDEX attachment_reference_number_unique ON attachment (reference_number) WHERE deleted_at IS NULL;
+                        CREATE TRIGGER attachment_version_bump AFTER UPDATE ON attachment WHEN NEW.version = OLD.version
+                            BEGIN UPDATE attachment SET version = OLD.version + 1 WHERE attachment_id = NEW.attachment_id; END;"
+                    )?;
+                    return Ok(())
+                });
+                conn.set_db_config(DbConfig::SQLITE_DBCONFIG_ENABLE_FKEY, true)?;
+                result?;
+            }
+
+            // The session that wrote each audit entry, so the change poll can tell its own changes from others'.
+            if version < 15 {
+                migrate_step(conn, 15, |conn| {
+                    conn.execute("ALTER TABLE audit_log ADD COLUMN session TEXT", ())?;
+                    return Ok(())
+                })?;
+            }
+
+            // Running sessions and when they last checked in, so one session leaves another's files alone and the
+            // library is not rewritten under someone else.
+            if version < 16 {
+                migrate_step(conn, 16, |conn| {
+                    conn.execute("CREATE TABLE live_session (
+                            session TEXT PRIMARY KEY,
+                            user_name TEXT NOT NULL,
+                            heartbeat INTEGER NOT NULL DEFAULT (unixepoch('now'))
+                        )", ()
+                    )?;
+                    return Ok(())
+                })?;
+            }
+
+            return Ok(())
         }
 
+        /// Opens the database with the settings every connection needs. SQLite keeps these per connection, so
+        /// they are set on each open: foreign keys so `ON DELETE CASCADE` fires, WAL so reads do not wait for a
+        /// writer, and a busy timeout so concurrent writes wait instead of failing.
         fn connect() -> Result<Connection, rusqlite::Error> {
-            return Connection::open("./data.db");
+            let conn = Connection::open("./data.db")?;
+            // SQLCipher needs the key before anything else reads the file.
+            if let Some(passphrase) = PASSPHRASE.lock().unwrap_or_else(|err| err.into_inner()).as_ref() {
+                conn.pragma_update(None, "key", passphrase)?;
+            }
+            conn.set_db_config(DbConfig::SQLITE_DBCONFIG_ENABLE_FKEY, true)?;
+            conn.pragma_update_and_check(None, "journal_mode", "WAL", |row| row.get::<_, String>(0))?;
+            conn.pragma_update(None, "synchronous", "NORMAL")?;
+            conn.busy_timeout(BUSY_TIMEOUT)?;
+            conn.create_collation("NATURAL_SORT", natural_cmp)?;
+            return Ok(conn);
         }
 
+        /// Every document that is not deleted, as summaries without attachments.
         pub(crate) fn read_document_table(&self) -> Result<Vec<Arc<Document>>, rusqlite::Error> {
-            let mut stmt = self.conn.prepare("SELECT document_id, document_number, document_type, comment, date_added FROM document").unwrap();
-            let document_data = stmt.query_map([], |row| {
-                Ok(Document::new(
-                    row.get(0)?,
-                    Arc::new(row.get(1)?),
-                    Arc::new(row.get(2)?),
-                    Some(Result::expect(self.read_attachment_table(row.get(0)?), "Error reading attachment table.")),
-                    Arc::new(row.get(3)?),
-                    row.get(4)?,
+            return self.read_summaries("ORDER BY document.document_id", [])
+        }
+
+        /// One page of the documents the list query selects, in list order, starting after the document `after_id`
+        /// or at the first one. Summaries carry attachment and page counts, but not the attachments themselves, so no
+        /// page files are read.
+        pub(crate) fn read_document_summaries(&self, list: &ListQuery, after_id: Option<u32>, limit: usize) -> Result<Vec<Arc<Document>>, rusqlite::Error> {
+            let (condition, params) = list.to_sql();
+            let mut params: Vec<Value> = params.into_iter().map(Value::Text).collect();
+            let terms = list.order_terms().join(", ");
+            // Keyset paging: continue after the order terms of the last document, which stays cheap however far
+            // the list is scrolled.
+            let after = match after_id {
+                Some(after_id) => {
+                    params.push(Value::Integer(after_id as i64));
+                    format!("AND ({}) {} (SELECT {} FROM document WHERE document.document_id = ?)", terms, if list.descending { "<" } else { ">" }, terms)
+                },
+                None => String::new()
+            };
+            params.push(Value::Integer(limit as i64));
+            return self.read_summaries(&format!("AND {} {} ORDER BY {} LIMIT ?", condition, after, list.order_by()), rusqlite::params_from_iter(params))
+        }
+
+        /// How many documents the list query selects.
+        pub(crate) fn count_documents(&self, list: &ListQuery) -> Result<usize, rusqlite::Error> {
+            let (condition, params) = list.to_sql();
+            return self.conn.query_row(
+                &format!("SELECT COUNT(*) FROM document WHERE document.deleted_at IS NULL AND {}", condition),
+                rusqlite::params_from_iter(params),
+                |row| row.get(0)
+            )
+        }
+
+        /// How many documents the list query selects in each of its groups, by group label.
+        pub(crate) fn count_document_groups(&self, list: &ListQuery) -> Result<HashMap<String, usize>, rusqlite::Error> {
+            let label = match list.group_by.label_sql() {
+                Some(label) => label,
+                None => return Ok(HashMap::new())
+            };
+            let (condition, params) = list.to_sql();
+            let mut stmt = self.conn.prepare(&format!("SELECT IFNULL({0}, ''), COUNT(*) FROM document WHERE document.deleted_at IS NULL AND {1} GROUP BY IFNULL({0}, '')", label, condition))?;
+            let counts = stmt.query_map(rusqlite::params_from_iter(params), |row| Ok((row.get(0)?, row.get(1)?)))?;
+            return counts.collect()
+        }
+
+        /// The ids of all the documents the list query selects, in list order.
+        pub(crate) fn read_document_ids(&self, list: &ListQuery) -> Result<Vec<u32>, rusqlite::Error> {
+            let (condition, params) = list.to_sql();
+            let mut stmt = self.conn.prepare(&format!("SELECT document.document_id FROM document WHERE document.deleted_at IS NULL AND {} ORDER BY {}", condition, list.order_by()))?;
+            let ids = stmt.query_map(rusqlite::params_from_iter(params), |row| row.get(0))?;
+            return ids.collect()
+        }
+
+        /// The document with its attachments and their pages, or `None` if it is deleted or does not exist.
+        pub(crate) fn read_document(&self, document_id: u32) -> Result<Option<Arc<Document>>, rusqlite::Error> {
+            let summary = match self.read_summaries("AND document.document_id = ?1", [document_id])?.pop() {
+                Some(summary) => summary,
+                None => return Ok(None)
+            };
+            let attachments = self.read_attachment_table(document_id)?;
+            return Ok(Some(Arc::new(Arc::unwrap_or_clone(summary).with_attachments(attachments))))
+        }
+
+        /// The document without its attachments, or `None` if it is deleted or does not exist.
+        pub(crate) fn read_document_summary(&self, document_id: u32) -> Result<Option<Arc<Document>>, rusqlite::Error> {
+            return Ok(self.read_summaries("AND document.document_id = ?1", [document_id])?.pop())
+        }
+
+        /// Reads the documents selected by `clause`, which follows the `WHERE` condition. Tags and field values are
+        /// read in one query each for all the documents instead of once per document.
+        fn read_summaries<P: rusqlite::Params>(&self, clause: &str, params: P) -> Result<Vec<Arc<Document>>, rusqlite::Error> {
+            let mut stmt = self.conn.prepare(&format!(
+                "SELECT document_id, document_number, document_type, comment, date_added, document_date, due_date, status, status_changed_at,
+                    (SELECT COUNT(*) FROM attachment WHERE attachment.document_id = document.document_id AND attachment.deleted_at IS NULL),
+                    (SELECT COUNT(*) FROM page JOIN attachment ON attachment.attachment_id = page.attachment_id WHERE attachment.document_id = document.document_id AND attachment.deleted_at IS NULL),
+                    version
+                FROM document WHERE document.deleted_at IS NULL {}", clause
+            ))?;
+            let rows = stmt.query_map(params, |row| {
+                Ok((
+                    row.get::<_, u32>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, String>(3)?,
+                    row.get::<_, i64>(4)?,
+                    row.get::<_, Option<String>>(5)?,
+                    row.get::<_, Option<String>>(6)?,
+                    row.get::<_, String>(7)?,
+                    row.get::<_, Option<i64>>(8)?,
+                    row.get::<_, usize>(9)?,
+                    row.get::<_, usize>(10)?,
+                    row.get::<_, u32>(11)?
                 ))
-            })?;
+            })?.collect::<Result<Vec<_>, _>>()?;
+            if rows.is_empty() {
+                return Ok(Vec::new())
+            }
+            // The documents of a sorted page are scattered over the table, so their ids are passed as a JSON array.
+            let document_ids = format!("[{}]", rows.iter().map(|row| row.0.to_string()).collect::<Vec<_>>().join(","));
 
-            let mut documents: Vec<Arc<Document>> = Vec::new();
-            for document in document_data {
-                documents.push(Arc::new(document?));
+            let mut tags: HashMap<u32, Vec<Tag>> = HashMap::new();
+            let mut tag_stmt = self.conn.prepare(
+                "SELECT document_tag.document_id, tag.tag_id, tag.name, tag.color FROM tag JOIN document_tag ON document_tag.tag_id = tag.tag_id
+                WHERE document_tag.document_id IN (SELECT value FROM json_each(?1)) ORDER BY tag.name"
+            )?;
+            for tag in tag_stmt.query_map([&document_ids], |row| Ok((row.get::<_, u32>(0)?, Tag::new(row.get(1)?, Arc::new(row.get(2)?), Arc::new(row.get(3)?)))))? {
+                let (document_id, tag) = tag?;
+                tags.entry(document_id).or_default().push(tag);
+            }
+
+            let mut field_values: HashMap<u32, HashMap<u32, String>> = HashMap::new();
+            let mut value_stmt = self.conn.prepare("SELECT document_id, field_id, value FROM field_value WHERE document_id IN (SELECT value FROM json_each(?1))")?;
+            for value in value_stmt.query_map([&document_ids], |row| Ok((row.get::<_, u32>(0)?, row.get::<_, u32>(1)?, row.get::<_, String>(2)?)))? {
+                let (document_id, field_id, value) = value?;
+                field_values.entry(document_id).or_default().insert(field_id, value);
             }
 
-            return Ok(documents);
+            return Ok(rows.into_iter().map(|(document_id, document_number, document_type, comment, date_added, document_date, due_date, status, status_changed_at, attachment_count, page_count, version)| {
+                Arc::new(Document::new(
+                    document_id,
+                    Arc::new(document_number),
+                    Arc::new(document_type),
+                    None,
+                    Arc::new(comment),
+                    date_added,
+                    DocumentMetadata {
+                        tags: tags.remove(&document_id).unwrap_or_default(),
+                        field_values: field_values.remove(&document_id).unwrap_or_default(),
+                        document_date: document_date.and_then(|date| parse_date(&date)),
+                        due_date: due_date.and_then(|date| parse_date(&date)),
+                        status: DocumentStatus::from_str(&status),
+                        status_changed_at: status_changed_at,
+                        attachment_count: attachment_count,
+                        page_count: page_count,
+                        version: version
+                    }
+                ))
+            }).collect())
         }
 
         pub(crate) fn read_attachment_table(&self, document_id: u32) -> Result<Vec<Arc<Attachment>>, rusqlite::Error> {
-            let mut stmt = self.conn.prepare("SELECT attachment_id, reference_number, comment, date_added, document_id FROM attachment WHERE document_id = ?1").unwrap();
+            let mut stmt = self.conn.prepare("SELECT attachment_id, reference_number, comment, date_added, document_id, version FROM attachment WHERE document_id = ?1 AND deleted_at IS NULL").unwrap();
             let attachment_data = stmt.query_map([document_id], |row| {
                 Ok(Attachment::new(
                     row.get(0)?,
@@ -109,8 +637,9 @@ pub(crate) mod db_module {
                     Arc::new(row.get(1)?),
                     Arc::new(row.get(2)?),
                     row.get(3)?,
-                    row.get(4)?
-                ))
+                    row.get(4)?,
+                    self.read_tags_of("SELECT tag.tag_id, tag.name, tag.color FROM tag JOIN attachment_tag ON attachment_tag.tag_id = tag.tag_id WHERE attachment_tag.attachment_id = ?1 ORDER BY tag.name", row.get(0)?)?
+                ).with_version(row.get(5)?))
             })?;
 
             let mut attachments: Vec<Arc<Attachment>> = Vec::new();
@@ -121,13 +650,36 @@ pub(crate) mod db_module {
             return Ok(attachments)
         }
         
+        /// The attachment without its pages, so no page files are read, or `None` if it is deleted or does not exist.
+        pub(crate) fn read_attachment_summary(&self, attachment_id: u32) -> Result<Option<Arc<Attachment>>, rusqlite::Error> {
+            let mut stmt = self.conn.prepare("SELECT attachment_id, reference_number, comment, date_added, document_id, version FROM attachment WHERE attachment_id = ?1 AND deleted_at IS NULL")?;
+            let attachment = stmt.query_row([attachment_id], |row| {
+                Ok(Attachment::new(
+                    row.get(0)?,
+                    Vec::new(),
+                    Arc::new(row.get(1)?),
+                    Arc::new(row.get(2)?),
+                    row.get(3)?,
+                    row.get(4)?,
+                    self.read_tags_of("SELECT tag.tag_id, tag.name, tag.color FROM tag JOIN attachment_tag ON attachment_tag.tag_id = tag.tag_id WHERE attachment_tag.attachment_id = ?1 ORDER BY tag.name", row.get(0)?)?
+                ).with_version(row.get(5)?))
+            }).optional()?;
+            return Ok(attachment.map(Arc::new))
+        }
+
+        /// The checksums of the attachment's pages, in page order.
+        pub(crate) fn read_page_checksums(&self, attachment_id: u32) -> Result<Vec<String>, rusqlite::Error> {
+            return page_checksums(&self.conn, attachment_id)
+        }
+
         pub(crate) fn read_pages_table(&self, attachment_id: u32) -> Result<Vec<AttachmentPage>, rusqlite::Error> {
-            let mut stmt = self.conn.prepare("SELECT page_id, file_path, attachment_id FROM page WHERE attachment_id = ?1").unwrap();
+            let mut stmt = self.conn.prepare("SELECT page_id, file_path, attachment_id, checksum FROM page WHERE attachment_id = ?1 ORDER BY page_id").unwrap();
             let page_data = stmt.query_map([attachment_id], |row| {
                 Ok(AttachmentPage::new(
                     row.get(0)?,
                     row.get(1)?,
-                    row.get(2)?
+                    row.get(2)?,
+                    row.get(3)?
                 ))
             })?;
 
@@ -139,35 +691,326 @@ pub(crate) mod db_module {
             return Ok(pages)
         }
 
-        pub(crate) fn new_document(&mut self, document_number: String, document_type: String, comment: String) -> Result<usize, rusqlite::Error> {
-            let result =  self.conn.execute("INSERT INTO document (document_number, document_type, comment) VALUES (?1, ?2, ?3)", (document_number, document_type, comment));
-            self.last_rowid = Some(self.conn.last_insert_rowid());
-            return result
+        pub(crate) fn new_document(&mut self, document_number: String, document_type: String, comment: String, field_values: Vec<(u32, String)>, document_date: Option<Date>, due_date: Option<Date>) -> Result<usize, rusqlite::Error> {
+            let transaction = self.conn.transaction()?;
+            let result = transaction.execute(
+                "INSERT INTO document (document_number, document_type, comment, document_date, due_date) VALUES (?1, ?2, ?3, ?4, ?5)",
+                (&document_number, document_type, comment, document_date.map(format_date), due_date.map(format_date))
+            )?;
+            let document_id = transaction.last_insert_rowid();
+            self.last_rowid = Some(document_id);
+            record_change(&transaction, document_id as u32, None, "created", Some("document_number"), None, Some(&document_number))?;
+            save_field_values(&transaction, document_id as u32, field_values)?;
+            transaction.commit()?;
+            return Ok(result)
         }
 
-        pub(crate) fn new_attachment(&mut self, file_paths: Vec<PathBuf>, reference_number: String, comment: String, document_id: u32) -> Result<(), rusqlite::Error> {
-            self.conn.execute("INSERT INTO attachment (reference_number, comment, document_id) VALUES (?1, ?2, ?3)", (reference_number, comment, document_id))?;
-            self.last_rowid = Some(self.conn.last_insert_rowid());
+        pub(crate) fn new_attachment(&mut self, pages: Vec<(PathBuf, String)>, reference_number: String, comment: String, document_id: u32) -> Result<(), rusqlite::Error> {
             let transaction = self.conn.transaction()?;
-            for path in file_paths {
-                transaction.execute("INSERT INTO page (file_path, attachment_id) VALUES (?1, ?2)", (path.to_string_lossy(), self.last_rowid))?;
+            transaction.execute("INSERT INTO attachment (reference_number, comment, document_id) VALUES (?1, ?2, ?3)", (&reference_number, comment, document_id))?;
+            self.last_rowid = Some(transaction.last_insert_rowid());
+            for (path, checksum) in pages {
+                transaction.execute("INSERT INTO page (file_path, attachment_id, checksum) VALUES (?1, ?2, ?3)", (path.to_string_lossy(), self.last_rowid, checksum))?;
             }
+            record_change(&transaction, document_id, self.last_rowid.map(|id| id as u32), "created", Some("reference_number"), None, Some(&reference_number))?;
             transaction.commit()
         }
 
-        pub(crate) fn edit_document_details(&mut self, document_id: u32, document_number: String, document_type: String, comment: String) -> Result<usize, rusqlite::Error> {
-            return self.conn.execute("UPDATE document SET document_number = ?1, document_type = ?2, comment = ?3 WHERE document_id = ?4", (document_number, document_type, comment, document_id))
+        /// Files the attachment under another document, under `reference_number`. Pages, versions and tags stay with
+        /// the attachment; page files are shared by checksum, so nothing on disk has to move.
+        /// With `expected_version`, fails with a conflict (see `is_conflict`) if the attachment changed since.
+        pub(crate) fn move_attachment(&mut self, attachment_id: u32, document_id: u32, reference_number: String, expected_version: Option<u32>) -> Result<(), rusqlite::Error> {
+            let transaction = self.conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+            let (old_document_id, old_reference_number): (u32, String) = transaction.query_row(
+                "SELECT document_id, reference_number FROM attachment WHERE attachment_id = ?1 AND (?2 IS NULL OR version = ?2)", (attachment_id, expected_version),
+                |row| Ok((row.get(0)?, row.get(1)?))
+            )?;
+            transaction.execute("UPDATE attachment SET reference_number = ?1 WHERE attachment_id = ?2", (&reference_number, attachment_id))?;
+            if old_document_id != document_id {
+                move_attachments(&transaction, &[attachment_id], old_document_id, document_id)?;
+            }
+            record_edit(&transaction, document_id, Some(attachment_id), "reference_number", &old_reference_number, &reference_number)?;
+            transaction.commit()
         }
 
-        pub(crate) fn edit_attachment_details(&mut self, attachment_id: u32, reference_number: String, comment: String) -> Result<usize, rusqlite::Error> {
-            return self.conn.execute("UPDATE attachment SET reference_number = ?1, comment = ?2 WHERE attachment_id = ?3", (reference_number, comment, attachment_id))
+        /// Adds a copy of the attachment, with the same pages, versions and tags, to `document_id`. The page rows of
+        /// the copy point to the same stored files.
+        /// With `expected_version`, fails with a conflict (see `is_conflict`) if the attachment changed since.
+        pub(crate) fn copy_attachment(&mut self, attachment_id: u32, document_id: u32, reference_number: String, expected_version: Option<u32>) -> Result<(), rusqlite::Error> {
+            let transaction = self.conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+            let (old_reference_number, comment): (String, Option<String>) = transaction.query_row(
+                "SELECT reference_number, comment FROM attachment WHERE attachment_id = ?1 AND (?2 IS NULL OR version = ?2)", (attachment_id, expected_version),
+                |row| Ok((row.get(0)?, row.get(1)?))
+            )?;
+            transaction.execute("INSERT INTO attachment (reference_number, comment, document_id) VALUES (?1, ?2, ?3)", (&reference_number, comment, document_id))?;
+            let copy_id = transaction.last_insert_rowid();
+            transaction.execute("INSERT INTO page (file_path, attachment_id, checksum) SELECT file_path, ?1, checksum FROM page WHERE attachment_id = ?2 ORDER BY page_id", (copy_id, attachment_id))?;
+            transaction.execute("INSERT INTO attachment_tag (attachment_id, tag_id) SELECT ?1, tag_id FROM attachment_tag WHERE attachment_id = ?2", (copy_id, attachment_id))?;
+            let versions: Vec<(u32, i64)> = transaction.prepare("SELECT version_id, replaced_at FROM attachment_version WHERE attachment_id = ?1 ORDER BY version_id")?
+                .query_map((attachment_id,), |row| Ok((row.get(0)?, row.get(1)?)))?
+                .collect::<Result<_, _>>()?;
+            for (version_id, replaced_at) in versions {
+                transaction.execute("INSERT INTO attachment_version (attachment_id, replaced_at) VALUES (?1, ?2)", (copy_id, replaced_at))?;
+                transaction.execute(
+                    "INSERT INTO version_page (version_id, position, file_path, checksum) SELECT ?1, position, file_path, checksum FROM version_page WHERE version_id = ?2",
+                    (transaction.last_insert_rowid(), version_id)
+                )?;
+            }
+            record_change(&transaction, document_id, Some(copy_id as u32), "copied", Some("reference_number"), Some(&old_reference_number), Some(&reference_number))?;
+            transaction.commit()?;
+            self.last_rowid = Some(copy_id);
+            return Ok(())
         }
 
-        pub(crate) fn edit_attachment_pages(&mut self, attachment_id: u32, file_paths: Vec<PathBuf>) -> Result<(), rusqlite::Error> {
-            let transaction = self.conn.transaction().expect("Error creating transaction");
-            transaction.execute("DELETE FROM page WHERE attachment_id = ?1", (attachment_id,))?;
-            for path in file_paths {
-                transaction.execute("INSERT INTO page (file_path, attachment_id) VALUES (?1, ?2)", (path.to_string_lossy(), attachment_id))?;
+        /// Merges `source_id` into `document_id`: the kept document takes the resolved details, and the attachments,
+        /// tags and links of the source. The emptied source goes to the trash. Page files do not move, as attachments
+        /// refer to them by checksum.
+        /// With `expected_versions`, the versions of the kept document and the source, fails with a conflict (see
+        /// `is_conflict`) if either changed since.
+        pub(crate) fn merge_documents(&mut self, document_id: u32, source_id: u32, details: &MergedDetails, expected_versions: Option<(u32, u32)>) -> Result<(), rusqlite::Error> {
+            let transaction = self.conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+            let (old_type, old_comment, old_document_date, old_due_date): (Option<String>, Option<String>, Option<String>, Option<String>) = transaction.query_row(
+                "SELECT document_type, comment, document_date, due_date FROM document WHERE document_id = ?1 AND (?2 IS NULL OR version = ?2)", (document_id, expected_versions.map(|(version, _)| version)),
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+            )?;
+            transaction.query_row(
+                "SELECT document_id FROM document WHERE document_id = ?1 AND (?2 IS NULL OR version = ?2)", (source_id, expected_versions.map(|(_, version)| version)),
+                |row| row.get::<_, u32>(0)
+            )?;
+            let document_date = details.document_date.map(format_date);
+            let due_date = details.due_date.map(format_date);
+            transaction.execute(
+                "UPDATE document SET document_type = ?1, comment = ?2, document_date = ?3, due_date = ?4 WHERE document_id = ?5",
+                (&details.document_type, &details.comment, &document_date, &due_date, document_id)
+            )?;
+            record_edit(&transaction, document_id, None, "document_type", &old_type.unwrap_or_default(), &details.document_type)?;
+            record_edit(&transaction, document_id, None, "comment", &old_comment.unwrap_or_default(), &details.comment)?;
+            record_edit(&transaction, document_id, None, "document_date", &old_document_date.unwrap_or_default(), &document_date.unwrap_or_default())?;
+            record_edit(&transaction, document_id, None, "due_date", &old_due_date.unwrap_or_default(), &due_date.unwrap_or_default())?;
+            save_field_values(&transaction, document_id, details.field_values.clone())?;
+
+            let attachment_ids: Vec<u32> = transaction.prepare("SELECT attachment_id FROM attachment WHERE document_id = ?1")?
+                .query_map((source_id,), |row| row.get(0))?
+                .collect::<Result<_, _>>()?;
+            move_attachments(&transaction, &attachment_ids, source_id, document_id)?;
+            transaction.execute("INSERT OR IGNORE INTO document_tag (document_id, tag_id) SELECT ?1, tag_id FROM document_tag WHERE document_id = ?2", (document_id, source_id))?;
+            // Links between the two documents would point at themselves after the merge and are dropped.
+            transaction.execute("UPDATE OR IGNORE document_link SET source_id = ?1 WHERE source_id = ?2 AND target_id != ?1", (document_id, source_id))?;
+            transaction.execute("UPDATE OR IGNORE document_link SET target_id = ?1 WHERE target_id = ?2 AND source_id != ?1", (document_id, source_id))?;
+            transaction.execute("DELETE FROM document_link WHERE source_id = ?1 OR target_id = ?1", (source_id,))?;
+
+            transaction.execute("UPDATE document SET deleted_at = unixepoch('now') WHERE document_id = ?1", (source_id,))?;
+            let source_number = document_number(&transaction, source_id)?;
+            let kept_number = document_number(&transaction, document_id)?;
+            for logged_document_id in [source_id, document_id] {
+                record_change(&transaction, logged_document_id, None, "merged", Some("document_number"), Some(&source_number), Some(&kept_number))?;
+            }
+            transaction.commit()
+        }
+
+        /// Moves the attachments into a new document numbered `document_number`, which starts out as a draft with the
+        /// type, comment, dates, custom fields and tags of the document they came from.
+        /// With `expected_version`, fails with a conflict (see `is_conflict`) if the document changed since.
+        pub(crate) fn split_document(&mut self, document_id: u32, attachment_ids: &[u32], document_number: String, expected_version: Option<u32>) -> Result<(), rusqlite::Error> {
+            let transaction = self.conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+            transaction.query_row(
+                "SELECT document_id FROM document WHERE document_id = ?1 AND (?2 IS NULL OR version = ?2)", (document_id, expected_version),
+                |row| row.get::<_, u32>(0)
+            )?;
+            transaction.execute(
+                "INSERT INTO document (document_number, document_type, comment, document_date, due_date)
+                SELECT ?1, document_type, comment, document_date, due_date FROM document WHERE document_id = ?2",
+                (&document_number, document_id)
+            )?;
+            let new_document_id = transaction.last_insert_rowid() as u32;
+            record_change(&transaction, new_document_id, None, "created", Some("document_number"), None, Some(&document_number))?;
+            transaction.execute("INSERT INTO field_value (document_id, field_id, value) SELECT ?1, field_id, value FROM field_value WHERE document_id = ?2", (new_document_id, document_id))?;
+            transaction.execute("INSERT INTO document_tag (document_id, tag_id) SELECT ?1, tag_id FROM document_tag WHERE document_id = ?2", (new_document_id, document_id))?;
+            move_attachments(&transaction, attachment_ids, document_id, new_document_id)?;
+            let old_number = self::document_number(&transaction, document_id)?;
+            for logged_document_id in [document_id, new_document_id] {
+                record_change(&transaction, logged_document_id, None, "split", Some("document_number"), Some(&old_number), Some(&document_number))?;
+            }
+            transaction.commit()?;
+            self.last_rowid = Some(new_document_id as i64);
+            return Ok(())
+        }
+
+        pub(crate) fn read_saved_views(&self) -> Result<Vec<SavedView>, rusqlite::Error> {
+            let mut stmt = self.conn.prepare("SELECT view_id, name, query, sort_key, descending, group_by FROM saved_view ORDER BY name COLLATE NOCASE")?;
+            let view_data = stmt.query_map([], |row| {
+                Ok(SavedView {
+                    view_id: row.get(0)?,
+                    name: row.get(1)?,
+                    query: row.get(2)?,
+                    sort_key: SortKey::from_str(&row.get::<_, String>(3)?),
+                    descending: row.get(4)?,
+                    group_by: GroupBy::from_str(&row.get::<_, String>(5)?)
+                })
+            })?;
+            return view_data.collect()
+        }
+
+        /// Stores the view under its name, replacing a view of the same name.
+        pub(crate) fn save_view(&mut self, name: String, query: String, sort_key: SortKey, descending: bool, group_by: GroupBy) -> Result<usize, rusqlite::Error> {
+            let result = self.conn.execute(
+                "INSERT INTO saved_view (name, query, sort_key, descending, group_by) VALUES (?1, ?2, ?3, ?4, ?5)
+                ON CONFLICT (name) DO UPDATE SET query = excluded.query, sort_key = excluded.sort_key, descending = excluded.descending, group_by = excluded.group_by",
+                (&name, &query, sort_key.as_str(), descending, group_by.as_str())
+            )?;
+            self.last_rowid = Some(self.conn.query_row("SELECT view_id FROM saved_view WHERE name = ?1", (&name,), |row| row.get(0))?);
+            return Ok(result)
+        }
+
+        pub(crate) fn delete_saved_view(&mut self, view_id: u32) -> Result<usize, rusqlite::Error> {
+            return self.conn.execute("DELETE FROM saved_view WHERE view_id = ?1", (view_id,))
+        }
+
+        /// The attachment outside the trash that has the reference number.
+        pub(crate) fn find_reference_number(&self, reference_number: &str) -> Result<Option<u32>, rusqlite::Error> {
+            return self.conn.query_row(
+                "SELECT attachment_id FROM attachment WHERE reference_number = ?1 AND deleted_at IS NULL", (reference_number,),
+                |row| row.get(0)
+            ).optional()
+        }
+
+        /// Updates the document's details. With `expected_version`, fails with a conflict (see `is_conflict`) if the
+        /// document changed since that version was read.
+        pub(crate) fn edit_document_details(&mut self, document_id: u32, details: &DocumentDetails, expected_version: Option<u32>) -> Result<usize, rusqlite::Error> {
+            let transaction = self.conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+            let (old_number, old_type, old_comment, old_document_date, old_due_date): (String, Option<String>, Option<String>, Option<String>, Option<String>) = transaction.query_row(
+                "SELECT document_number, document_type, comment, document_date, due_date FROM document WHERE document_id = ?1 AND (?2 IS NULL OR version = ?2)", (document_id, expected_version),
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?))
+            )?;
+            let document_date = details.document_date.map(format_date);
+            let due_date = details.due_date.map(format_date);
+            let result = transaction.execute(
+                "UPDATE document SET document_number = ?1, document_type = ?2, comment = ?3, document_date = ?4, due_date = ?5 WHERE document_id = ?6",
+                (&details.document_number, &details.document_type, &details.comment, &document_date, &due_date, document_id)
+            )?;
+            record_edit(&transaction, document_id, None, "document_number", &old_number, &details.document_number)?;
+            record_edit(&transaction, document_id, None, "document_type", &old_type.unwrap_or_default(), &details.document_type)?;
+            record_edit(&transaction, document_id, None, "comment", &old_comment.unwrap_or_default(), &details.comment)?;
+            record_edit(&transaction, document_id, None, "document_date", &old_document_date.unwrap_or_default(), &document_date.unwrap_or_default())?;
+            record_edit(&transaction, document_id, None, "due_date", &old_due_date.unwrap_or_default(), &due_date.unwrap_or_default())?;
+            save_field_values(&transaction, document_id, details.field_values.clone())?;
+            transaction.commit()?;
+            return Ok(result)
+        }
+
+        /// The links of the document in both directions. Links to documents in the trash are left out until they are restored.
+        pub(crate) fn read_document_links(&self, document_id: u32) -> Result<Vec<DocumentLink>, rusqlite::Error> {
+            let mut stmt = self.conn.prepare("SELECT document_link.link_id, document_link.kind, document_link.source_id, document.document_id, document.document_number
+                FROM document_link JOIN document ON document.document_id = CASE WHEN document_link.source_id = ?1 THEN document_link.target_id ELSE document_link.source_id END
+                WHERE (document_link.source_id = ?1 OR document_link.target_id = ?1) AND document.deleted_at IS NULL
+                ORDER BY document_link.link_id")?;
+            let link_data = stmt.query_map([document_id], |row| {
+                Ok(DocumentLink {
+                    link_id: row.get(0)?,
+                    kind: LinkKind::from_str(&row.get::<_, String>(1)?),
+                    outgoing: row.get::<_, u32>(2)? == document_id,
+                    other_document_id: row.get(3)?,
+                    other_document_number: Arc::new(row.get(4)?)
+                })
+            })?;
+            return link_data.collect()
+        }
+
+        /// Links `source_id` to `target_id`, recording the new link on both documents. Fails if the link already exists.
+        pub(crate) fn add_document_link(&mut self, source_id: u32, target_id: u32, kind: LinkKind) -> Result<(), rusqlite::Error> {
+            let transaction = self.conn.transaction()?;
+            transaction.execute("INSERT INTO document_link (source_id, target_id, kind) VALUES (?1, ?2, ?3)", (source_id, target_id, kind.as_str()))?;
+            record_link(&transaction, source_id, target_id, kind, true)?;
+            transaction.commit()
+        }
+
+        pub(crate) fn remove_document_link(&mut self, link_id: u32) -> Result<(), rusqlite::Error> {
+            let transaction = self.conn.transaction()?;
+            let (source_id, target_id, kind): (u32, u32, String) = transaction.query_row(
+                "SELECT source_id, target_id, kind FROM document_link WHERE link_id = ?1", (link_id,),
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+            )?;
+            transaction.execute("DELETE FROM document_link WHERE link_id = ?1", (link_id,))?;
+            record_link(&transaction, source_id, target_id, LinkKind::from_str(&kind), false)?;
+            transaction.commit()
+        }
+
+        /// Moves the document to `status` and records the transition. Whether the transition is allowed is up to the caller.
+        /// With `expected_version`, fails with a conflict (see `is_conflict`) if the document changed since.
+        pub(crate) fn change_document_status(&mut self, document_id: u32, status: DocumentStatus, expected_version: Option<u32>) -> Result<(), rusqlite::Error> {
+            let transaction = self.conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+            let old_status: String = transaction.query_row(
+                "SELECT status FROM document WHERE document_id = ?1 AND (?2 IS NULL OR version = ?2)", (document_id, expected_version),
+                |row| row.get(0)
+            )?;
+            transaction.execute("UPDATE document SET status = ?1, status_changed_at = unixepoch('now') WHERE document_id = ?2", (status.as_str(), document_id))?;
+            record_edit(&transaction, document_id, None, "status", &old_status, status.as_str())?;
+            transaction.commit()
+        }
+
+        /// Records that a locked document was unlocked for editing.
+        pub(crate) fn record_unlock(&mut self, document_id: u32) -> Result<(), rusqlite::Error> {
+            return record_change(&self.conn, document_id, None, "unlocked", None, None, None)
+        }
+
+        /// Updates the attachment's details and, when given, replaces its pages, all in one transaction. With
+        /// `expected_version`, fails with a conflict (see `is_conflict`) if the attachment changed since that version was read.
+        pub(crate) fn save_attachment(&mut self, attachment_id: u32, reference_number: String, comment: String, pages: Option<Vec<(PathBuf, String)>>, expected_version: Option<u32>) -> Result<(), rusqlite::Error> {
+            return self.write_attachment(attachment_id, reference_number, comment, pages, expected_version, false)
+        }
+
+        /// Like `save_attachment`, but for going back to pages the attachment had before, as undo and redo do: the
+        /// version that kept those pages takes the replaced ones instead of a new version being added.
+        pub(crate) fn swap_attachment_pages(&mut self, attachment_id: u32, reference_number: String, comment: String, pages: Vec<(PathBuf, String)>, expected_version: Option<u32>) -> Result<(), rusqlite::Error> {
+            return self.write_attachment(attachment_id, reference_number, comment, Some(pages), expected_version, true)
+        }
+
+        fn write_attachment(&mut self, attachment_id: u32, reference_number: String, comment: String, pages: Option<Vec<(PathBuf, String)>>, expected_version: Option<u32>, swap_version: bool) -> Result<(), rusqlite::Error> {
+            let transaction = self.conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+            let (document_id, old_reference_number, old_comment): (u32, String, Option<String>) = transaction.query_row(
+                "SELECT document_id, reference_number, comment FROM attachment WHERE attachment_id = ?1 AND (?2 IS NULL OR version = ?2)", (attachment_id, expected_version),
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+            )?;
+            transaction.execute("UPDATE attachment SET reference_number = ?1, comment = ?2 WHERE attachment_id = ?3", (&reference_number, &comment, attachment_id))?;
+            record_edit(&transaction, document_id, Some(attachment_id), "reference_number", &old_reference_number, &reference_number)?;
+            record_edit(&transaction, document_id, Some(attachment_id), "comment", &old_comment.unwrap_or_default(), &comment)?;
+            if let Some(pages) = pages {
+                let old_checksums = page_checksums(&transaction, attachment_id)?;
+                let new_checksums: Vec<String> = pages.iter().map(|(_, checksum)| checksum.clone()).collect();
+                let swapped_version = match swap_version && old_checksums != new_checksums {
+                    true => find_version(&transaction, attachment_id, &new_checksums)?,
+                    false => None
+                };
+                if let Some(version_id) = swapped_version {
+                    transaction.execute("DELETE FROM version_page WHERE version_id = ?1", (version_id,))?;
+                    if old_checksums.is_empty() {
+                        transaction.execute("DELETE FROM attachment_version WHERE version_id = ?1", (version_id,))?;
+                    }
+                    else {
+                        transaction.execute("UPDATE attachment_version SET replaced_at = unixepoch('now') WHERE version_id = ?1", (version_id,))?;
+                        transaction.execute(
+                            "INSERT INTO version_page (version_id, position, file_path, checksum)
+                            SELECT ?1, ROW_NUMBER() OVER (ORDER BY page_id), file_path, checksum FROM page WHERE attachment_id = ?2",
+                            (version_id, attachment_id)
+                        )?;
+                    }
+                }
+                // The replaced pages are kept as a version, so they can be compared with or restored later.
+                else if old_checksums != new_checksums && !old_checksums.is_empty() {
+                    transaction.execute("INSERT INTO attachment_version (attachment_id) VALUES (?1)", (attachment_id,))?;
+                    transaction.execute(
+                        "INSERT INTO version_page (version_id, position, file_path, checksum)
+                        SELECT ?1, ROW_NUMBER() OVER (ORDER BY page_id), file_path, checksum FROM page WHERE attachment_id = ?2",
+                        (transaction.last_insert_rowid(), attachment_id)
+                    )?;
+                }
+                transaction.execute("DELETE FROM page WHERE attachment_id = ?1", (attachment_id,))?;
+                for (path, checksum) in &pages {
+                    transaction.execute("INSERT INTO page (file_path, attachment_id, checksum) VALUES (?1, ?2, ?3)", (path.to_string_lossy(), attachment_id, checksum))?;
+                }
+                if old_checksums != new_checksums {
+                    record_change(&transaction, document_id, Some(attachment_id), "edited", Some("pages"), Some(&old_checksums.len().to_string()), Some(&new_checksums.len().to_string()))?;
+                }
             }
             transaction.commit()
         }
@@ -176,16 +1019,562 @@ pub(crate) mod db_module {
             return self.conn.execute("UPDATE attachment SET file_path = ?1 WHERE attachment_id = ?2", (file_path, attachment_id))
         }
 
+        /// Moves the document to the trash. Its attachments go with it, so their numbers are free for reuse as well.
         pub(crate) fn delete_document(&mut self, document_id: u32) -> Result<usize, rusqlite::Error> {
-            return self.conn.execute("DELETE FROM document WHERE document_id = ?1", (document_id.clone(),))
+            let transaction = self.conn.transaction()?;
+            let result = transaction.execute("UPDATE document SET deleted_at = unixepoch('now') WHERE document_id = ?1", (document_id,))?;
+            transaction.execute(
+                "UPDATE attachment SET deleted_at = (SELECT deleted_at FROM document WHERE document_id = ?1) WHERE document_id = ?1 AND deleted_at IS NULL", (document_id,)
+            )?;
+            record_change(&transaction, document_id, None, "deleted", None, None, None)?;
+            transaction.commit()?;
+            return Ok(result)
         }
 
         pub(crate) fn delete_attachment(&mut self, attachment_id: u32) -> Result<usize, rusqlite::Error> {
-            return self.conn.execute("DELETE FROM attachment WHERE attachment_id = ?1", (attachment_id,))
-            // let transaction = self.conn.transaction().expect("Error creating transaction");
-            // transaction.execute("DELETE FROM page WHERE attachment_id = ?1", (attachment_id,))?;
-            // transaction.execute("DELETE FROM attachment WHERE attachment_id = ?1", (attachment_id,))?;
-            // transaction.commit()
+            let transaction = self.conn.transaction()?;
+            let result = transaction.execute("UPDATE attachment SET deleted_at = unixepoch('now') WHERE attachment_id = ?1", (attachment_id,))?;
+            record_change(&transaction, attachment_document_id(&transaction, attachment_id)?, Some(attachment_id), "deleted", None, None, None)?;
+            transaction.commit()?;
+            return Ok(result)
+        }
+
+        /// Takes the document out of the trash with the attachments deleted along with it. Fails with a constraint
+        /// error (see `is_number_taken`) if one of their numbers was given to another item meanwhile.
+        pub(crate) fn restore_document(&mut self, document_id: u32) -> Result<usize, rusqlite::Error> {
+            let transaction = self.conn.transaction()?;
+            transaction.execute(
+                "UPDATE attachment SET deleted_at = NULL WHERE document_id = ?1 AND deleted_at = (SELECT deleted_at FROM document WHERE document_id = ?1)", (document_id,)
+            )?;
+            let result = transaction.execute("UPDATE document SET deleted_at = NULL WHERE document_id = ?1", (document_id,))?;
+            record_change(&transaction, document_id, None, "restored", None, None, None)?;
+            transaction.commit()?;
+            return Ok(result)
+        }
+
+        /// Fails with a constraint error (see `is_number_taken`) if another attachment got the number meanwhile.
+        pub(crate) fn restore_attachment(&mut self, attachment_id: u32) -> Result<usize, rusqlite::Error> {
+            let transaction = self.conn.transaction()?;
+            let result = transaction.execute("UPDATE attachment SET deleted_at = NULL WHERE attachment_id = ?1", (attachment_id,))?;
+            record_change(&transaction, attachment_document_id(&transaction, attachment_id)?, Some(attachment_id), "restored", None, None, None)?;
+            transaction.commit()?;
+            return Ok(result)
+        }
+
+        /// Permanently removes the document with its attachments and pages. Page files are left for
+        /// `remove_unreferenced_objects`, as other pages may share them.
+        pub(crate) fn purge_document(&mut self, document_id: u32) -> Result<(), rusqlite::Error> {
+            let transaction = self.conn.transaction()?;
+            transaction.execute("DELETE FROM version_page WHERE version_id IN (
+                SELECT version_id FROM attachment_version WHERE attachment_id IN (SELECT attachment_id FROM attachment WHERE document_id = ?1))", (document_id,))?;
+            transaction.execute("DELETE FROM attachment_version WHERE attachment_id IN (SELECT attachment_id FROM attachment WHERE document_id = ?1)", (document_id,))?;
+            transaction.execute("DELETE FROM attachment_tag WHERE attachment_id IN (SELECT attachment_id FROM attachment WHERE document_id = ?1)", (document_id,))?;
+            transaction.execute("DELETE FROM document_tag WHERE document_id = ?1", (document_id,))?;
+            transaction.execute("DELETE FROM field_value WHERE document_id = ?1", (document_id,))?;
+            transaction.execute("DELETE FROM document_link WHERE source_id = ?1 OR target_id = ?1", (document_id,))?;
+            transaction.execute("DELETE FROM page WHERE attachment_id IN (SELECT attachment_id FROM attachment WHERE document_id = ?1)", (document_id,))?;
+            transaction.execute("DELETE FROM attachment WHERE document_id = ?1", (document_id,))?;
+            transaction.execute("DELETE FROM document WHERE document_id = ?1", (document_id,))?;
+            record_change(&transaction, document_id, None, "purged", None, None, None)?;
+            transaction.commit()
+        }
+
+        pub(crate) fn purge_attachment(&mut self, attachment_id: u32) -> Result<(), rusqlite::Error> {
+            let transaction = self.conn.transaction()?;
+            let document_id = attachment_document_id(&transaction, attachment_id)?;
+            transaction.execute("DELETE FROM version_page WHERE version_id IN (SELECT version_id FROM attachment_version WHERE attachment_id = ?1)", (attachment_id,))?;
+            transaction.execute("DELETE FROM attachment_version WHERE attachment_id = ?1", (attachment_id,))?;
+            transaction.execute("DELETE FROM attachment_tag WHERE attachment_id = ?1", (attachment_id,))?;
+            transaction.execute("DELETE FROM page WHERE attachment_id = ?1", (attachment_id,))?;
+            transaction.execute("DELETE FROM attachment WHERE attachment_id = ?1", (attachment_id,))?;
+            record_change(&transaction, document_id, Some(attachment_id), "purged", None, None, None)?;
+            transaction.commit()
+        }
+
+        /// Deleted documents, and deleted attachments of documents that are not themselves in the trash.
+        pub(crate) fn read_trash(&self) -> Result<Vec<TrashEntry>, rusqlite::Error> {
+            let mut stmt = self.conn.prepare(
+                "SELECT 'document', document_id, document_number, '', deleted_at FROM document WHERE deleted_at IS NOT NULL
+                UNION ALL
+                SELECT 'attachment', attachment.attachment_id, attachment.reference_number, document.document_number, attachment.deleted_at
+                FROM attachment JOIN document ON document.document_id = attachment.document_id
+                WHERE attachment.deleted_at IS NOT NULL AND document.deleted_at IS NULL
+                ORDER BY 5 DESC"
+            )?;
+            let trash_data = stmt.query_map([], |row| {
+                let kind: String = row.get(0)?;
+                Ok(TrashEntry {
+                    kind: if kind == "document" { TrashKind::Document } else { TrashKind::Attachment },
+                    id: row.get(1)?,
+                    name: row.get(2)?,
+                    document_number: row.get(3)?,
+                    deleted_at: row.get(4)?
+                })
+            })?;
+
+            let mut entries: Vec<TrashEntry> = Vec::new();
+            for entry in trash_data {
+                entries.push(entry?);
+            }
+
+            return Ok(entries)
+        }
+
+        /// Purges everything that has been in the trash for longer than `retention_days`. With 0 days nothing
+        /// expires, and the trash is only emptied by hand.
+        pub(crate) fn purge_expired(&mut self, retention_days: u32) -> Result<usize, rusqlite::Error> {
+            if retention_days == 0 {
+                return Ok(0)
+            }
+            let cutoff = format!("-{} days", retention_days);
+            let transaction = self.conn.transaction()?;
+            transaction.execute("INSERT INTO audit_log (document_id, attachment_id, action, user_name, session)
+                SELECT document_id, attachment_id, 'purged', ?2, ?3 FROM attachment
+                WHERE deleted_at < unixepoch('now', ?1) AND document_id NOT IN (SELECT document_id FROM document WHERE deleted_at < unixepoch('now', ?1))
+                UNION ALL
+                SELECT document_id, NULL, 'purged', ?2, ?3 FROM document WHERE deleted_at < unixepoch('now', ?1)", (&cutoff, os_user_name(), session_id()))?;
+            transaction.execute("DELETE FROM version_page WHERE version_id IN (
+                SELECT version_id FROM attachment_version WHERE attachment_id IN (
+                SELECT attachment_id FROM attachment WHERE deleted_at < unixepoch('now', ?1)
+                OR document_id IN (SELECT document_id FROM document WHERE deleted_at < unixepoch('now', ?1))))", (&cutoff,))?;
+            transaction.execute("DELETE FROM attachment_version WHERE attachment_id IN (
+                SELECT attachment_id FROM attachment WHERE deleted_at < unixepoch('now', ?1)
+                OR document_id IN (SELECT document_id FROM document WHERE deleted_at < unixepoch('now', ?1)))", (&cutoff,))?;
+            transaction.execute("DELETE FROM attachment_tag WHERE attachment_id IN (
+                SELECT attachment_id FROM attachment WHERE deleted_at < unixepoch('now', ?1)
+                OR document_id IN (SELECT document_id FROM document WHERE deleted_at < unixepoch('now', ?1)))", (&cutoff,))?;
+            transaction.execute("DELETE FROM document_tag WHERE document_id IN (SELECT document_id FROM document WHERE deleted_at < unixepoch('now', ?1))", (&cutoff,))?;
+            transaction.execute("DELETE FROM field_value WHERE document_id IN (SELECT document_id FROM document WHERE deleted_at < unixepoch('now', ?1))", (&cutoff,))?;
+            transaction.execute("DELETE FROM document_link WHERE source_id IN (SELECT document_id FROM document WHERE deleted_at < unixepoch('now', ?1))
+                OR target_id IN (SELECT document_id FROM document WHERE deleted_at < unixepoch('now', ?1))", (&cutoff,))?;
+            transaction.execute("DELETE FROM page WHERE attachment_id IN (
+                SELECT attachment_id FROM attachment WHERE deleted_at < unixepoch('now', ?1)
+                OR document_id IN (SELECT document_id FROM document WHERE deleted_at < unixepoch('now', ?1)))", (&cutoff,))?;
+            transaction.execute("DELETE FROM attachment WHERE deleted_at < unixepoch('now', ?1)
+                OR document_id IN (SELECT document_id FROM document WHERE deleted_at < unixepoch('now', ?1))", (&cutoff,))?;
+            let purged = transaction.execute("DELETE FROM document WHERE deleted_at < unixepoch('now', ?1)", (&cutoff,))?;
+            transaction.commit()?;
+            return Ok(purged)
+        }
+
+        pub(crate) fn read_page_records(&self) -> Result<Vec<PageRecord>, rusqlite::Error> {
+            let mut stmt = self.conn.prepare("SELECT page_id, file_path, attachment_id, checksum FROM page ORDER BY page_id")?;
+            let page_data = stmt.query_map([], |row| {
+                Ok(PageRecord {
+                    page_id: row.get(0)?,
+                    file_path: row.get(1)?,
+                    attachment_id: row.get(2)?,
+                    checksum: row.get(3)?
+                })
+            })?;
+
+            let mut pages: Vec<PageRecord> = Vec::new();
+            for page in page_data {
+                pages.push(page?);
+            }
+
+            return Ok(pages)
+        }
+
+        pub(crate) fn read_tags(&self) -> Result<Vec<Tag>, rusqlite::Error> {
+            let mut stmt = self.conn.prepare("SELECT tag_id, name, color FROM tag ORDER BY name")?;
+            let tag_data = stmt.query_map([], |row| Ok(Tag::new(row.get(0)?, Arc::new(row.get(1)?), Arc::new(row.get(2)?))))?;
+            return tag_data.collect()
+        }
+
+        fn read_tags_of(&self, query: &str, id: u32) -> Result<Vec<Tag>, rusqlite::Error> {
+            let mut stmt = self.conn.prepare(query)?;
+            let tag_data = stmt.query_map([id], |row| Ok(Tag::new(row.get(0)?, Arc::new(row.get(1)?), Arc::new(row.get(2)?))))?;
+            return tag_data.collect()
+        }
+
+        /// Tags the document, creating the tag if no tag has that name yet. Names are matched case-insensitively.
+        pub(crate) fn add_document_tag(&mut self, document_id: u32, name: String) -> Result<(), rusqlite::Error> {
+            let transaction = self.conn.transaction()?;
+            let tag_id = find_or_create_tag(&transaction, name.trim())?;
+            if transaction.execute("INSERT OR IGNORE INTO document_tag (document_id, tag_id) VALUES (?1, ?2)", (document_id, tag_id))? > 0 {
+                record_change(&transaction, document_id, None, "edited", Some("tags"), None, Some(&tag_name(&transaction, tag_id)?))?;
+            }
+            transaction.commit()
+        }
+
+        pub(crate) fn remove_document_tag(&mut self, document_id: u32, tag_id: u32) -> Result<(), rusqlite::Error> {
+            let transaction = self.conn.transaction()?;
+            if transaction.execute("DELETE FROM document_tag WHERE document_id = ?1 AND tag_id = ?2", (document_id, tag_id))? > 0 {
+                record_change(&transaction, document_id, None, "edited", Some("tags"), Some(&tag_name(&transaction, tag_id)?), None)?;
+            }
+            transaction.commit()
+        }
+
+        pub(crate) fn add_attachment_tag(&mut self, attachment_id: u32, name: String) -> Result<(), rusqlite::Error> {
+            let transaction = self.conn.transaction()?;
+            let tag_id = find_or_create_tag(&transaction, name.trim())?;
+            if transaction.execute("INSERT OR IGNORE INTO attachment_tag (attachment_id, tag_id) VALUES (?1, ?2)", (attachment_id, tag_id))? > 0 {
+                record_change(&transaction, attachment_document_id(&transaction, attachment_id)?, Some(attachment_id), "edited", Some("tags"), None, Some(&tag_name(&transaction, tag_id)?))?;
+            }
+            transaction.commit()
+        }
+
+        pub(crate) fn remove_attachment_tag(&mut self, attachment_id: u32, tag_id: u32) -> Result<(), rusqlite::Error> {
+            let transaction = self.conn.transaction()?;
+            if transaction.execute("DELETE FROM attachment_tag WHERE attachment_id = ?1 AND tag_id = ?2", (attachment_id, tag_id))? > 0 {
+                record_change(&transaction, attachment_document_id(&transaction, attachment_id)?, Some(attachment_id), "edited", Some("tags"), Some(&tag_name(&transaction, tag_id)?), None)?;
+            }
+            transaction.commit()
+        }
+
+        /// Fails with a constraint error if another tag already has the name; merge the tags instead.
+        pub(crate) fn rename_tag(&mut self, tag_id: u32, name: String) -> Result<usize, rusqlite::Error> {
+            return self.conn.execute("UPDATE tag SET name = ?1 WHERE tag_id = ?2", (name.trim(), tag_id))
+        }
+
+        pub(crate) fn edit_tag_color(&mut self, tag_id: u32, color: String) -> Result<usize, rusqlite::Error> {
+            return self.conn.execute("UPDATE tag SET color = ?1 WHERE tag_id = ?2", (color, tag_id))
+        }
+
+        /// Moves every use of `source_tag_id` over to `target_tag_id` and deletes the source tag.
+        pub(crate) fn merge_tags(&mut self, source_tag_id: u32, target_tag_id: u32) -> Result<(), rusqlite::Error> {
+            let transaction = self.conn.transaction()?;
+            transaction.execute("INSERT OR IGNORE INTO document_tag (document_id, tag_id) SELECT document_id, ?2 FROM document_tag WHERE tag_id = ?1", (source_tag_id, target_tag_id))?;
+            transaction.execute("INSERT OR IGNORE INTO attachment_tag (attachment_id, tag_id) SELECT attachment_id, ?2 FROM attachment_tag WHERE tag_id = ?1", (source_tag_id, target_tag_id))?;
+            transaction.execute("DELETE FROM document_tag WHERE tag_id = ?1", (source_tag_id,))?;
+            transaction.execute("DELETE FROM attachment_tag WHERE tag_id = ?1", (source_tag_id,))?;
+            transaction.execute("DELETE FROM tag WHERE tag_id = ?1", (source_tag_id,))?;
+            transaction.commit()
+        }
+
+        pub(crate) fn delete_tag(&mut self, tag_id: u32) -> Result<(), rusqlite::Error> {
+            let transaction = self.conn.transaction()?;
+            transaction.execute("DELETE FROM document_tag WHERE tag_id = ?1", (tag_id,))?;
+            transaction.execute("DELETE FROM attachment_tag WHERE tag_id = ?1", (tag_id,))?;
+            transaction.execute("DELETE FROM tag WHERE tag_id = ?1", (tag_id,))?;
+            transaction.commit()
+        }
+
+        pub(crate) fn read_document_types(&self) -> Result<Vec<DocumentType>, rusqlite::Error> {
+            let mut stmt = self.conn.prepare("SELECT type_id, name, number_pattern, auto_number FROM document_type ORDER BY name")?;
+            let type_data = stmt.query_map([], |row| Ok((row.get::<_, u32>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?, row.get::<_, bool>(3)?)))?;
+
+            let mut document_types: Vec<DocumentType> = Vec::new();
+            for document_type in type_data {
+                let (type_id, name, number_pattern, auto_number) = document_type?;
+                let mut field_stmt = self.conn.prepare("SELECT field_id, name, kind, required, options FROM type_field WHERE type_id = ?1 ORDER BY field_id")?;
+                let field_data = field_stmt.query_map([type_id], |row| {
+                    let options: String = row.get(4)?;
+                    Ok(TypeField {
+                        field_id: row.get(0)?,
+                        name: row.get(1)?,
+                        kind: FieldKind::from_str(&row.get::<_, String>(2)?),
+                        required: row.get(3)?,
+                        options: options.split(',').map(|option| option.trim().to_string()).filter(|option| !option.is_empty()).collect()
+                    })
+                })?;
+                document_types.push(DocumentType::new(type_id, Arc::new(name), field_data.collect::<Result<Vec<TypeField>, rusqlite::Error>>()?, Arc::new(number_pattern), auto_number));
+            }
+
+            return Ok(document_types)
+        }
+
+        pub(crate) fn new_document_type(&mut self, name: String) -> Result<usize, rusqlite::Error> {
+            let result = self.conn.execute("INSERT INTO document_type (name) VALUES (?1)", (name.trim(),));
+            self.last_rowid = Some(self.conn.last_insert_rowid());
+            return result
+        }
+
+        pub(crate) fn edit_number_pattern(&mut self, type_id: u32, number_pattern: String, auto_number: bool) -> Result<usize, rusqlite::Error> {
+            return self.conn.execute("UPDATE document_type SET number_pattern = ?1, auto_number = ?2 WHERE type_id = ?3", (number_pattern.trim(), auto_number, type_id))
+        }
+
+        /// The document outside the trash that has the number.
+        pub(crate) fn find_document_number(&self, document_number: &str) -> Result<Option<u32>, rusqlite::Error> {
+            return self.conn.query_row(
+                "SELECT document_id FROM document WHERE document_number = ?1 AND deleted_at IS NULL", (document_number,),
+                |row| row.get(0)
+            ).optional()
+        }
+
+        /// Renames the type and every document that uses it. Fails if another type already has the name.
+        pub(crate) fn rename_document_type(&mut self, type_id: u32, name: String) -> Result<(), rusqlite::Error> {
+            let transaction = self.conn.transaction()?;
+            let old_name: String = transaction.query_row("SELECT name FROM document_type WHERE type_id = ?1", (type_id,), |row| row.get(0))?;
+            transaction.execute("UPDATE document_type SET name = ?1 WHERE type_id = ?2", (name.trim(), type_id))?;
+            transaction.execute("UPDATE document SET document_type = ?1 WHERE document_type = ?2 COLLATE NOCASE", (name.trim(), old_name))?;
+            transaction.commit()
+        }
+
+        /// Moves the documents of `source_type_id` to `target_type_id`. Fields the target has under the same name
+        /// keep their values; the source's other fields are moved to the target.
+        pub(crate) fn merge_document_types(&mut self, source_type_id: u32, target_type_id: u32) -> Result<(), rusqlite::Error> {
+            let transaction = self.conn.transaction()?;
+            let source_name: String = transaction.query_row("SELECT name FROM document_type WHERE type_id = ?1", (source_type_id,), |row| row.get(0))?;
+            let target_name: String = transaction.query_row("SELECT name FROM document_type WHERE type_id = ?1", (target_type_id,), |row| row.get(0))?;
+            transaction.execute(
+                "INSERT OR IGNORE INTO field_value (document_id, field_id, value)
+                SELECT field_value.document_id, target.field_id, field_value.value
+                FROM field_value
+                JOIN type_field AS source ON source.field_id = field_value.field_id
+                JOIN type_field AS target ON target.type_id = ?2 AND target.name = source.name COLLATE NOCASE
+                WHERE source.type_id = ?1", (source_type_id, target_type_id)
+            )?;
+            transaction.execute(
+                "DELETE FROM field_value WHERE field_id IN (
+                SELECT source.field_id FROM type_field AS source
+                JOIN type_field AS target ON target.type_id = ?2 AND target.name = source.name COLLATE NOCASE
+                WHERE source.type_id = ?1)", (source_type_id, target_type_id)
+            )?;
+            transaction.execute(
+                "DELETE FROM type_field WHERE type_id = ?1 AND name COLLATE NOCASE IN (SELECT name FROM type_field WHERE type_id = ?2)", (source_type_id, target_type_id)
+            )?;
+            transaction.execute("UPDATE type_field SET type_id = ?2 WHERE type_id = ?1", (source_type_id, target_type_id))?;
+            transaction.execute("UPDATE document SET document_type = ?1 WHERE document_type = ?2 COLLATE NOCASE", (target_name, source_name))?;
+            transaction.execute("DELETE FROM document_type WHERE type_id = ?1", (source_type_id,))?;
+            transaction.commit()
+        }
+
+        /// Deletes the type with its fields and their values. Its documents are left without a type.
+        pub(crate) fn delete_document_type(&mut self, type_id: u32) -> Result<(), rusqlite::Error> {
+            let transaction = self.conn.transaction()?;
+            let name: String = transaction.query_row("SELECT name FROM document_type WHERE type_id = ?1", (type_id,), |row| row.get(0))?;
+            transaction.execute("DELETE FROM field_value WHERE field_id IN (SELECT field_id FROM type_field WHERE type_id = ?1)", (type_id,))?;
+            transaction.execute("DELETE FROM type_field WHERE type_id = ?1", (type_id,))?;
+            transaction.execute("UPDATE document SET document_type = '' WHERE document_type = ?1 COLLATE NOCASE", (name,))?;
+            transaction.execute("DELETE FROM document_type WHERE type_id = ?1", (type_id,))?;
+            transaction.commit()
+        }
+
+        pub(crate) fn new_type_field(&mut self, type_id: u32, field: &TypeField) -> Result<usize, rusqlite::Error> {
+            return self.conn.execute(
+                "INSERT INTO type_field (type_id, name, kind, required, options) VALUES (?1, ?2, ?3, ?4, ?5)",
+                (type_id, field.name.trim(), field.kind.as_str(), field.required, field.options.join(", "))
+            )
+        }
+
+        pub(crate) fn edit_type_field(&mut self, field: &TypeField) -> Result<usize, rusqlite::Error> {
+            return self.conn.execute(
+                "UPDATE type_field SET name = ?1, kind = ?2, required = ?3, options = ?4 WHERE field_id = ?5",
+                (field.name.trim(), field.kind.as_str(), field.required, field.options.join(", "), field.field_id)
+            )
+        }
+
+        pub(crate) fn delete_type_field(&mut self, field_id: u32) -> Result<(), rusqlite::Error> {
+            let transaction = self.conn.transaction()?;
+            transaction.execute("DELETE FROM field_value WHERE field_id = ?1", (field_id,))?;
+            transaction.execute("DELETE FROM type_field WHERE field_id = ?1", (field_id,))?;
+            transaction.commit()
+        }
+
+        /// Earlier page sets of the attachment, newest first.
+        pub(crate) fn read_attachment_versions(&self, attachment_id: u32) -> Result<Vec<AttachmentVersion>, rusqlite::Error> {
+            let mut stmt = self.conn.prepare("SELECT version_id, replaced_at FROM attachment_version WHERE attachment_id = ?1 ORDER BY version_id DESC")?;
+            let version_data = stmt.query_map([attachment_id], |row| Ok((row.get::<_, u32>(0)?, row.get::<_, i64>(1)?)))?;
+
+            let mut versions: Vec<AttachmentVersion> = Vec::new();
+            for version in version_data {
+                let (version_id, replaced_at) = version?;
+                let mut page_stmt = self.conn.prepare("SELECT file_path, checksum FROM version_page WHERE version_id = ?1 ORDER BY position")?;
+                let page_data = page_stmt.query_map([version_id], |row| {
+                    Ok((PathBuf::from(row.get::<_, String>(0)?), row.get::<_, Option<String>>(1)?.unwrap_or_default()))
+                })?;
+                versions.push(AttachmentVersion {
+                    version_id: version_id,
+                    replaced_at: replaced_at,
+                    pages: page_data.collect::<Result<Vec<(PathBuf, String)>, rusqlite::Error>>()?
+                });
+            }
+
+            return Ok(versions)
+        }
+
+        /// File paths used by stored versions, which must survive even when no current page uses them.
+        pub(crate) fn read_version_file_paths(&self) -> Result<Vec<String>, rusqlite::Error> {
+            let mut stmt = self.conn.prepare("SELECT DISTINCT file_path FROM version_page")?;
+            let paths = stmt.query_map([], |row| row.get(0))?;
+            return paths.collect()
+        }
+
+        /// Deletes all but the newest `versions_kept` versions of every attachment. Returns how many were removed.
+        pub(crate) fn purge_old_versions(&mut self, versions_kept: u32) -> Result<usize, rusqlite::Error> {
+            let transaction = self.conn.transaction()?;
+            transaction.execute("CREATE TEMP TABLE expired_version AS
+                SELECT version_id FROM attachment_version AS version
+                WHERE (SELECT COUNT(*) FROM attachment_version AS newer WHERE newer.attachment_id = version.attachment_id AND newer.version_id > version.version_id) >= ?1", (versions_kept,))?;
+            transaction.execute("DELETE FROM version_page WHERE version_id IN (SELECT version_id FROM expired_version)", ())?;
+            let purged = transaction.execute("DELETE FROM attachment_version WHERE version_id IN (SELECT version_id FROM expired_version)", ())?;
+            transaction.execute("DROP TABLE expired_version", ())?;
+            transaction.commit()?;
+            return Ok(purged)
+        }
+
+        pub(crate) fn read_attachment_locations(&self) -> Result<Vec<AttachmentLocation>, rusqlite::Error> {
+            let mut stmt = self.conn.prepare(
+                "SELECT attachment.attachment_id, attachment.reference_number, document.document_id, document.document_number, COUNT(page.page_id)
+                FROM attachment
+                JOIN document ON document.document_id = attachment.document_id
+                LEFT JOIN page ON page.attachment_id = attachment.attachment_id
+                WHERE attachment.deleted_at IS NULL AND document.deleted_at IS NULL
+                GROUP BY attachment.attachment_id"
+            )?;
+            let attachment_data = stmt.query_map([], |row| {
+                Ok(AttachmentLocation {
+                    attachment_id: row.get(0)?,
+                    reference_number: row.get(1)?,
+                    document_id: row.get(2)?,
+                    document_number: row.get(3)?,
+                    page_count: row.get(4)?
+                })
+            })?;
+
+            let mut attachments: Vec<AttachmentLocation> = Vec::new();
+            for attachment in attachment_data {
+                attachments.push(attachment?);
+            }
+
+            return Ok(attachments)
+        }
+
+        pub(crate) fn read_document_numbers(&self) -> Result<Vec<(u32, String)>, rusqlite::Error> {
+            let mut stmt = self.conn.prepare("SELECT document_id, document_number FROM document WHERE deleted_at IS NULL")?;
+            let document_data = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+
+            let mut documents: Vec<(u32, String)> = Vec::new();
+            for document in document_data {
+                documents.push(document?);
+            }
+
+            return Ok(documents)
+        }
+
+        pub(crate) fn add_page(&mut self, attachment_id: u32, file_path: PathBuf, checksum: String) -> Result<usize, rusqlite::Error> {
+            let transaction = self.conn.transaction()?;
+            let result = transaction.execute("INSERT INTO page (file_path, attachment_id, checksum) VALUES (?1, ?2, ?3)", (file_path.to_string_lossy(), attachment_id, &checksum))?;
+            self.last_rowid = Some(transaction.last_insert_rowid());
+            record_change(&transaction, attachment_document_id(&transaction, attachment_id)?, Some(attachment_id), "edited", Some("page"), None, Some(&checksum))?;
+            transaction.commit()?;
+            return Ok(result)
+        }
+
+        pub(crate) fn edit_page_checksum(&mut self, page_id: u32, checksum: String) -> Result<usize, rusqlite::Error> {
+            let transaction = self.conn.transaction()?;
+            let (document_id, attachment_id, old_checksum) = page_owner(&transaction, page_id)?;
+            let result = transaction.execute("UPDATE page SET checksum = ?1 WHERE page_id = ?2", (&checksum, page_id))?;
+            record_edit(&transaction, document_id, Some(attachment_id), "page", &old_checksum.unwrap_or_default(), &checksum)?;
+            transaction.commit()?;
+            return Ok(result)
+        }
+
+        pub(crate) fn edit_page_file(&mut self, page_id: u32, file_path: PathBuf, checksum: String) -> Result<usize, rusqlite::Error> {
+            let transaction = self.conn.transaction()?;
+            let (document_id, attachment_id, old_checksum) = page_owner(&transaction, page_id)?;
+            let result = transaction.execute("UPDATE page SET file_path = ?1, checksum = ?2 WHERE page_id = ?3", (file_path.to_string_lossy(), &checksum, page_id))?;
+            record_edit(&transaction, document_id, Some(attachment_id), "page", &old_checksum.unwrap_or_default(), &checksum)?;
+            transaction.commit()?;
+            return Ok(result)
+        }
+
+        /// Points the pages and versions stored in `old_path` at `new_path`, which holds the same content under the
+        /// checksum `checksum`. Not audited, as no page changes.
+        pub(crate) fn rename_page_object(&mut self, old_path: &str, new_path: &Path, checksum: &str) -> Result<usize, rusqlite::Error> {
+            let transaction = self.conn.transaction()?;
+            let mut renamed = transaction.execute("UPDATE page SET file_path = ?1, checksum = ?2 WHERE file_path = ?3", (new_path.to_string_lossy(), checksum, old_path))?;
+            renamed += transaction.execute("UPDATE version_page SET file_path = ?1, checksum = ?2 WHERE file_path = ?3", (new_path.to_string_lossy(), checksum, old_path))?;
+            transaction.commit()?;
+            return Ok(renamed)
+        }
+
+        pub(crate) fn delete_page(&mut self, page_id: u32) -> Result<usize, rusqlite::Error> {
+            let transaction = self.conn.transaction()?;
+            let (document_id, attachment_id, old_checksum) = page_owner(&transaction, page_id)?;
+            let result = transaction.execute("DELETE FROM page WHERE page_id = ?1", (page_id,))?;
+            record_change(&transaction, document_id, Some(attachment_id), "edited", Some("page"), old_checksum.as_deref(), None)?;
+            transaction.commit()?;
+            return Ok(result)
+        }
+
+        /// The recorded changes to the document and its attachments, newest first.
+        pub(crate) fn read_audit_log(&self, document_id: u32) -> Result<Vec<AuditEntry>, rusqlite::Error> {
+            let mut stmt = self.conn.prepare(
+                "SELECT audit_log.attachment_id, attachment.reference_number, audit_log.action, audit_log.field, audit_log.old_value, audit_log.new_value, audit_log.changed_at, audit_log.user_name
+                FROM audit_log LEFT JOIN attachment ON attachment.attachment_id = audit_log.attachment_id
+                WHERE audit_log.document_id = ?1
+                ORDER BY audit_log.audit_id DESC"
+            )?;
+            let audit_data = stmt.query_map([document_id], |row| {
+                Ok(AuditEntry {
+                    attachment_id: row.get(0)?,
+                    reference_number: row.get(1)?,
+                    action: row.get(2)?,
+                    field: row.get(3)?,
+                    old_value: row.get(4)?,
+                    new_value: row.get(5)?,
+                    changed_at: row.get(6)?,
+                    user_name: row.get(7)?
+                })
+            })?;
+
+            let mut entries: Vec<AuditEntry> = Vec::new();
+            for entry in audit_data {
+                entries.push(entry?);
+            }
+
+            return Ok(entries)
+        }
+
+        /// The id of the newest audit entry, which marks how far the library's changes have been seen.
+        pub(crate) fn last_audit_id(&self) -> Result<i64, rusqlite::Error> {
+            return self.conn.query_row("SELECT IFNULL(MAX(audit_id), 0) FROM audit_log", [], |row| row.get(0))
+        }
+
+        /// The documents other sessions changed after the audit entry `audit_id` up to `until_id`, each with the user
+        /// who changed it last. This session's own changes are left out, as its screens already show them.
+        pub(crate) fn read_changes_since(&self, audit_id: i64, until_id: i64) -> Result<Vec<(u32, String)>, rusqlite::Error> {
+            let mut stmt = self.conn.prepare(
+                "SELECT document_id, user_name FROM audit_log WHERE audit_id IN (
+                    SELECT MAX(audit_id) FROM audit_log WHERE audit_id > ?1 AND audit_id <= ?2 AND session IS NOT ?3 GROUP BY document_id
+                )"
+            )?;
+            let changes = stmt.query_map((audit_id, until_id, session_id()), |row| Ok((row.get(0)?, row.get(1)?)))?;
+            return changes.collect()
+        }
+
+        /// Takes or renews this session's lock on the attachment. Returns the user holding it instead when another
+        /// session does; its lock counts until it goes `LOCK_TIMEOUT` seconds without being renewed. The lock is only
+        /// advisory: saves check versions either way.
+        pub(crate) fn lock_attachment(&mut self, attachment_id: u32) -> Result<Option<String>, rusqlite::Error> {
+            let transaction = self.conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+            let holder: Option<(String, String)> = transaction.query_row(
+                "SELECT session, user_name FROM attachment_lock WHERE attachment_id = ?1 AND heartbeat > unixepoch('now') - ?2", (attachment_id, LOCK_TIMEOUT),
+                |row| Ok((row.get(0)?, row.get(1)?))
+            ).optional()?;
+            if let Some((session, user_name)) = holder && session != session_id() {
+                return Ok(Some(user_name))
+            }
+            transaction.execute(
+                "INSERT OR REPLACE INTO attachment_lock (attachment_id, session, user_name, heartbeat) VALUES (?1, ?2, ?3, unixepoch('now'))",
+                (attachment_id, session_id(), os_user_name())
+            )?;
+            transaction.commit()?;
+            return Ok(None)
+        }
+
+        /// Releases this session's lock on the attachment, if it holds one.
+        pub(crate) fn unlock_attachment(&mut self, attachment_id: u32) -> Result<usize, rusqlite::Error> {
+            return self.conn.execute("DELETE FROM attachment_lock WHERE attachment_id = ?1 AND session = ?2", (attachment_id, session_id()))
+        }
+
+        /// Marks this session as running for `SESSION_TIMEOUT` seconds, and forgets sessions that stopped renewing.
+        pub(crate) fn renew_session(&mut self) -> Result<(), rusqlite::Error> {
+            let transaction = self.conn.transaction()?;
+            transaction.execute("DELETE FROM live_session WHERE heartbeat <= unixepoch('now') - ?1", (SESSION_TIMEOUT,))?;
+            transaction.execute(
+                "INSERT OR REPLACE INTO live_session (session, user_name, heartbeat) VALUES (?1, ?2, unixepoch('now'))",
+                (session_id(), os_user_name())
+            )?;
+            return transaction.commit()
+        }
+
+        /// The sessions running on the library, this one included, each with its user.
+        pub(crate) fn read_live_sessions(&self) -> Result<Vec<(String, String)>, rusqlite::Error> {
+            return live_sessions(&self.conn)
         }
 
         pub(crate) fn last_rowid(&self) -> Option<i64> {
@@ -193,12 +1582,585 @@ pub(crate) mod db_module {
         }
     }
 
-    
+    /// Whether `./data.db` is encrypted, in which case it has to be unlocked before anything reads it. A plain
+    /// SQLite file starts with a fixed header; an encrypted one does not.
+    pub(crate) fn library_is_encrypted() -> bool {
+        let mut header = [0u8; 16];
+        return match File::open("./data.db").and_then(|mut file| file.read_exact(&mut header)) {
+            Ok(_) => &header != b"SQLite format 3\0",
+            Err(_) => false
+        }
+    }
+
+    /// Opens the encrypted library with the passphrase and loads its page key. The error is meant for the user.
+    pub(crate) fn unlock_library(passphrase: &str) -> Result<(), String> {
+        let conn = Connection::open("./data.db").map_err(|err| format!("Error opening library: {}", err))?;
+        conn.pragma_update(None, "key", passphrase).map_err(|err| format!("Error opening library: {}", err))?;
+        let page_key: Vec<u8> = conn.query_row("SELECT page_key FROM library_key", [], |row| row.get(0)).map_err(|err| {
+            match err.sqlite_error_code() {
+                Some(ErrorCode::NotADatabase) => String::from("Wrong passphrase."),
+                _ => format!("Error opening library: {}", err)
+            }
+        })?;
+        let page_key: [u8; 32] = page_key.try_into().map_err(|_| String::from("The library's page key is damaged."))?;
+        *PASSPHRASE.lock().unwrap_or_else(|err| err.into_inner()) = Some(passphrase.to_string());
+        set_page_key(Some(page_key));
+        return Ok(())
+    }
+
+    /// Whether `passphrase` is the one the library was unlocked with, for the application lock.
+    pub(crate) fn is_library_passphrase(passphrase: &str) -> bool {
+        return PASSPHRASE.lock().unwrap_or_else(|err| err.into_inner()).as_deref() == Some(passphrase)
+    }
+
+    /// Encrypts the plain library's database with the passphrase and stores a new random page key inside it.
+    /// Page files are encrypted afterwards by `storage::encrypt_page_files`, which picks up where it left off if
+    /// interrupted. Refused while another session has the library open.
+    pub(crate) fn encrypt_library(passphrase: &str) -> Result<(), String> {
+        if library_is_encrypted() {
+            return Err(String::from("The library is already encrypted."))
+        }
+        return rewrite_library(passphrase, Some(random_key()))
+    }
+
+    /// Re-encrypts the database with a new passphrase. The page key inside it stays the same, so page files
+    /// need no changes.
+    pub(crate) fn change_passphrase(current: &str, new: &str) -> Result<(), String> {
+        if !is_library_passphrase(current) {
+            return Err(String::from("The current passphrase is wrong."))
+        }
+        return rewrite_library(new, None)
+    }
+
+    /// Copies the database into a new file encrypted with `passphrase`, adding `page_key` if given, and swaps it
+    /// in. Until the swap the old file is untouched, so an interruption leaves the library as it was.
+    fn rewrite_library(passphrase: &str, page_key: Option<[u8; 32]>) -> Result<(), String> {
+        const NEW_PATH: &str = "./data.db.new";
+        // Held throughout, so no connection is opened on the old file meanwhile.
+        let mut pool = POOL.lock().unwrap_or_else(|err| err.into_inner());
+        pool.clear();
+        // Other sessions' writes would go to the old file and be lost, and Windows cannot replace a file that is open.
+        let mut other_users: Vec<String> = DbConnection::connect().and_then(|conn| live_sessions(&conn))
+            .map_err(|err| format!("Error reading sessions: {}", err))?
+            .into_iter().filter(|(session, _)| session != session_id()).map(|(_, user_name)| user_name).collect();
+        other_users.sort();
+        other_users.dedup();
+        if !other_users.is_empty() {
+            return Err(format!("The library is open on another computer or window ({}). Close it there first; if it was closed just now, try again in a minute.", other_users.join(", ")))
+        }
+        let _ = fs::remove_file(NEW_PATH);
+
+        let export = || -> Result<(), rusqlite::Error> {
+            let conn = DbConnection::connect()?;
+            conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+            conn.execute("ATTACH DATABASE ?1 AS encrypted KEY ?2", (NEW_PATH, passphrase))?;
+            conn.query_row("SELECT sqlcipher_export('encrypted')", [], |_| Ok(()))?;
+            let version: i64 = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+            conn.pragma_update(Some("encrypted"), "user_version", version)?;
+            if let Some(page_key) = page_key {
+                conn.execute("CREATE TABLE encrypted.library_key (page_key BLOB NOT NULL)", ())?;
+                conn.execute("INSERT INTO encrypted.library_key (page_key) VALUES (?1)", (page_key.as_slice(),))?;
+            }
+            return conn.execute("DETACH DATABASE encrypted", ()).map(|_| ())
+        };
+        if let Err(err) = export() {
+            let _ = fs::remove_file(NEW_PATH);
+            return Err(format!("Error encrypting library: {}", err))
+        }
+
+        fs::rename(NEW_PATH, "./data.db").map_err(|err| format!("Error replacing library file: {}", err))?;
+        // Connections still borrowed point at the old file and its key; they are closed instead of pooled.
+        POOL_GENERATION.fetch_add(1, Ordering::Relaxed);
+        // The write-ahead log and its index belonged to the old file and were emptied before copying.
+        for path in ["./data.db-wal", "./data.db-shm"] {
+            let _ = fs::remove_file(path);
+        }
+        *PASSPHRASE.lock().unwrap_or_else(|err| err.into_inner()) = Some(passphrase.to_string());
+        if page_key.is_some() {
+            set_page_key(page_key);
+        }
+        return Ok(())
+    }
+
+    /// Whether a save or restore failed because the document or attachment number is used by another item.
+    pub(crate) fn is_number_taken(err: &rusqlite::Error) -> bool {
+        // Foreign key, NOT NULL and CHECK failures are constraint violations too, but say nothing about numbers.
+        return matches!(err, rusqlite::Error::SqliteFailure(error, _) if error.extended_code == SQLITE_CONSTRAINT_UNIQUE)
+    }
+
+    /// Whether a save failed because the row changed or was removed since it was read, most likely by another user.
+    pub(crate) fn is_conflict(err: &rusqlite::Error) -> bool {
+        return matches!(err, rusqlite::Error::QueryReturnedNoRows)
+    }
+
+    fn live_sessions(conn: &Connection) -> Result<Vec<(String, String)>, rusqlite::Error> {
+        let mut stmt = conn.prepare("SELECT session, user_name FROM live_session WHERE heartbeat > unixepoch('now') - ?1 ORDER BY session")?;
+        let sessions = stmt.query_map([SESSION_TIMEOUT], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        return sessions.collect()
+    }
+
+    /// Tells this run of the program apart from others on the same library, including the same user's.
+    pub(crate) fn session_id() -> &'static str {
+        static SESSION_ID: OnceLock<String> = OnceLock::new();
+        return SESSION_ID.get_or_init(|| {
+            let started = SystemTime::now().duration_since(UNIX_EPOCH).map(|duration| duration.as_nanos()).unwrap_or_default();
+            format!("{}-{}-{}", os_user_name(), std::process::id(), started)
+        })
+    }
+
+    /// Runs one step of `migrate` and raises `user_version` to `version` in a single transaction.
+    fn migrate_step(conn: &Connection, version: i64, step: impl FnOnce(&Connection) -> Result<(), rusqlite::Error>) -> Result<(), rusqlite::Error> {
+        let transaction = conn.unchecked_transaction()?;
+        step(&transaction)?;
+        transaction.pragma_update(None, "user_version", version)?;
+        return transaction.commit()
+    }
+
+    /// Writes one `audit_log` row. Call inside the transaction that makes the change, so both are committed together.
+    fn record_change(conn: &Connection, document_id: u32, attachment_id: Option<u32>, action: &str, field: Option<&str>, old_value: Option<&str>, new_value: Option<&str>) -> Result<(), rusqlite::Error> {
+        conn.execute(
+            "INSERT INTO audit_log (document_id, attachment_id, action, field, old_value, new_value, user_name, session) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            (document_id, attachment_id, action, field, old_value, new_value, os_user_name(), session_id())
+        )?;
+        return Ok(())
+    }
+
+    /// Refiles the attachments from one document under another, recording the move on both.
+    fn move_attachments(conn: &Connection, attachment_ids: &[u32], from_document_id: u32, to_document_id: u32) -> Result<(), rusqlite::Error> {
+        let from_number = document_number(conn, from_document_id)?;
+        let to_number = document_number(conn, to_document_id)?;
+        for attachment_id in attachment_ids {
+            conn.execute("UPDATE attachment SET document_id = ?1 WHERE attachment_id = ?2 AND document_id = ?3", (to_document_id, attachment_id, from_document_id))?;
+            for logged_document_id in [from_document_id, to_document_id] {
+                record_change(conn, logged_document_id, Some(*attachment_id), "moved", Some("document_number"), Some(&from_number), Some(&to_number))?;
+            }
+        }
+        return Ok(())
+    }
+
+    fn document_number(conn: &Connection, document_id: u32) -> Result<String, rusqlite::Error> {
+        return conn.query_row("SELECT document_number FROM document WHERE document_id = ?1", (document_id,), |row| row.get(0))
+    }
+
+    /// Records an added or removed link on both of its documents, each with the wording for its side.
+    fn record_link(conn: &Connection, source_id: u32, target_id: u32, kind: LinkKind, added: bool) -> Result<(), rusqlite::Error> {
+        for (document_id, other_id, outgoing) in [(source_id, target_id, true), (target_id, source_id, false)] {
+            let other_number = document_number(conn, other_id)?;
+            let link = format!("{} {}", kind.label(outgoing).to_lowercase(), other_number);
+            match added {
+                true => record_change(conn, document_id, None, "edited", Some("links"), Some(""), Some(&link))?,
+                false => record_change(conn, document_id, None, "edited", Some("links"), Some(&link), Some(""))?
+            }
+        }
+        return Ok(())
+    }
+
+    /// Records an edited field, skipping fields that were saved unchanged.
+    fn record_edit(conn: &Connection, document_id: u32, attachment_id: Option<u32>, field: &str, old_value: &str, new_value: &str) -> Result<(), rusqlite::Error> {
+        if old_value == new_value {
+            return Ok(())
+        }
+        return record_change(conn, document_id, attachment_id, "edited", Some(field), Some(old_value), Some(new_value))
+    }
+
+    /// Stores the document's custom field values, recording each change. Empty values are removed.
+    fn save_field_values(conn: &Connection, document_id: u32, field_values: Vec<(u32, String)>) -> Result<(), rusqlite::Error> {
+        for (field_id, value) in field_values {
+            let value = value.trim();
+            let old_value: Option<String> = conn.query_row("SELECT value FROM field_value WHERE document_id = ?1 AND field_id = ?2", (document_id, field_id), |row| row.get(0)).optional()?;
+            if value.is_empty() {
+                conn.execute("DELETE FROM field_value WHERE document_id = ?1 AND field_id = ?2", (document_id, field_id))?;
+            }
+            else {
+                conn.execute("INSERT OR REPLACE INTO field_value (document_id, field_id, value) VALUES (?1, ?2, ?3)", (document_id, field_id, value))?;
+            }
+            let field_name: String = conn.query_row("SELECT name FROM type_field WHERE field_id = ?1", (field_id,), |row| row.get(0))?;
+            record_edit(conn, document_id, None, &field_name, &old_value.unwrap_or_default(), value)?;
+        }
+        return Ok(())
+    }
+
+    fn find_or_create_tag(conn: &Connection, name: &str) -> Result<u32, rusqlite::Error> {
+        if let Some(tag_id) = conn.query_row("SELECT tag_id FROM tag WHERE name = ?1", (name,), |row| row.get(0)).optional()? {
+            return Ok(tag_id)
+        }
+        let tag_count: usize = conn.query_row("SELECT COUNT(*) FROM tag", (), |row| row.get(0))?;
+        conn.execute("INSERT INTO tag (name, color) VALUES (?1, ?2)", (name, TAG_COLORS[tag_count % TAG_COLORS.len()]))?;
+        return Ok(conn.last_insert_rowid() as u32)
+    }
+
+    fn tag_name(conn: &Connection, tag_id: u32) -> Result<String, rusqlite::Error> {
+        return conn.query_row("SELECT name FROM tag WHERE tag_id = ?1", (tag_id,), |row| row.get(0))
+    }
+
+    fn attachment_document_id(conn: &Connection, attachment_id: u32) -> Result<u32, rusqlite::Error> {
+        return conn.query_row("SELECT document_id FROM attachment WHERE attachment_id = ?1", (attachment_id,), |row| row.get(0))
+    }
+
+    /// The document and attachment a page belongs to, with its current checksum.
+    fn page_owner(conn: &Connection, page_id: u32) -> Result<(u32, u32, Option<String>), rusqlite::Error> {
+        return conn.query_row(
+            "SELECT attachment.document_id, page.attachment_id, page.checksum FROM page JOIN attachment ON attachment.attachment_id = page.attachment_id WHERE page.page_id = ?1",
+            (page_id,),
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+        )
+    }
+
+    /// The newest version of the attachment that holds exactly the pages with `checksums`.
+    fn find_version(conn: &Connection, attachment_id: u32, checksums: &[String]) -> Result<Option<u32>, rusqlite::Error> {
+        let mut stmt = conn.prepare("SELECT version_id FROM attachment_version WHERE attachment_id = ?1 ORDER BY version_id DESC")?;
+        let version_ids = stmt.query_map([attachment_id], |row| row.get::<_, u32>(0))?.collect::<Result<Vec<_>, _>>()?;
+        let mut page_stmt = conn.prepare("SELECT checksum FROM version_page WHERE version_id = ?1 ORDER BY position")?;
+        for version_id in version_ids {
+            let version_checksums = page_stmt.query_map([version_id], |row| Ok(row.get::<_, Option<String>>(0)?.unwrap_or_default()))?.collect::<Result<Vec<_>, _>>()?;
+            if version_checksums == checksums {
+                return Ok(Some(version_id))
+            }
+        }
+        return Ok(None)
+    }
+
+    fn page_checksums(conn: &Connection, attachment_id: u32) -> Result<Vec<String>, rusqlite::Error> {
+        let mut stmt = conn.prepare("SELECT checksum FROM page WHERE attachment_id = ?1 ORDER BY page_id")?;
+        let checksums = stmt.query_map([attachment_id], |row| Ok(row.get::<_, Option<String>>(0)?.unwrap_or_default()))?;
+        return checksums.collect()
+    }
+
+    /// The name of the logged-in OS user, as recorded in the audit log.
+    fn os_user_name() -> String {
+        return std::env::var("USERNAME")
+            .or_else(|_| std::env::var("USER"))
+            .unwrap_or_else(|_| String::from("unknown"))
+    }
+
+    /// A `page` row without the image bytes, for checks that only need the stored metadata.
+    #[derive(Debug, Clone)]
+    pub(crate) struct PageRecord {
+        pub(crate) page_id: u32,
+        pub(crate) file_path: String,
+        pub(crate) attachment_id: u32,
+        pub(crate) checksum: Option<String>
+    }
+
+    /// Which document an attachment belongs to, with its page count.
+    #[derive(Debug, Clone)]
+    pub(crate) struct AttachmentLocation {
+        pub(crate) attachment_id: u32,
+        pub(crate) reference_number: String,
+        pub(crate) document_id: u32,
+        pub(crate) document_number: String,
+        pub(crate) page_count: u32
+    }
+
+    /// A set of pages an attachment had before they were replaced.
+    #[derive(Debug, Clone)]
+    pub(crate) struct AttachmentVersion {
+        pub(crate) version_id: u32,
+        pub(crate) replaced_at: i64,
+        pub(crate) pages: Vec<(PathBuf, String)>
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub(crate) enum TrashKind {
+        Document,
+        Attachment
+    }
+
+    /// A deleted document or attachment as listed in the trash.
+    #[derive(Debug, Clone)]
+    pub(crate) struct TrashEntry {
+        pub(crate) kind: TrashKind,
+        pub(crate) id: u32,
+        pub(crate) name: String,
+        pub(crate) document_number: String,
+        pub(crate) deleted_at: i64
+    }
+
+    /// One recorded change to a document or one of its attachments.
+    #[derive(Debug, Clone)]
+    pub(crate) struct AuditEntry {
+        pub(crate) attachment_id: Option<u32>,
+        /// The attachment's current reference number, or `None` once it has been purged.
+        pub(crate) reference_number: Option<String>,
+        pub(crate) action: String,
+        pub(crate) field: Option<String>,
+        pub(crate) old_value: Option<String>,
+        pub(crate) new_value: Option<String>,
+        pub(crate) changed_at: i64,
+        pub(crate) user_name: String
+    }
+
+    impl AuditEntry {
+        pub(crate) fn description(&self) -> String {
+            let subject = match (self.attachment_id, &self.reference_number) {
+                (None, _) => String::from("Document"),
+                (Some(_), Some(reference_number)) => format!("Attachment {}", reference_number),
+                (Some(attachment_id), None) => format!("Attachment #{}", attachment_id)
+            };
+            let old_value = self.old_value.clone().unwrap_or_default();
+            let new_value = self.new_value.clone().unwrap_or_default();
+
+            match (self.action.as_str(), self.field.as_deref()) {
+                ("created", _) => format!("{} created as {}", subject, new_value),
+                ("edited", Some("pages")) => format!("{} pages replaced ({} pages before, {} after)", subject, old_value, new_value),
+                ("edited", Some("page")) if old_value.is_empty() => format!("{} page added", subject),
+                ("edited", Some("page")) if new_value.is_empty() => format!("{} page removed", subject),
+                ("edited", Some("page")) => format!("{} page file replaced", subject),
+                ("edited", Some("tags")) if old_value.is_empty() => format!("{} tagged \"{}\"", subject, new_value),
+                ("edited", Some("tags")) => format!("{} tag \"{}\" removed", subject, old_value),
+                ("moved", _) => format!("{} moved from document {} to {}", subject, old_value, new_value),
+                ("merged", _) => format!("Document {} merged into {}", old_value, new_value),
+                ("split", _) => format!("Attachments split off from {} into {}", old_value, new_value),
+                ("copied", _) => format!("{} copied from {}", subject, old_value),
+                ("edited", Some("links")) if old_value.is_empty() => format!("{} linked: {}", subject, new_value),
+                ("edited", Some("links")) => format!("{} link removed: {}", subject, old_value),
+                ("edited", Some("status")) => format!("{} moved from {} to {}", subject, DocumentStatus::from_str(&old_value), DocumentStatus::from_str(&new_value)),
+                ("edited", Some(field)) => format!("{} {} changed from \"{}\" to \"{}\"", subject, field.replace('_', " "), old_value, new_value),
+                (action, _) => format!("{} {}", subject, action)
+            }
+        }
+    }
 
     pub(crate) enum DbTable {
         DocumentTable,
         FilePathTable,
     }
 
-    
-}
\ No newline at end of file
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        const LATEST_VERSION: i64 = 16;
+
+        fn user_version(conn: &Connection) -> i64 {
+            return conn.pragma_query_value(None, "user_version", |row| row.get(0)).unwrap()
+        }
+
+        fn columns(conn: &Connection, table: &str) -> Vec<String> {
+            let mut stmt = conn.prepare("SELECT name FROM pragma_table_info(?1)").unwrap();
+            return stmt.query_map([table], |row| row.get(0)).unwrap().collect::<Result<_, _>>().unwrap()
+        }
+
+        #[test]
+        fn test_migrate_new_database() {
+            let conn = Connection::open_in_memory().unwrap();
+            DbConnection::create_schema(&conn).unwrap();
+            DbConnection::migrate(&conn).unwrap();
+            assert_eq!(user_version(&conn), LATEST_VERSION);
+            assert!(columns(&conn, "document").contains(&String::from("version")));
+            assert!(columns(&conn, "audit_log").contains(&String::from("session")));
+
+            // A migrated database is left as it is.
+            DbConnection::migrate(&conn).unwrap();
+            assert_eq!(user_version(&conn), LATEST_VERSION);
+        }
+
+        #[test]
+        fn test_migrate_step_rolls_back_on_failure() {
+            let conn = Connection::open_in_memory().unwrap();
+            DbConnection::create_schema(&conn).unwrap();
+            // Fails on the second statement, after the first one changed `document`.
+            let result = migrate_step(&conn, 1, |conn| {
+                conn.execute("ALTER TABLE document ADD COLUMN extra TEXT", ())?;
+                conn.execute("ALTER TABLE missing ADD COLUMN extra TEXT", ())?;
+                return Ok(())
+            });
+
+            assert!(result.is_err());
+            assert_eq!(user_version(&conn), 0);
+            assert!(!columns(&conn, "document").contains(&String::from("extra")));
+        }
+
+        #[test]
+        fn test_numbers_in_trash_can_be_reused() {
+            let conn = Connection::open_in_memory().unwrap();
+            DbConnection::create_schema(&conn).unwrap();
+            DbConnection::migrate(&conn).unwrap();
+            conn.execute("INSERT INTO document (document_id, document_number, deleted_at) VALUES (1, 'A-1', unixepoch('now'))", ()).unwrap();
+            conn.execute("INSERT INTO document (document_id, document_number) VALUES (2, 'A-1')", ()).unwrap();
+            assert!(conn.execute("INSERT INTO document (document_id, document_number) VALUES (3, 'A-1')", ()).is_err());
+
+            // Restoring the first document would clash with the second one.
+            let err = conn.execute("UPDATE document SET deleted_at = NULL WHERE document_id = 1", ()).unwrap_err();
+            assert!(is_number_taken(&err));
+            let err = conn.execute("INSERT INTO document (document_id, document_number) VALUES (4, NULL)", ()).unwrap_err();
+            assert!(!is_number_taken(&err));
+        }
+
+        #[test]
+        fn test_copy_attachment_keeps_versions() {
+            let mut conn = DbConnection::in_memory();
+            conn.new_document(String::from("D-1"), String::new(), String::new(), Vec::new(), None, None).unwrap();
+            let document_id = conn.last_rowid().unwrap() as u32;
+            let pages = |checksum: &str| vec![(PathBuf::from(checksum), String::from(checksum))];
+            conn.new_attachment(pages("a"), String::from("R-1"), String::new(), document_id).unwrap();
+            let attachment_id = conn.last_rowid().unwrap() as u32;
+            conn.save_attachment(attachment_id, String::from("R-1"), String::new(), Some(pages("b")), None).unwrap();
+
+            conn.copy_attachment(attachment_id, document_id, String::from("R-2"), None).unwrap();
+            let copy_id = conn.last_rowid().unwrap() as u32;
+            assert_eq!(conn.read_page_checksums(copy_id).unwrap(), vec!["b"]);
+            let versions = conn.read_attachment_versions(copy_id).unwrap();
+            assert_eq!(versions.len(), 1);
+            assert_eq!(versions[0].pages, pages("a"));
+        }
+
+        #[test]
+        fn test_changes_of_other_sessions() {
+            let mut conn = DbConnection::in_memory();
+            let start = conn.last_audit_id().unwrap();
+            conn.new_document(String::from("D-1"), String::new(), String::new(), Vec::new(), None, None).unwrap();
+            let document_id = conn.last_rowid().unwrap() as u32;
+            conn.conn.execute("INSERT INTO audit_log (document_id, action, user_name, session) VALUES (?1, 'edited', 'someone', 'other')", (document_id,)).unwrap();
+            conn.new_document(String::from("D-2"), String::new(), String::new(), Vec::new(), None, None).unwrap();
+
+            let latest = conn.last_audit_id().unwrap();
+            assert_eq!(conn.read_changes_since(start, latest).unwrap(), vec![(document_id, String::from("someone"))]);
+            assert!(conn.read_changes_since(latest, latest).unwrap().is_empty());
+        }
+
+        #[test]
+        fn test_live_sessions() {
+            let mut conn = DbConnection::in_memory();
+            conn.renew_session().unwrap();
+            conn.conn.execute("INSERT INTO live_session (session, user_name, heartbeat) VALUES ('crashed', 'someone', unixepoch('now') - ?1)", [SESSION_TIMEOUT]).unwrap();
+            assert_eq!(conn.read_live_sessions().unwrap(), vec![(session_id().to_string(), os_user_name())]);
+            // Renewing forgets the crashed session.
+            conn.renew_session().unwrap();
+            assert_eq!(conn.conn.query_row("SELECT COUNT(*) FROM live_session", [], |row| row.get::<_, i64>(0)).unwrap(), 1);
+        }
+
+        #[test]
+        fn test_rename_page_object() {
+            let mut conn = DbConnection::in_memory();
+            conn.new_document(String::from("D-1"), String::new(), String::new(), Vec::new(), None, None).unwrap();
+            let document_id = conn.last_rowid().unwrap() as u32;
+            let pages = |checksum: &str| vec![(PathBuf::from(checksum), String::from(checksum))];
+            conn.new_attachment(pages("a"), String::from("R-1"), String::new(), document_id).unwrap();
+            let attachment_id = conn.last_rowid().unwrap() as u32;
+            conn.save_attachment(attachment_id, String::from("R-1"), String::new(), Some(pages("b")), None).unwrap();
+
+            assert_eq!(conn.rename_page_object("a", Path::new("c"), "c").unwrap(), 1);
+            assert_eq!(conn.rename_page_object("b", Path::new("d"), "d").unwrap(), 1);
+            assert_eq!(conn.read_page_checksums(attachment_id).unwrap(), vec!["d"]);
+            assert_eq!(conn.read_attachment_versions(attachment_id).unwrap()[0].pages, pages("c"));
+        }
+
+        #[test]
+        fn test_document_summaries() {
+            let mut conn = DbConnection::in_memory();
+            for document_number in ["D-1", "D-2", "D-3"] {
+                conn.new_document(String::from(document_number), String::new(), String::new(), Vec::new(), None, None).unwrap();
+            }
+            let pages = |count: usize| (0..count).map(|page| (PathBuf::from(page.to_string()), page.to_string())).collect::<Vec<_>>();
+            conn.new_attachment(pages(2), String::from("R-1"), String::new(), 2).unwrap();
+            conn.new_attachment(pages(3), String::from("R-2"), String::new(), 2).unwrap();
+            let deleted_id = conn.last_rowid().unwrap() as u32;
+            conn.new_attachment(pages(1), String::from("R-3"), String::new(), 2).unwrap();
+            conn.delete_attachment(deleted_id).unwrap();
+
+            let list = ListQuery::default();
+            assert_eq!(conn.count_documents(&list).unwrap(), 3);
+            let summaries = conn.read_document_summaries(&list, Some(1), 2).unwrap();
+            let numbers: Vec<String> = summaries.iter().map(|document| document.get_document_number().to_string()).collect();
+            assert_eq!(numbers, vec!["D-2", "D-3"]);
+            // Counts leave out the deleted attachment and its pages.
+            assert_eq!((summaries[0].get_attachment_count(), summaries[0].get_page_count()), (2, 3));
+            assert!(summaries[0].get_attachments().is_none());
+            assert_eq!((summaries[1].get_attachment_count(), summaries[1].get_page_count()), (0, 0));
+            assert!(conn.read_document_summaries(&list, Some(3), 2).unwrap().is_empty());
+        }
+
+        #[test]
+        fn test_document_list_query() {
+            let mut conn = DbConnection::in_memory();
+            conn.new_document(String::from("INV-10"), String::from("Receipt"), String::new(), Vec::new(), parse_date("2024-05-01"), None).unwrap();
+            conn.new_document(String::from("inv-9"), String::new(), String::new(), Vec::new(), None, None).unwrap();
+            conn.new_document(String::from("A-2"), String::from("invoice"), String::new(), Vec::new(), parse_date("2023-01-01"), None).unwrap();
+            conn.add_document_tag(3, String::from("paid")).unwrap();
+            let numbers = |conn: &DbConnection, list: &ListQuery| -> Vec<String> {
+                // One document per page, so every page continues after the last one.
+                let mut numbers = Vec::new();
+                let mut after_id = None;
+                while let Some(document) = conn.read_document_summaries(list, after_id, 1).unwrap().pop() {
+                    after_id = Some(document.get_document_id());
+                    numbers.push(document.get_document_number().to_string());
+                }
+                return numbers
+            };
+
+            let mut list = ListQuery::default();
+            assert_eq!(numbers(&conn, &list), vec!["A-2", "inv-9", "INV-10"]);
+            list.descending = true;
+            assert_eq!(numbers(&conn, &list), vec!["INV-10", "inv-9", "A-2"]);
+            assert_eq!(conn.read_document_ids(&list).unwrap(), vec![1, 2, 3]);
+            list = ListQuery { sort_key: SortKey::DocumentDate, ..ListQuery::default() };
+            assert_eq!(numbers(&conn, &list), vec!["A-2", "INV-10", "inv-9"]);
+
+            // Groups follow the sort direction, but documents without a type stay last.
+            list = ListQuery { group_by: GroupBy::DocumentType, ..ListQuery::default() };
+            assert_eq!(numbers(&conn, &list), vec!["A-2", "INV-10", "inv-9"]);
+            list.descending = true;
+            assert_eq!(numbers(&conn, &list), vec!["INV-10", "A-2", "inv-9"]);
+            assert_eq!(conn.count_document_groups(&list).unwrap(), HashMap::from([(String::from("Receipt"), 1), (String::from("invoice"), 1), (String::new(), 1)]));
+
+            list = ListQuery { document_type: Some(String::from("receipt")), ..ListQuery::default() };
+            assert_eq!(numbers(&conn, &list), vec!["INV-10"]);
+            let paid = conn.read_tags().unwrap()[0].get_tag_id();
+            list = ListQuery { tag_ids: vec![paid], match_all_tags: true, ..ListQuery::default() };
+            assert_eq!(numbers(&conn, &list), vec!["A-2"]);
+            assert_eq!(conn.count_documents(&list).unwrap(), 1);
+        }
+
+        #[test]
+        fn test_save_checks_version() {
+            let mut conn = DbConnection::in_memory();
+            conn.new_document(String::from("D-1"), String::new(), String::new(), Vec::new(), None, None).unwrap();
+            let document_id = conn.last_rowid().unwrap() as u32;
+            conn.new_attachment(Vec::new(), String::from("R-1"), String::new(), document_id).unwrap();
+            let attachment_id = conn.last_rowid().unwrap() as u32;
+            let version = conn.read_document(document_id).unwrap().unwrap().get_version();
+            let details = DocumentDetails {
+                document_number: String::from("D-1"),
+                document_type: String::new(),
+                comment: String::from("changed"),
+                field_values: Vec::new(),
+                document_date: None,
+                due_date: None
+            };
+
+            conn.edit_document_details(document_id, &details, Some(version)).unwrap();
+            // The first save raised the version, so saving again from the same read is a conflict.
+            assert!(is_conflict(&conn.edit_document_details(document_id, &details, Some(version)).unwrap_err()));
+            assert!(conn.edit_document_details(document_id, &details, None).is_ok());
+
+            conn.save_attachment(attachment_id, String::from("R-1"), String::new(), None, Some(0)).unwrap();
+            assert!(is_conflict(&conn.save_attachment(attachment_id, String::from("R-1"), String::new(), None, Some(0)).unwrap_err()));
+            assert!(is_conflict(&conn.copy_attachment(attachment_id, document_id, String::from("R-2"), Some(0)).unwrap_err()));
+
+            // The other writes to a document check the version the same way.
+            assert!(is_conflict(&conn.change_document_status(document_id, DocumentStatus::Review, Some(version)).unwrap_err()));
+            assert!(is_conflict(&conn.split_document(document_id, &[attachment_id], String::from("D-2"), Some(version)).unwrap_err()));
+            conn.new_document(String::from("D-3"), String::new(), String::new(), Vec::new(), None, None).unwrap();
+            let source_id = conn.last_rowid().unwrap() as u32;
+            let merged = MergedDetails { document_type: String::new(), comment: String::new(), field_values: Vec::new(), document_date: None, due_date: None };
+            let current = conn.read_document(document_id).unwrap().unwrap().get_version();
+            assert!(is_conflict(&conn.merge_documents(document_id, source_id, &merged, Some((current, 5))).unwrap_err()));
+            conn.merge_documents(document_id, source_id, &merged, Some((current, 0))).unwrap();
+        }
+
+        #[test]
+        fn test_attachment_lock() {
+            let mut conn = DbConnection::in_memory();
+            assert_eq!(conn.lock_attachment(1).unwrap(), None);
+            // Renewing our own lock is fine.
+            assert_eq!(conn.lock_attachment(1).unwrap(), None);
+
+            conn.conn.execute("INSERT INTO attachment_lock (attachment_id, session, user_name) VALUES (2, 'other', 'alice')", ()).unwrap();
+            assert_eq!(conn.lock_attachment(2).unwrap(), Some(String::from("alice")));
+            // A lock that was not renewed in time no longer counts.
+            conn.conn.execute("UPDATE attachment_lock SET heartbeat = heartbeat - ?1 WHERE attachment_id = 2", [LOCK_TIMEOUT]).unwrap();
+            assert_eq!(conn.lock_attachment(2).unwrap(), None);
+        }
+    }
+}
@@ -1,8 +1,9 @@
 pub(crate) mod document {
-    use std::sync::Arc;
+    use std::{collections::HashMap, fmt, sync::Arc};
 
-    use crate::attachment::attachment::Attachment;
+    use crate::{attachment::attachment::Attachment, tag::tag::Tag};
     use iced::{Element, widget::Container};
+    use time::{Date, macros::format_description};
 
     #[derive(Debug, Clone)]
     pub(crate) struct Document {
@@ -12,10 +13,34 @@ pub(crate) mod document {
         attachments: Option<Vec<Arc<Attachment>>>,
         comment: Arc<String>,
         date_added: i64,
+        tags: Vec<Tag>,
+        field_values: HashMap<u32, String>,
+        document_date: Option<Date>,
+        due_date: Option<Date>,
+        status: DocumentStatus,
+        status_changed_at: Option<i64>,
+        attachment_count: usize,
+        page_count: usize,
+        /// Raised on every change to the row, so a save can tell whether someone else changed it in between.
+        version: u32
+    }
+
+    /// What a document row carries besides its number, type and comment, as read from the library.
+    #[derive(Debug, Clone, Default)]
+    pub(crate) struct DocumentMetadata {
+        pub(crate) tags: Vec<Tag>,
+        pub(crate) field_values: HashMap<u32, String>,
+        pub(crate) document_date: Option<Date>,
+        pub(crate) due_date: Option<Date>,
+        pub(crate) status: DocumentStatus,
+        pub(crate) status_changed_at: Option<i64>,
+        pub(crate) attachment_count: usize,
+        pub(crate) page_count: usize,
+        pub(crate) version: u32
     }
 
     impl Document {
-        pub(crate) fn new(document_id:u32, document_number: Arc<String>, document_type: Arc<String>, attachments: Option<Vec<Arc<Attachment>>>, comment: Arc<String>, date_added: i64) -> Document {
+        pub(crate) fn new(document_id:u32, document_number: Arc<String>, document_type: Arc<String>, attachments: Option<Vec<Arc<Attachment>>>, comment: Arc<String>, date_added: i64, metadata: DocumentMetadata) -> Document {
             Document {
                 document_id: document_id,
                 document_number: document_number,
@@ -23,9 +48,24 @@ pub(crate) mod document {
                 attachments: attachments,
                 comment: comment,
                 date_added: date_added,
+                tags: metadata.tags,
+                field_values: metadata.field_values,
+                document_date: metadata.document_date,
+                due_date: metadata.due_date,
+                status: metadata.status,
+                status_changed_at: metadata.status_changed_at,
+                attachment_count: metadata.attachment_count,
+                page_count: metadata.page_count,
+                version: metadata.version
             }
         }
 
+        /// The document with its attachments loaded, as opened in the list.
+        pub(crate) fn with_attachments(mut self, attachments: Vec<Arc<Attachment>>) -> Document {
+            self.attachments = Some(attachments);
+            return self
+        }
+
         pub(crate) fn get_document_id(&self) -> u32 {
             return self.document_id
         }
@@ -38,10 +78,24 @@ pub(crate) mod document {
             return self.document_type.clone()
         }
 
+        /// `None` for summaries read for the document list; `DbConnection::read_document` loads them.
         pub(crate) fn get_attachments(&self) -> Option<Vec<Arc<Attachment>>> {
             return self.attachments.clone()
         }
 
+        pub(crate) fn get_attachment_count(&self) -> usize {
+            return self.attachment_count
+        }
+
+        /// The number of pages over all attachments.
+        pub(crate) fn get_page_count(&self) -> usize {
+            return self.page_count
+        }
+
+        pub(crate) fn get_version(&self) -> u32 {
+            return self.version
+        }
+
         pub(crate) fn get_comment(&self) -> Arc<String> {
             return self.comment.clone()
         }
@@ -49,6 +103,111 @@ pub(crate) mod document {
         pub(crate) fn get_date_added(&self) -> i64 {
             return self.date_added
         }
+
+        pub(crate) fn get_tags(&self) -> &Vec<Tag> {
+            return &self.tags
+        }
+
+        /// Custom field values, keyed by `TypeField::field_id`.
+        pub(crate) fn get_field_values(&self) -> &HashMap<u32, String> {
+            return &self.field_values
+        }
+
+        /// The date printed on the document, as opposed to when it was added to the library.
+        pub(crate) fn get_document_date(&self) -> Option<Date> {
+            return self.document_date
+        }
+
+        /// When an invoice is due or a contract expires.
+        pub(crate) fn get_due_date(&self) -> Option<Date> {
+            return self.due_date
+        }
+
+        pub(crate) fn get_status(&self) -> DocumentStatus {
+            return self.status
+        }
+
+        /// When the document entered its current status; `None` for documents that never left draft.
+        pub(crate) fn get_status_changed_at(&self) -> Option<i64> {
+            return self.status_changed_at
+        }
+
+        /// Archived documents are never overdue.
+        pub(crate) fn is_overdue(&self, today: Date) -> bool {
+            return self.status != DocumentStatus::Archived && self.due_date.is_some_and(|due_date| due_date < today)
+        }
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+    pub(crate) enum DocumentStatus {
+        #[default]
+        Draft,
+        Review,
+        Approved,
+        Archived
+    }
+
+    impl DocumentStatus {
+        pub(crate) const ALL: [DocumentStatus; 4] = [DocumentStatus::Draft, DocumentStatus::Review, DocumentStatus::Approved, DocumentStatus::Archived];
+
+        /// The value stored in the `document.status` column.
+        pub(crate) fn as_str(&self) -> &'static str {
+            match self {
+                DocumentStatus::Draft => "draft",
+                DocumentStatus::Review => "review",
+                DocumentStatus::Approved => "approved",
+                DocumentStatus::Archived => "archived"
+            }
+        }
+
+        /// The statuses a document in this status can be moved to.
+        pub(crate) fn transitions(&self) -> &'static [DocumentStatus] {
+            match self {
+                DocumentStatus::Draft => &[DocumentStatus::Review],
+                DocumentStatus::Review => &[DocumentStatus::Draft, DocumentStatus::Approved],
+                DocumentStatus::Approved => &[DocumentStatus::Review, DocumentStatus::Archived],
+                DocumentStatus::Archived => &[DocumentStatus::Approved]
+            }
+        }
+
+        pub(crate) fn can_change_to(&self, status: DocumentStatus) -> bool {
+            return self.transitions().contains(&status)
+        }
+
+        /// Approved and archived documents can only be edited after an explicit unlock.
+        pub(crate) fn is_locked(&self) -> bool {
+            return matches!(self, DocumentStatus::Approved | DocumentStatus::Archived)
+        }
+
+        pub(crate) fn from_str(status: &str) -> DocumentStatus {
+            match status {
+                "review" => DocumentStatus::Review,
+                "approved" => DocumentStatus::Approved,
+                "archived" => DocumentStatus::Archived,
+                _ => DocumentStatus::Draft
+            }
+        }
+    }
+
+    impl fmt::Display for DocumentStatus {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            let name = match self {
+                DocumentStatus::Draft => "Draft",
+                DocumentStatus::Review => "In Review",
+                DocumentStatus::Approved => "Approved",
+                DocumentStatus::Archived => "Archived"
+            };
+            write!(f, "{}", name)
+        }
+    }
+
+    /// Dates are stored and entered as `YYYY-MM-DD`.
+    pub(crate) fn parse_date(text: &str) -> Option<Date> {
+        return Date::parse(text.trim(), format_description!("[year]-[month]-[day]")).ok()
+    }
+
+    pub(crate) fn format_date(date: Date) -> String {
+        return date.format(format_description!("[year]-[month]-[day]")).unwrap_or_default()
     }
 
     pub(crate) enum Message {
@@ -0,0 +1,73 @@
+pub(crate) mod document_link {
+    use std::{fmt, sync::Arc};
+
+    /// A typed link between two documents. Each link is stored once, from `source` to `target`,
+    /// and shown on both documents with the wording for its direction.
+    #[derive(Debug, Clone, PartialEq)]
+    pub(crate) struct DocumentLink {
+        pub(crate) link_id: u32,
+        pub(crate) kind: LinkKind,
+        /// The document at the other end, seen from the document the link was read for.
+        pub(crate) other_document_id: u32,
+        pub(crate) other_document_number: Arc<String>,
+        /// Whether the link was made from the document it was read for.
+        pub(crate) outgoing: bool
+    }
+
+    impl DocumentLink {
+        pub(crate) fn label(&self) -> &'static str {
+            return self.kind.label(self.outgoing)
+        }
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+    pub(crate) enum LinkKind {
+        #[default]
+        RelatesTo,
+        Supersedes,
+        PaymentFor,
+        DeliveryFor
+    }
+
+    impl LinkKind {
+        pub(crate) const ALL: [LinkKind; 4] = [LinkKind::RelatesTo, LinkKind::Supersedes, LinkKind::PaymentFor, LinkKind::DeliveryFor];
+
+        /// The value stored in the `document_link.kind` column.
+        pub(crate) fn as_str(&self) -> &'static str {
+            match self {
+                LinkKind::RelatesTo => "relates_to",
+                LinkKind::Supersedes => "supersedes",
+                LinkKind::PaymentFor => "payment_for",
+                LinkKind::DeliveryFor => "delivery_for"
+            }
+        }
+
+        pub(crate) fn from_str(kind: &str) -> LinkKind {
+            match kind {
+                "supersedes" => LinkKind::Supersedes,
+                "payment_for" => LinkKind::PaymentFor,
+                "delivery_for" => LinkKind::DeliveryFor,
+                _ => LinkKind::RelatesTo
+            }
+        }
+
+        /// How the link reads from the source (`outgoing`) or from the target.
+        pub(crate) fn label(&self, outgoing: bool) -> &'static str {
+            match (self, outgoing) {
+                (LinkKind::RelatesTo, _) => "Relates to",
+                (LinkKind::Supersedes, true) => "Supersedes",
+                (LinkKind::Supersedes, false) => "Superseded by",
+                (LinkKind::PaymentFor, true) => "Payment for",
+                (LinkKind::PaymentFor, false) => "Paid by",
+                (LinkKind::DeliveryFor, true) => "Delivery for",
+                (LinkKind::DeliveryFor, false) => "Delivered by"
+            }
+        }
+    }
+
+    impl fmt::Display for LinkKind {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "{}", self.label(true))
+        }
+    }
+}
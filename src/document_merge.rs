@@ -0,0 +1,174 @@
+pub(crate) mod document_merge {
+    use std::collections::HashSet;
+
+    use time::Date;
+
+    use crate::{document::document::{Document, format_date}, document_type::document_type::DocumentType};
+
+    /// A detail two documents can disagree on when they are merged.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub(crate) enum MergeField {
+        DocumentType,
+        Comment,
+        DocumentDate,
+        DueDate,
+        /// A custom field, by `field_id`.
+        Field(u32)
+    }
+
+    /// A detail that is filled in on both documents with different values.
+    #[derive(Debug, Clone)]
+    pub(crate) struct Conflict {
+        pub(crate) field: MergeField,
+        pub(crate) label: String,
+        pub(crate) kept_value: String,
+        pub(crate) merged_value: String
+    }
+
+    /// The details the kept document ends up with after the merge.
+    #[derive(Debug, Clone, PartialEq)]
+    pub(crate) struct MergedDetails {
+        pub(crate) document_type: String,
+        pub(crate) comment: String,
+        pub(crate) field_values: Vec<(u32, String)>,
+        pub(crate) document_date: Option<Date>,
+        pub(crate) due_date: Option<Date>
+    }
+
+    /// The details of `kept` and `merged` that need a decision. Details filled in on only one of the documents
+    /// are taken over without asking.
+    pub(crate) fn conflicts(kept: &Document, merged: &Document, document_types: &[DocumentType]) -> Vec<Conflict> {
+        let mut conflicts = Vec::new();
+        let details = [
+            (MergeField::DocumentType, String::from("Type"), kept.get_document_type().to_string(), merged.get_document_type().to_string()),
+            (MergeField::Comment, String::from("Comment"), kept.get_comment().to_string(), merged.get_comment().to_string()),
+            (MergeField::DocumentDate, String::from("Document Date"), kept.get_document_date().map(format_date).unwrap_or_default(), merged.get_document_date().map(format_date).unwrap_or_default()),
+            (MergeField::DueDate, String::from("Due Date"), kept.get_due_date().map(format_date).unwrap_or_default(), merged.get_due_date().map(format_date).unwrap_or_default())
+        ];
+        for (field, label, kept_value, merged_value) in details {
+            if is_conflict(&kept_value, &merged_value) {
+                conflicts.push(Conflict { field: field, label: label, kept_value: kept_value, merged_value: merged_value });
+            }
+        }
+
+        let mut field_ids: Vec<u32> = kept.get_field_values().keys().chain(merged.get_field_values().keys()).copied().collect();
+        field_ids.sort();
+        field_ids.dedup();
+        for field_id in field_ids {
+            let kept_value = kept.get_field_values().get(&field_id).cloned().unwrap_or_default();
+            let merged_value = merged.get_field_values().get(&field_id).cloned().unwrap_or_default();
+            if is_conflict(&kept_value, &merged_value) {
+                conflicts.push(Conflict { field: MergeField::Field(field_id), label: field_name(document_types, field_id), kept_value: kept_value, merged_value: merged_value });
+            }
+        }
+        return conflicts
+    }
+
+    /// Combines the details of both documents, taking the merged document's value for the conflicts listed in
+    /// `take_merged` and the kept document's value for all others. Only the custom fields of the resulting type
+    /// are kept.
+    pub(crate) fn merged_details(kept: &Document, merged: &Document, document_types: &[DocumentType], take_merged: &HashSet<MergeField>) -> MergedDetails {
+        let pick = |field: MergeField, kept_value: String, merged_value: String| -> String {
+            if kept_value.trim().is_empty() || (take_merged.contains(&field) && !merged_value.trim().is_empty()) {
+                return merged_value
+            }
+            return kept_value
+        };
+
+        let document_type = pick(MergeField::DocumentType, kept.get_document_type().to_string(), merged.get_document_type().to_string());
+        let type_fields = document_types.iter()
+            .find(|candidate| candidate.get_name().eq_ignore_ascii_case(document_type.trim()))
+            .map(|candidate| candidate.get_fields().clone())
+            .unwrap_or_default();
+        let field_values = type_fields.iter().map(|field| (field.field_id, pick(
+            MergeField::Field(field.field_id),
+            kept.get_field_values().get(&field.field_id).cloned().unwrap_or_default(),
+            merged.get_field_values().get(&field.field_id).cloned().unwrap_or_default()
+        ))).collect();
+        let date = |field: MergeField, kept_date: Option<Date>, merged_date: Option<Date>| -> Option<Date> {
+            if kept_date.is_none() || (take_merged.contains(&field) && merged_date.is_some()) {
+                return merged_date
+            }
+            return kept_date
+        };
+
+        return MergedDetails {
+            document_type: document_type,
+            comment: pick(MergeField::Comment, kept.get_comment().to_string(), merged.get_comment().to_string()),
+            field_values: field_values,
+            document_date: date(MergeField::DocumentDate, kept.get_document_date(), merged.get_document_date()),
+            due_date: date(MergeField::DueDate, kept.get_due_date(), merged.get_due_date())
+        }
+    }
+
+    fn is_conflict(kept_value: &str, merged_value: &str) -> bool {
+        return !kept_value.trim().is_empty() && !merged_value.trim().is_empty() && kept_value.trim() != merged_value.trim()
+    }
+
+    fn field_name(document_types: &[DocumentType], field_id: u32) -> String {
+        return document_types.iter()
+            .flat_map(|document_type| document_type.get_fields())
+            .find(|field| field.field_id == field_id)
+            .map(|field| field.name.clone())
+            .unwrap_or_else(|| format!("Field #{}", field_id))
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use std::{collections::HashMap, sync::Arc};
+
+        use time::macros::date;
+
+        use crate::{document::document::DocumentMetadata, document_type::document_type::{FieldKind, TypeField}};
+
+        use super::*;
+
+        fn document(document_type: &str, comment: &str, field_values: &[(u32, &str)], document_date: Option<Date>) -> Document {
+            return Document::new(1, Arc::new(String::from("D-1")), Arc::new(document_type.to_string()), None, Arc::new(comment.to_string()), 0, DocumentMetadata {
+                field_values: field_values.iter().map(|(field_id, value)| (*field_id, value.to_string())).collect::<HashMap<_, _>>(),
+                document_date: document_date,
+                ..Default::default()
+            })
+        }
+
+        fn invoice_type() -> DocumentType {
+            let field = TypeField { field_id: 7, name: String::from("Amount"), kind: FieldKind::Currency, required: false, options: Vec::new() };
+            return DocumentType::new(1, Arc::new(String::from("Invoice")), vec![field], Arc::new(String::new()), false)
+        }
+
+        #[test]
+        fn test_conflicts() {
+            let kept = document("Invoice", "kept", &[(7, "10.00"), (8, "only kept")], Some(date!(2024 - 01 - 01)));
+            let merged = document("invoice ", " kept ", &[(7, "12.00"), (9, "only merged")], Some(date!(2024 - 02 - 01)));
+            let conflicts = conflicts(&kept, &merged, &[invoice_type()]);
+
+            // Values are compared without the spaces around them, but with their case.
+            let fields: Vec<MergeField> = conflicts.iter().map(|conflict| conflict.field).collect();
+            assert_eq!(fields, vec![MergeField::DocumentType, MergeField::DocumentDate, MergeField::Field(7)]);
+            assert_eq!(conflicts[1].kept_value, "2024-01-01");
+            assert_eq!(conflicts[1].merged_value, "2024-02-01");
+            assert_eq!(conflicts[2].label, "Amount");
+        }
+
+        #[test]
+        fn test_no_conflicts_when_one_side_is_empty() {
+            let kept = document("Invoice", "", &[(7, "10.00")], None);
+            let merged = document("", "merged", &[(7, " ")], Some(date!(2024 - 02 - 01)));
+            assert!(conflicts(&kept, &merged, &[invoice_type()]).is_empty());
+        }
+
+        #[test]
+        fn test_merged_details() {
+            let kept = document("Invoice", "", &[(7, "10.00"), (8, "old type")], None);
+            let merged = document("Invoice", "merged", &[(7, "12.00")], Some(date!(2024 - 02 - 01)));
+
+            let details = merged_details(&kept, &merged, &[invoice_type()], &HashSet::new());
+            assert_eq!(details.comment, "merged");
+            assert_eq!(details.field_values, vec![(7, String::from("10.00"))]);
+            assert_eq!(details.document_date, Some(date!(2024 - 02 - 01)));
+
+            let details = merged_details(&kept, &merged, &[invoice_type()], &HashSet::from([MergeField::Field(7)]));
+            assert_eq!(details.field_values, vec![(7, String::from("12.00"))]);
+        }
+    }
+}
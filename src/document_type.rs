@@ -0,0 +1,168 @@
+pub(crate) mod document_type {
+    use std::{fmt, sync::Arc};
+
+    use time::{Date, macros::format_description};
+
+    use crate::number_pattern::number_pattern::NumberPattern;
+
+    /// A managed document type and the custom fields documents of that type carry.
+    #[derive(Debug, Clone, PartialEq)]
+    pub(crate) struct DocumentType {
+        type_id: u32,
+        name: Arc<String>,
+        fields: Vec<TypeField>,
+        /// Template or regular expression the document numbers of this type have to match; empty for none.
+        number_pattern: Arc<String>,
+        auto_number: bool
+    }
+
+    impl DocumentType {
+        pub(crate) fn new(type_id: u32, name: Arc<String>, fields: Vec<TypeField>, number_pattern: Arc<String>, auto_number: bool) -> DocumentType {
+            DocumentType {
+                type_id: type_id,
+                name: name,
+                fields: fields,
+                number_pattern: number_pattern,
+                auto_number: auto_number
+            }
+        }
+
+        pub(crate) fn get_type_id(&self) -> u32 {
+            return self.type_id
+        }
+
+        pub(crate) fn get_name(&self) -> Arc<String> {
+            return self.name.clone()
+        }
+
+        pub(crate) fn get_fields(&self) -> &Vec<TypeField> {
+            return &self.fields
+        }
+
+        pub(crate) fn get_number_pattern(&self) -> Arc<String> {
+            return self.number_pattern.clone()
+        }
+
+        /// Whether new documents of this type get the next number of the pattern filled in.
+        pub(crate) fn get_auto_number(&self) -> bool {
+            return self.auto_number
+        }
+
+        /// The parsed number pattern, or `None` when the type has none or it no longer parses.
+        pub(crate) fn number_pattern(&self) -> Option<NumberPattern> {
+            if self.number_pattern.trim().is_empty() {
+                return None
+            }
+            return NumberPattern::parse(&self.number_pattern).ok()
+        }
+    }
+
+    impl fmt::Display for DocumentType {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "{}", self.name)
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    pub(crate) struct TypeField {
+        pub(crate) field_id: u32,
+        pub(crate) name: String,
+        pub(crate) kind: FieldKind,
+        pub(crate) required: bool,
+        /// The choices of an `Enum` field; empty for other kinds.
+        pub(crate) options: Vec<String>
+    }
+
+    impl TypeField {
+        /// Checks a value entered for the field. Empty values only fail when the field is required.
+        pub(crate) fn validate(&self, value: &str) -> Result<(), String> {
+            let value = value.trim();
+            if value.is_empty() {
+                if self.required {
+                    return Err(format!("{} is required.", self.name))
+                }
+                return Ok(())
+            }
+
+            let valid = match self.kind {
+                FieldKind::Text => true,
+                FieldKind::Number => value.parse::<f64>().is_ok(),
+                FieldKind::Date => Date::parse(value, format_description!("[year]-[month]-[day]")).is_ok(),
+                FieldKind::Currency => is_currency(value),
+                FieldKind::Enum => self.options.iter().any(|option| option == value)
+            };
+            if valid {
+                return Ok(())
+            }
+            return Err(match self.kind {
+                FieldKind::Number => format!("{} must be a number.", self.name),
+                FieldKind::Date => format!("{} must be a date like 2025-01-31.", self.name),
+                FieldKind::Currency => format!("{} must be an amount like 1234.50.", self.name),
+                _ => format!("{} must be one of: {}.", self.name, self.options.join(", "))
+            })
+        }
+    }
+
+    impl fmt::Display for TypeField {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "{}", self.name)
+        }
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub(crate) enum FieldKind {
+        Text,
+        Number,
+        Date,
+        Currency,
+        Enum
+    }
+
+    impl FieldKind {
+        pub(crate) const ALL: [FieldKind; 5] = [FieldKind::Text, FieldKind::Number, FieldKind::Date, FieldKind::Currency, FieldKind::Enum];
+
+        /// The value stored in the `type_field.kind` column.
+        pub(crate) fn as_str(&self) -> &'static str {
+            match self {
+                FieldKind::Text => "text",
+                FieldKind::Number => "number",
+                FieldKind::Date => "date",
+                FieldKind::Currency => "currency",
+                FieldKind::Enum => "enum"
+            }
+        }
+
+        pub(crate) fn from_str(kind: &str) -> FieldKind {
+            match kind {
+                "number" => FieldKind::Number,
+                "date" => FieldKind::Date,
+                "currency" => FieldKind::Currency,
+                "enum" => FieldKind::Enum,
+                _ => FieldKind::Text
+            }
+        }
+    }
+
+    impl fmt::Display for FieldKind {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            let name = match self {
+                FieldKind::Text => "Text",
+                FieldKind::Number => "Number",
+                FieldKind::Date => "Date",
+                FieldKind::Currency => "Currency",
+                FieldKind::Enum => "Choice"
+            };
+            write!(f, "{}", name)
+        }
+    }
+
+    /// An amount with at most two decimals, optionally negative.
+    fn is_currency(value: &str) -> bool {
+        let digits = value.strip_prefix('-').unwrap_or(value);
+        let (whole, cents) = match digits.split_once('.') {
+            Some((whole, cents)) => (whole, cents),
+            None => (digits, "")
+        };
+        return !whole.is_empty() && whole.chars().all(|c| c.is_ascii_digit()) && cents.len() <= 2 && cents.chars().all(|c| c.is_ascii_digit())
+    }
+}
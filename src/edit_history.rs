@@ -0,0 +1,327 @@
+pub(crate) mod edit_history {
+    use std::path::PathBuf;
+
+    use time::Date;
+
+    use crate::db::db_module::DbConnection;
+
+    /// How many edits are kept before the oldest ones are dropped.
+    const HISTORY_LIMIT: usize = 100;
+
+    #[derive(Debug, Clone, PartialEq)]
+    pub(crate) struct DocumentDetails {
+        pub(crate) document_number: String,
+        pub(crate) document_type: String,
+        pub(crate) comment: String,
+        pub(crate) field_values: Vec<(u32, String)>,
+        pub(crate) document_date: Option<Date>,
+        pub(crate) due_date: Option<Date>
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    pub(crate) struct AttachmentDetails {
+        pub(crate) reference_number: String,
+        pub(crate) comment: String,
+        pub(crate) pages: Vec<(PathBuf, String)>
+    }
+
+    /// A change made from the document list that can be reverted and applied again.
+    #[derive(Debug, Clone, PartialEq)]
+    pub(crate) enum Edit {
+        CreateDocument { document_id: u32 },
+        ChangeDocument { document_id: u32, before: DocumentDetails, after: DocumentDetails },
+        DeleteDocument { document_id: u32 },
+        CreateAttachment { document_id: u32, attachment_id: u32 },
+        ChangeAttachment { document_id: u32, attachment_id: u32, before: AttachmentDetails, after: AttachmentDetails },
+        DeleteAttachment { document_id: u32, attachment_id: u32 },
+        /// `document_id` is the document the attachment was moved to.
+        MoveAttachment { document_id: u32, attachment_id: u32, from_document_id: u32, before_reference_number: String, after_reference_number: String }
+    }
+
+    impl Edit {
+        pub(crate) fn document_id(&self) -> u32 {
+            match self {
+                Edit::CreateDocument { document_id } |
+                Edit::ChangeDocument { document_id, .. } |
+                Edit::DeleteDocument { document_id } |
+                Edit::CreateAttachment { document_id, .. } |
+                Edit::ChangeAttachment { document_id, .. } |
+                Edit::DeleteAttachment { document_id, .. } |
+                Edit::MoveAttachment { document_id, .. } => *document_id
+            }
+        }
+
+        pub(crate) fn attachment_id(&self) -> Option<u32> {
+            match self {
+                Edit::CreateAttachment { attachment_id, .. } |
+                Edit::ChangeAttachment { attachment_id, .. } |
+                Edit::DeleteAttachment { attachment_id, .. } |
+                Edit::MoveAttachment { attachment_id, .. } => Some(*attachment_id),
+                _ => None
+            }
+        }
+
+        /// Whether the document or attachment the edit is about still exists after `revert`/`apply`.
+        pub(crate) fn leaves_target(&self, reverted: bool) -> bool {
+            match self {
+                Edit::CreateDocument { .. } | Edit::CreateAttachment { .. } => !reverted,
+                Edit::DeleteDocument { .. } | Edit::DeleteAttachment { .. } => reverted,
+                Edit::ChangeDocument { .. } | Edit::ChangeAttachment { .. } | Edit::MoveAttachment { .. } => true
+            }
+        }
+
+        /// The documents the edit touches, which all have to be unlocked to revert or apply it.
+        pub(crate) fn document_ids(&self) -> Vec<u32> {
+            match self {
+                Edit::MoveAttachment { document_id, from_document_id, .. } => vec![*document_id, *from_document_id],
+                _ => vec![self.document_id()]
+            }
+        }
+
+        fn revert(&self, conn: &mut DbConnection) -> Result<(), rusqlite::Error> {
+            match self {
+                Edit::CreateDocument { document_id } => conn.delete_document(*document_id).map(|_| ()),
+                Edit::ChangeDocument { document_id, before, after } => save_document(conn, *document_id, after, before),
+                Edit::DeleteDocument { document_id } => conn.restore_document(*document_id).map(|_| ()),
+                Edit::CreateAttachment { attachment_id, .. } => conn.delete_attachment(*attachment_id).map(|_| ()),
+                Edit::ChangeAttachment { attachment_id, before, after, .. } => save_attachment(conn, *attachment_id, after, before),
+                Edit::DeleteAttachment { attachment_id, .. } => conn.restore_attachment(*attachment_id).map(|_| ()),
+                Edit::MoveAttachment { document_id, attachment_id, from_document_id, before_reference_number, after_reference_number } => {
+                    move_attachment(conn, *attachment_id, (*document_id, after_reference_number), (*from_document_id, before_reference_number))
+                }
+            }
+        }
+
+        fn apply(&self, conn: &mut DbConnection) -> Result<(), rusqlite::Error> {
+            match self {
+                Edit::CreateDocument { document_id } => conn.restore_document(*document_id).map(|_| ()),
+                Edit::ChangeDocument { document_id, before, after } => save_document(conn, *document_id, before, after),
+                Edit::DeleteDocument { document_id } => conn.delete_document(*document_id).map(|_| ()),
+                Edit::CreateAttachment { attachment_id, .. } => conn.restore_attachment(*attachment_id).map(|_| ()),
+                Edit::ChangeAttachment { attachment_id, before, after, .. } => save_attachment(conn, *attachment_id, before, after),
+                Edit::DeleteAttachment { attachment_id, .. } => conn.delete_attachment(*attachment_id).map(|_| ()),
+                Edit::MoveAttachment { document_id, attachment_id, from_document_id, before_reference_number, after_reference_number } => {
+                    move_attachment(conn, *attachment_id, (*from_document_id, before_reference_number), (*document_id, after_reference_number))
+                }
+            }
+        }
+    }
+
+    #[derive(Debug, Clone, Default)]
+    pub(crate) struct EditHistory {
+        undo_stack: Vec<Edit>,
+        redo_stack: Vec<Edit>
+    }
+
+    impl EditHistory {
+        pub(crate) fn new() -> EditHistory {
+            EditHistory {
+                undo_stack: Vec::new(),
+                redo_stack: Vec::new()
+            }
+        }
+
+        pub(crate) fn push(&mut self, edit: Edit) {
+            self.redo_stack.clear();
+            self.undo_stack.push(edit);
+            if self.undo_stack.len() > HISTORY_LIMIT {
+                self.undo_stack.remove(0);
+            }
+        }
+
+        /// Reverts the latest edit. The edit stays on the undo stack if reverting it fails.
+        pub(crate) fn undo(&mut self, conn: &mut DbConnection) -> Result<Option<Edit>, rusqlite::Error> {
+            let edit = match self.undo_stack.pop() {
+                Some(edit) => edit,
+                None => return Ok(None)
+            };
+            if let Err(err) = edit.revert(conn) {
+                self.undo_stack.push(edit);
+                return Err(err)
+            }
+            self.redo_stack.push(edit.clone());
+            return Ok(Some(edit))
+        }
+
+        pub(crate) fn redo(&mut self, conn: &mut DbConnection) -> Result<Option<Edit>, rusqlite::Error> {
+            let edit = match self.redo_stack.pop() {
+                Some(edit) => edit,
+                None => return Ok(None)
+            };
+            if let Err(err) = edit.apply(conn) {
+                self.redo_stack.push(edit);
+                return Err(err)
+            }
+            self.undo_stack.push(edit.clone());
+            return Ok(Some(edit))
+        }
+
+        /// The edit `undo` would revert next.
+        pub(crate) fn next_undo(&self) -> Option<&Edit> {
+            return self.undo_stack.last()
+        }
+
+        pub(crate) fn next_redo(&self) -> Option<&Edit> {
+            return self.redo_stack.last()
+        }
+
+        pub(crate) fn can_undo(&self) -> bool {
+            return !self.undo_stack.is_empty()
+        }
+
+        pub(crate) fn can_redo(&self) -> bool {
+            return !self.redo_stack.is_empty()
+        }
+    }
+
+    /// Writes `details` over the document if it still has the `expected` details, failing with a conflict (see
+    /// `is_conflict`) if someone changed it since. The write itself is checked against the version that was read.
+    fn save_document(conn: &mut DbConnection, document_id: u32, expected: &DocumentDetails, details: &DocumentDetails) -> Result<(), rusqlite::Error> {
+        let document = conn.read_document_summary(document_id)?.ok_or(rusqlite::Error::QueryReturnedNoRows)?;
+        let current = DocumentDetails {
+            document_number: document.get_document_number().to_string(),
+            document_type: document.get_document_type().to_string(),
+            comment: document.get_comment().to_string(),
+            field_values: expected.field_values.iter().map(|(field_id, _)| {
+                (*field_id, document.get_field_values().get(field_id).cloned().unwrap_or_default())
+            }).collect(),
+            document_date: document.get_document_date(),
+            due_date: document.get_due_date()
+        };
+        if current != *expected {
+            return Err(rusqlite::Error::QueryReturnedNoRows)
+        }
+        conn.edit_document_details(document_id, details, Some(document.get_version()))?;
+        return Ok(())
+    }
+
+    /// Like `save_document`. Pages go back through `swap_attachment_pages`, so stepping through the history does
+    /// not add a version each time.
+    fn save_attachment(conn: &mut DbConnection, attachment_id: u32, expected: &AttachmentDetails, details: &AttachmentDetails) -> Result<(), rusqlite::Error> {
+        let attachment = conn.read_attachment_summary(attachment_id)?.ok_or(rusqlite::Error::QueryReturnedNoRows)?;
+        let checksums = conn.read_page_checksums(attachment_id)?;
+        if *attachment.get_reference_number() != expected.reference_number || *attachment.get_comment() != expected.comment || checksums != page_checksums(expected) {
+            return Err(rusqlite::Error::QueryReturnedNoRows)
+        }
+        return match page_checksums(details) == checksums {
+            true => conn.save_attachment(attachment_id, details.reference_number.clone(), details.comment.clone(), None, Some(attachment.get_version())),
+            false => conn.swap_attachment_pages(attachment_id, details.reference_number.clone(), details.comment.clone(), details.pages.clone(), Some(attachment.get_version()))
+        }
+    }
+
+    /// Moves the attachment from `expected`, a document id and attachment number, to `target`, failing with a
+    /// conflict if it is no longer where the edit left it.
+    fn move_attachment(conn: &mut DbConnection, attachment_id: u32, expected: (u32, &String), target: (u32, &String)) -> Result<(), rusqlite::Error> {
+        let attachment = conn.read_attachment_summary(attachment_id)?.ok_or(rusqlite::Error::QueryReturnedNoRows)?;
+        if attachment.get_document_id() != expected.0 || *attachment.get_reference_number() != *expected.1 {
+            return Err(rusqlite::Error::QueryReturnedNoRows)
+        }
+        return conn.move_attachment(attachment_id, target.0, target.1.clone(), Some(attachment.get_version()))
+    }
+
+    fn page_checksums(details: &AttachmentDetails) -> Vec<String> {
+        return details.pages.iter().map(|(_, checksum)| checksum.clone()).collect()
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn details(comment: &str) -> DocumentDetails {
+            return DocumentDetails {
+                document_number: String::from("D-1"),
+                document_type: String::new(),
+                comment: String::from(comment),
+                field_values: Vec::new(),
+                document_date: None,
+                due_date: None
+            }
+        }
+
+        fn new_document(conn: &mut DbConnection, comment: &str) -> u32 {
+            let details = details(comment);
+            conn.new_document(details.document_number, details.document_type, details.comment, Vec::new(), None, None).unwrap();
+            return conn.last_rowid().unwrap() as u32
+        }
+
+        fn comment_of(conn: &DbConnection, document_id: u32) -> String {
+            return conn.read_document_summary(document_id).unwrap().unwrap().get_comment().to_string()
+        }
+
+        fn pages(checksums: &[&str]) -> Vec<(PathBuf, String)> {
+            return checksums.iter().map(|checksum| (PathBuf::from(format!("./data/objects/{}", checksum)), checksum.to_string())).collect()
+        }
+
+        #[test]
+        fn test_push_clears_redo_and_drops_oldest() {
+            let mut history = EditHistory::new();
+            for document_id in 0..HISTORY_LIMIT as u32 + 1 {
+                history.push(Edit::CreateDocument { document_id: document_id });
+            }
+            assert_eq!(history.undo_stack.len(), HISTORY_LIMIT);
+            assert_eq!(history.undo_stack[0], Edit::CreateDocument { document_id: 1 });
+
+            history.redo_stack.push(Edit::DeleteDocument { document_id: 0 });
+            assert!(history.can_redo());
+            history.push(Edit::DeleteDocument { document_id: 5 });
+            assert!(!history.can_redo());
+            assert_eq!(history.next_undo(), Some(&Edit::DeleteDocument { document_id: 5 }));
+        }
+
+        #[test]
+        fn test_undo_and_redo() {
+            let mut conn = DbConnection::in_memory();
+            let document_id = new_document(&mut conn, "before");
+            conn.edit_document_details(document_id, &details("after"), None).unwrap();
+            let mut history = EditHistory::new();
+            history.push(Edit::ChangeDocument { document_id: document_id, before: details("before"), after: details("after") });
+
+            assert!(history.undo(&mut conn).unwrap().is_some());
+            assert_eq!(comment_of(&conn, document_id), "before");
+            assert!(!history.can_undo());
+            assert_eq!(history.undo(&mut conn).unwrap(), None);
+
+            assert!(history.redo(&mut conn).unwrap().is_some());
+            assert_eq!(comment_of(&conn, document_id), "after");
+            assert!(history.can_undo() && !history.can_redo());
+        }
+
+        #[test]
+        fn test_undo_fails_after_change_elsewhere() {
+            let mut conn = DbConnection::in_memory();
+            let document_id = new_document(&mut conn, "after");
+            let mut history = EditHistory::new();
+            history.push(Edit::ChangeDocument { document_id: document_id, before: details("before"), after: details("after") });
+            conn.edit_document_details(document_id, &details("someone else"), None).unwrap();
+
+            let err = history.undo(&mut conn).unwrap_err();
+            assert!(crate::db::db_module::is_conflict(&err));
+            assert_eq!(comment_of(&conn, document_id), "someone else");
+            // The edit stays, so it can be undone once the other change is reverted.
+            assert!(history.can_undo());
+        }
+
+        #[test]
+        fn test_undo_of_pages_reuses_version() {
+            let mut conn = DbConnection::in_memory();
+            let document_id = new_document(&mut conn, "");
+            conn.new_attachment(pages(&["a"]), String::from("R-1"), String::new(), document_id).unwrap();
+            let attachment_id = conn.last_rowid().unwrap() as u32;
+            conn.save_attachment(attachment_id, String::from("R-1"), String::new(), Some(pages(&["b"])), None).unwrap();
+            let before = AttachmentDetails { reference_number: String::from("R-1"), comment: String::new(), pages: pages(&["a"]) };
+            let after = AttachmentDetails { pages: pages(&["b"]), ..before.clone() };
+            let mut history = EditHistory::new();
+            history.push(Edit::ChangeAttachment { document_id: document_id, attachment_id: attachment_id, before: before, after: after });
+
+            for _ in 0..3 {
+                history.undo(&mut conn).unwrap();
+                assert_eq!(conn.read_page_checksums(attachment_id).unwrap(), vec!["a"]);
+                history.redo(&mut conn).unwrap();
+                assert_eq!(conn.read_page_checksums(attachment_id).unwrap(), vec!["b"]);
+            }
+            let versions = conn.read_attachment_versions(attachment_id).unwrap();
+            assert_eq!(versions.len(), 1);
+            assert_eq!(versions[0].pages, pages(&["a"]));
+        }
+    }
+}
@@ -0,0 +1,251 @@
+pub(crate) mod library_check {
+    use std::{collections::HashSet, fs, path::{Path, PathBuf}};
+
+    use sha2::{Digest, Sha256};
+
+    use crate::{db::db_module::DbConnection, storage::storage};
+
+    /// A single disagreement between the `page`/`attachment` rows and the `./data` folder.
+    #[derive(Debug, Clone, PartialEq)]
+    pub(crate) enum Issue {
+        MissingFile { page_id: u32, attachment_id: u32, file_path: String },
+        OrphanFile { file_path: PathBuf },
+        OrphanDirectory { path: PathBuf },
+        ChecksumMismatch { page_id: u32, file_path: String, expected: String, actual: String },
+        EmptyAttachment { attachment_id: u32, reference_number: String, document_number: String }
+    }
+
+    impl Issue {
+        pub(crate) fn description(&self) -> String {
+            match self {
+                Issue::MissingFile { file_path, .. } => format!("Missing page file: {}", file_path),
+                Issue::OrphanFile { file_path } => format!("Stored file not used by any page: {}", file_path.to_string_lossy()),
+                Issue::OrphanDirectory { path } => format!("Unimported folder from the old layout: {}", path.to_string_lossy()),
+                Issue::ChecksumMismatch { file_path, .. } => format!("Checksum mismatch: {}", file_path),
+                Issue::EmptyAttachment { reference_number, document_number, .. } => format!("Attachment {} of document {} has no pages", reference_number, document_number)
+            }
+        }
+    }
+
+    #[derive(Debug, Clone, Default)]
+    pub(crate) struct IntegrityReport {
+        issues: Vec<Issue>,
+        pages_checked: usize,
+        files_checked: usize
+    }
+
+    impl IntegrityReport {
+        pub(crate) fn issues(&self) -> &Vec<Issue> {
+            return &self.issues
+        }
+
+        pub(crate) fn pages_checked(&self) -> usize {
+            return self.pages_checked
+        }
+
+        pub(crate) fn files_checked(&self) -> usize {
+            return self.files_checked
+        }
+    }
+
+    pub(crate) fn checksum(bytes: &[u8]) -> String {
+        let digest = Sha256::digest(bytes);
+        let mut hex = String::with_capacity(digest.len() * 2);
+        for byte in digest {
+            hex.push_str(&format!("{:02x}", byte));
+        }
+        return hex
+    }
+
+    pub(crate) fn scan(conn: &DbConnection) -> Result<IntegrityReport, rusqlite::Error> {
+        let mut report = IntegrityReport::default();
+        let pages = conn.read_page_records()?;
+        let attachments = conn.read_attachment_locations()?;
+
+        let mut referenced_files: HashSet<PathBuf> = HashSet::new();
+        for page in &pages {
+            report.pages_checked += 1;
+            referenced_files.insert(PathBuf::from(&page.file_path));
+            match storage::read_page_file(&page.file_path) {
+                Ok(bytes) => {
+                    if let Some(expected) = &page.checksum {
+                        let actual = storage::object_name(&bytes);
+                        if &actual != expected {
+                            report.issues.push(Issue::ChecksumMismatch {
+                                page_id: page.page_id,
+                                file_path: page.file_path.clone(),
+                                expected: expected.clone(),
+                                actual: actual
+                            });
+                        }
+                    }
+                },
+                Err(_) => {
+                    report.issues.push(Issue::MissingFile {
+                        page_id: page.page_id,
+                        attachment_id: page.attachment_id,
+                        file_path: page.file_path.clone()
+                    });
+                }
+            }
+        }
+
+        for attachment in &attachments {
+            if attachment.page_count == 0 {
+                report.issues.push(Issue::EmptyAttachment {
+                    attachment_id: attachment.attachment_id,
+                    reference_number: attachment.reference_number.clone(),
+                    document_number: attachment.document_number.clone()
+                });
+            }
+        }
+
+        referenced_files.extend(conn.read_version_file_paths()?.into_iter().map(PathBuf::from));
+        for object in storage::read_objects() {
+            report.files_checked += 1;
+            if !referenced_files.contains(&object) {
+                report.issues.push(Issue::OrphanFile { file_path: object });
+            }
+        }
+
+        // Anything else under ./data is left over from the folder-per-document layout.
+        for dir in read_dir_entries(&data_dir()) {
+            if dir.is_dir() && !storage::is_object(&dir) && !dir.starts_with(storage::STAGING_DIR) && contains_pages(&dir) {
+                report.issues.push(Issue::OrphanDirectory { path: dir });
+            }
+        }
+
+        return Ok(report)
+    }
+
+    /// Stores `source` as the page's new content.
+    pub(crate) fn relink_page(conn: &mut DbConnection, page_id: u32, source: &Path) -> Result<(), String> {
+        let bytes = fs::read(source).map_err(|err| format!("Error reading replacement file: {}", err))?;
+        let (path, page_checksum) = storage::store_page(&bytes).map_err(|err| format!("Error storing page file: {}", err))?;
+        conn.edit_page_file(page_id, path, page_checksum).map_err(|err| format!("Error updating page: {}", err))?;
+        return Ok(())
+    }
+
+    /// Deletes a stored page file that no page refers to.
+    pub(crate) fn remove_orphan_file(file_path: &Path) -> Result<(), String> {
+        fs::remove_file(file_path).map_err(|err| format!("Error removing orphaned file: {}", err))?;
+        return Ok(())
+    }
+
+    /// Recreates the document and/or attachment rows for a leftover `./data/{document_number}/{reference_number}`
+    /// folder and moves its files into the object store as pages.
+    pub(crate) fn import_orphan_directory(conn: &mut DbConnection, path: &Path) -> Result<(), String> {
+        let relative = path.strip_prefix(data_dir()).map_err(|_| "Folder is outside the data folder")?;
+        let names: Vec<String> = relative.components().map(|component| component.as_os_str().to_string_lossy().to_string()).collect();
+
+        match names.as_slice() {
+            [document_number] => {
+                let document_id = find_or_create_document(conn, document_number)?;
+                for attachment_dir in read_dir_entries(path) {
+                    if attachment_dir.is_dir() {
+                        import_attachment_dir(conn, document_id, &attachment_dir)?;
+                    }
+                }
+            },
+            [document_number, _] => {
+                let document_id = find_or_create_document(conn, document_number)?;
+                import_attachment_dir(conn, document_id, path)?;
+                if let Some(document_dir) = path.parent() {
+                    let _ = fs::remove_dir(document_dir);
+                }
+            },
+            _ => return Err(String::from("Folder is not a document or attachment folder"))
+        }
+        let _ = fs::remove_dir(path);
+
+        return Ok(())
+    }
+
+    pub(crate) fn remove_dead_page(conn: &mut DbConnection, page_id: u32) -> Result<(), String> {
+        conn.delete_page(page_id).map_err(|err| format!("Error removing page: {}", err))?;
+        return Ok(())
+    }
+
+    pub(crate) fn remove_empty_attachment(conn: &mut DbConnection, attachment_id: u32) -> Result<(), String> {
+        conn.purge_attachment(attachment_id).map_err(|err| format!("Error removing attachment: {}", err))?;
+        return Ok(())
+    }
+
+    /// Accepts the file currently on disk as the correct content for the page.
+    pub(crate) fn accept_checksum(conn: &mut DbConnection, page_id: u32, actual: &str) -> Result<(), String> {
+        conn.edit_page_checksum(page_id, actual.to_string()).map_err(|err| format!("Error updating page checksum: {}", err))?;
+        return Ok(())
+    }
+
+    fn import_attachment_dir(conn: &mut DbConnection, document_id: u32, path: &Path) -> Result<(), String> {
+        let reference_number = path.file_name().ok_or("Folder has no name")?.to_string_lossy().to_string();
+        let mut files: Vec<PathBuf> = read_dir_entries(path).into_iter().filter(|file| file.is_file() && !is_export(file)).collect();
+        if files.is_empty() {
+            return Ok(())
+        }
+        files.sort();
+
+        let mut pages: Vec<(PathBuf, String)> = Vec::new();
+        for file in &files {
+            let bytes = storage::read_page_file(file).map_err(|err| format!("Error reading orphaned file: {}", err))?;
+            pages.push(storage::store_page(&bytes).map_err(|err| format!("Error storing page file: {}", err))?);
+        }
+
+        match find_attachment(conn, document_id, &reference_number)? {
+            Some(attachment_id) => {
+                for (file_path, page_checksum) in pages {
+                    conn.add_page(attachment_id, file_path, page_checksum).map_err(|err| format!("Error adding page: {}", err))?;
+                }
+            },
+            None => {
+                conn.new_attachment(pages, reference_number, String::new(), document_id).map_err(|err| format!("Error creating attachment: {}", err))?;
+            }
+        }
+
+        for file in files {
+            fs::remove_file(&file).unwrap_or_else(|err| {
+                println!("Error removing imported file: {}", err);
+            });
+        }
+        let _ = fs::remove_dir(path);
+
+        return Ok(())
+    }
+
+    fn find_or_create_document(conn: &mut DbConnection, document_number: &str) -> Result<u32, String> {
+        let documents = conn.read_document_numbers().map_err(|err| format!("Error reading documents: {}", err))?;
+        if let Some((document_id, _)) = documents.iter().find(|(_, number)| number == document_number) {
+            return Ok(*document_id)
+        }
+        conn.new_document(document_number.to_string(), String::new(), String::from("Imported by integrity check"), Vec::new(), None, None).map_err(|err| format!("Error creating document: {}", err))?;
+        return Ok(conn.last_rowid().unwrap() as u32)
+    }
+
+    fn find_attachment(conn: &DbConnection, document_id: u32, reference_number: &str) -> Result<Option<u32>, String> {
+        let attachments = conn.read_attachment_locations().map_err(|err| format!("Error reading attachments: {}", err))?;
+        return Ok(attachments.iter().find(|attachment| attachment.document_id == document_id && attachment.reference_number == reference_number).map(|attachment| attachment.attachment_id))
+    }
+
+    fn read_dir_entries(path: &Path) -> Vec<PathBuf> {
+        match fs::read_dir(path) {
+            Ok(entries) => entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()).collect(),
+            Err(err) => {
+                println!("Error reading folder {}: {}", path.to_string_lossy(), err);
+                Vec::new()
+            }
+        }
+    }
+
+    fn contains_pages(path: &Path) -> bool {
+        return read_dir_entries(path).iter().any(|entry| if entry.is_dir() { contains_pages(entry) } else { !is_export(entry) })
+    }
+
+    // Exported PDFs used to be written next to the pages and were never tracked in the database.
+    fn is_export(path: &Path) -> bool {
+        return path.extension().map(|extension| extension.eq_ignore_ascii_case("pdf")).unwrap_or(false)
+    }
+
+    fn data_dir() -> PathBuf {
+        return PathBuf::from("./data")
+    }
+}
@@ -4,13 +4,27 @@ mod screen;
 mod document;
 mod attachment;
 mod attachment_page;
+mod library_check;
+mod storage;
+mod edit_history;
+mod tag;
+mod document_type;
+mod number_pattern;
+mod document_link;
+mod document_merge;
+mod bulk;
+mod saved_view;
+mod query;
+mod background;
+mod crypto;
 
 use std::fs;
 use std::path::Path;
+use std::time::{Duration, Instant};
 
 use hide_console_ng::hide_console;
 use iced::alignment::Horizontal::Left;
-use iced::{Border, Color, Element, Length, Subscription, Task, Theme};
+use iced::{Border, Color, Element, Event, Length, Subscription, Task, Theme, event, window};
 use iced::widget::{Button, Column, Container, Text, button, column, container, row, rule};
 use iced_aw::sidebar::TabLabel;
 use iced_aw::style::{card, sidebar};
@@ -22,11 +36,24 @@ use log4rs::encode::pattern::PatternEncoder;
 use screen::main_menu::main_menu;
 use screen::document_list::document_list;
 use screen::settings::settings;
+use screen::integrity::integrity;
+use screen::trash::trash;
+use screen::tags::tags;
+use screen::document_types::document_types;
+use screen::unlock::unlock;
 use serde::{Deserialize, Serialize};
 
 use crate::screen::{MainMenu};
 use crate::screen::DocumentList;
 use crate::screen::Settings;
+use crate::screen::Integrity;
+use crate::screen::Trash;
+use crate::screen::Tags;
+use crate::screen::DocumentTypes;
+use crate::screen::Unlock;
+use crate::db::db_module::{DbConnection, SESSION_HEARTBEAT, library_is_encrypted};
+use crate::saved_view::saved_view::SavedView;
+use crate::storage::storage::{encrypt_page_files, migrate_legacy_pages, recover_staged_pages, remove_unreferenced_objects};
 
 const ERROR_FERRIS: &[u8] = include_bytes!("../ferris-error-handling.webp");
 const HOME_IMAGE: &[u8] = include_bytes!("../home.jpg");
@@ -60,7 +87,18 @@ enum Message {
     SelectedTab(Tab),
     MainMenu(main_menu::Message),
     DocumentList(document_list::Message),
-    Settings(settings::Message)
+    Settings(settings::Message),
+    Integrity(integrity::Message),
+    Trash(trash::Message),
+    Tags(tags::Message),
+    DocumentTypes(document_types::Message),
+    Unlock(unlock::Message),
+    /// A key press or mouse input, which restarts the idle time.
+    UserActivity,
+    CheckIdle,
+    /// Renews this session's heartbeat, so other sessions know it is still running.
+    Heartbeat,
+    Lock
 }
 
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Default, Copy)]
@@ -68,6 +106,10 @@ pub(crate) enum Tab {
     #[default]
     Home,
     DocumentList,
+    DocumentTypes,
+    Tags,
+    Trash,
+    Integrity,
     Settings
 }
 
@@ -158,10 +200,30 @@ impl Into<Theme> for LocalTheme {
     }
 }
 
+/// How the document list shows documents.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub(crate) enum DocumentLayout {
+    #[default]
+    Grid,
+    Table
+}
+
 #[derive(Serialize, Deserialize)]
 struct Config {
     current_theme: LocalTheme,
-    show_console: bool
+    show_console: bool,
+    #[serde(default = "Config::default_trash_retention_days")]
+    trash_retention_days: u32,
+    #[serde(default = "Config::default_versions_kept")]
+    versions_kept: u32,
+    #[serde(default)]
+    document_layout: DocumentLayout,
+    /// Minutes without keyboard or mouse input before the application locks itself; 0 turns it off.
+    #[serde(default)]
+    idle_lock_minutes: u32,
+    /// Salted hash of the PIN that unlocks the application lock.
+    #[serde(default)]
+    lock_pin: Option<String>
 }
 
 impl Config {
@@ -179,6 +241,24 @@ impl Config {
         }
     }
 
+    fn save(&self) {
+        let serialized = toml::to_string(self).unwrap_or_else(|err| {
+            println!("Error serializing config to toml: {}", err);
+            String::new()
+        });
+        fs::write("./config.toml", serialized).unwrap_or_else(|err| {
+            println!("Error writing to config file: {}", err);
+        });
+    }
+
+    fn default_trash_retention_days() -> u32 {
+        30
+    }
+
+    fn default_versions_kept() -> u32 {
+        10
+    }
+
     fn change_theme(&mut self, theme: Theme) {
         self.current_theme = LocalTheme::from(theme);
     }
@@ -203,7 +283,12 @@ impl Default for Config {
     fn default() -> Self {
         Config {
             current_theme: LocalTheme::from(Theme::CatppuccinMacchiato),
-            show_console: false
+            show_console: false,
+            trash_retention_days: Config::default_trash_retention_days(),
+            versions_kept: Config::default_versions_kept(),
+            document_layout: DocumentLayout::default(),
+            idle_lock_minutes: 0,
+            lock_pin: None
         }
     }
 }
@@ -213,8 +298,16 @@ struct State {
     main_menu: MainMenu,
     document_list: DocumentList,
     settings: Settings,
+    integrity: Integrity,
+    trash: Trash,
+    tags: Tags,
+    document_types: DocumentTypes,
     config: Config,
-    previous_tab: Option<Tab>
+    previous_tab: Option<Tab>,
+    /// Shown instead of the sidebar and screens until an encrypted library is unlocked, or while the application
+    /// is locked.
+    unlock: Option<Unlock>,
+    last_activity: Instant
 }
 
 impl State {
@@ -249,24 +342,119 @@ impl State {
                 _ => Theme::Dark
             }
         );
-        State {
+        settings.set_trash_retention_days(config.trash_retention_days);
+        settings.set_versions_kept(config.versions_kept);
+        settings.set_encrypted(library_is_encrypted());
+        settings.set_lock(config.idle_lock_minutes, config.lock_pin.clone());
+        let mut state = State {
             current_tab: Tab::default(),
-            main_menu: MainMenu::new(),
-            document_list: DocumentList::new(),
+            main_menu: MainMenu::default(),
+            document_list: DocumentList::default(),
             settings,
+            integrity: Integrity::default(),
+            trash: Trash::default(),
+            tags: Tags::default(),
+            document_types: DocumentTypes::default(),
             config,
-            previous_tab: None
+            previous_tab: None,
+            unlock: None,
+            last_activity: Instant::now()
+        };
+        if library_is_encrypted() {
+            state.unlock = Some(Unlock::new());
+        }
+        else {
+            state.open_library();
+        }
+        state
+    }
+
+    /// Tidies up the library and loads the screens from it. Runs at startup, or once an encrypted library was unlocked.
+    fn open_library(&mut self) {
+        let config = &self.config;
+        // Checked in first, so sessions starting meanwhile leave this one's files alone.
+        DbConnection::new().renew_session().unwrap_or_else(|err| {
+            println!("Error renewing session: {}", err);
+        });
+        recover_staged_pages(&DbConnection::new()).unwrap_or_else(|err| {
+            println!("Error recovering interrupted saves: {}", err);
+        });
+        if library_is_encrypted() {
+            // Finishes encrypting page files if that was interrupted.
+            encrypt_page_files(&mut DbConnection::new(), |_, _| {}).unwrap_or_else(|err| {
+                println!("Error encrypting page files: {}", err);
+                0
+            });
         }
+        migrate_legacy_pages(&mut DbConnection::new()).unwrap_or_else(|err| {
+            println!("Error moving pages into the object store: {}", err);
+        });
+        trash::purge_expired(config.trash_retention_days);
+        DbConnection::new().purge_old_versions(config.versions_kept).unwrap_or_else(|err| {
+            println!("Error removing old attachment versions: {}", err);
+            0
+        });
+        remove_unreferenced_objects(&DbConnection::new()).unwrap_or_else(|err| {
+            println!("Error removing unused page files: {}", err);
+            0
+        });
+        let mut document_list = DocumentList::new();
+        document_list.set_layout(config.document_layout);
+        self.main_menu = MainMenu::new();
+        self.document_list = document_list;
+        self.integrity = Integrity::new();
+        self.trash = Trash::new(config.trash_retention_days);
+        self.tags = Tags::new();
+        self.document_types = DocumentTypes::new();
     }
 
     fn update(&mut self, message: Message) -> Task<Message> {
+        // Settings stay open while the library is encrypted, so nothing is edited until its page files are done.
+        if self.settings.is_encrypting() && leaves_settings(&message) {
+            return Task::none()
+        }
         match message {
+            Message::Unlock(unlock_message) => {
+                match unlock_message {
+                    unlock::Message::Unlocked => {
+                        let opens_library = self.unlock.as_ref().is_some_and(Unlock::opens_library);
+                        self.unlock = None;
+                        self.last_activity = Instant::now();
+                        if opens_library {
+                            self.open_library();
+                        }
+                    },
+                    _ => {
+                        if let Some(unlock) = self.unlock.as_mut() {
+                            return unlock.update(unlock_message).map(Message::Unlock)
+                        }
+                    }
+                }
+            },
+            Message::UserActivity => {
+                self.last_activity = Instant::now();
+            },
+            Message::CheckIdle => {
+                let idle_limit = Duration::from_secs(u64::from(self.config.idle_lock_minutes) * 60);
+                if self.unlock.is_none() && self.config.idle_lock_minutes > 0 && self.last_activity.elapsed() >= idle_limit {
+                    self.lock();
+                }
+            },
+            Message::Lock => {
+                self.lock();
+            },
+            Message::Heartbeat => {
+                DbConnection::new().renew_session().unwrap_or_else(|err| {
+                    println!("Error renewing session: {}", err);
+                });
+            },
             Message::SelectedTab(tab) => {
                 match tab {
                     Tab::Home => {
                         if tab != self.current_tab {
                             self.previous_tab = Some(self.current_tab);
                         }
+                        self.main_menu.reload();
                         self.current_tab = tab;
                     },
                     Tab::DocumentList => {
@@ -276,6 +464,33 @@ impl State {
                         self.document_list.set_current_theme(self.config.current_theme().into());
                         self.current_tab = tab;
                     },
+                    Tab::DocumentTypes => {
+                        if tab != self.current_tab {
+                            self.previous_tab = Some(self.current_tab);
+                        }
+                        self.document_types.reload();
+                        self.current_tab = tab;
+                    },
+                    Tab::Tags => {
+                        if tab != self.current_tab {
+                            self.previous_tab = Some(self.current_tab);
+                        }
+                        self.tags.reload();
+                        self.current_tab = tab;
+                    },
+                    Tab::Trash => {
+                        if tab != self.current_tab {
+                            self.previous_tab = Some(self.current_tab);
+                        }
+                        self.trash.reload();
+                        self.current_tab = tab;
+                    },
+                    Tab::Integrity => {
+                        if tab != self.current_tab {
+                            self.previous_tab = Some(self.current_tab);
+                        }
+                        self.current_tab = tab;
+                    },
                     Tab::Settings => {
                         if tab != self.current_tab {
                             self.previous_tab = Some(self.current_tab);
@@ -286,6 +501,12 @@ impl State {
             },
             Message::MainMenu(main_menu_message) => {
                 match main_menu_message {
+                    main_menu::Message::OpenDocument(document) => {
+                        self.previous_tab = Some(self.current_tab);
+                        self.current_tab = Tab::DocumentList;
+                        self.document_list.set_current_theme(self.config.current_theme().into());
+                        return self.document_list.update(document_list::Message::OpenDocument(document)).map(Message::DocumentList)
+                    },
                     _ => {
                         return self.main_menu.update(main_menu_message).map(Message::MainMenu)
                     }
@@ -293,6 +514,19 @@ impl State {
             },
             Message::DocumentList(document_list_message) => {
                 match document_list_message {
+                    document_list::Message::OpenView(_) => {
+                        if self.current_tab != Tab::DocumentList {
+                            self.previous_tab = Some(self.current_tab);
+                        }
+                        self.current_tab = Tab::DocumentList;
+                        self.document_list.set_current_theme(self.config.current_theme().into());
+                        return self.document_list.update(document_list_message).map(Message::DocumentList)
+                    },
+                    document_list::Message::LayoutChange(layout) => {
+                        self.config.document_layout = layout;
+                        self.config.save();
+                        return self.document_list.update(document_list_message).map(Message::DocumentList)
+                    },
                     document_list::Message::Back => {
                         if self.current_tab != self.previous_tab.unwrap() {
                             self.current_tab = self.previous_tab.unwrap_or_else(|| {
@@ -312,29 +546,55 @@ impl State {
                 }
             },
             Message::Settings(settings_message) => {
+                // Activity is only tracked while the idle lock is armed, and setting the minutes or a PIN, or
+                // finishing encryption, may have just armed it.
+                self.last_activity = Instant::now();
                 match settings_message {
                     settings::Message::ChangeTheme(theme) => {
                         self.config.change_theme(theme.clone());
-                        let serialized = toml::to_string(&self.config).unwrap_or_else(|err| {
-                            println!("Error serializing config to toml: {}", err);
-                            String::new()
-                        });
-                        fs::write("./config.toml", serialized).unwrap_or_else(|err| {
-                            println!("Error writing to config file: {}", err);
-                        });
+                        self.config.save();
                         self.settings.set_theme(theme.clone());
                         self.document_list.set_current_theme(theme.clone().into());
                     }
                     settings::Message::ShowConsole(show_console) => {
                         self.config.show_console = show_console;
                         self.config.show_console();
-                        let serialized = toml::to_string(&self.config).unwrap_or_else(|err| {
-                            println!("Error serializing config to toml: {}", err);
-                            String::new()
-                        });
-                        fs::write("./config.toml", serialized).unwrap_or_else(|err| {
-                            println!("Error writing to config file: {}", err);
-                        });
+                        self.config.save();
+                        return self.settings.update(settings_message).map(Message::Settings)
+                    }
+                    settings::Message::TrashRetentionChange(ref input) => {
+                        if let Ok(days) = input.trim().parse::<u32>() {
+                            self.config.trash_retention_days = days;
+                            self.config.save();
+                            self.trash.set_retention_days(days);
+                        }
+                        return self.settings.update(settings_message).map(Message::Settings)
+                    }
+                    settings::Message::VersionsKeptChange(ref input) => {
+                        if let Ok(versions_kept) = input.trim().parse::<u32>() {
+                            self.config.versions_kept = versions_kept;
+                            self.config.save();
+                        }
+                        return self.settings.update(settings_message).map(Message::Settings)
+                    }
+                    settings::Message::IdleLockChange(ref input) => {
+                        if let Ok(minutes) = input.trim().parse::<u32>() {
+                            self.config.idle_lock_minutes = minutes;
+                            self.config.save();
+                        }
+                        return self.settings.update(settings_message).map(Message::Settings)
+                    }
+                    settings::Message::EncryptLibrary | settings::Message::ChangePassphrase => {
+                        // The job's connection would keep writing to the old library file.
+                        if let Some(label) = self.document_list.running_job() {
+                            self.settings.set_encryption_message(format!("Wait for \"{}\" to finish or cancel it first.", label));
+                            return Task::none()
+                        }
+                        return self.settings.update(settings_message).map(Message::Settings)
+                    }
+                    settings::Message::LockPinChanged(ref pin_hash) => {
+                        self.config.lock_pin = pin_hash.clone();
+                        self.config.save();
                         return self.settings.update(settings_message).map(Message::Settings)
                     }
                     settings::Message::Back => {
@@ -351,6 +611,94 @@ impl State {
                         return self.settings.update(settings_message).map(Message::Settings)
                     }
                 }
+            },
+            Message::Trash(trash_message) => {
+                match trash_message {
+                    trash::Message::Back => {
+                        if self.current_tab != self.previous_tab.unwrap() {
+                            self.current_tab = self.previous_tab.unwrap_or_else(|| {
+                                println!("No previous tab");
+                                self.current_tab
+                            });
+                        } else {
+                            self.current_tab = Tab::Home;
+                        }
+                    }
+                    _ => {
+                        let changes_library = Trash::changes_library(&trash_message);
+                        let task = self.trash.update(trash_message).map(Message::Trash);
+                        if changes_library {
+                            self.document_list.reload_documents();
+                        }
+                        return task
+                    }
+                }
+            },
+            Message::DocumentTypes(document_types_message) => {
+                match document_types_message {
+                    document_types::Message::Back => {
+                        if self.current_tab != self.previous_tab.unwrap() {
+                            self.current_tab = self.previous_tab.unwrap_or_else(|| {
+                                println!("No previous tab");
+                                self.current_tab
+                            });
+                        } else {
+                            self.current_tab = Tab::Home;
+                        }
+                    }
+                    _ => {
+                        let changes_library = DocumentTypes::changes_library(&document_types_message);
+                        let task = self.document_types.update(document_types_message).map(Message::DocumentTypes);
+                        if changes_library {
+                            self.document_list.reload_documents();
+                        }
+                        return task
+                    }
+                }
+            },
+            Message::Tags(tags_message) => {
+                match tags_message {
+                    tags::Message::Back => {
+                        if self.current_tab != self.previous_tab.unwrap() {
+                            self.current_tab = self.previous_tab.unwrap_or_else(|| {
+                                println!("No previous tab");
+                                self.current_tab
+                            });
+                        } else {
+                            self.current_tab = Tab::Home;
+                        }
+                    }
+                    _ => {
+                        let changes_library = Tags::changes_library(&tags_message);
+                        let task = self.tags.update(tags_message).map(Message::Tags);
+                        if changes_library {
+                            self.document_list.reload_documents();
+                        }
+                        return task
+                    }
+                }
+            },
+            Message::Integrity(integrity_message) => {
+                match integrity_message {
+                    integrity::Message::Back => {
+                        if self.current_tab != self.previous_tab.unwrap() {
+                            self.current_tab = self.previous_tab.unwrap_or_else(|| {
+                                println!("No previous tab");
+                                self.current_tab
+                            });
+                        } else {
+                            self.current_tab = Tab::Home;
+                        }
+                    }
+                    _ => {
+                        let changes_library = Integrity::changes_library(&integrity_message);
+                        let task = self.integrity.update(integrity_message).map(Message::Integrity);
+                        if changes_library {
+                            self.document_list.reload_documents();
+                        }
+                        return task
+                    }
+                }
             }
             
         }
@@ -359,30 +707,80 @@ impl State {
 
     fn view(&self) -> Element<Message> {
         self.config.show_console();
+        if let Some(unlock) = &self.unlock {
+            return unlock.view().map(Message::Unlock)
+        }
         let screen = match &self.current_tab {
             Tab::Home => self.main_menu.view().map(Message::MainMenu),
             Tab::DocumentList => self.document_list.view().map(Message::DocumentList),
+            Tab::DocumentTypes => self.document_types.view().map(Message::DocumentTypes),
+            Tab::Tags => self.tags.view().map(Message::Tags),
+            Tab::Trash => self.trash.view().map(Message::Trash),
+            Tab::Integrity => self.integrity.view().map(Message::Integrity),
             Tab::Settings => self.settings.view().map(Message::Settings)
         };
         Container::new(row![
             Container::new(
-                sidebar(self.current_tab)
+                sidebar(self.current_tab, self.document_list.saved_views(), self.can_lock())
             ).padding(5),
             container(screen).padding(5).width(Length::FillPortion(5))
         ]).into()
     }
 
     fn subscription(&self) -> Subscription<Message> {
-        match &self.current_tab {
+        // Background jobs keep running while the application is locked, so the session keeps checking in too.
+        if self.unlock.as_ref().is_some_and(Unlock::opens_library) {
+            return Subscription::none()
+        }
+        let heartbeat = iced::time::every(SESSION_HEARTBEAT).map(|_| Message::Heartbeat);
+        if self.unlock.is_some() {
+            return heartbeat
+        }
+        let screen = match &self.current_tab {
             Tab::DocumentList => {
                 self.document_list.subscription().map(Message::DocumentList)
             }
             Tab::Home => {
                 Subscription::none()
             },
+            Tab::DocumentTypes => {
+                Subscription::none()
+            },
+            Tab::Tags => {
+                Subscription::none()
+            },
+            Tab::Trash => {
+                Subscription::none()
+            },
+            Tab::Integrity => {
+                Subscription::none()
+            },
             Tab::Settings => {
                 Subscription::none()
             }
+        };
+        if self.config.idle_lock_minutes == 0 || !self.can_lock() {
+            return Subscription::batch([screen, heartbeat])
+        }
+        Subscription::batch([
+            screen,
+            heartbeat,
+            event::listen_with(user_activity),
+            iced::time::every(Duration::from_secs(10)).map(|_| Message::CheckIdle)
+        ])
+    }
+
+    /// The application can only be locked with something to unlock it: a PIN, or the passphrase of an encrypted
+    /// library.
+    fn can_lock(&self) -> bool {
+        return self.config.lock_pin.is_some() || self.settings.is_encrypted()
+    }
+
+    /// Hides the sidebar and screens behind the lock screen. They keep their state and background jobs keep
+    /// running, so unlocking returns to where the user left off.
+    fn lock(&mut self) {
+        if self.unlock.is_none() && self.can_lock() {
+            self.unlock = Some(Unlock::application(self.config.lock_pin.clone()));
         }
     }
 
@@ -397,7 +795,34 @@ impl Default for State {
     }
 }
 
-fn sidebar(selected_tab: Tab) -> Element<'static, Message> {
+/// Whether the message would take the user away from the settings.
+fn leaves_settings(message: &Message) -> bool {
+    return matches!(message,
+        Message::SelectedTab(_)
+        | Message::MainMenu(main_menu::Message::OpenDocument(_))
+        | Message::DocumentList(document_list::Message::OpenView(_))
+        | Message::Settings(settings::Message::Back)
+    )
+}
+
+fn user_activity(event: Event, _status: event::Status, _window: window::Id) -> Option<Message> {
+    match event {
+        Event::Keyboard(_) | Event::Mouse(_) => Some(Message::UserActivity),
+        _ => None
+    }
+}
+
+fn sidebar(selected_tab: Tab, saved_views: &[SavedView], can_lock: bool) -> Element<'static, Message> {
+    let mut view_buttons = column![].spacing(2).padding(iced::Padding::ZERO.left(15));
+    for view in saved_views {
+        view_buttons = view_buttons.push(
+            button(Text::new(view.name.clone()).size(14))
+                .on_press(Message::DocumentList(document_list::Message::OpenView(view.view_id)))
+                .width(Length::Fill)
+                .style(move |theme: &Theme, status| sidebar_button_style(theme, status))
+        );
+    }
+
     Container::new(
         column![
             button(Text::from("Home").size(18)).on_press(Message::SelectedTab(Tab::Home)).width(Length::Fill).style(move |theme: &Theme, status| 
@@ -416,6 +841,39 @@ fn sidebar(selected_tab: Tab) -> Element<'static, Message> {
                     sidebar_button_style(theme, status)
                 }
             ),
+            view_buttons,
+            button(Text::from("Document Types").size(18)).on_press(Message::SelectedTab(Tab::DocumentTypes)).width(Length::Fill).style(move |theme: &Theme, status|
+                if selected_tab == Tab::DocumentTypes {
+                    sidebar_button_selected_style(theme)
+                }
+                else {
+                    sidebar_button_style(theme, status)
+                }
+            ),
+            button(Text::from("Tags").size(18)).on_press(Message::SelectedTab(Tab::Tags)).width(Length::Fill).style(move |theme: &Theme, status|
+                if selected_tab == Tab::Tags {
+                    sidebar_button_selected_style(theme)
+                }
+                else {
+                    sidebar_button_style(theme, status)
+                }
+            ),
+            button(Text::from("Trash").size(18)).on_press(Message::SelectedTab(Tab::Trash)).width(Length::Fill).style(move |theme: &Theme, status|
+                if selected_tab == Tab::Trash {
+                    sidebar_button_selected_style(theme)
+                }
+                else {
+                    sidebar_button_style(theme, status)
+                }
+            ),
+            button(Text::from("Integrity").size(18)).on_press(Message::SelectedTab(Tab::Integrity)).width(Length::Fill).style(move |theme: &Theme, status|
+                if selected_tab == Tab::Integrity {
+                    sidebar_button_selected_style(theme)
+                }
+                else {
+                    sidebar_button_style(theme, status)
+                }
+            ),
             button(Text::from("Settings").size(18)).on_press(Message::SelectedTab(Tab::Settings)).width(Length::Fill).style(move |theme: &Theme, status|
                 if selected_tab == Tab::Settings {
                     sidebar_button_selected_style(theme)
@@ -423,7 +881,9 @@ fn sidebar(selected_tab: Tab) -> Element<'static, Message> {
                 else {
                     sidebar_button_style(theme, status)
                 }
-            )
+            ),
+            can_lock.then(|| rule::horizontal(1)),
+            can_lock.then(|| button(Text::from("Lock").size(18)).on_press(Message::Lock).width(Length::Fill).style(move |theme: &Theme, status| sidebar_button_style(theme, status)))
         ].spacing(5).align_x(Left)
     ).width(Length::FillPortion(1)).into()
 }
@@ -0,0 +1,216 @@
+pub(crate) mod number_pattern {
+    use regex::Regex;
+    use time::Date;
+
+    /// A rule document numbers have to follow: a template such as `INV-{YYYY}-{seq:5}` or a regular expression.
+    #[derive(Debug, Clone)]
+    pub(crate) struct NumberPattern {
+        pattern: String,
+        regex: Regex,
+        /// `None` for regular expressions, which can check numbers but not generate them.
+        segments: Option<Vec<Segment>>
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    enum Segment {
+        Literal(String),
+        Year,
+        ShortYear,
+        Month,
+        Day,
+        /// The running number, zero-padded to the given width.
+        Sequence(usize)
+    }
+
+    impl NumberPattern {
+        /// Braces starting with a letter make the pattern a template; anything else is read as a regular expression
+        /// that has to match the whole number.
+        pub(crate) fn parse(pattern: &str) -> Result<NumberPattern, String> {
+            let pattern = pattern.trim();
+            if pattern.is_empty() {
+                return Err(String::from("The pattern is empty."))
+            }
+
+            if is_template(pattern) {
+                let segments = parse_template(pattern)?;
+                let regex = Regex::new(&format!("^{}$", segments.iter().map(segment_regex).collect::<String>())).map_err(|err| err.to_string())?;
+                return Ok(NumberPattern {
+                    pattern: pattern.to_string(),
+                    regex: regex,
+                    segments: Some(segments)
+                })
+            }
+
+            let regex = Regex::new(&format!("^(?:{})$", pattern)).map_err(|err| format!("The pattern is not a valid regular expression: {}", err))?;
+            return Ok(NumberPattern {
+                pattern: pattern.to_string(),
+                regex: regex,
+                segments: None
+            })
+        }
+
+        pub(crate) fn as_str(&self) -> &str {
+            return &self.pattern
+        }
+
+        pub(crate) fn matches(&self, document_number: &str) -> bool {
+            return self.regex.is_match(document_number)
+        }
+
+        pub(crate) fn can_generate(&self) -> bool {
+            return self.segments.is_some()
+        }
+
+        /// The number following the highest one in `used` that was given out for the same date parts, so the
+        /// sequence starts over with every new year when the template contains `{YYYY}`. Returns `None` for regular
+        /// expressions and for templates without `{seq}` whose only number is already used.
+        pub(crate) fn next_number(&self, date: Date, used: &[String]) -> Option<String> {
+            let segments = self.segments.as_ref()?;
+
+            let mut sequence_regex = String::from("^");
+            for segment in segments {
+                match segment {
+                    Segment::Sequence(_) => sequence_regex.push_str("(\\d+)"),
+                    _ => sequence_regex.push_str(&regex::escape(&render(segment, date, 0)))
+                }
+            }
+            sequence_regex.push('$');
+            let sequence_regex = Regex::new(&sequence_regex).ok()?;
+
+            let highest = used.iter()
+                .filter_map(|document_number| sequence_regex.captures(document_number))
+                .filter_map(|captures| captures.get(1).and_then(|sequence| sequence.as_str().parse::<u64>().ok()))
+                .max()
+                .unwrap_or(0);
+            let next: String = segments.iter().map(|segment| render(segment, date, highest + 1)).collect();
+            if used.contains(&next) {
+                return None
+            }
+            return Some(next)
+        }
+    }
+
+    fn is_template(pattern: &str) -> bool {
+        return pattern.match_indices('{').any(|(index, _)| pattern[index + 1..].starts_with(|c: char| c.is_ascii_alphabetic()))
+    }
+
+    fn parse_template(pattern: &str) -> Result<Vec<Segment>, String> {
+        let mut segments: Vec<Segment> = Vec::new();
+        let mut rest = pattern;
+
+        while let Some(start) = rest.find('{') {
+            if start > 0 {
+                segments.push(Segment::Literal(rest[..start].to_string()));
+            }
+            let end = match rest[start..].find('}') {
+                Some(end) => start + end,
+                None => return Err(format!("The placeholder starting at \"{}\" is not closed.", &rest[start..]))
+            };
+            let placeholder = &rest[start + 1..end];
+            segments.push(match placeholder {
+                "YYYY" => Segment::Year,
+                "YY" => Segment::ShortYear,
+                "MM" => Segment::Month,
+                "DD" => Segment::Day,
+                "seq" => Segment::Sequence(0),
+                _ => match placeholder.strip_prefix("seq:").and_then(|width| width.parse::<usize>().ok()) {
+                    Some(width) if (1..=12).contains(&width) => Segment::Sequence(width),
+                    _ => return Err(format!("Unknown placeholder {{{}}}. Use {{YYYY}}, {{YY}}, {{MM}}, {{DD}}, {{seq}} or {{seq:N}}.", placeholder))
+                }
+            });
+            rest = &rest[end + 1..];
+        }
+        if !rest.is_empty() {
+            segments.push(Segment::Literal(rest.to_string()));
+        }
+
+        if segments.iter().filter(|segment| matches!(segment, Segment::Sequence(_))).count() > 1 {
+            return Err(String::from("A pattern can only contain one {seq}."))
+        }
+        return Ok(segments)
+    }
+
+    fn segment_regex(segment: &Segment) -> String {
+        return match segment {
+            Segment::Literal(text) => regex::escape(text),
+            Segment::Year => String::from("\\d{4}"),
+            Segment::ShortYear | Segment::Month | Segment::Day => String::from("\\d{2}"),
+            Segment::Sequence(0) => String::from("\\d+"),
+            Segment::Sequence(width) => format!("\\d{{{},}}", width)
+        }
+    }
+
+    fn render(segment: &Segment, date: Date, sequence: u64) -> String {
+        return match segment {
+            Segment::Literal(text) => text.clone(),
+            Segment::Year => format!("{:04}", date.year()),
+            Segment::ShortYear => format!("{:02}", date.year() % 100),
+            Segment::Month => format!("{:02}", date.month() as u8),
+            Segment::Day => format!("{:02}", date.day()),
+            Segment::Sequence(width) => format!("{:0width$}", sequence, width = *width)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use time::macros::date;
+
+        fn used(numbers: &[&str]) -> Vec<String> {
+            return numbers.iter().map(|number| number.to_string()).collect()
+        }
+
+        #[test]
+        fn test_parse_template() {
+            let pattern = NumberPattern::parse(" INV-{YYYY}-{seq:5} ").unwrap();
+            assert_eq!(pattern.as_str(), "INV-{YYYY}-{seq:5}");
+            assert!(pattern.can_generate());
+            assert!(pattern.matches("INV-2024-00042"));
+            assert!(pattern.matches("INV-2024-123456"));
+            assert!(!pattern.matches("INV-2024-42"));
+            assert!(!pattern.matches("INV-24-00042"));
+            assert!(!pattern.matches("XINV-2024-00042"));
+        }
+
+        #[test]
+        fn test_parse_regex() {
+            let pattern = NumberPattern::parse("[A-Z]{2}\\d+").unwrap();
+            assert!(!pattern.can_generate());
+            assert!(pattern.matches("AB12"));
+            assert!(!pattern.matches("AB12x"));
+            assert_eq!(pattern.next_number(date!(2024 - 03 - 05), &[]), None);
+        }
+
+        #[test]
+        fn test_parse_errors() {
+            assert!(NumberPattern::parse("  ").is_err());
+            assert!(NumberPattern::parse("A-{YYYY").is_err());
+            assert!(NumberPattern::parse("A-{week}").is_err());
+            assert!(NumberPattern::parse("A-{seq:0}").is_err());
+            assert!(NumberPattern::parse("{seq}-{seq}").is_err());
+            assert!(NumberPattern::parse("[A-Z").is_err());
+        }
+
+        #[test]
+        fn test_next_number() {
+            let pattern = NumberPattern::parse("INV-{YYYY}-{seq:3}").unwrap();
+            let today = date!(2024 - 03 - 05);
+            assert_eq!(pattern.next_number(today, &[]), Some(String::from("INV-2024-001")));
+            assert_eq!(pattern.next_number(today, &used(&["INV-2024-001", "INV-2024-007", "INV-2023-050", "other"])), Some(String::from("INV-2024-008")));
+            // The sequence starts over in a new year and grows past its width.
+            assert_eq!(pattern.next_number(date!(2025 - 01 - 01), &used(&["INV-2024-007"])), Some(String::from("INV-2025-001")));
+            assert_eq!(pattern.next_number(today, &used(&["INV-2024-999"])), Some(String::from("INV-2024-1000")));
+        }
+
+        #[test]
+        fn test_next_number_date_parts() {
+            let pattern = NumberPattern::parse("{YY}{MM}{DD}-{seq}").unwrap();
+            let today = date!(2024 - 03 - 05);
+            assert_eq!(pattern.next_number(today, &used(&["240305-9"])), Some(String::from("240305-10")));
+
+            let pattern = NumberPattern::parse("DAY-{YYYY}{MM}{DD}").unwrap();
+            assert_eq!(pattern.next_number(today, &[]), Some(String::from("DAY-20240305")));
+            assert_eq!(pattern.next_number(today, &used(&["DAY-20240305"])), None);
+        }
+    }
+}
@@ -0,0 +1,470 @@
+pub(crate) mod query {
+    use std::fmt;
+
+    use crate::document::document::{DocumentStatus, parse_date};
+
+    /// The fields a search term can name, with what autocomplete says about them.
+    const FIELDS: [(&str, QueryField, &str); 9] = [
+        ("number", QueryField::Number, "document number"),
+        ("type", QueryField::DocumentType, "document type"),
+        ("comment", QueryField::Comment, "words in the comment"),
+        ("ref", QueryField::Reference, "attachment number"),
+        ("tag", QueryField::Tag, "tag of the document or an attachment"),
+        ("status", QueryField::Status, "draft, review, approved or archived"),
+        ("added", QueryField::Added, "date added, e.g. added:>2025-01-01"),
+        ("date", QueryField::DocumentDate, "document date"),
+        ("due", QueryField::DueDate, "due date")
+    ];
+
+    /// A parsed search such as `type:invoice added:>2025-01-01 ref:PO-* -comment:void`. All terms have to match.
+    #[derive(Debug, Clone, PartialEq)]
+    pub(crate) struct Query {
+        terms: Vec<Term>
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Term {
+        negated: bool,
+        /// `None` for plain words, which are looked for in every text field.
+        field: Option<QueryField>,
+        comparison: Comparison,
+        value: String
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    enum QueryField {
+        Number,
+        DocumentType,
+        Comment,
+        Reference,
+        Tag,
+        Status,
+        Added,
+        DocumentDate,
+        DueDate,
+        /// A custom field, by its name in lower case with spaces written as underscores.
+        Custom(String)
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    enum Comparison {
+        Matches,
+        Less,
+        LessOrEqual,
+        Greater,
+        GreaterOrEqual
+    }
+
+    impl Comparison {
+        fn operator(&self) -> &'static str {
+            match self {
+                Comparison::Matches => "=",
+                Comparison::Less => "<",
+                Comparison::LessOrEqual => "<=",
+                Comparison::Greater => ">",
+                Comparison::GreaterOrEqual => ">="
+            }
+        }
+    }
+
+    /// Why a search could not be parsed, and where in the text.
+    #[derive(Debug, Clone, PartialEq)]
+    pub(crate) struct QueryError {
+        pub(crate) message: String,
+        /// Character offset of the term the error is about.
+        pub(crate) position: usize
+    }
+
+    impl fmt::Display for QueryError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "{} (at character {})", self.message, self.position + 1)
+        }
+    }
+
+    impl Query {
+        /// Parses the search text. `custom_fields` are the names of the custom fields that can be searched, as
+        /// returned by `field_key`.
+        pub(crate) fn parse(text: &str, custom_fields: &[String]) -> Result<Query, QueryError> {
+            let mut terms = Vec::new();
+            for (position, token) in tokenize(text)? {
+                terms.push(parse_term(&token, position, custom_fields)?);
+            }
+            return Ok(Query { terms: terms })
+        }
+
+        /// The plain words of the query, for lists such as attachments where field terms do not apply.
+        pub(crate) fn words(&self) -> Vec<String> {
+            return self.terms.iter()
+                .filter(|term| term.field.is_none() && !term.negated)
+                .map(|term| term.value.to_lowercase())
+                .collect()
+        }
+
+        /// A condition on the `document` table with `?` placeholders, and the values for them in order.
+        pub(crate) fn to_sql(&self) -> (String, Vec<String>) {
+            if self.terms.is_empty() {
+                return (String::from("1"), Vec::new())
+            }
+            let mut conditions = Vec::new();
+            let mut params = Vec::new();
+            for term in &self.terms {
+                let condition = term_sql(term, &mut params);
+                conditions.push(match term.negated {
+                    true => format!("NOT IFNULL(({}), 0)", condition),
+                    false => format!("({})", condition)
+                });
+            }
+            return (conditions.join(" AND "), params)
+        }
+    }
+
+    /// How a custom field is named in queries: lower case, with spaces written as underscores.
+    pub(crate) fn field_key(field_name: &str) -> String {
+        return field_name.trim().to_lowercase().replace(' ', "_")
+    }
+
+    /// Completions for the term being typed at the end of `text`, each with a short description.
+    pub(crate) fn suggestions(text: &str, custom_fields: &[String]) -> Vec<(String, String)> {
+        let token = last_token(text);
+        let (negation, token) = match token.strip_prefix('-') {
+            Some(rest) => ("-", rest),
+            None => ("", token)
+        };
+        if token.is_empty() {
+            return Vec::new()
+        }
+
+        if let Some(value) = token.strip_prefix("status:") {
+            return DocumentStatus::ALL.iter()
+                .map(|status| status.as_str())
+                .filter(|status| status.starts_with(&value.to_lowercase()) && *status != value)
+                .map(|status| (format!("{}status:{}", negation, status), String::from("status")))
+                .collect()
+        }
+        if token.contains(':') {
+            return Vec::new()
+        }
+
+        let token = token.to_lowercase();
+        let mut suggestions: Vec<(String, String)> = FIELDS.iter()
+            .filter(|(key, _, _)| key.starts_with(&token))
+            .map(|(key, _, description)| (format!("{}{}:", negation, key), description.to_string()))
+            .collect();
+        suggestions.extend(custom_fields.iter()
+            .filter(|key| key.starts_with(&token))
+            .map(|key| (format!("{}{}:", negation, key), String::from("custom field"))));
+        return suggestions
+    }
+
+    /// Replaces the term being typed at the end of `text` with `suggestion`.
+    pub(crate) fn complete(text: &str, suggestion: &str) -> String {
+        let token = last_token(text);
+        return format!("{}{}", &text[..text.len() - token.len()], suggestion)
+    }
+
+    fn last_token(text: &str) -> &str {
+        return match text.rfind(char::is_whitespace) {
+            Some(index) => &text[index + 1..],
+            None => text
+        }
+    }
+
+    /// Splits the text at whitespace outside double quotes, keeping each term's starting character offset.
+    fn tokenize(text: &str) -> Result<Vec<(usize, String)>, QueryError> {
+        let mut tokens = Vec::new();
+        let mut current = String::new();
+        let mut start = 0;
+        let mut quote_start: Option<usize> = None;
+        for (position, c) in text.chars().enumerate() {
+            if c == '"' {
+                quote_start = match quote_start {
+                    Some(_) => None,
+                    None => Some(position)
+                };
+            }
+            if c.is_whitespace() && quote_start.is_none() {
+                if !current.is_empty() {
+                    tokens.push((start, std::mem::take(&mut current)));
+                }
+                continue
+            }
+            if current.is_empty() {
+                start = position;
+            }
+            current.push(c);
+        }
+        if let Some(position) = quote_start {
+            return Err(QueryError { message: String::from("The quote is not closed."), position: position })
+        }
+        if !current.is_empty() {
+            tokens.push((start, current));
+        }
+        return Ok(tokens)
+    }
+
+    fn parse_term(token: &str, position: usize, custom_fields: &[String]) -> Result<Term, QueryError> {
+        let error = |message: String| QueryError { message: message, position: position };
+        let (negated, token) = match token.strip_prefix('-') {
+            Some(rest) if !rest.is_empty() => (true, rest),
+            _ => (false, token)
+        };
+
+        let (key, rest) = match token.split_once(':') {
+            Some((key, rest)) if !key.is_empty() && !key.contains('"') => (key, rest),
+            _ => return Ok(Term { negated: negated, field: None, comparison: Comparison::Matches, value: unquote(token) })
+        };
+        let field = match FIELDS.iter().find(|(name, _, _)| name.eq_ignore_ascii_case(key)) {
+            Some((_, field, _)) => field.clone(),
+            None if custom_fields.contains(&key.to_lowercase()) => QueryField::Custom(key.to_lowercase()),
+            None => return Err(error(match closest_field(key, custom_fields) {
+                Some(candidate) => format!("Unknown field \"{}\". Did you mean \"{}\"?", key, candidate),
+                None => format!("Unknown field \"{}\". Use {} or a custom field name.", key, FIELDS.map(|(name, _, _)| name).join(", "))
+            }))
+        };
+
+        let (comparison, value) = [(">=", Comparison::GreaterOrEqual), ("<=", Comparison::LessOrEqual), (">", Comparison::Greater), ("<", Comparison::Less), ("=", Comparison::Matches)]
+            .iter()
+            .find_map(|(operator, comparison)| rest.strip_prefix(operator).map(|value| (*comparison, value)))
+            .unwrap_or((Comparison::Matches, rest));
+        let value = unquote(value);
+        if value.trim().is_empty() {
+            return Err(error(format!("\"{}:\" needs a value, e.g. {}", key, example(&field))))
+        }
+
+        let is_date = matches!(field, QueryField::Added | QueryField::DocumentDate | QueryField::DueDate);
+        if comparison != Comparison::Matches && !is_date {
+            return Err(error(format!("\"{}\" can't be compared with \"{}\"; only added, date and due can.", key, comparison.operator())))
+        }
+        if is_date {
+            let valid = match comparison {
+                Comparison::Matches => is_partial_date(&value),
+                _ => parse_date(&value).is_some()
+            };
+            if !valid {
+                return Err(error(format!("\"{}\" is not a date. Write dates as 2025-01-31{}.", value, if comparison == Comparison::Matches { ", 2025-01 or 2025" } else { "" })))
+            }
+        }
+        if field == QueryField::Status && status_value(&value).is_none() {
+            return Err(error(format!("Unknown status \"{}\". Use draft, review, approved or archived.", value)))
+        }
+
+        return Ok(Term { negated: negated, field: Some(field), comparison: comparison, value: value })
+    }
+
+    fn term_sql(term: &Term, params: &mut Vec<String>) -> String {
+        let field = match &term.field {
+            Some(field) => field,
+            None => {
+                let pattern = contains_pattern(&term.value);
+                params.extend(std::iter::repeat_n(pattern, 6));
+                return String::from("document.document_number LIKE ? ESCAPE '\\' OR IFNULL(document.document_type, '') LIKE ? ESCAPE '\\' OR IFNULL(document.comment, '') LIKE ? ESCAPE '\\'
+                    OR EXISTS (SELECT 1 FROM document_tag JOIN tag ON tag.tag_id = document_tag.tag_id WHERE document_tag.document_id = document.document_id AND tag.name LIKE ? ESCAPE '\\')
+                    OR EXISTS (SELECT 1 FROM field_value WHERE field_value.document_id = document.document_id AND field_value.value LIKE ? ESCAPE '\\')
+                    OR EXISTS (SELECT 1 FROM attachment WHERE attachment.document_id = document.document_id AND attachment.deleted_at IS NULL AND attachment.reference_number LIKE ? ESCAPE '\\')")
+            }
+        };
+
+        match field {
+            QueryField::Number => {
+                params.push(glob_pattern(&term.value));
+                String::from("document.document_number LIKE ? ESCAPE '\\'")
+            },
+            QueryField::DocumentType => {
+                params.push(glob_pattern(&term.value));
+                String::from("IFNULL(document.document_type, '') LIKE ? ESCAPE '\\'")
+            },
+            QueryField::Comment => {
+                params.push(contains_pattern(&term.value));
+                String::from("IFNULL(document.comment, '') LIKE ? ESCAPE '\\'")
+            },
+            QueryField::Reference => {
+                params.push(glob_pattern(&term.value));
+                String::from("EXISTS (SELECT 1 FROM attachment WHERE attachment.document_id = document.document_id AND attachment.deleted_at IS NULL AND attachment.reference_number LIKE ? ESCAPE '\\')")
+            },
+            QueryField::Tag => {
+                let pattern = glob_pattern(&term.value);
+                params.push(pattern.clone());
+                params.push(pattern);
+                String::from("EXISTS (SELECT 1 FROM document_tag JOIN tag ON tag.tag_id = document_tag.tag_id WHERE document_tag.document_id = document.document_id AND tag.name LIKE ? ESCAPE '\\')
+                    OR EXISTS (SELECT 1 FROM attachment_tag JOIN tag ON tag.tag_id = attachment_tag.tag_id JOIN attachment ON attachment.attachment_id = attachment_tag.attachment_id
+                        WHERE attachment.document_id = document.document_id AND attachment.deleted_at IS NULL AND tag.name LIKE ? ESCAPE '\\')")
+            },
+            QueryField::Status => {
+                params.push(status_value(&term.value).unwrap_or_default().as_str().to_string());
+                String::from("document.status = ?")
+            },
+            QueryField::Added => date_sql("date(document.date_added, 'unixepoch', 'localtime')", term, params),
+            QueryField::DocumentDate => date_sql("document.document_date", term, params),
+            QueryField::DueDate => date_sql("document.due_date", term, params),
+            QueryField::Custom(key) => {
+                params.push(key.clone());
+                params.push(contains_pattern(&term.value));
+                String::from("EXISTS (SELECT 1 FROM field_value JOIN type_field ON type_field.field_id = field_value.field_id
+                    WHERE field_value.document_id = document.document_id AND REPLACE(LOWER(TRIM(type_field.name)), ' ', '_') = ? AND field_value.value LIKE ? ESCAPE '\\')")
+            }
+        }
+    }
+
+    /// Dates are stored as `YYYY-MM-DD` text, so they compare as strings. A plain match also takes a month or a year.
+    fn date_sql(column: &str, term: &Term, params: &mut Vec<String>) -> String {
+        if term.comparison == Comparison::Matches {
+            params.push(format!("{}%", term.value));
+            return format!("{} LIKE ?", column)
+        }
+        params.push(term.value.clone());
+        return format!("{} {} ?", column, term.comparison.operator())
+    }
+
+    fn status_value(value: &str) -> Option<DocumentStatus> {
+        let value = value.trim().to_lowercase();
+        return DocumentStatus::ALL.iter()
+            .find(|status| status.as_str() == value || status.to_string().to_lowercase() == value)
+            .copied()
+    }
+
+    fn is_partial_date(value: &str) -> bool {
+        let parts: Vec<&str> = value.split('-').collect();
+        let widths = [4, 2, 2];
+        return parts.len() <= 3 && parts.iter().zip(widths).all(|(part, width)| part.len() == width && part.chars().all(|c| c.is_ascii_digit()))
+    }
+
+    fn unquote(value: &str) -> String {
+        return value.replace('"', "")
+    }
+
+    /// `*` stands for any run of characters and `?` for one; everything else is matched literally, ignoring case.
+    fn glob_pattern(value: &str) -> String {
+        let mut pattern = String::new();
+        for c in value.chars() {
+            match c {
+                '*' => pattern.push('%'),
+                '?' => pattern.push('_'),
+                '%' | '_' | '\\' => {
+                    pattern.push('\\');
+                    pattern.push(c);
+                },
+                _ => pattern.push(c)
+            }
+        }
+        return pattern
+    }
+
+    fn contains_pattern(value: &str) -> String {
+        return format!("%{}%", glob_pattern(value))
+    }
+
+    fn example(field: &QueryField) -> &'static str {
+        match field {
+            QueryField::Added | QueryField::DocumentDate | QueryField::DueDate => "added:>2025-01-01",
+            QueryField::Status => "status:approved",
+            QueryField::Reference => "ref:PO-*",
+            _ => "type:invoice"
+        }
+    }
+
+    /// The known field name closest to a mistyped one, if any is close enough to be a likely typo.
+    fn closest_field(key: &str, custom_fields: &[String]) -> Option<String> {
+        let key = key.to_lowercase();
+        return FIELDS.iter().map(|(name, _, _)| name.to_string())
+            .chain(custom_fields.iter().cloned())
+            .map(|name| (edit_distance(&key, &name), name))
+            .filter(|(distance, name)| *distance <= 2 || name.starts_with(&key))
+            .min_by_key(|(distance, _)| *distance)
+            .map(|(_, name)| name)
+    }
+
+    fn edit_distance(left: &str, right: &str) -> usize {
+        let right: Vec<char> = right.chars().collect();
+        let mut previous: Vec<usize> = (0..=right.len()).collect();
+        for (i, left_char) in left.chars().enumerate() {
+            let mut current = vec![i + 1];
+            for (j, right_char) in right.iter().enumerate() {
+                let substitution = previous[j] + if left_char == *right_char { 0 } else { 1 };
+                current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+            }
+            previous = current;
+        }
+        return previous[right.len()]
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use time::macros::date;
+
+        use crate::{db::db_module::DbConnection, saved_view::saved_view::ListQuery};
+
+        use super::*;
+
+        fn term(negated: bool, field: Option<QueryField>, comparison: Comparison, value: &str) -> Term {
+            return Term { negated: negated, field: field, comparison: comparison, value: value.to_string() }
+        }
+
+        fn search(conn: &DbConnection, text: &str) -> Vec<u32> {
+            let list = ListQuery { query: Some(Query::parse(text, &[String::from("amount")]).unwrap()), ..ListQuery::default() };
+            let mut ids = conn.read_document_ids(&list).unwrap();
+            ids.sort();
+            return ids
+        }
+
+        #[test]
+        fn test_parse() {
+            let query = Query::parse(r#"type:invoice  added:>=2025-01-01 -comment:"not paid" Word status:Approved"#, &[]).unwrap();
+            assert_eq!(query.terms, vec![
+                term(false, Some(QueryField::DocumentType), Comparison::Matches, "invoice"),
+                term(false, Some(QueryField::Added), Comparison::GreaterOrEqual, "2025-01-01"),
+                term(true, Some(QueryField::Comment), Comparison::Matches, "not paid"),
+                term(false, None, Comparison::Matches, "Word"),
+                term(false, Some(QueryField::Status), Comparison::Matches, "Approved")
+            ]);
+            assert_eq!(query.words(), vec!["word"]);
+            assert_eq!(Query::parse("Amount:12", &[String::from("amount")]).unwrap().terms, vec![term(false, Some(QueryField::Custom(String::from("amount"))), Comparison::Matches, "12")]);
+            assert!(Query::parse("   ", &[]).unwrap().terms.is_empty());
+        }
+
+        #[test]
+        fn test_parse_errors() {
+            let error = Query::parse("type:invoice typo:x", &[]).unwrap_err();
+            assert_eq!(error.position, 13);
+            assert_eq!(error.message, "Unknown field \"typo\". Did you mean \"type\"?");
+            assert_eq!(Query::parse("comment:\"open", &[]).unwrap_err().position, 8);
+            assert!(Query::parse("type:", &[]).is_err());
+            assert!(Query::parse("type:>a", &[]).is_err());
+            assert!(Query::parse("added:>2025-01", &[]).is_err());
+            assert!(Query::parse("added:2025-1", &[]).is_err());
+            assert!(Query::parse("status:done", &[]).is_err());
+            assert!(Query::parse("added:2025-01 due:<2025-02-01", &[]).is_ok());
+        }
+
+        #[test]
+        fn test_to_sql() {
+            let (condition, params) = Query::parse("", &[]).unwrap().to_sql();
+            assert_eq!((condition.as_str(), params.len()), ("1", 0));
+
+            let (condition, params) = Query::parse("number:INV-1?_* -due:<2025-02-01", &[]).unwrap().to_sql();
+            assert_eq!(condition, "(document.document_number LIKE ? ESCAPE '\\') AND NOT IFNULL((document.due_date < ?), 0)");
+            assert_eq!(params, vec!["INV-1_\\_%", "2025-02-01"]);
+        }
+
+        #[test]
+        fn test_search() {
+            let mut conn = DbConnection::in_memory();
+            conn.new_document(String::from("INV-1"), String::from("Invoice"), String::from("paid in full"), Vec::new(), Some(date!(2025 - 01 - 15)), None).unwrap();
+            conn.new_document(String::from("INV-2"), String::from("Invoice"), String::new(), Vec::new(), Some(date!(2025 - 02 - 15)), Some(date!(2025 - 03 - 01))).unwrap();
+            conn.new_document(String::from("R-100%"), String::from("Receipt"), String::from("Invoice copy"), Vec::new(), None, None).unwrap();
+            conn.add_document_tag(2, String::from("urgent")).unwrap();
+
+            assert_eq!(search(&conn, "type:invoice"), vec![1, 2]);
+            assert_eq!(search(&conn, "invoice"), vec![1, 2, 3]);
+            assert_eq!(search(&conn, "number:inv-*"), vec![1, 2]);
+            assert_eq!(search(&conn, "number:R-100%"), vec![3]);
+            assert_eq!(search(&conn, "date:2025-01"), vec![1]);
+            assert_eq!(search(&conn, "date:>2025-01-31"), vec![2]);
+            // Documents without a due date do not match a comparison, so negating it keeps them.
+            assert_eq!(search(&conn, "-due:<2025-04-01"), vec![1, 3]);
+            assert_eq!(search(&conn, "tag:urg*"), vec![2]);
+            assert_eq!(search(&conn, "-tag:urgent type:invoice"), vec![1]);
+            assert_eq!(search(&conn, "status:draft comment:paid"), vec![1]);
+            assert!(search(&conn, "amount:12").is_empty());
+        }
+    }
+}
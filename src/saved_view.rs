@@ -0,0 +1,326 @@
+pub(crate) mod saved_view {
+    use std::{cmp::Ordering, fmt, sync::Arc};
+
+    use time::{OffsetDateTime, macros::format_description};
+
+    use crate::{document::document::{Document, DocumentStatus}, query::query::Query};
+
+    /// A named search with its sort and grouping, stored in the library and listed in the sidebar.
+    #[derive(Debug, Clone, PartialEq)]
+    pub(crate) struct SavedView {
+        pub(crate) view_id: u32,
+        pub(crate) name: String,
+        pub(crate) query: String,
+        pub(crate) sort_key: SortKey,
+        pub(crate) descending: bool,
+        pub(crate) group_by: GroupBy
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+    pub(crate) enum SortKey {
+        #[default]
+        DocumentNumber,
+        DocumentType,
+        Comment,
+        DocumentDate,
+        DateAdded,
+        AttachmentCount,
+        PageCount
+    }
+
+    impl SortKey {
+        pub(crate) const ALL: [SortKey; 7] = [SortKey::DocumentNumber, SortKey::DocumentType, SortKey::Comment, SortKey::DocumentDate, SortKey::DateAdded, SortKey::AttachmentCount, SortKey::PageCount];
+
+        /// The value stored in the `saved_view.sort_key` column.
+        pub(crate) fn as_str(&self) -> &'static str {
+            match self {
+                SortKey::DocumentNumber => "document_number",
+                SortKey::DocumentType => "document_type",
+                SortKey::Comment => "comment",
+                SortKey::DocumentDate => "document_date",
+                SortKey::DateAdded => "date_added",
+                SortKey::AttachmentCount => "attachment_count",
+                SortKey::PageCount => "page_count"
+            }
+        }
+
+        /// The expression on the `document` table the list is ordered by, in ascending order.
+        fn order_sql(&self) -> &'static str {
+            match self {
+                SortKey::DocumentNumber => "document.document_number COLLATE NATURAL_SORT",
+                SortKey::DocumentType => "LOWER(document.document_type)",
+                SortKey::Comment => "LOWER(document.comment)",
+                // Documents without a document date sort after those with one.
+                SortKey::DocumentDate => "IFNULL(document.document_date, '~')",
+                SortKey::DateAdded => "document.date_added",
+                SortKey::AttachmentCount => "(SELECT COUNT(*) FROM attachment WHERE attachment.document_id = document.document_id AND attachment.deleted_at IS NULL)",
+                SortKey::PageCount => "(SELECT COUNT(*) FROM page JOIN attachment ON attachment.attachment_id = page.attachment_id WHERE attachment.document_id = document.document_id AND attachment.deleted_at IS NULL)"
+            }
+        }
+
+        pub(crate) fn from_str(sort_key: &str) -> SortKey {
+            match sort_key {
+                "document_type" => SortKey::DocumentType,
+                "comment" => SortKey::Comment,
+                "document_date" => SortKey::DocumentDate,
+                "date_added" => SortKey::DateAdded,
+                "attachment_count" => SortKey::AttachmentCount,
+                "page_count" => SortKey::PageCount,
+                _ => SortKey::DocumentNumber
+            }
+        }
+    }
+
+    impl fmt::Display for SortKey {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "{}", match self {
+                SortKey::DocumentNumber => "Number",
+                SortKey::DocumentType => "Type",
+                SortKey::Comment => "Comment",
+                SortKey::DocumentDate => "Date",
+                SortKey::DateAdded => "Date Added",
+                SortKey::AttachmentCount => "Attachments",
+                SortKey::PageCount => "Pages"
+            })
+        }
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+    pub(crate) enum GroupBy {
+        #[default]
+        Nothing,
+        DocumentType,
+        /// The month of the document date, or of the date added for documents without one.
+        Month
+    }
+
+    impl GroupBy {
+        pub(crate) const ALL: [GroupBy; 3] = [GroupBy::Nothing, GroupBy::DocumentType, GroupBy::Month];
+
+        /// The value stored in the `saved_view.group_by` column.
+        pub(crate) fn as_str(&self) -> &'static str {
+            match self {
+                GroupBy::Nothing => "",
+                GroupBy::DocumentType => "document_type",
+                GroupBy::Month => "month"
+            }
+        }
+
+        /// The expression on the `document` table giving the label of a document's group, or `None` when the list
+        /// is not grouped. Matches `group_label`.
+        pub(crate) fn label_sql(&self) -> Option<&'static str> {
+            match self {
+                GroupBy::Nothing => None,
+                GroupBy::DocumentType => Some("TRIM(document.document_type)"),
+                GroupBy::Month => Some("SUBSTR(IFNULL(document.document_date, DATE(document.date_added, 'unixepoch')), 1, 7)")
+            }
+        }
+
+        pub(crate) fn from_str(group_by: &str) -> GroupBy {
+            match group_by {
+                "document_type" => GroupBy::DocumentType,
+                "month" => GroupBy::Month,
+                _ => GroupBy::Nothing
+            }
+        }
+    }
+
+    impl fmt::Display for GroupBy {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "{}", match self {
+                GroupBy::Nothing => "No Grouping",
+                GroupBy::DocumentType => "Group by Type",
+                GroupBy::Month => "Group by Month"
+            })
+        }
+    }
+
+    /// What the document list shows: the search, the filters and the order. The library filters and sorts, so the
+    /// list only loads the documents that are scrolled into view.
+    #[derive(Debug, Clone, PartialEq, Default)]
+    pub(crate) struct ListQuery {
+        pub(crate) query: Option<Query>,
+        pub(crate) status: Option<DocumentStatus>,
+        pub(crate) document_type: Option<String>,
+        /// A field of the filtered type and the text its value has to contain.
+        pub(crate) field_value: Option<(u32, String)>,
+        /// Tags a document or one of its attachments has to carry; all of them when `match_all_tags` is set.
+        pub(crate) tag_ids: Vec<u32>,
+        pub(crate) match_all_tags: bool,
+        pub(crate) sort_key: SortKey,
+        pub(crate) descending: bool,
+        pub(crate) group_by: GroupBy
+    }
+
+    impl ListQuery {
+        /// A condition on the `document` table with `?` placeholders, and the values for them in order.
+        pub(crate) fn to_sql(&self) -> (String, Vec<String>) {
+            let (mut conditions, mut params) = match &self.query {
+                Some(query) => {
+                    let (condition, params) = query.to_sql();
+                    (vec![format!("({})", condition)], params)
+                },
+                None => (Vec::new(), Vec::new())
+            };
+            if let Some(status) = self.status {
+                conditions.push(String::from("document.status = ?"));
+                params.push(status.as_str().to_string());
+            }
+            if let Some(document_type) = &self.document_type {
+                conditions.push(String::from("TRIM(document.document_type) = TRIM(?) COLLATE NOCASE"));
+                params.push(document_type.clone());
+            }
+            if let Some((field_id, value)) = self.field_value.as_ref().filter(|(_, value)| !value.is_empty()) {
+                conditions.push(format!(
+                    "EXISTS (SELECT 1 FROM field_value WHERE field_value.document_id = document.document_id AND field_value.field_id = {} AND INSTR(LOWER(field_value.value), LOWER(?)) > 0)",
+                    field_id
+                ));
+                params.push(value.clone());
+            }
+            if !self.tag_ids.is_empty() {
+                let tags: Vec<String> = self.tag_ids.iter().map(|tag_id| format!(
+                    "(EXISTS (SELECT 1 FROM document_tag WHERE document_tag.document_id = document.document_id AND document_tag.tag_id = {0})
+                    OR EXISTS (SELECT 1 FROM attachment_tag JOIN attachment ON attachment.attachment_id = attachment_tag.attachment_id
+                        WHERE attachment.document_id = document.document_id AND attachment.deleted_at IS NULL AND attachment_tag.tag_id = {0}))",
+                    tag_id
+                )).collect();
+                conditions.push(format!("({})", tags.join(if self.match_all_tags { " AND " } else { " OR " })));
+            }
+            if conditions.is_empty() {
+                return (String::from("1"), params)
+            }
+            return (conditions.join(" AND "), params)
+        }
+
+        /// The expressions the list is ordered by: the group, the sort key, and `document_id` so that no two
+        /// documents compare equal. Pages continue after the last document by comparing them as a row value.
+        pub(crate) fn order_terms(&self) -> Vec<String> {
+            let mut terms = Vec::new();
+            if let Some(label) = self.group_by.label_sql() {
+                // Groups follow the sort direction, but documents without a label come last either way.
+                terms.push(format!("({} {} '')", label, if self.descending { "<>" } else { "=" }));
+                terms.push(format!("LOWER({})", label));
+            }
+            terms.push(self.sort_key.order_sql().to_string());
+            terms.push(String::from("document.document_id"));
+            return terms
+        }
+
+        /// The `ORDER BY` list for `order_terms`.
+        pub(crate) fn order_by(&self) -> String {
+            let direction = if self.descending { " DESC" } else { "" };
+            return self.order_terms().iter().map(|term| format!("{}{}", term, direction)).collect::<Vec<_>>().join(", ")
+        }
+    }
+
+    /// Splits already sorted documents into labelled groups, keeping the order within each group. Groups follow
+    /// the sort direction of their labels; documents without a type come last.
+    pub(crate) fn group_documents(documents: Vec<Arc<Document>>, group_by: GroupBy, descending: bool) -> Vec<(String, Vec<Arc<Document>>)> {
+        if group_by == GroupBy::Nothing {
+            return vec![(String::new(), documents)]
+        }
+
+        let mut groups: Vec<(String, Vec<Arc<Document>>)> = Vec::new();
+        for document in documents {
+            let label = group_label(&document, group_by);
+            match groups.iter_mut().find(|(group_label, _)| *group_label == label) {
+                Some((_, group)) => group.push(document),
+                None => groups.push((label, vec![document]))
+            }
+        }
+        groups.sort_by(|(left, _), (right, _)| {
+            let order = (left.is_empty(), left.to_lowercase()).cmp(&(right.is_empty(), right.to_lowercase()));
+            if descending && !left.is_empty() && !right.is_empty() { order.reverse() } else { order }
+        });
+        return groups
+    }
+
+    fn group_label(document: &Document, group_by: GroupBy) -> String {
+        match group_by {
+            GroupBy::Nothing => String::new(),
+            GroupBy::DocumentType => document.get_document_type().trim().to_string(),
+            GroupBy::Month => {
+                let date = document.get_document_date().or_else(|| OffsetDateTime::from_unix_timestamp(document.get_date_added()).ok().map(|datetime| datetime.date()));
+                date.and_then(|date| date.format(format_description!("[year]-[month]")).ok()).unwrap_or_default()
+            }
+        }
+    }
+
+    /// Compares document numbers with runs of digits by value, so `INV-9` sorts before `INV-10`. The library
+    /// sorts with it as the `NATURAL_SORT` collation.
+    pub(crate) fn natural_cmp(left: &str, right: &str) -> Ordering {
+        return natural_key(left).cmp(&natural_key(right))
+    }
+
+    fn natural_key(text: &str) -> Vec<(String, u64)> {
+        let mut key = Vec::new();
+        let mut chars = text.chars().peekable();
+        while chars.peek().is_some() {
+            let prefix: String = std::iter::from_fn(|| chars.next_if(|c| !c.is_ascii_digit())).collect();
+            let digits: String = std::iter::from_fn(|| chars.next_if(|c| c.is_ascii_digit())).collect();
+            key.push((prefix.to_lowercase(), digits.parse().unwrap_or(0)));
+        }
+        return key
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use time::{Date, macros::date};
+
+        use crate::document::document::DocumentMetadata;
+
+        use super::*;
+
+        fn document(document_id: u32, document_number: &str, document_type: &str, document_date: Option<Date>) -> Arc<Document> {
+            return Arc::new(Document::new(document_id, Arc::new(document_number.to_string()), Arc::new(document_type.to_string()), None, Arc::new(String::new()), 0, DocumentMetadata {
+                document_date: document_date,
+                ..Default::default()
+            }))
+        }
+
+        fn numbers(documents: &[Arc<Document>]) -> Vec<String> {
+            return documents.iter().map(|document| document.get_document_number().to_string()).collect()
+        }
+
+        #[test]
+        fn test_natural_order() {
+            let mut numbers = vec!["INV-10", "inv-9", "A-2"];
+            numbers.sort_by(|left, right| natural_cmp(left, right));
+            assert_eq!(numbers, vec!["A-2", "inv-9", "INV-10"]);
+        }
+
+        #[test]
+        fn test_group_by_type() {
+            let documents = vec![document(1, "A", "Receipt", None), document(2, "B", "", None), document(3, "C", "invoice", None), document(4, "D", "Receipt ", None)];
+            let groups = group_documents(documents.clone(), GroupBy::DocumentType, false);
+            let labels: Vec<&str> = groups.iter().map(|(label, _)| label.as_str()).collect();
+            assert_eq!(labels, vec!["invoice", "Receipt", ""]);
+            assert_eq!(numbers(&groups[1].1), vec!["A", "D"]);
+
+            // Descending order reverses the labels, but documents without a type stay last.
+            let groups = group_documents(documents, GroupBy::DocumentType, true);
+            let labels: Vec<&str> = groups.iter().map(|(label, _)| label.as_str()).collect();
+            assert_eq!(labels, vec!["Receipt", "invoice", ""]);
+        }
+
+        #[test]
+        fn test_group_by_month() {
+            let documents = vec![document(1, "A", "", Some(date!(2024 - 05 - 31))), document(2, "B", "", Some(date!(2024 - 05 - 01))), document(3, "C", "", None)];
+            let groups = group_documents(documents, GroupBy::Month, false);
+            let labels: Vec<&str> = groups.iter().map(|(label, _)| label.as_str()).collect();
+            // Without a document date, the date added (here 1970-01-01) is used.
+            assert_eq!(labels, vec!["1970-01", "2024-05"]);
+            assert_eq!(numbers(&groups[1].1), vec!["A", "B"]);
+        }
+
+        #[test]
+        fn test_stored_names() {
+            for sort_key in SortKey::ALL {
+                assert_eq!(SortKey::from_str(sort_key.as_str()), sort_key);
+            }
+            for group_by in GroupBy::ALL {
+                assert_eq!(GroupBy::from_str(group_by.as_str()), group_by);
+            }
+        }
+    }
+}
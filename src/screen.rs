@@ -1,7 +1,17 @@
 pub(crate) mod main_menu;
 pub(crate) mod document_list;
 pub(crate) mod settings;
+pub(crate) mod integrity;
+pub(crate) mod trash;
+pub(crate) mod tags;
+pub(crate) mod document_types;
+pub(crate) mod unlock;
 
 pub(crate) use crate::screen::main_menu::main_menu::MainMenu;
 pub(crate) use crate::screen::document_list::document_list::DocumentList;
-pub(crate) use crate::screen::settings::settings::Settings;
\ No newline at end of file
+pub(crate) use crate::screen::settings::settings::Settings;
+pub(crate) use crate::screen::integrity::integrity::Integrity;
+pub(crate) use crate::screen::trash::trash::Trash;
+pub(crate) use crate::screen::tags::tags::Tags;
+pub(crate) use crate::screen::document_types::document_types::DocumentTypes;
+pub(crate) use crate::screen::unlock::unlock::Unlock;
\ No newline at end of file
@@ -1,9 +1,9 @@
 pub(crate) mod document_list {
-    use std::{env::{current_dir, current_exe}, fs, io::Cursor, path::PathBuf, process::Stdio, sync::Arc, time::{SystemTime, UNIX_EPOCH}};
+    use std::{collections::{HashMap, HashSet}, env::{current_dir, current_exe}, fs, io::Cursor, path::PathBuf, process::Stdio, sync::Arc, time::{SystemTime, UNIX_EPOCH}};
 
     use caesium::{compress_in_memory, convert_in_memory, parameters::{CSParameters, PngParameters}};
     use file_format::FileFormat;
-    use iced::{Alignment::Center, Background, Border, Color, Element, Event, Gradient, Length, Renderer, Shadow, Subscription, Task, Theme, advanced::graphics::futures::subscription, border::Radius, gradient::{ColorStop, Linear}, keyboard::{self, Key, key}, mouse::Interaction, theme::Palette, wgpu::rwh, widget::{Container, Id, MouseArea, ProgressBar, Space, Stack, Text, TextInput, button, center, column, container::{self, Style}, image::{Handle, Viewer}, mouse_area, operation::focus_next, progress_bar, row, rule, scrollable}, window::events};
+    use iced::{Alignment::Center, Background, Border, Color, Element, Event, Gradient, Length, Renderer, Shadow, Subscription, Task, Theme, advanced::graphics::futures::subscription, border::Radius, gradient::{ColorStop, Linear}, keyboard::{self, Key, key}, mouse::Interaction, theme::Palette, wgpu::rwh, widget::{Container, Id, MouseArea, PickList, ProgressBar, Space, Stack, Text, TextInput, button, center, checkbox, column, container::{self, Style}, image::{Handle, Viewer}, mouse_area, operation::{focus, focus_next}, progress_bar, row, rule, scrollable}, window::events};
     use iced::widget::text_input;
     use iced_aw::{Card, TabBarPosition, TabLabel, Tabs, card::Status, style::card};
     use iced_dialog::dialog;
@@ -11,19 +11,47 @@ pub(crate) mod document_list {
     use pdfium_render::prelude::{PdfBitmap, PdfBitmapFormat, PdfPageImageObject, PdfPageObjectsCommon, PdfPageOrientation, PdfPagePaperSize, PdfPageRenderRotation, PdfPoints, PdfRenderConfig, Pdfium, PdfiumError, PdfiumLibraryBindings};
     use rfd::FileDialog;
     use rusqlite::ffi::SQLITE_LIMIT_FUNCTION_ARG;
-    use time::{Duration, OffsetDateTime, UtcDateTime, macros::format_description};
+    use time::{Date, Duration, OffsetDateTime, UtcDateTime, macros::format_description};
 
-    use crate::{ERROR_FERRIS, LocalTheme, State, attachment::attachment::Attachment, attachment_page::attachment_page::AttachmentPage, db::db_module::DbConnection, document::document::Document};
+    use crate::{ERROR_FERRIS, DocumentLayout, LocalTheme, State, background::background::{self, Job, JobEvent, Progress}, attachment::attachment::Attachment, bulk::bulk::{BulkAction, BulkJob, ExportTarget}, attachment_page::attachment_page::AttachmentPage, db::db_module::{AttachmentVersion, AuditEntry, DbConnection, is_conflict}, document::document::{Document, DocumentStatus, format_date, parse_date}, document_link::document_link::{DocumentLink, LinkKind}, document_merge::document_merge::{MergeField, conflicts, merged_details}, saved_view::saved_view::{GroupBy, ListQuery, SavedView, SortKey, group_documents}, query::query::{Query, complete, field_key, suggestions}, document_type::document_type::{DocumentType, TypeField, FieldKind}, storage::storage::{StagedPages, read_page_file}, screen::tags::tags::tag_chip, tag::tag::Tag, edit_history::edit_history::{AttachmentDetails, DocumentDetails, Edit, EditHistory}};
 
     #[derive(Debug, Clone, Default)]
     pub(crate) struct DocumentList {
+        /// Summaries of the documents `listed` selects, in list order, as far as they are loaded. The rest load a
+        /// page at a time as the list is scrolled to its end.
         documents: Vec<Arc<Document>>,
+        /// The search, filters and order the loaded documents are for, or `None` before the first load.
+        listed: Option<ListQuery>,
+        total_documents: usize,
+        /// How many documents each group holds, by group label, counting those not loaded yet.
+        group_counts: HashMap<String, usize>,
+        /// The id and number of every document, for the pickers that choose another document.
+        document_numbers: Vec<(u32, String)>,
+        /// Scroll position and height of the document list, which decide the rows that get built.
+        list_offset: f32,
+        list_height: f32,
+        /// How many cards the grid builds; grows as the list is scrolled to its end.
+        grid_limit: usize,
         search_text: String,
+        /// The parsed search text, or `None` when there is no query.
+        query: Option<Query>,
+        query_error: Option<String>,
+        /// The search text `query` was parsed from, or `None` when it has to be parsed again because the document
+        /// types changed.
+        queried_search: Option<String>,
         current_open_document: Option<Arc<Document>>,
         current_document_tab: Tab,
         current_document_number: String,
         current_document_type: String,
         current_comment: String,
+        current_document_date: String,
+        current_due_date: String,
+        status_changes: Vec<AuditEntry>,
+        unlocked_document_id: Option<u32>,
+        status_filter: Option<DocumentStatus>,
+        links: Vec<DocumentLink>,
+        link_kind: LinkKind,
+        link_target: Option<String>,
         current_open_attachment: Option<Arc<Attachment>>,
         current_attachment_reference_number: String,
         current_attachment_comment: String,
@@ -47,19 +75,115 @@ pub(crate) mod document_list {
         scan_progress: f32,
         current_theme: Option<LocalTheme>,
         show_confirm_delete: bool,
-        show_empty_field_warning: bool
+        show_empty_field_warning: bool,
+        error_message: Option<String>,
+        edit_history: EditHistory,
+        history: Option<Vec<AuditEntry>>,
+        versions: Option<Vec<AttachmentVersion>>,
+        compare_left: Option<(String, Vec<Handle>)>,
+        compare_right: Option<(String, Vec<Handle>)>,
+        all_tags: Vec<Tag>,
+        tag_filter: Vec<u32>,
+        match_all_tags: bool,
+        new_tag_name: String,
+        document_types: Vec<DocumentType>,
+        current_field_values: HashMap<u32, String>,
+        type_filter: Option<DocumentType>,
+        field_filter: Option<TypeField>,
+        field_filter_value: String,
+        number_error: Option<String>,
+        show_transfer: bool,
+        transfer_target: Option<String>,
+        transfer_reference_number: String,
+        show_merge: bool,
+        merge_source: Option<String>,
+        merge_take: HashSet<MergeField>,
+        show_split: bool,
+        split_selection: HashSet<u32>,
+        split_document_number: String,
+        modifiers: keyboard::Modifiers,
+        selected_documents: HashSet<u32>,
+        selected_attachments: HashSet<u32>,
+        /// The card last clicked without shift, where a shift-click range starts.
+        selection_anchor: Option<u32>,
+        bulk_tag_name: String,
+        bulk_type: Option<DocumentType>,
+        bulk_move_target: Option<String>,
+        /// The last bulk action, kept after it ran to show how it went.
+        bulk_job: Option<BulkJob>,
+        /// The background job running for this screen; most actions wait until it is done.
+        job: Option<Job>,
+        /// The newest audit entry when the list was last read; newer ones come from other users.
+        last_audit_id: i64,
+        /// Set when another user changed the open document while it had unsaved edits here.
+        remote_change: Option<String>,
+        /// The open attachment's advisory lock, with the user holding it if that is someone else.
+        attachment_lock: Option<(u32, Option<String>)>,
+        sort_key: SortKey,
+        sort_descending: bool,
+        group_by: GroupBy,
+        saved_views: Vec<SavedView>,
+        /// The saved view last opened or saved, which Delete View removes.
+        current_view: Option<u32>,
+        view_name: String,
+        layout: DocumentLayout,
+        /// Widths of the table columns, in the order of `TABLE_COLUMNS`.
+        column_widths: [f32; 6],
+        /// The table column whose right edge is being dragged.
+        resizing_column: Option<usize>
     }
 
+    /// The columns of the document table and the sort key each header sorts by.
+    const TABLE_COLUMNS: [(&str, SortKey); 6] = [
+        ("Number", SortKey::DocumentNumber),
+        ("Type", SortKey::DocumentType),
+        ("Comment", SortKey::Comment),
+        ("Date", SortKey::DocumentDate),
+        ("Attachments", SortKey::AttachmentCount),
+        ("Pages", SortKey::PageCount)
+    ];
+    const DOCUMENT_PAGE_SIZE: usize = 500;
+    const GRID_BATCH: usize = 100;
+    const TABLE_ROW_HEIGHT: f32 = 28.0;
+    /// Table rows built above and below the visible ones, so scrolling does not show gaps.
+    const TABLE_ROW_BUFFER: usize = 10;
+    const DEFAULT_COLUMN_WIDTHS: [f32; 6] = [140.0, 120.0, 320.0, 100.0, 100.0, 70.0];
+    const MIN_COLUMN_WIDTH: f32 = 40.0;
+    /// Room left of the first column for the selection checkbox.
+    const CHECKBOX_COLUMN_WIDTH: f32 = 30.0;
+    const COLUMN_HANDLE_WIDTH: f32 = 6.0;
+    /// How often the library is checked for changes by other users, which also renews the attachment lock.
+    const CHANGE_POLL_INTERVAL: iced::time::Duration = iced::time::Duration::from_secs(5);
+    const CHANGED_ELSEWHERE: &str = "Someone else changed this since you opened it. Reload to see their changes, then make yours again.";
+
     impl DocumentList {
         pub(crate) fn new() -> DocumentList {
-            DocumentList {
-                documents: Result::expect(DbConnection::new().read_document_table(), "Error retrieving data from database."),
+            let mut document_list = DocumentList {
+                documents: Vec::new(),
+                listed: None,
+                total_documents: 0,
+                group_counts: HashMap::new(),
+                document_numbers: Vec::new(),
+                list_offset: 0.0,
+                list_height: 0.0,
+                grid_limit: GRID_BATCH,
                 search_text: String::from(""),
+                query: None,
+                query_error: None,
+                queried_search: None,
                 current_open_document: None,
                 current_document_tab: Tab::default(),
                 current_document_number: String::default(),
                 current_document_type: String::default(),
                 current_comment: String::default(),
+                current_document_date: String::default(),
+                current_due_date: String::default(),
+                status_changes: Vec::new(),
+                unlocked_document_id: None,
+                status_filter: None,
+                links: Vec::new(),
+                link_kind: LinkKind::RelatesTo,
+                link_target: None,
                 current_open_attachment: None,
                 current_attachment_reference_number: String::default(),
                 current_attachment_comment: String::default(),
@@ -83,15 +207,190 @@ pub(crate) mod document_list {
                 scan_progress: f32::default(),
                 current_theme: None,
                 show_confirm_delete: false,
-                show_empty_field_warning: false
-            }
+                show_empty_field_warning: false,
+                error_message: None,
+                edit_history: EditHistory::new(),
+                history: None,
+                versions: None,
+                compare_left: None,
+                compare_right: None,
+                all_tags: DbConnection::new().read_tags().unwrap_or_default(),
+                tag_filter: Vec::new(),
+                match_all_tags: true,
+                new_tag_name: String::default(),
+                document_types: DbConnection::new().read_document_types().unwrap_or_default(),
+                current_field_values: HashMap::new(),
+                type_filter: None,
+                field_filter: None,
+                field_filter_value: String::default(),
+                number_error: None,
+                show_transfer: false,
+                transfer_target: None,
+                transfer_reference_number: String::default(),
+                show_merge: false,
+                merge_source: None,
+                merge_take: HashSet::new(),
+                show_split: false,
+                split_selection: HashSet::new(),
+                split_document_number: String::default(),
+                modifiers: keyboard::Modifiers::default(),
+                selected_documents: HashSet::new(),
+                selected_attachments: HashSet::new(),
+                selection_anchor: None,
+                bulk_tag_name: String::default(),
+                bulk_type: None,
+                bulk_move_target: None,
+                bulk_job: None,
+                job: None,
+                last_audit_id: 0,
+                remote_change: None,
+                attachment_lock: None,
+                sort_key: SortKey::default(),
+                sort_descending: false,
+                group_by: GroupBy::default(),
+                saved_views: DbConnection::new().read_saved_views().unwrap_or_else(|err| {
+                    println!("Error reading saved views: {}", err);
+                    Vec::new()
+                }),
+                current_view: None,
+                view_name: String::default(),
+                layout: DocumentLayout::default(),
+                column_widths: DEFAULT_COLUMN_WIDTHS,
+                resizing_column: None
+            };
+            document_list.refresh_documents();
+            document_list.last_audit_id = DbConnection::new().last_audit_id().unwrap_or_else(|err| {
+                println!("Error reading audit log: {}", err);
+                0
+            });
+            return document_list
         }
 
         pub(crate) fn set_current_theme(&mut self, theme: LocalTheme) {
             self.current_theme = Some(theme);
         }
 
+        pub(crate) fn set_layout(&mut self, layout: DocumentLayout) {
+            self.layout = layout;
+        }
+
+        /// The search, filters and order chosen in the list.
+        fn list_query(&self) -> ListQuery {
+            return ListQuery {
+                query: self.query.clone(),
+                status: self.status_filter,
+                document_type: self.type_filter.as_ref().map(|document_type| document_type.get_name().to_string()),
+                field_value: self.field_filter.as_ref().map(|field| (field.field_id, self.field_filter_value.clone())),
+                tag_ids: self.tag_filter.clone(),
+                match_all_tags: self.match_all_tags,
+                sort_key: self.sort_key,
+                descending: self.sort_descending,
+                group_by: self.group_by
+            }
+        }
+
+        /// Re-reads the documents loaded so far, after an edit, or the first page when the search, filters or
+        /// order changed. Later pages load as the list is scrolled.
+        fn refresh_documents(&mut self) {
+            let list = self.list_query();
+            let limit = match self.listed.as_ref() == Some(&list) {
+                true => self.documents.len().max(DOCUMENT_PAGE_SIZE),
+                false => DOCUMENT_PAGE_SIZE
+            };
+            let conn = DbConnection::new();
+            self.documents = Result::expect(conn.read_document_summaries(&list, None, limit), "Error retrieving data from database");
+            self.total_documents = conn.count_documents(&list).unwrap_or_else(|err| {
+                println!("Error counting documents: {}", err);
+                self.documents.len()
+            });
+            self.group_counts = conn.count_document_groups(&list).unwrap_or_else(|err| {
+                println!("Error counting documents: {}", err);
+                HashMap::new()
+            });
+            self.document_numbers = conn.read_document_numbers().unwrap_or_else(|err| {
+                println!("Error reading document numbers: {}", err);
+                Vec::new()
+            });
+            self.listed = Some(list);
+        }
+
+        /// Loads the page after the last loaded document, when the list is scrolled near its end.
+        fn load_document_page(&mut self) {
+            let list = match &self.listed {
+                Some(list) => list,
+                None => return
+            };
+            let after_id = self.documents.last().map(|document| document.get_document_id());
+            let page = DbConnection::new().read_document_summaries(list, after_id, DOCUMENT_PAGE_SIZE).unwrap_or_else(|err| {
+                println!("Error reading documents: {}", err);
+                Vec::new()
+            });
+            if page.is_empty() {
+                // Documents were deleted elsewhere since counting; stop asking for more.
+                self.total_documents = self.documents.len();
+                return
+            }
+            self.documents.extend(page);
+        }
+
+        /// The summary of the document with the number chosen in a picker.
+        fn find_document(&self, document_number: &str) -> Option<Arc<Document>> {
+            let document_id = self.document_numbers.iter().find(|(_, number)| number == document_number)?.0;
+            return DbConnection::new().read_document_summary(document_id).unwrap_or_else(|err| {
+                println!("Error reading document: {}", err);
+                None
+            })
+        }
+
+        /// The numbers to choose from in a picker, leaving out the document `own_document_id`.
+        fn other_document_numbers(&self, own_document_id: u32) -> Vec<String> {
+            return self.document_numbers.iter()
+                .filter(|(document_id, _)| *document_id != own_document_id)
+                .map(|(_, document_number)| document_number.clone())
+                .collect()
+        }
+
+        /// The document with its attachments, for opening it.
+        fn load_document(&self, document_id: u32) -> Option<Arc<Document>> {
+            return DbConnection::new().read_document(document_id).unwrap_or_else(|err| {
+                println!("Error reading document: {}", err);
+                None
+            })
+        }
+
+        /// Re-reads the document table after another screen changed the library.
+        pub(crate) fn reload_documents(&mut self) {
+            self.refresh_documents();
+            self.reload_tags();
+            self.reload_document_types();
+            self.reset_state();
+        }
+
         pub(crate) fn update(&mut self, message: Message) -> Task<Message> {
+            let task = self.handle_message(message);
+            if self.queried_search.as_ref() != Some(&self.search_text) {
+                self.run_query();
+            }
+            if self.listed.as_ref() != Some(&self.list_query()) {
+                self.refresh_documents();
+            }
+            self.sync_attachment_lock();
+            return task
+        }
+
+        fn handle_message(&mut self, message: Message) -> Task<Message> {
+            if let Some(job) = self.job.as_ref().filter(|_| waits_for_job(&message)) {
+                self.error_message = Some(format!("Wait for \"{}\" to finish or cancel it.", job.label()));
+                return Task::none()
+            }
+            if edits_document(&message) && self.is_locked() {
+                self.error_message = Some(self.locked_message());
+                return Task::none()
+            }
+            if let Some(user_name) = self.attachment_holder().filter(|_| edits_attachment(&message)) {
+                self.error_message = Some(format!("{} is editing this attachment. Wait until they close it.", user_name));
+                return Task::none()
+            }
             match message {
                 Message::NewDocument => {
                     self.reset_state();
@@ -100,60 +399,124 @@ pub(crate) mod document_list {
                 },
                 Message::SaveNewDocument => {
                     if self.current_document_number.is_empty() {
+                        self.fill_next_number();
+                    }
+                    self.number_error = self.document_number_error();
+                    let field_check = self.validate_fields();
+                    let dates = self.validate_dates();
+                    if self.current_document_number.is_empty() || self.number_error.is_some() || field_check.is_err() || dates.is_err() {
                         self.show_empty_field_warning = true;
+                        self.error_message = field_check.err().or(dates.err());
                     }
                     else {
                         let mut conn = DbConnection::new();
-                        conn.new_document(
+                        let Ok((document_date, due_date)) = dates else { return Task::none() };
+                        match conn.new_document(
                             self.current_document_number.clone(),
                             self.current_document_type.clone(), 
-                            self.current_comment.clone()
-                        ).unwrap_or_else(|err| {
-                            println!("Error adding new document: {}", err);
-                            0
-                        });
+                            self.current_comment.clone(),
+                            self.field_values_to_save(),
+                            document_date,
+                            due_date
+                        ) {
+                            Ok(_) => self.edit_history.push(Edit::CreateDocument { document_id: conn.last_rowid().unwrap() as u32 }),
+                            Err(err) => {
+                                println!("Error adding new document: {}", err);
+                                self.error_message = Some(save_error_message(&err, &self.current_document_number));
+                                return Task::none()
+                            }
+                        }
 
                         self.reset_state();
-                        self.documents = Result::expect(conn.read_document_table(), "Error retrieving data from database");
-                        self.current_open_document = self.documents.iter().find(|document| document.get_document_id() == conn.last_rowid().unwrap() as u32).cloned();
+                        self.refresh_documents();
+                        self.current_open_document = self.load_document(conn.last_rowid().unwrap() as u32);
                         self.current_document_number = self.current_open_document.as_ref().unwrap().get_document_number().to_string();
                         self.current_document_type = self.current_open_document.as_ref().unwrap().get_document_type().to_string();
                         self.current_comment = self.current_open_document.as_ref().unwrap().get_comment().to_string();
-                        let file_path = format!("./data/{}", self.current_open_document.as_ref().unwrap().get_document_number());
-                        fs::create_dir(file_path).unwrap_or_else(|err| {
-                            println!("Error creating document's attachment folder: {}", err);
-                        });
+                        self.current_field_values = self.current_open_document.as_ref().unwrap().get_field_values().clone();
+                        self.load_details();
                     }
                     
                     Task::none()
                 },
+                Message::OpenLinkedDocument(document_id) => {
+                    match DbConnection::new().read_document_summary(document_id) {
+                        Ok(Some(document)) => return self.handle_message(Message::OpenDocument(document)),
+                        Ok(None) => self.error_message = Some(String::from("The linked document is in the trash.")),
+                        Err(err) => {
+                            println!("Error reading document: {}", err);
+                            self.error_message = Some(format!("Error reading document: {}", err));
+                        }
+                    }
+                    Task::none()
+                },
                 Message::OpenDocument(document) => {
-                    self.current_open_document = Some(document.clone());
+                    if self.current_open_document.is_some() && self.data_changed {
+                        self.error_message = Some(String::from("Save your changes before opening another document."));
+                        return Task::none()
+                    }
+                    if self.unlocked_document_id != Some(document.get_document_id()) {
+                        self.unlocked_document_id = None;
+                    }
+                    self.reset_state();
+                    self.current_open_document = Some(self.load_document(document.get_document_id()).unwrap_or(document.clone()));
                     self.current_document_number = document.clone().get_document_number().to_string();
                     self.current_document_type = document.clone().get_document_type().to_string();
                     self.current_comment = document.clone().get_comment().to_string();
+                    self.current_field_values = document.get_field_values().clone();
+                    self.load_details();
                     Task::none()
                 }
                 Message::SaveCurrentDocument => {
-                    if self.current_document_number.is_empty() {
+                    self.number_error = self.document_number_error();
+                    let field_check = self.validate_fields();
+                    let dates = self.validate_dates();
+                    if self.current_document_number.is_empty() || self.number_error.is_some() || field_check.is_err() || dates.is_err() {
                         self.show_empty_field_warning = true;
+                        self.error_message = field_check.err().or(dates.err());
                     }
                     else {
                         let mut conn=  DbConnection::new();
-                        let current_document_id = self.current_open_document.as_ref().unwrap().get_document_id();
-                        conn.edit_document_details(
-                            current_document_id,
-                            self.current_document_number.clone(),
-                            self.current_document_type.clone(),
-                            self.current_comment.clone()
-                        ).unwrap_or_else(|err| {
-                            println!("Error editing document: {}", err);
-                            0
-                        });
+                        let current_document = self.current_open_document.clone().unwrap();
+                        let current_document_id = current_document.get_document_id();
+                        let Ok((document_date, due_date)) = dates else { return Task::none() };
+                        let after = DocumentDetails {
+                            document_number: self.current_document_number.clone(),
+                            document_type: self.current_document_type.clone(),
+                            comment: self.current_comment.clone(),
+                            field_values: self.field_values_to_save(),
+                            document_date: document_date,
+                            due_date: due_date
+                        };
+                        match conn.edit_document_details(current_document_id, &after, Some(current_document.get_version())) {
+                            Ok(_) => self.edit_history.push(Edit::ChangeDocument {
+                                document_id: current_document_id,
+                                before: DocumentDetails {
+                                    document_number: current_document.get_document_number().to_string(),
+                                    document_type: current_document.get_document_type().to_string(),
+                                    comment: current_document.get_comment().to_string(),
+                                    field_values: after.field_values.iter().map(|(field_id, _)| {
+                                        (*field_id, current_document.get_field_values().get(field_id).cloned().unwrap_or_default())
+                                    }).collect(),
+                                    document_date: current_document.get_document_date(),
+                                    due_date: current_document.get_due_date()
+                                },
+                                after: after
+                            }),
+                            Err(err) => {
+                                println!("Error editing document: {}", err);
+                                self.error_message = Some(save_error_message(&err, &self.current_document_number));
+                                return Task::none()
+                            }
+                        }
 
+                        let show_history = self.history.is_some();
                         self.reset_state();
-                        self.documents = Result::expect(conn.read_document_table(), "Error retrieving data from database");
-                        self.current_open_document = self.documents.iter().find(|document| document.get_document_id() == current_document_id).cloned();
+                        self.refresh_documents();
+                        self.reopen(Some(current_document_id), None);
+                        if show_history {
+                            self.load_history();
+                        }
                     }
                     
                     Task::none()
@@ -163,6 +526,7 @@ pub(crate) mod document_list {
                     Task::none()
                 },
                 Message::CloseDocument => {
+                    self.unlocked_document_id = None;
                     self.reset_state();
                     String::clear(&mut self.search_text);
                     Task::none()
@@ -171,15 +535,30 @@ pub(crate) mod document_list {
                     self.search_text = input;
                     Task::none()
                 },
+                Message::ApplySuggestion(suggestion) => {
+                    self.search_text = complete(&self.search_text, &suggestion);
+                    focus(Id::new("search"))
+                },
                 Message::Back => { Task::none() },
                 Message::None => { Task::none() },
                 Message::CurrentDocumentNumberChange(input) => {
                     self.current_document_number = input;
+                    self.number_error = self.document_number_error();
                     self.data_changed = true;
                     Task::none()
                 },
                 Message::CurrentDocumentTypeChange(input) => {
                     self.current_document_type = input;
+                    if self.create_new_document && self.current_document_number.is_empty() {
+                        self.fill_next_number();
+                    }
+                    self.number_error = self.document_number_error();
+                    self.data_changed = true;
+                    Task::none()
+                },
+                Message::NextDocumentNumber => {
+                    self.fill_next_number();
+                    self.number_error = self.document_number_error();
                     self.data_changed = true;
                     Task::none()
                 },
@@ -188,6 +567,465 @@ pub(crate) mod document_list {
                     self.data_changed = true;
                     Task::none()
                 },
+                Message::CurrentDocumentDateChange(input) => {
+                    self.current_document_date = input;
+                    self.data_changed = true;
+                    Task::none()
+                },
+                Message::CurrentDueDateChange(input) => {
+                    self.current_due_date = input;
+                    self.data_changed = true;
+                    Task::none()
+                },
+                Message::ChangeStatus(status) => {
+                    let document = self.current_open_document.clone().unwrap();
+                    if self.data_changed {
+                        self.error_message = Some(String::from("Save your changes before changing the status."));
+                        return Task::none()
+                    }
+                    if !document.get_status().can_change_to(status) {
+                        self.error_message = Some(format!("A document that is {} cannot be moved to {}.", document.get_status(), status));
+                        return Task::none()
+                    }
+                    let mut conn = DbConnection::new();
+                    if let Err(err) = conn.change_document_status(document.get_document_id(), status, Some(document.get_version())) {
+                        println!("Error changing document status: {}", err);
+                        self.error_message = Some(if is_conflict(&err) { String::from(CHANGED_ELSEWHERE) } else { format!("Error changing document status: {}", err) });
+                        return Task::none()
+                    }
+                    // Moving between locked statuses locks the document again.
+                    self.unlocked_document_id = None;
+                    let show_history = self.history.is_some();
+                    self.refresh_documents();
+                    self.reset_state();
+                    self.reopen(Some(document.get_document_id()), None);
+                    if show_history {
+                        self.load_history();
+                    }
+                    Task::none()
+                },
+                Message::Unlock => {
+                    let document_id = self.current_open_document.as_ref().unwrap().get_document_id();
+                    DbConnection::new().record_unlock(document_id).unwrap_or_else(|err| {
+                        println!("Error recording unlock: {}", err);
+                    });
+                    self.unlocked_document_id = Some(document_id);
+                    self.error_message = None;
+                    if self.history.is_some() {
+                        self.load_history();
+                    }
+                    Task::none()
+                },
+                Message::Lock => {
+                    self.unlocked_document_id = None;
+                    Task::none()
+                },
+                Message::LinkKindChange(kind) => {
+                    self.link_kind = kind;
+                    Task::none()
+                },
+                Message::LinkTargetChange(document_number) => {
+                    self.link_target = Some(document_number);
+                    Task::none()
+                },
+                Message::AddLink => {
+                    let document_id = self.current_open_document.as_ref().unwrap().get_document_id();
+                    let target_id = match self.link_target.as_ref().and_then(|document_number| self.document_numbers.iter().find(|(_, number)| number == document_number)) {
+                        Some((target_id, _)) => *target_id,
+                        None => {
+                            self.error_message = Some(String::from("Choose the document to link."));
+                            return Task::none()
+                        }
+                    };
+                    if self.links.iter().any(|link| link.other_document_id == target_id && link.kind == self.link_kind) {
+                        self.error_message = Some(format!("The documents are already linked as \"{}\".", self.link_kind));
+                        return Task::none()
+                    }
+                    match DbConnection::new().add_document_link(document_id, target_id, self.link_kind) {
+                        Ok(_) => {
+                            self.link_target = None;
+                            self.error_message = None;
+                        },
+                        Err(err) => {
+                            println!("Error linking documents: {}", err);
+                            self.error_message = Some(format!("Error linking documents: {}", err));
+                        }
+                    }
+                    self.load_links();
+                    if self.history.is_some() {
+                        self.load_history();
+                    }
+                    Task::none()
+                },
+                Message::RemoveLink(link_id) => {
+                    DbConnection::new().remove_document_link(link_id).unwrap_or_else(|err| {
+                        println!("Error removing link: {}", err);
+                        self.error_message = Some(format!("Error removing link: {}", err));
+                    });
+                    self.load_links();
+                    if self.history.is_some() {
+                        self.load_history();
+                    }
+                    Task::none()
+                },
+                Message::ToggleMerge => {
+                    self.show_merge = !self.show_merge;
+                    self.show_split = false;
+                    self.merge_source = None;
+                    self.merge_take.clear();
+                    Task::none()
+                },
+                Message::MergeSourceChange(document_number) => {
+                    self.merge_source = Some(document_number);
+                    self.merge_take.clear();
+                    Task::none()
+                },
+                Message::MergeChoice(field, take_merged) => {
+                    if take_merged {
+                        self.merge_take.insert(field);
+                    }
+                    else {
+                        self.merge_take.remove(&field);
+                    }
+                    Task::none()
+                },
+                Message::MergeDocuments => {
+                    let document = self.current_open_document.clone().unwrap();
+                    if self.data_changed {
+                        self.error_message = Some(String::from("Save or discard the changes to the document first."));
+                        return Task::none()
+                    }
+                    let source = match self.merge_source.as_ref().and_then(|document_number| self.find_document(document_number)) {
+                        Some(source) => source,
+                        None => {
+                            self.error_message = Some(String::from("Choose the document to merge into this one."));
+                            return Task::none()
+                        }
+                    };
+                    let mut conn = DbConnection::new();
+                    if let Some(locked) = self.locked_document(&conn, vec![document.get_document_id(), source.get_document_id()]) {
+                        self.error_message = Some(format!("Document {} is locked. Unlock it to merge it.", locked.get_document_number()));
+                        return Task::none()
+                    }
+                    let details = merged_details(&document, &source, &self.document_types, &self.merge_take);
+                    match conn.merge_documents(document.get_document_id(), source.get_document_id(), &details, Some((document.get_version(), source.get_version()))) {
+                        Ok(_) => {
+                            self.refresh_documents();
+                            self.reset_state();
+                            self.reopen(Some(document.get_document_id()), None);
+                        },
+                        Err(err) => {
+                            println!("Error merging documents: {}", err);
+                            self.error_message = Some(if is_conflict(&err) { String::from(CHANGED_ELSEWHERE) } else { format!("Error merging documents: {}", err) });
+                        }
+                    }
+                    Task::none()
+                },
+                Message::ToggleSplit => {
+                    self.show_split = !self.show_split;
+                    self.show_merge = false;
+                    self.split_selection.clear();
+                    self.split_document_number = self.next_document_number().unwrap_or_default();
+                    Task::none()
+                },
+                Message::SplitAttachmentChange(attachment_id, selected) => {
+                    if selected {
+                        self.split_selection.insert(attachment_id);
+                    }
+                    else {
+                        self.split_selection.remove(&attachment_id);
+                    }
+                    Task::none()
+                },
+                Message::SplitDocumentNumberChange(document_number) => {
+                    self.split_document_number = document_number;
+                    Task::none()
+                },
+                Message::SplitDocument => {
+                    let document = self.current_open_document.clone().unwrap();
+                    let document_id = document.get_document_id();
+                    if self.data_changed {
+                        self.error_message = Some(String::from("Save or discard the changes to the document first."));
+                        return Task::none()
+                    }
+                    if self.split_selection.is_empty() {
+                        self.error_message = Some(String::from("Choose the attachments to split off."));
+                        return Task::none()
+                    }
+                    let document_number = self.split_document_number.trim().to_string();
+                    if let Some(error) = self.split_number_error(&document_number) {
+                        self.error_message = Some(error);
+                        return Task::none()
+                    }
+                    let attachment_ids: Vec<u32> = self.split_selection.iter().copied().collect();
+                    let mut conn = DbConnection::new();
+                    match conn.split_document(document_id, &attachment_ids, document_number, Some(document.get_version())) {
+                        Ok(_) => {
+                            let new_document_id = conn.last_rowid().unwrap() as u32;
+                            self.refresh_documents();
+                            self.reset_state();
+                            self.reopen(Some(new_document_id), None);
+                        },
+                        Err(err) => {
+                            println!("Error splitting document: {}", err);
+                            self.error_message = Some(if is_conflict(&err) { String::from(CHANGED_ELSEWHERE) } else { format!("Error splitting document: {}", err) });
+                        }
+                    }
+                    Task::none()
+                },
+                Message::SortKeyChange(sort_key) => {
+                    self.sort_key = sort_key;
+                    Task::none()
+                },
+                Message::ToggleSortDirection => {
+                    self.sort_descending = !self.sort_descending;
+                    Task::none()
+                },
+                Message::PollChanges => {
+                    // A running job's own writes would look like someone else's.
+                    if self.job.is_none() {
+                        self.poll_changes();
+                    }
+                    Task::none()
+                },
+                Message::ReloadOpenDocument => {
+                    self.reload_open_document();
+                    Task::none()
+                },
+                Message::ListScrolled(viewport) => {
+                    self.list_offset = viewport.absolute_offset().y;
+                    self.list_height = viewport.bounds().height;
+                    if viewport.relative_offset().y > 0.9 {
+                        self.grid_limit += GRID_BATCH;
+                        if self.documents.len() < self.total_documents {
+                            self.load_document_page();
+                        }
+                    }
+                    Task::none()
+                },
+                Message::LayoutChange(layout) => {
+                    self.layout = layout;
+                    Task::none()
+                },
+                Message::SortColumn(sort_key) => {
+                    match self.sort_key == sort_key {
+                        true => self.sort_descending = !self.sort_descending,
+                        false => {
+                            self.sort_key = sort_key;
+                            self.sort_descending = false;
+                        }
+                    }
+                    Task::none()
+                },
+                Message::ResizeColumnStart(column) => {
+                    self.resizing_column = Some(column);
+                    Task::none()
+                },
+                Message::ResizeColumnMove(position) => {
+                    if let Some(column) = self.resizing_column {
+                        let start = CHECKBOX_COLUMN_WIDTH + self.column_widths[..column].iter().map(|width| width + COLUMN_HANDLE_WIDTH).sum::<f32>();
+                        self.column_widths[column] = (position.x - start).max(MIN_COLUMN_WIDTH);
+                    }
+                    Task::none()
+                },
+                Message::ResizeColumnEnd => {
+                    self.resizing_column = None;
+                    Task::none()
+                },
+                Message::GroupByChange(group_by) => {
+                    self.group_by = group_by;
+                    Task::none()
+                },
+                Message::ViewNameChange(name) => {
+                    self.view_name = name;
+                    Task::none()
+                },
+                Message::SaveView => {
+                    let name = self.view_name.trim().to_string();
+                    if name.is_empty() {
+                        self.error_message = Some(String::from("Enter a name for the view."));
+                        return Task::none()
+                    }
+                    let mut conn = DbConnection::new();
+                    match conn.save_view(name, self.search_text.clone(), self.sort_key, self.sort_descending, self.group_by) {
+                        Ok(_) => {
+                            self.current_view = conn.last_rowid().map(|view_id| view_id as u32);
+                            self.error_message = None;
+                        },
+                        Err(err) => {
+                            println!("Error saving view: {}", err);
+                            self.error_message = Some(format!("Error saving view: {}", err));
+                        }
+                    }
+                    self.reload_saved_views();
+                    Task::none()
+                },
+                Message::DeleteView(view_id) => {
+                    DbConnection::new().delete_saved_view(view_id).unwrap_or_else(|err| {
+                        println!("Error deleting view: {}", err);
+                        self.error_message = Some(format!("Error deleting view: {}", err));
+                        0
+                    });
+                    self.current_view = None;
+                    self.view_name.clear();
+                    self.reload_saved_views();
+                    Task::none()
+                },
+                Message::OpenView(view_id) => {
+                    if self.data_changed || self.files_changed {
+                        self.error_message = Some(String::from("Save or discard the open changes first."));
+                        return Task::none()
+                    }
+                    let view = match self.saved_views.iter().find(|view| view.view_id == view_id) {
+                        Some(view) => view.clone(),
+                        None => return Task::none()
+                    };
+                    self.reset_state();
+                    self.clear_selection();
+                    self.status_filter = None;
+                    self.type_filter = None;
+                    self.field_filter = None;
+                    self.field_filter_value.clear();
+                    self.tag_filter.clear();
+                    self.search_text = view.query;
+                    self.sort_key = view.sort_key;
+                    self.sort_descending = view.descending;
+                    self.group_by = view.group_by;
+                    self.view_name = view.name;
+                    self.current_view = Some(view.view_id);
+                    Task::none()
+                },
+                Message::ModifiersChanged(modifiers) => {
+                    self.modifiers = modifiers;
+                    Task::none()
+                },
+                Message::SelectDocument(document_id) => {
+                    let visible: Vec<u32> = self.documents.iter().map(|document| document.get_document_id()).collect();
+                    select(&mut self.selected_documents, &mut self.selection_anchor, &visible, document_id, self.modifiers.shift());
+                    Task::none()
+                },
+                Message::SelectAttachment(attachment_id) => {
+                    let visible: Vec<u32> = self.visible_attachments().iter().map(|attachment| attachment.get_attachment_id()).collect();
+                    select(&mut self.selected_attachments, &mut self.selection_anchor, &visible, attachment_id, self.modifiers.shift());
+                    Task::none()
+                },
+                Message::SelectAll => {
+                    match self.current_open_document {
+                        Some(_) => self.selected_attachments = self.visible_attachments().iter().map(|attachment| attachment.get_attachment_id()).collect(),
+                        // The documents not loaded yet are selected too.
+                        None => self.selected_documents = self.listed_document_ids().into_iter().collect()
+                    }
+                    Task::none()
+                },
+                Message::ClearSelection => {
+                    self.clear_selection();
+                    Task::none()
+                },
+                Message::BulkTagNameChange(name) => {
+                    self.bulk_tag_name = name;
+                    Task::none()
+                },
+                Message::BulkTypeChange(document_type) => {
+                    self.bulk_type = Some(document_type);
+                    Task::none()
+                },
+                Message::BulkMoveTargetChange(document_number) => {
+                    self.bulk_move_target = Some(document_number);
+                    Task::none()
+                },
+                Message::BulkDelete => {
+                    self.show_confirm_delete = false;
+                    return self.start_bulk(match self.current_open_document {
+                        Some(_) => BulkAction::DeleteAttachments,
+                        None => BulkAction::DeleteDocuments
+                    })
+                },
+                Message::BulkTag => {
+                    let name = self.bulk_tag_name.trim().to_string();
+                    if name.is_empty() {
+                        self.error_message = Some(String::from("Enter the tag to add."));
+                        return Task::none()
+                    }
+                    return self.start_bulk(match self.current_open_document {
+                        Some(_) => BulkAction::TagAttachments(name),
+                        None => BulkAction::TagDocuments(name)
+                    })
+                },
+                Message::BulkChangeType => {
+                    match &self.bulk_type {
+                        Some(document_type) => return self.start_bulk(BulkAction::ChangeDocumentType(document_type.clone())),
+                        None => self.error_message = Some(String::from("Choose the type to change to."))
+                    }
+                    Task::none()
+                },
+                Message::BulkMove => {
+                    let target_id = match self.bulk_move_target.as_ref().and_then(|document_number| self.document_numbers.iter().find(|(_, number)| number == document_number)) {
+                        Some((target_id, _)) => *target_id,
+                        None => {
+                            self.error_message = Some(String::from("Choose the document to move the attachments to."));
+                            return Task::none()
+                        }
+                    };
+                    let source_id = self.current_open_document.as_ref().map(|document| document.get_document_id()).unwrap_or(target_id);
+                    if let Some(document) = self.locked_document(&DbConnection::new(), vec![source_id, target_id]) {
+                        self.error_message = Some(format!("Document {} is locked. Unlock it to move attachments.", document.get_document_number()));
+                        return Task::none()
+                    }
+                    return self.start_bulk(BulkAction::MoveAttachments(target_id))
+                },
+                Message::BulkExport => {
+                    let folder = match FileDialog::new().set_title("Export to Folder").pick_folder() {
+                        Some(folder) => folder,
+                        None => return Task::none()
+                    };
+                    return self.start_bulk(match self.current_open_document {
+                        Some(_) => BulkAction::ExportAttachments(ExportTarget::Folder(folder)),
+                        None => BulkAction::ExportDocuments(ExportTarget::Folder(folder))
+                    })
+                },
+                Message::BulkArchive => {
+                    let path = match FileDialog::new().set_title("Export to Archive").set_file_name("export.zip").add_filter("ZIP archive (.zip)", &["zip"]).save_file() {
+                        Some(path) => path,
+                        None => return Task::none()
+                    };
+                    return self.start_bulk(match self.current_open_document {
+                        Some(_) => BulkAction::ExportAttachments(ExportTarget::Archive(path)),
+                        None => BulkAction::ExportDocuments(ExportTarget::Archive(path))
+                    })
+                },
+                Message::Job(event) => {
+                    match event {
+                        JobEvent::Progress(done, total) => {
+                            if let Some(job) = self.job.as_mut() {
+                                job.set_progress(done, total);
+                            }
+                        },
+                        JobEvent::Finished(output) => {
+                            let cancelled = self.job.take().is_some_and(|job| job.is_cancelled());
+                            self.finish_job(output, cancelled);
+                        },
+                        JobEvent::Failed(err) => {
+                            let label = self.job.take().map(|job| job.label().to_string()).unwrap_or_default();
+                            self.error_message = Some(format!("{} failed: {}", label, err));
+                            self.refresh_open_items();
+                        }
+                    }
+                    Task::none()
+                },
+                Message::CancelJob => {
+                    if let Some(job) = &self.job {
+                        job.cancel();
+                    }
+                    Task::none()
+                },
+                Message::CloseBulk => {
+                    self.bulk_job = None;
+                    Task::none()
+                },
+                Message::StatusFilterChange(status) => {
+                    self.status_filter = status;
+                    Task::none()
+                },
                 Message::NewAttachment => {
                     self.reset_attachment_state();
                     self.create_new_attachment = true;
@@ -205,88 +1043,17 @@ pub(crate) mod document_list {
                         }
                     );
                     if previous_file_paths != self.selected_file_paths && self.selected_file_paths.is_some() {
-                        self.files_changed = true;
-                        let mut selected_file_bytes: Vec<Vec<u8>> = Vec::new();
-                        for path in self.selected_file_paths.as_ref().unwrap() {
-                            match fs::read(&path) {
-                                Ok(bytes) => {
-                                    if file_format::FileFormat::from_bytes(&bytes) == FileFormat::PortableDocumentFormat {
-                                        for image in pdf_to_png(bytes) {
-                                            selected_file_bytes.push(image);
-                                        }
-                                    }
-                                    else {
-                                        selected_file_bytes.push(bytes);
-                                    }
-                                }
-                                Err(err) => {
-                                    println!("Error reading files from paths: {}", err);
-                                }
-                            }
-                        }
-
-                        for bytes in selected_file_bytes {
-                            self.add_file_bytes(bytes);
-                        }
-
-                        self.update_file_handles();
-                        self.current_page_index = 0;
+                        let paths = self.selected_file_paths.clone().unwrap();
+                        return self.start_job("Reading files", move |progress| JobOutput::FilesRead(read_page_files(paths, progress)))
                     }
                     Task::none()
                 },
                 Message::SaveNewAttachment => {
                     if self.current_attachment_reference_number.is_empty() || self.current_file_bytes.is_none() {
                         self.show_empty_field_warning = true;
+                        return Task::none()
                     }
-                    else {
-                        let file_path = format!("./data/{}/{}", self.current_open_document.as_ref().unwrap().get_document_number(), self.current_attachment_reference_number);
-                        fs::create_dir(&file_path).unwrap_or_else(|err| {
-                            println!("Error creating document's attachment folder: {}", err);
-                        });
-                        let mut conn = DbConnection::new();
-                        let current_document_id = self.current_open_document.clone().unwrap().get_document_id();
-                        let current_document_number = &self.current_document_number;
-
-                        let mut data_file_paths: Vec<PathBuf> = Vec::new();
-                        for (index, _) in self.current_file_handles.as_ref().unwrap().iter().enumerate() {
-                            data_file_paths.push(format!("{}/{}_{}_{}.png", &file_path, current_document_number, self.current_attachment_reference_number, index + 1).into())
-                        }
-                        
-                        conn.new_attachment(data_file_paths, self.current_attachment_reference_number.clone(), self.current_attachment_comment.clone(), current_document_id).unwrap_or_else(|err| {
-                            println!("Error creating new attachment: {}", err);
-                        });
-
-                        for (index, bytes) in self.current_file_bytes.as_mut().unwrap().iter_mut().enumerate() {
-                            let file_name = format!("{}_{}_{}.png", current_document_number, self.current_attachment_reference_number, index + 1);
-                            let file_path = format!("./data/{}/{}/{}", current_document_number, self.current_attachment_reference_number, file_name);
-                            if FileFormat::from_bytes(&bytes) != FileFormat::PortableNetworkGraphics {
-                                let img = image::load_from_memory(&bytes);
-                                match img.unwrap().write_to(&mut Cursor::new(&mut *bytes), image::ImageFormat::Png) {
-                                    Err(err) => println!("Error converting image format: {}", err),
-                                    _ => {}
-                                }
-                            }
-
-                            let compressed_bytes = compress_image(bytes.to_vec());
-
-                            fs::write(&file_path, compressed_bytes).unwrap_or_else(|err| {
-                                println!("Error writing file to data folder: {}", err);
-                            });
-                        }
-
-                        self.reset_attachment_state();
-                        self.documents = Result::expect(conn.read_document_table(), "Error retrieving data from database");
-                        self.current_open_document = self.documents.iter().find(|document| document.get_document_id() == current_document_id).cloned();
-                        self.current_open_attachment = self.current_open_document.as_ref().unwrap().get_attachments().unwrap().iter().find(|attachment| attachment.get_attachment_id() == conn.last_rowid().unwrap() as u32).cloned();
-                        self.current_attachment_reference_number = self.current_open_attachment.as_ref().unwrap().get_reference_number().to_string();
-                        self.current_attachment_comment = self.current_open_attachment.as_ref().unwrap().get_comment().to_string();
-                        self.current_file_bytes = Some(Vec::new());
-                        for page in self.current_open_attachment.as_ref().unwrap().pages() {
-                            self.current_file_bytes.as_mut().unwrap().push(page.image().to_vec());
-                        }
-                        self.update_file_handles();
-                    }
-                    Task::none()
+                    return self.start_staging()
                 },
                 Message::OpenAttachment(attachment) => {
                     self.current_open_attachment = Some(attachment.clone());
@@ -304,90 +1071,13 @@ pub(crate) mod document_list {
                 Message::SaveCurrentAttachment => {
                     if self.current_attachment_reference_number.is_empty() {
                         self.show_empty_field_warning = true;
+                        return Task::none()
                     }
-                    else {
-                        let mut conn = DbConnection::new();
-                        let current_document_id = self.current_open_document.as_ref().unwrap().get_document_id();
-                        let current_document_number = self.current_open_document.as_ref().unwrap().get_document_number();
-                        let current_attachment_id = self.current_open_attachment.as_ref().unwrap().get_attachment_id();
-                        let old_attachment_reference_number = self.current_open_attachment.as_ref().unwrap().get_reference_number();
-                        let mut old_file_paths: Vec<String> = Vec::new();
-                        for page in self.current_open_attachment.as_ref().unwrap().pages() {
-                            old_file_paths.push(page.file_path().to_string());
-                        }
-                        
-                        conn.edit_attachment_details(
-                            current_attachment_id,
-                            self.current_attachment_reference_number.clone(),
-                            self.current_attachment_comment.clone()
-                        ).unwrap_or_else(|err| {
-                            println!("Error editing attachment: {}", err);
-                            0
-                        });
-
-                        if self.files_changed {
-                            let path = format!("./data/{}/{}", current_document_number, self.current_open_attachment.as_ref().unwrap().get_reference_number());
-                            fs::remove_dir_all(&path).expect("Error deleting old directory");
-                            let mut file_paths: Vec<PathBuf> = Vec::new();
-                            let file_dir = format!("./data/{}/{}", current_document_number, self.current_attachment_reference_number);
-                            fs::create_dir(&file_dir).expect("Error creating new directory");
-
-                            for (index, bytes) in self.current_file_bytes.as_mut().unwrap().iter_mut().enumerate() {
-                                let file_name = format!("{}_{}_{}.png", current_document_number, self.current_attachment_reference_number, index + 1);
-                                let file_path = format!("{}/{}", file_dir, file_name);
-                                
-                                if FileFormat::from_bytes(&bytes) != FileFormat::PortableNetworkGraphics {
-                                    let img = image::load_from_memory(&bytes);
-                                    match img.unwrap().write_to(&mut Cursor::new(&mut *bytes), image::ImageFormat::Png) {
-                                        Err(err) => println!("Error converting image format: {}", err),
-                                        _ => {}
-                                    }
-                                }
-
-                                let compressed_bytes = compress_image(bytes.to_vec());
-
-                                fs::write(&file_path, compressed_bytes).unwrap_or_else(|err| {
-                                    println!("Error writing file to data folder: {}", err);
-                                });
-
-                                file_paths.push(file_path.into());
-                            }
-
-                            conn.edit_attachment_pages(self.current_open_attachment.as_ref().unwrap().get_attachment_id(), file_paths).expect("Error editing attachment pages");
-
-                        }
-                        else {
-                            let mut new_file_paths: Vec<PathBuf> = Vec::new();
-                            let old_file_dir = format!("./data/{}/{}", current_document_number, old_attachment_reference_number);
-                            let new_file_dir = format!("./data/{}/{}", current_document_number, self.current_attachment_reference_number);
-                            
-                            for (index, path) in old_file_paths.into_iter().enumerate() {
-                                let file_name = format!("{}_{}_{}.png", current_document_number, self.current_attachment_reference_number, index + 1);
-                                let old_file_path = format!("{}/{}", old_file_dir, file_name);
-
-                                fs::rename(path, &old_file_path).expect("Error renaming file");
-                                let new_file_path = format!("{}/{}", new_file_dir, file_name);
-                                new_file_paths.push(new_file_path.into());
-                            }
-                            fs::rename(old_file_dir, &new_file_dir).expect("Error renaming directory");
-
-                            conn.edit_attachment_pages(self.current_open_attachment.as_ref().unwrap().get_attachment_id(), new_file_paths).expect("Error editing attachment pages");
-                        }
-
-                        self.reset_attachment_state();
-                        self.documents = Result::expect(conn.read_document_table(), "Error retrieving data from database");
-                        self.current_open_document = self.documents.iter().find(|document| document.get_document_id() == current_document_id).cloned();
-                        self.current_open_attachment = self.current_open_document.as_ref().unwrap().get_attachments().unwrap().iter().find(|attachment| attachment.get_attachment_id() == current_attachment_id).cloned();
-                        self.current_attachment_reference_number = self.current_open_attachment.as_ref().unwrap().get_reference_number().to_string();
-                        self.current_attachment_comment = self.current_open_attachment.as_ref().unwrap().get_comment().to_string();
-                        self.current_file_bytes = Some(Vec::new());
-                        self.current_file_handles = Some(Vec::new());
-                        for page in self.current_open_attachment.as_ref().unwrap().pages() {
-                            self.current_file_bytes.as_mut().unwrap().push(page.image().to_vec());
-                        }
-                        self.update_file_handles();
+                    // Page files are named by content, so a changed reference number needs no file changes.
+                    if self.files_changed {
+                        return self.start_staging()
                     }
-
+                    self.save_current_attachment(None);
                     Task::none()
                 },
                 Message::CurrentAttachmentReferenceNumberChange(input) => {
@@ -469,7 +1159,6 @@ pub(crate) mod document_list {
                                     println!("Error running powershell command: {}", err);
                                     String::new()
                                 }
-                                _ => String::new()
                             }
                         },
 
@@ -495,78 +1184,301 @@ pub(crate) mod document_list {
                             self.update_file_handles();
                         },
                         Err(err) => {
-                            println!("Error reading scanned image: {}", err);
-                            self.current_file = Some(Handle::from_bytes(ERROR_FERRIS));
-                            self.current_file_path = None;
+                            println!("Error reading scanned image: {}", err);
+                            self.current_file = Some(Handle::from_bytes(ERROR_FERRIS));
+                            self.current_file_path = None;
+                        }
+                    }
+                    Task::none()
+                },
+                Message::ScanFail => {
+                    self.scanning = false;
+                    self.scan_progress = 0.0;
+                    println!("Scan failed or was cancelled.");
+                    Task::none()
+                },
+                Message::ScanTick => {
+                    if self.scanning {
+                        self.scan_progress += 0.02;
+                        if self.scan_progress > 1.0 {
+                            self.scan_progress = 0.0;
+                        }
+                    }
+                    Task::none()
+                },
+                Message::FinalizeScan => {
+                    for bytes in self.scanned_file_bytes.as_ref().unwrap() {
+                        self.current_file_handles.as_mut().unwrap().push(Handle::from_bytes(bytes.to_vec()));
+                    }
+                    Task::none()
+                },
+                Message::ClearImageFiles => {
+                    self.current_file_bytes = None;
+                    self.update_file_handles();
+                    Task::none()
+                }
+                Message::DeleteDocument => {
+                    let mut conn = DbConnection::new();
+                    let current_document_id = self.current_open_document.as_ref().unwrap().get_document_id();
+                    match conn.delete_document(current_document_id) {
+                        Ok(_) => self.edit_history.push(Edit::DeleteDocument { document_id: current_document_id }),
+                        Err(err) => println!("Error deleting document: {}", err)
+                    }
+
+                    self.refresh_documents();
+                    self.reset_state();
+                    
+                    Task::none()
+                },
+                Message::DeleteAttachment => {
+                    let mut conn = DbConnection::new();
+                    let current_document_id = self.current_open_document.as_ref().unwrap().get_document_id();
+                    let current_attachment_id = self.current_open_attachment.as_ref().unwrap().get_attachment_id();
+                    match conn.delete_attachment(current_attachment_id) {
+                        Ok(_) => self.edit_history.push(Edit::DeleteAttachment { document_id: current_document_id, attachment_id: current_attachment_id }),
+                        Err(err) => println!("Error deleting attachment: {}", err)
+                    }
+
+                    self.refresh_documents();
+                    self.current_open_document = self.load_document(current_document_id);
+                    self.reset_attachment_state();
+                    
+                    Task::none()
+                },
+                Message::ShowConfirmDelete => {
+                    self.show_confirm_delete = true;
+                    Task::none()
+                },
+                Message::ToggleHistory => {
+                    if self.history.is_some() {
+                        self.history = None;
+                    }
+                    else {
+                        self.load_history();
+                    }
+                    Task::none()
+                },
+                Message::ToggleVersions => {
+                    if self.versions.is_some() {
+                        self.versions = None;
+                        self.compare_left = None;
+                        self.compare_right = None;
+                        self.current_page_index = 0;
+                    }
+                    else {
+                        self.load_versions();
+                    }
+                    Task::none()
+                },
+                Message::ToggleTransfer => {
+                    self.show_transfer = !self.show_transfer;
+                    self.transfer_target = None;
+                    self.transfer_reference_number = self.current_open_attachment.as_ref().map(|attachment| attachment.get_reference_number().to_string()).unwrap_or_default();
+                    Task::none()
+                },
+                Message::TransferTargetChange(document_number) => {
+                    self.transfer_target = Some(document_number);
+                    Task::none()
+                },
+                Message::TransferReferenceNumberChange(reference_number) => {
+                    self.transfer_reference_number = reference_number;
+                    Task::none()
+                },
+                Message::MoveAttachment | Message::CopyAttachment => {
+                    let copy = matches!(message, Message::CopyAttachment);
+                    let attachment = self.current_open_attachment.clone().unwrap();
+                    let attachment_id = attachment.get_attachment_id();
+                    let from_document_id = self.current_open_document.as_ref().unwrap().get_document_id();
+                    if self.data_changed || self.files_changed {
+                        self.error_message = Some(String::from("Save or discard the changes to the attachment first."));
+                        return Task::none()
+                    }
+                    let target_id = match self.transfer_target.as_ref().and_then(|document_number| self.document_numbers.iter().find(|(_, number)| number == document_number)) {
+                        Some((target_id, _)) => *target_id,
+                        None => {
+                            self.error_message = Some(String::from("Choose the document to move or copy the attachment to."));
+                            return Task::none()
+                        }
+                    };
+                    // A move takes the attachment out of its document as well, so both have to be unlocked.
+                    let document_ids = if copy { vec![target_id] } else { vec![from_document_id, target_id] };
+                    let mut conn = DbConnection::new();
+                    if let Some(document) = self.locked_document(&conn, document_ids) {
+                        self.error_message = Some(format!("Document {} is locked. Unlock it to {} attachments.", document.get_document_number(), if copy { "add" } else { "move" }));
+                        return Task::none()
+                    }
+                    let reference_number = self.transfer_reference_number.trim().to_string();
+                    if reference_number.is_empty() {
+                        self.error_message = Some(String::from("Enter the attachment number to use."));
+                        return Task::none()
+                    }
+                    if let Some(error) = self.reference_number_error(&reference_number, if copy { None } else { Some(attachment_id) }) {
+                        self.error_message = Some(error);
+                        return Task::none()
+                    }
+
+                    let result = match copy {
+                        true => conn.copy_attachment(attachment_id, target_id, reference_number.clone(), Some(attachment.get_version())),
+                        false => conn.move_attachment(attachment_id, target_id, reference_number.clone(), Some(attachment.get_version()))
+                    };
+                    let moved_attachment_id = match result {
+                        Ok(_) if copy => {
+                            let copy_id = conn.last_rowid().unwrap() as u32;
+                            self.edit_history.push(Edit::CreateAttachment { document_id: target_id, attachment_id: copy_id });
+                            copy_id
+                        },
+                        Ok(_) => {
+                            self.edit_history.push(Edit::MoveAttachment {
+                                document_id: target_id,
+                                attachment_id: attachment_id,
+                                from_document_id: from_document_id,
+                                before_reference_number: attachment.get_reference_number().to_string(),
+                                after_reference_number: reference_number
+                            });
+                            attachment_id
+                        },
+                        Err(err) => {
+                            println!("Error transferring attachment: {}", err);
+                            self.error_message = Some(if is_conflict(&err) { String::from(CHANGED_ELSEWHERE) } else { format!("Error transferring attachment: {}", err) });
+                            return Task::none()
                         }
+                    };
+                    self.refresh_documents();
+                    self.reset_state();
+                    self.reopen(Some(target_id), Some(moved_attachment_id));
+                    self.current_document_tab = Tab::Attachments;
+                    Task::none()
+                },
+                Message::CompareVersion(side, version_id) => {
+                    // `None` stands for the attachment's current pages.
+                    let pages = match version_id.and_then(|version_id| self.versions.as_ref().and_then(|versions| versions.iter().find(|version| version.version_id == version_id))) {
+                        Some(version) => (version_label(version), version.pages.iter().map(|(path, _)| {
+                            Handle::from_bytes(read_page_file(path).unwrap_or_else(|err| {
+                                println!("Error reading page file: {}", err);
+                                ERROR_FERRIS.to_vec()
+                            }))
+                        }).collect()),
+                        None => (String::from("Current"), self.current_file_handles.clone().unwrap_or_default())
+                    };
+                    match side {
+                        CompareSide::Left => self.compare_left = Some(pages),
+                        CompareSide::Right => self.compare_right = Some(pages)
                     }
+                    self.current_page_index = 0;
                     Task::none()
                 },
-                Message::ScanFail => {
-                    self.scanning = false;
-                    self.scan_progress = 0.0;
-                    println!("Scan failed or was cancelled.");
+                Message::CloseCompare => {
+                    self.compare_left = None;
+                    self.compare_right = None;
+                    self.current_page_index = 0;
                     Task::none()
                 },
-                Message::ScanTick => {
-                    if self.scanning {
-                        self.scan_progress += 0.02;
-                        if self.scan_progress > 1.0 {
-                            self.scan_progress = 0.0;
+                Message::RestoreVersion(version_id) => {
+                    let version = match self.versions.as_ref().and_then(|versions| versions.iter().find(|version| version.version_id == version_id)) {
+                        Some(version) => version.clone(),
+                        None => return Task::none()
+                    };
+                    let mut conn = DbConnection::new();
+                    let current_document_id = self.current_open_document.as_ref().unwrap().get_document_id();
+                    let current_attachment = self.current_open_attachment.clone().unwrap();
+                    let current_attachment_id = current_attachment.get_attachment_id();
+                    let before = attachment_details(&current_attachment);
+                    let after = AttachmentDetails {
+                        reference_number: before.reference_number.clone(),
+                        comment: before.comment.clone(),
+                        pages: version.pages.clone()
+                    };
+
+                    match conn.save_attachment(current_attachment_id, after.reference_number.clone(), after.comment.clone(), Some(after.pages.clone()), Some(current_attachment.get_version())) {
+                        Ok(_) => self.edit_history.push(Edit::ChangeAttachment { document_id: current_document_id, attachment_id: current_attachment_id, before: before, after: after }),
+                        Err(err) => {
+                            println!("Error restoring attachment version: {}", err);
+                            self.error_message = Some(if is_conflict(&err) { String::from(CHANGED_ELSEWHERE) } else { format!("Error restoring version: {}", err) });
+                            return Task::none()
                         }
                     }
+
+                    self.reset_state();
+                    self.refresh_documents();
+                    self.reopen(Some(current_document_id), Some(current_attachment_id));
+                    self.current_document_tab = Tab::Attachments;
+                    self.load_versions();
                     Task::none()
                 },
-                Message::FinalizeScan => {
-                    for bytes in self.scanned_file_bytes.as_ref().unwrap() {
-                        self.current_file_handles.as_mut().unwrap().push(Handle::from_bytes(bytes.to_vec()));
-                    }
+                Message::FieldValueChange(field_id, input) => {
+                    self.current_field_values.insert(field_id, input);
+                    self.data_changed = true;
                     Task::none()
                 },
-                Message::ClearImageFiles => {
-                    self.current_file_bytes = None;
-                    self.update_file_handles();
+                Message::TypeFilterChange(document_type) => {
+                    self.type_filter = document_type;
+                    self.field_filter = None;
+                    self.field_filter_value.clear();
                     Task::none()
-                }
-                Message::DeleteDocument => {
-                    let mut conn = DbConnection::new();
-                    conn.delete_document(self.current_open_document.as_ref().unwrap().get_document_id());
-                    match fs::remove_dir_all(format!("./data/{}", self.current_open_document.as_ref().unwrap().get_document_number())) {
-                        Err(err) => println!("Error deleting data directory: {}", err),
-                        Ok(_) => {}
+                },
+                Message::FieldFilterChange(field) => {
+                    self.field_filter = Some(field);
+                    Task::none()
+                },
+                Message::FieldFilterValueChange(input) => {
+                    self.field_filter_value = input;
+                    Task::none()
+                },
+                Message::ToggleTagFilter(tag_id) => {
+                    if self.tag_filter.contains(&tag_id) {
+                        self.tag_filter.retain(|selected| *selected != tag_id);
+                    }
+                    else {
+                        self.tag_filter.push(tag_id);
                     }
-
-                    self.documents = Result::expect(conn.read_document_table(), "Error retrieving data from database");
-                    self.reset_state();
-                    
                     Task::none()
                 },
-                Message::DeleteAttachment => {
-                    let mut conn = DbConnection::new();
-                    match conn.delete_attachment(self.current_open_attachment.as_ref().unwrap().get_attachment_id()) {
-                        Ok(_) => {},
-                        Err(err) => println!("Error deleting attachment: {}", err)
+                Message::ToggleTagMatchMode => {
+                    self.match_all_tags = !self.match_all_tags;
+                    Task::none()
+                },
+                Message::NewTagNameChange(input) => {
+                    self.new_tag_name = input;
+                    Task::none()
+                },
+                Message::AddTag => {
+                    if self.new_tag_name.trim().is_empty() {
+                        return Task::none()
                     }
-                    match fs::remove_dir_all(format!("./data/{}/{}", self.current_open_document.as_ref().unwrap().get_document_number(), self.current_open_attachment.as_ref().unwrap().get_reference_number())) {
-                        Err(err) => println!("Error deleting file: {}", err),
-                        Ok(_) => {}
+                    let mut conn = DbConnection::new();
+                    let result = match self.tagged_attachment() {
+                        Some(attachment_id) => conn.add_attachment_tag(attachment_id, self.new_tag_name.clone()),
+                        None => conn.add_document_tag(self.current_open_document.as_ref().unwrap().get_document_id(), self.new_tag_name.clone())
+                    };
+                    match result {
+                        Ok(_) => self.new_tag_name.clear(),
+                        Err(err) => {
+                            println!("Error adding tag: {}", err);
+                            self.error_message = Some(format!("Error adding tag: {}", err));
+                        }
                     }
-
-                    let current_document_id = self.current_open_document.as_ref().unwrap().get_document_id();
-                    self.documents = Result::expect(conn.read_document_table(), "Error retrieving data from database");
-                    self.current_open_document = self.documents.iter().find(|document| document.get_document_id() == current_document_id).cloned();
-                    self.reset_attachment_state();
-                    
+                    self.refresh_open_items();
                     Task::none()
                 },
-                Message::ShowConfirmDelete => {
-                    self.show_confirm_delete = true;
+                Message::RemoveTag(tag_id) => {
+                    let mut conn = DbConnection::new();
+                    let result = match self.tagged_attachment() {
+                        Some(attachment_id) => conn.remove_attachment_tag(attachment_id, tag_id),
+                        None => conn.remove_document_tag(self.current_open_document.as_ref().unwrap().get_document_id(), tag_id)
+                    };
+                    result.unwrap_or_else(|err| {
+                        println!("Error removing tag: {}", err);
+                        self.error_message = Some(format!("Error removing tag: {}", err));
+                    });
+                    self.refresh_open_items();
                     Task::none()
                 },
                 Message::ExportToPdf => {
                     let file_name = format!("{}_{}.pdf", self.current_open_document.as_ref().unwrap().get_document_number(), self.current_open_attachment.as_ref().unwrap().get_reference_number());
-                    let path = format!("./data/{}/{}/{}", self.current_open_document.as_ref().unwrap().get_document_number(), self.current_open_attachment.as_ref().unwrap().get_reference_number(), file_name);
-                    export_to_pdf(self.current_file_bytes.as_ref().unwrap().to_vec(), path.into());
+                    if let Some(path) = FileDialog::new().set_title("Export to PDF").set_file_name(file_name).add_filter("PDF (.pdf)", &["pdf"]).save_file() {
+                        let pages = self.current_file_bytes.as_ref().unwrap().to_vec();
+                        return self.start_job("Exporting PDF", move |progress| JobOutput::Exported(export_to_pdf(pages, path, Some(progress))))
+                    }
                     Task::none()
                 },
                 Message::PrevPage => {
@@ -575,8 +1487,49 @@ pub(crate) mod document_list {
                     }
                     Task::none()
                 },
+                Message::Undo => {
+                    // Showing the edit reloads the open document, which would drop its unsaved changes.
+                    if self.data_changed || self.files_changed {
+                        self.error_message = Some(String::from("Save or discard the open changes first."));
+                        return Task::none()
+                    }
+                    let mut conn = DbConnection::new();
+                    if let Some(document) = self.edit_history.next_undo().and_then(|edit| self.locked_document(&conn, edit.document_ids())) {
+                        self.error_message = Some(format!("Document {} is locked; open and unlock it to undo this change.", document.get_document_number()));
+                        return Task::none()
+                    }
+                    match self.edit_history.undo(&mut conn) {
+                        Ok(Some(edit)) => self.show_edit(&edit, true),
+                        Ok(None) => {},
+                        Err(err) => {
+                            println!("Error undoing edit: {}", err);
+                            self.error_message = Some(if is_conflict(&err) { String::from(CHANGED_ELSEWHERE) } else { format!("Error undoing edit: {}", err) });
+                        }
+                    }
+                    Task::none()
+                },
+                Message::Redo => {
+                    if self.data_changed || self.files_changed {
+                        self.error_message = Some(String::from("Save or discard the open changes first."));
+                        return Task::none()
+                    }
+                    let mut conn = DbConnection::new();
+                    if let Some(document) = self.edit_history.next_redo().and_then(|edit| self.locked_document(&conn, edit.document_ids())) {
+                        self.error_message = Some(format!("Document {} is locked; open and unlock it to redo this change.", document.get_document_number()));
+                        return Task::none()
+                    }
+                    match self.edit_history.redo(&mut conn) {
+                        Ok(Some(edit)) => self.show_edit(&edit, false),
+                        Ok(None) => {},
+                        Err(err) => {
+                            println!("Error redoing edit: {}", err);
+                            self.error_message = Some(if is_conflict(&err) { String::from(CHANGED_ELSEWHERE) } else { format!("Error redoing edit: {}", err) });
+                        }
+                    }
+                    Task::none()
+                },
                 Message::NextPage => {
-                    if self.current_page_index < self.current_file_handles.as_ref().unwrap().len() - 1 {
+                    if self.current_page_index + 1 < self.viewer_page_count() {
                         self.current_page_index += 1;
                     }
                     
@@ -587,6 +1540,17 @@ pub(crate) mod document_list {
 
 
         pub(crate) fn view(&self) -> Element<Message> {
+            let mut content = column![].spacing(5);
+            if self.job.is_some() {
+                content = content.push(self.job_bar());
+            }
+            if self.remote_change.is_some() || self.attachment_holder().is_some() {
+                content = content.push(self.sharing_bar());
+            }
+            return content.push(self.screen_view()).into()
+        }
+
+        fn screen_view(&self) -> Element<'_, Message> {
             let test_linear = Linear {
                 stops: [
                     Some(ColorStop { offset: 0.0, color: Color::from_rgb(1.0, 0.0, 0.0)}),
@@ -604,12 +1568,15 @@ pub(crate) mod document_list {
             let test_gradient = Gradient::Linear(test_linear);
             let test_background = Background::Gradient(test_gradient);
 
-            //let mut document_cards: Vec<MouseArea<'static, Message>> = Vec::new();
-            let mut document_cards: Vec<DataCard> = Vec::new();
-
-            for document in &self.documents {
-                document_cards.push(DataCard::new(Some(document.clone()), None, self.current_theme.clone().unwrap()));
-            }
+            let groups = group_documents(self.documents.clone(), self.group_by, self.sort_descending);
+            let document_groups: Element<Message> = match self.layout {
+                DocumentLayout::Grid => scrollable(self.document_grid(groups)).on_scroll(Message::ListScrolled).into(),
+                DocumentLayout::Table => mouse_area(column![self.table_header(), scrollable(self.document_table(groups)).on_scroll(Message::ListScrolled)].spacing(5))
+                    .on_move(Message::ResizeColumnMove)
+                    .on_release(Message::ResizeColumnEnd)
+                    .on_exit(Message::ResizeColumnEnd)
+                    .into()
+            };
 
             match &self.current_open_document {
                 None => {
@@ -620,7 +1587,9 @@ pub(crate) mod document_list {
                                 Container::new(row![
                                     button("<").on_press(Message::CloseDocument),
                                     button("Save").on_press(Message::SaveNewDocument),
-                                ].spacing(5)).width(Length::Fill).padding(5).style(container::bordered_box),
+                                    Space::new().width(Length::Fill),
+                                    error_text(&self.error_message)
+                                ].spacing(5).align_y(Center)).width(Length::Fill).padding(5).style(container::bordered_box),
                                 Container::new(column![
                                     row![
                                         Text::new("New Document").size(20).align_y(Center)
@@ -631,21 +1600,31 @@ pub(crate) mod document_list {
                                             Text::new("Document Number "),
                                             Text::new("*").color(Color::from_rgb(1.0, 0.0, 0.0))
                                         ].width(Length::FillPortion(1)),
-                                        if self.show_empty_field_warning && self.current_document_number.is_empty() {
-                                            text_input("", &self.current_document_number).on_input(Message::CurrentDocumentNumberChange).id(self.input1_id.as_ref().unwrap().clone()).width(Length::FillPortion(4)).style(|theme, _| empty_text_input_warning(theme))
-                                        }
-                                        else {
-                                            text_input("", &self.current_document_number).on_input(Message::CurrentDocumentNumberChange).id(self.input1_id.as_ref().unwrap().clone()).width(Length::FillPortion(4))
-                                        }
-                                    ].spacing(5).align_y(Center),
-                                    row![
-                                        Text::new("Document Type").width(Length::FillPortion(1)), 
-                                        text_input("", &self.current_document_type).on_input(Message::CurrentDocumentTypeChange).id(self.input2_id.as_ref().unwrap().clone()).width(Length::FillPortion(4))
+                                        column![
+                                            row![
+                                                if (self.show_empty_field_warning && self.current_document_number.is_empty()) || self.number_error.is_some() {
+                                                    text_input("", &self.current_document_number).on_input(Message::CurrentDocumentNumberChange).id(self.input1_id.as_ref().unwrap().clone()).style(|theme, _| empty_text_input_warning(theme))
+                                                }
+                                                else {
+                                                    text_input("", &self.current_document_number).on_input(Message::CurrentDocumentNumberChange).id(self.input1_id.as_ref().unwrap().clone())
+                                                },
+                                                if self.current_type().is_some_and(|document_type| document_type.get_auto_number()) {
+                                                    button("Next Number").on_press(Message::NextDocumentNumber)
+                                                }
+                                                else {
+                                                    button("Next Number")
+                                                }
+                                            ].spacing(5),
+                                            error_text(&self.number_error)
+                                        ].width(Length::FillPortion(4))
                                     ].spacing(5).align_y(Center),
+                                    type_picker(&self.document_types, &self.current_document_type),
+                                    dates_form(&self.current_document_date, &self.current_due_date),
                                     row![
                                         Text::new("Comment").width(Length::FillPortion(1)), 
                                         text_input("", &self.current_comment).on_input(Message::CurrentCommentChange).id(self.input3_id.as_ref().unwrap().clone()).width(Length::FillPortion(4))
-                                    ].spacing(5).align_y(Center)
+                                    ].spacing(5).align_y(Center),
+                                    field_form(self.current_type_fields(), &self.current_field_values, self.show_empty_field_warning)
                                 ].spacing(5)).padding(5).style(container::bordered_box).width(Length::Fill).height(Length::Fill)
                                 
                             ].spacing(5)
@@ -656,25 +1635,25 @@ pub(crate) mod document_list {
                             Container::new(column![
                                 Container::new(row![
                                         button("<").on_press(Message::Back),
-                                        button("New").on_press(Message::NewDocument)
+                                        button("New").on_press(Message::NewDocument),
+                                        Space::new().width(Length::Fill),
+                                        if self.edit_history.can_undo() { button("Undo").on_press(Message::Undo) } else { button("Undo") },
+                                        if self.edit_history.can_redo() { button("Redo").on_press(Message::Redo) } else { button("Redo") }
                                 ].spacing(5)).width(Length::Fill).padding(5).style(container::bordered_box),
                                 Container::new(column![
                                     row![
                                         Text::new("Documents").align_y(Center).size(20),
+                                        Text::new(format!("{} documents", self.total_documents)).size(12),
                                         Space::new().width(Length::Fill),
-                                        text_input("Search", &self.search_text).on_input(Message::SearchTextChange).id(Id::new("search")),
+                                        button("Select All").on_press(Message::SelectAll),
+                                        self.search_box(),
                                     ].spacing(5),
                                     rule::horizontal(2),
-                                    scrollable(row(
-                                        document_cards.into_iter().filter(|card| {
-                                            card.get_document().get_document_number().to_string().to_lowercase().contains(&self.search_text.to_lowercase()) ||
-                                            card.get_document().get_document_type().to_string().to_lowercase().contains(&self.search_text.to_lowercase()) ||
-                                            card.get_document().get_comment().to_string().to_lowercase().contains(&self.search_text.to_lowercase())
-                                            
-                                        }).map(|card| {
-                                            card.new_document_card().into()
-                                        }) 
-                                    ).spacing(10).wrap()),
+                                    self.filter_bar(),
+                                    self.tag_filter_bar(),
+                                    self.view_bar(),
+                                    self.bulk_bar(),
+                                    document_groups,
                                 ].spacing(5)).padding(5).style(container::bordered_box).width(Length::Fill).height(Length::Fill)
                             ].spacing(5)
                             ).width(Length::Fill).height(Length::Fill).into()
@@ -697,7 +1676,11 @@ pub(crate) mod document_list {
                                             button("Save")
                                         },
                                         button("New").on_press(Message::NewDocument),
+                                        button(if self.history.is_some() { "Hide History" } else { "History" }).on_press(Message::ToggleHistory),
+                                        button("Merge").on_press(Message::ToggleMerge),
+                                        button("Split").on_press(Message::ToggleSplit),
                                         Space::new().width(Length::Fill),
+                                        error_text(&self.error_message),
                                         if self.show_confirm_delete {
                                             row![
                                                 Text::from("Confirm deletion: "),
@@ -714,27 +1697,44 @@ pub(crate) mod document_list {
                                             Text::new(format!("Document - {}", self.current_document_number),).size(20)
                                         ].spacing(5).align_y(Center),
                                         rule::horizontal(2),
+                                        self.status_bar(document),
                                         row![
                                             row![
                                                 Text::new("Document Number "),
                                                 Text::new("*").color(Color::from_rgb(1.0, 0.0, 0.0))
                                             ].width(Length::FillPortion(1)),
-                                            if self.show_empty_field_warning && self.current_document_number.is_empty() {
-                                                text_input(&document.get_document_number().to_string(), &self.current_document_number).on_input(Message::CurrentDocumentNumberChange).width(Length::FillPortion(4)).id(self.input1_id.as_ref().unwrap().clone()).style(|theme, _| empty_text_input_warning(theme))
-                                            }
-                                            else {
-                                                text_input(&document.get_document_number().to_string(), &self.current_document_number).on_input(Message::CurrentDocumentNumberChange).width(Length::FillPortion(4)).id(self.input1_id.as_ref().unwrap().clone())
-                                            }
-                                        ].spacing(5).align_y(Center),
-                                        row![
-                                            Text::new("Document Type").width(Length::FillPortion(1)), 
-                                            text_input(&document.get_document_type().to_string(), &self.current_document_type).on_input(Message::CurrentDocumentTypeChange).width(Length::FillPortion(4)).id(self.input2_id.as_ref().unwrap().clone())
+                                            column![
+                                                if (self.show_empty_field_warning && self.current_document_number.is_empty()) || self.number_error.is_some() {
+                                                    text_input(&document.get_document_number().to_string(), &self.current_document_number).on_input(Message::CurrentDocumentNumberChange).id(self.input1_id.as_ref().unwrap().clone()).style(|theme, _| empty_text_input_warning(theme))
+                                                }
+                                                else {
+                                                    text_input(&document.get_document_number().to_string(), &self.current_document_number).on_input(Message::CurrentDocumentNumberChange).id(self.input1_id.as_ref().unwrap().clone())
+                                                },
+                                                error_text(&self.number_error)
+                                            ].width(Length::FillPortion(4))
                                         ].spacing(5).align_y(Center),
+                                        type_picker(&self.document_types, &self.current_document_type),
+                                        dates_form(&self.current_document_date, &self.current_due_date),
                                         row![
                                             Text::new("Comment").width(Length::FillPortion(1)), 
                                             text_input(&document.get_comment().to_string(), &self.current_comment).on_input(Message::CurrentCommentChange).width(Length::FillPortion(4)).id(self.input3_id.as_ref().unwrap().clone())
                                         ].spacing(5).align_y(Center),
-                                        
+                                        field_form(self.current_type_fields(), &self.current_field_values, self.show_empty_field_warning),
+                                        tag_editor(document.get_tags(), &self.new_tag_name),
+                                        self.related_panel(document),
+                                        if self.show_merge {
+                                            self.merge_panel(document)
+                                        }
+                                        else if self.show_split {
+                                            self.split_panel(document)
+                                        }
+                                        else {
+                                            column![].into()
+                                        },
+                                        match &self.history {
+                                            Some(entries) => history_panel(entries),
+                                            None => column![].into()
+                                        }
                                     ].spacing(5)).padding(5).style(container::bordered_box).width(Length::Fill).height(Length::Fill),
                                 ].spacing(5)
                                 ).height(Length::Fill).width(Length::Fill)
@@ -742,8 +1742,8 @@ pub(crate) mod document_list {
                             Tab::Attachments => {
                                 let mut attachment_cards: Vec<DataCard> = Vec::new();
 
-                                for attachment in &self.current_open_document.as_ref().unwrap().get_attachments().unwrap() {
-                                    attachment_cards.push(DataCard::new(None, Some(attachment.clone()), self.current_theme.clone().unwrap()));
+                                for attachment in &self.visible_attachments() {
+                                    attachment_cards.push(DataCard::new(None, Some(attachment.clone()), self.current_theme.clone().unwrap(), self.selected_attachments.contains(&attachment.get_attachment_id())));
                                 }
                                 match &self.current_open_attachment {
                                     None => {
@@ -753,7 +1753,9 @@ pub(crate) mod document_list {
                                                 Container::new(column![
                                                     Container::new(row![
                                                         button("<").on_press(Message::CloseAttachment),
-                                                        button("Save").on_press(Message::SaveNewAttachment)
+                                                        button("Save").on_press(Message::SaveNewAttachment),
+                                                        Space::new().width(Length::Fill),
+                                                        error_text(&self.error_message)
                                                     ].spacing(5).align_y(Center)).width(Length::Fill).padding(5).style(container::bordered_box),
                                                     Container::new(column![
                                                         row![
@@ -813,7 +1815,7 @@ pub(crate) mod document_list {
                                                                             button("<")
                                                                         },
                                                                         Text::new(self.current_page_index + 1),
-                                                                        if self.current_page_index + 1 < self.current_file_handles.as_ref().unwrap().len() {
+                                                                        if self.current_page_index + 1 < self.viewer_page_count() {
                                                                             button(">").on_press(Message::NextPage)
                                                                         }
                                                                         else {
@@ -837,13 +1839,12 @@ pub(crate) mod document_list {
                                                         row![
                                                             Text::new("Attachments").size(20).align_y(Center),
                                                             Space::new().width(Length::Fill),
-                                                            text_input("Search", &self.search_text).on_input(Message::SearchTextChange).id(Id::new("search"))
-                                                        ],
+                                                            button("Select All").on_press(Message::SelectAll),
+                                                            self.search_box()
+                                                        ].spacing(5),
                                                         rule::horizontal(2),
-                                                        scrollable(row(attachment_cards.into_iter().filter(|card| {
-                                                            card.get_attachment().get_reference_number().to_string().to_lowercase().contains(&self.search_text) ||
-                                                            card.get_attachment().get_comment().to_string().to_lowercase().contains(&self.search_text)
-                                                        }).map(|card| {
+                                                        self.bulk_bar(),
+                                                        scrollable(row(attachment_cards.into_iter().map(|card| {
                                                             card.new_attachment_card().into()
                                                         })).spacing(10).wrap()),
                                                     ].spacing(5)).style(container::bordered_box).padding(5).width(Length::Fill).height(Length::Fill),
@@ -864,7 +1865,10 @@ pub(crate) mod document_list {
                                                     button("Save")
                                                 },
                                                 button("New").on_press(Message::NewAttachment),
+                                                button(if self.versions.is_some() { "Hide Versions" } else { "Versions" }).on_press(Message::ToggleVersions),
+                                                button("Move / Copy").on_press(Message::ToggleTransfer),
                                                 Space::new().width(Length::Fill),
+                                                error_text(&self.error_message),
                                                 if self.show_confirm_delete {
                                                     row![
                                                         Text::from("Confirm deletion: "),
@@ -896,6 +1900,7 @@ pub(crate) mod document_list {
                                                         },
                                                         Text::new("Comment"), 
                                                         text_input(&attachment.get_comment().to_string(), &self.current_attachment_comment).on_input(Message::CurrentAttachmentCommentChange).id(self.input2_id.as_ref().unwrap().clone()),
+                                                        tag_editor(attachment.get_tags(), &self.new_tag_name),
                                                         row![
                                                             Text::new("Image File "),
                                                             Text::new("*").color(Color::from_rgb(1.0, 0.0, 0.0))
@@ -916,16 +1921,33 @@ pub(crate) mod document_list {
                                                                 button(Text::new("Clear").center()).on_press(Message::ClearImageFiles).width(Length::FillPortion(1))
                                                             ].spacing(5)
                                                         ].spacing(5),
-                                                        ProgressBar::new(0.0..=1.0, self.scan_progress)
+                                                        ProgressBar::new(0.0..=1.0, self.scan_progress),
+                                                        if self.show_transfer {
+                                                            self.transfer_panel(attachment.get_attachment_id())
+                                                        }
+                                                        else {
+                                                            column![].into()
+                                                        },
+                                                        match &self.versions {
+                                                            Some(versions) => versions_panel(versions, self.compare_left.is_some() || self.compare_right.is_some()),
+                                                            None => column![].into()
+                                                        }
                                                     ].spacing(5)).padding(5).style(container::bordered_box).width(Length::FillPortion(1)).height(Length::Fill),
                                                     rule::vertical(2),
                                                     Container::new(
                                                         column![
-                                                            if self.current_file_handles.is_none() || self.current_file_handles.as_ref().unwrap().len() == 0 {
-                                                                Viewer::new(Handle::from_bytes(ERROR_FERRIS)).width(Length::Fill).height(Length::Fill)
+                                                            if self.compare_left.is_some() || self.compare_right.is_some() {
+                                                                let current = (String::from("Current"), self.current_file_handles.clone().unwrap_or_default());
+                                                                let (left_label, left_pages) = self.compare_left.clone().unwrap_or(current.clone());
+                                                                let (right_label, right_pages) = self.compare_right.clone().unwrap_or(current);
+                                                                Element::from(row![
+                                                                    column![Text::new(left_label), page_viewer(&left_pages, self.current_page_index)].spacing(5).align_x(Center).width(Length::Fill),
+                                                                    rule::vertical(2),
+                                                                    column![Text::new(right_label), page_viewer(&right_pages, self.current_page_index)].spacing(5).align_x(Center).width(Length::Fill)
+                                                                ].spacing(5).height(Length::Fill))
                                                             }
                                                             else {
-                                                                Viewer::new(self.current_file_handles.as_ref().unwrap()[self.current_page_index].clone()).width(Length::Fill).height(Length::Fill)
+                                                                page_viewer(self.current_file_handles.as_ref().unwrap_or(&Vec::new()), self.current_page_index)
                                                             },
                                                             rule::horizontal(2),
                                                             row![
@@ -936,7 +1958,7 @@ pub(crate) mod document_list {
                                                                     button("<")
                                                                 },
                                                                 Text::new(self.current_page_index + 1),
-                                                                if self.current_page_index + 1 < self.current_file_handles.as_ref().unwrap().len() {
+                                                                if self.current_page_index + 1 < self.viewer_page_count() {
                                                                     button(">").on_press(Message::NextPage)
                                                                 }
                                                                 else {
@@ -953,38 +1975,1091 @@ pub(crate) mod document_list {
                                 }
                             },
                         },
-                        Container::new(
-                            // Navigation Tabs between Document details and attachments
-                            Tabs::new(Message::SwitchTab)
-                                .push(Tab::Details, TabLabel::Text(String::from("Details")), Space::new())
-                                .push(Tab::Attachments, TabLabel::Text(String::from("Attachments")), Space::new())
-                                .tab_bar_position(TabBarPosition::Bottom)
-                                .set_active_tab(&self.current_document_tab)
-                                .height(Length::Shrink)
-                        )
-                    ].spacing(5)).into()
+                        Container::new(
+                            // Navigation Tabs between Document details and attachments
+                            Tabs::new(Message::SwitchTab)
+                                .push(Tab::Details, TabLabel::Text(String::from("Details")), Space::new())
+                                .push(Tab::Attachments, TabLabel::Text(String::from("Attachments")), Space::new())
+                                .tab_bar_position(TabBarPosition::Bottom)
+                                .set_active_tab(&self.current_document_tab)
+                                .height(Length::Shrink)
+                        )
+                    ].spacing(5)).into()
+                }
+            }
+        }
+
+        pub(crate) fn subscription(&self) -> Subscription<Message> {
+            let kb_event = keyboard::listen().map(|event| {
+                match event {
+                    keyboard::Event::KeyPressed { key, modifiers, ..} => {
+                        match key.as_ref() {
+                            Key::Character("z" | "Z") if modifiers.command() && modifiers.shift() => Message::Redo,
+                            Key::Character("z") if modifiers.command() => Message::Undo,
+                            Key::Character("y") if modifiers.command() => Message::Redo,
+                            _ => Message::KeyEvent(key)
+                        }
+                    }
+                    keyboard::Event::ModifiersChanged(modifiers) => Message::ModifiersChanged(modifiers),
+                    _ => Message::None
+                }
+            });
+
+            let tick_event = if self.scanning {
+                iced::time::every(iced::time::Duration::from_millis(100)).map(|_| Message::ScanTick)
+            }
+            else {
+                Subscription::none()
+            };
+
+            let poll_event = iced::time::every(CHANGE_POLL_INTERVAL).map(|_| Message::PollChanges);
+
+            Subscription::batch(vec![kb_event, tick_event, poll_event])
+        }
+
+        /// Reloads the documents and shows whatever an undone or redone edit touched.
+        fn show_edit(&mut self, edit: &Edit, reverted: bool) {
+            let tab = self.current_document_tab.clone();
+            self.refresh_documents();
+            self.reset_state();
+            if edit.leaves_target(reverted) {
+                self.reopen(Some(edit.document_id()), edit.attachment_id());
+            }
+            else if edit.attachment_id().is_some() {
+                self.reopen(Some(edit.document_id()), None);
+            }
+            self.current_document_tab = if edit.attachment_id().is_some() { Tab::Attachments } else { tab };
+        }
+
+        /// Opens the document and attachment with the given ids, if they still exist.
+        fn reopen(&mut self, document_id: Option<u32>, attachment_id: Option<u32>) {
+            let document = match document_id.and_then(|id| self.load_document(id)) {
+                Some(document) => document,
+                None => return
+            };
+            self.current_open_document = Some(document.clone());
+            self.current_document_number = document.get_document_number().to_string();
+            self.current_document_type = document.get_document_type().to_string();
+            self.current_comment = document.get_comment().to_string();
+            self.current_field_values = document.get_field_values().clone();
+            self.load_details();
+
+            let attachment = match attachment_id.and_then(|id| document.get_attachments().unwrap_or_default().into_iter().find(|attachment| attachment.get_attachment_id() == id)) {
+                Some(attachment) => attachment,
+                None => return
+            };
+            self.current_open_attachment = Some(attachment.clone());
+            self.current_attachment_reference_number = attachment.get_reference_number().to_string();
+            self.current_attachment_comment = attachment.get_comment().to_string();
+            self.current_file_bytes = Some(attachment.pages().iter().map(|page| page.image().to_vec()).collect());
+            self.update_file_handles();
+        }
+
+        fn load_history(&mut self) {
+            let document_id = match &self.current_open_document {
+                Some(document) => document.get_document_id(),
+                None => return
+            };
+            self.history = Some(DbConnection::new().read_audit_log(document_id).unwrap_or_else(|err| {
+                println!("Error reading document history: {}", err);
+                Vec::new()
+            }));
+        }
+
+        fn reload_document_types(&mut self) {
+            self.document_types = DbConnection::new().read_document_types().unwrap_or_else(|err| {
+                println!("Error reading document types: {}", err);
+                Vec::new()
+            });
+            self.type_filter = self.type_filter.as_ref().and_then(|selected| self.document_types.iter().find(|document_type| document_type.get_type_id() == selected.get_type_id()).cloned());
+            self.field_filter = None;
+            self.queried_search = None;
+        }
+
+        /// The managed type chosen in the open form.
+        fn current_type(&self) -> Option<&DocumentType> {
+            return self.document_types.iter().find(|document_type| document_type.get_name().eq_ignore_ascii_case(self.current_document_type.trim()))
+        }
+
+        /// The custom fields of the type chosen in the open form.
+        fn current_type_fields(&self) -> Vec<TypeField> {
+            return self.current_type().map(|document_type| document_type.get_fields().clone()).unwrap_or_default()
+        }
+
+        /// Checks the number against the type's pattern and the numbers already used. A document keeps its
+        /// number without checks as long as neither the number nor the type changes, so numbers given out before
+        /// a pattern was set stay editable.
+        fn document_number_error(&self) -> Option<String> {
+            let document_number = &self.current_document_number;
+            if document_number.is_empty() {
+                return None
+            }
+            let open_document_id = self.current_open_document.as_ref().map(|document| document.get_document_id());
+            if let Some(document) = &self.current_open_document && document.get_document_number().as_str() == document_number && document.get_document_type().as_str() == self.current_document_type {
+                return None
+            }
+
+            if let Some(pattern) = self.current_type().and_then(|document_type| document_type.number_pattern()) && !pattern.matches(document_number) {
+                return Some(format!("{} numbers have to look like {}.", self.current_document_type, pattern.as_str()))
+            }
+
+            return match DbConnection::new().find_document_number(document_number) {
+                Ok(Some(document_id)) if Some(document_id) == open_document_id => None,
+                Ok(Some(_)) => Some(format!("Document number {} is already used.", document_number)),
+                Ok(None) => None,
+                Err(err) => {
+                    println!("Error checking document number: {}", err);
+                    None
+                }
+            }
+        }
+
+        fn load_details(&mut self) {
+            let document = match &self.current_open_document {
+                Some(document) => document.clone(),
+                None => return
+            };
+            self.current_document_date = document.get_document_date().map(format_date).unwrap_or_default();
+            self.current_due_date = document.get_due_date().map(format_date).unwrap_or_default();
+            self.status_changes = DbConnection::new().read_audit_log(document.get_document_id()).unwrap_or_else(|err| {
+                println!("Error reading status changes: {}", err);
+                Vec::new()
+            }).into_iter().filter(|entry| entry.field.as_deref() == Some("status")).collect();
+            self.load_links();
+        }
+
+        fn load_links(&mut self) {
+            let document_id = match &self.current_open_document {
+                Some(document) => document.get_document_id(),
+                None => return
+            };
+            self.links = DbConnection::new().read_document_links(document_id).unwrap_or_else(|err| {
+                println!("Error reading document links: {}", err);
+                Vec::new()
+            });
+        }
+
+        fn related_panel(&self, document: &Document) -> Element<'_, Message> {
+            let mut link_rows = column![].spacing(5);
+            for link in &self.links {
+                link_rows = link_rows.push(row![
+                    Text::new(link.label()).width(Length::Fixed(110.0)),
+                    button(Text::new(link.other_document_number.to_string())).style(button::text).on_press(Message::OpenLinkedDocument(link.other_document_id)),
+                    Space::new().width(Length::Fill),
+                    button("Remove").on_press(Message::RemoveLink(link.link_id))
+                ].spacing(5).align_y(Center));
+            }
+            let document_numbers = self.other_document_numbers(document.get_document_id());
+
+            row![
+                Text::new("Related").width(Length::FillPortion(1)),
+                column![
+                    link_rows,
+                    row![
+                        PickList::new(LinkKind::ALL, Some(self.link_kind), Message::LinkKindChange),
+                        PickList::new(document_numbers, self.link_target.clone(), Message::LinkTargetChange).placeholder("Document").width(Length::Fill),
+                        button("Link").on_press(Message::AddLink)
+                    ].spacing(5).align_y(Center)
+                ].spacing(5).width(Length::FillPortion(4))
+            ].spacing(5).into()
+        }
+
+        fn transfer_panel(&self, attachment_id: u32) -> Element<'_, Message> {
+            let current_document_id = self.current_open_document.as_ref().map(|document| document.get_document_id()).unwrap_or_default();
+            let document_numbers = self.other_document_numbers(current_document_id);
+            let reference_number = self.transfer_reference_number.trim();
+            // A move may keep the attachment's own number, a copy needs a new one.
+            let taken_by_others = self.reference_number_error(reference_number, Some(attachment_id)).is_some();
+            let taken = self.reference_number_error(reference_number, None).is_some();
+
+            column![
+                rule::horizontal(2),
+                Text::new("Move or copy to"),
+                PickList::new(document_numbers, self.transfer_target.clone(), Message::TransferTargetChange).placeholder("Document").width(Length::Fill),
+                if taken {
+                    text_input("Attachment Number", &self.transfer_reference_number).on_input(Message::TransferReferenceNumberChange).style(|theme, _| empty_text_input_warning(theme))
+                }
+                else {
+                    text_input("Attachment Number", &self.transfer_reference_number).on_input(Message::TransferReferenceNumberChange)
+                },
+                row![
+                    if taken_by_others { button("Move") } else { button("Move").on_press(Message::MoveAttachment) },
+                    if taken { button("Copy") } else { button("Copy").on_press(Message::CopyAttachment) },
+                    button("Cancel").on_press(Message::ToggleTransfer)
+                ].spacing(5)
+            ].spacing(5).into()
+        }
+
+        /// Why `reference_number` cannot be used, ignoring the attachment `own_attachment_id`. Numbers in the trash
+        /// are free; restoring their attachment fails while another one uses the number.
+        fn reference_number_error(&self, reference_number: &str, own_attachment_id: Option<u32>) -> Option<String> {
+            return match DbConnection::new().find_reference_number(reference_number) {
+                Ok(Some(attachment_id)) if Some(attachment_id) == own_attachment_id => None,
+                Ok(Some(_)) => Some(format!("Attachment number {} is already in use.", reference_number)),
+                Ok(None) => None,
+                Err(err) => {
+                    println!("Error checking attachment number: {}", err);
+                    None
+                }
+            }
+        }
+
+        /// The ids of all the documents the list selects, loaded or not, in list order.
+        fn listed_document_ids(&self) -> Vec<u32> {
+            return DbConnection::new().read_document_ids(&self.list_query()).unwrap_or_else(|err| {
+                println!("Error reading documents: {}", err);
+                self.documents.iter().map(|document| document.get_document_id()).collect()
+            })
+        }
+
+        pub(crate) fn saved_views(&self) -> &Vec<SavedView> {
+            return &self.saved_views
+        }
+
+        /// The label of the background job running, if any.
+        pub(crate) fn running_job(&self) -> Option<&str> {
+            return self.job.as_ref().map(|job| job.label())
+        }
+
+        fn reload_saved_views(&mut self) {
+            self.saved_views = DbConnection::new().read_saved_views().unwrap_or_else(|err| {
+                println!("Error reading saved views: {}", err);
+                Vec::new()
+            });
+        }
+
+        /// Sorting, grouping and the controls to save the current search as a view.
+        fn view_bar(&self) -> Element<'_, Message> {
+            row![
+                Text::new("Sort"),
+                PickList::new(SortKey::ALL, Some(self.sort_key), Message::SortKeyChange),
+                button(if self.sort_descending { "Descending" } else { "Ascending" }).on_press(Message::ToggleSortDirection),
+                PickList::new(GroupBy::ALL, Some(self.group_by), Message::GroupByChange),
+                match self.layout {
+                    DocumentLayout::Grid => button("Table").on_press(Message::LayoutChange(DocumentLayout::Table)),
+                    DocumentLayout::Table => button("Grid").on_press(Message::LayoutChange(DocumentLayout::Grid))
+                },
+                Space::new().width(Length::Fill),
+                text_input("View name", &self.view_name).on_input(Message::ViewNameChange).on_submit(Message::SaveView).width(Length::Fixed(200.0)),
+                button("Save View").on_press(Message::SaveView),
+                match self.current_view {
+                    Some(view_id) => button("Delete View").on_press(Message::DeleteView(view_id)),
+                    None => button("Delete View")
+                }
+            ].spacing(5).align_y(Center).into()
+        }
+
+        /// The column titles of the document table. Clicking a title sorts by it, clicking it again reverses the order,
+        /// and the handle after each title drags its width.
+        fn table_header(&self) -> Element<'_, Message> {
+            let mut header = row![Space::new().width(Length::Fixed(CHECKBOX_COLUMN_WIDTH))].align_y(Center);
+            for (index, (title, sort_key)) in TABLE_COLUMNS.into_iter().enumerate() {
+                let title = match (self.sort_key == sort_key, self.sort_descending) {
+                    (true, false) => format!("{} ▲", title),
+                    (true, true) => format!("{} ▼", title),
+                    (false, _) => title.to_string()
+                };
+                header = header.push(button(Text::new(title).wrapping(iced::widget::text::Wrapping::None))
+                    .style(button::text)
+                    .padding([2, 0])
+                    .width(Length::Fixed(self.column_widths[index]))
+                    .on_press(Message::SortColumn(sort_key)));
+                header = header.push(mouse_area(Container::new(rule::vertical(2)).center_x(Length::Fixed(COLUMN_HANDLE_WIDTH)).height(Length::Fixed(20.0)))
+                    .on_press(Message::ResizeColumnStart(index))
+                    .interaction(Interaction::ResizingHorizontally));
+            }
+            return Container::new(header).style(container::bordered_box).into()
+        }
+
+        /// The cards of the groups, up to `grid_limit` of them; more are built as the list is scrolled to its end.
+        fn document_grid(&self, groups: Vec<(String, Vec<Arc<Document>>)>) -> Element<'_, Message> {
+            let mut grid = column![].spacing(10);
+            let mut remaining = self.grid_limit;
+            for (label, documents) in groups {
+                if remaining == 0 {
+                    break
+                }
+                if self.group_by != GroupBy::Nothing {
+                    grid = grid.push(Text::new(group_title(&label, self.group_by, self.group_counts.get(&label).copied().unwrap_or(documents.len()))).size(16));
+                }
+                let shown = documents.len().min(remaining);
+                remaining -= shown;
+                grid = grid.push(row(documents[..shown].iter().map(|document| {
+                    DataCard::new(Some(document.clone()), None, self.current_theme.clone().unwrap(), self.selected_documents.contains(&document.get_document_id())).new_document_card().into()
+                })).spacing(10).wrap());
+            }
+            return grid.into()
+        }
+
+        /// One line per document and group title, all `TABLE_ROW_HEIGHT` tall. Only the lines in view are built;
+        /// empty space stands in for the others so the scroll bar keeps its size.
+        fn document_table(&self, groups: Vec<(String, Vec<Arc<Document>>)>) -> Element<'_, Message> {
+            let mut lines: Vec<Result<Arc<Document>, String>> = Vec::new();
+            for (label, documents) in groups {
+                if self.group_by != GroupBy::Nothing {
+                    lines.push(Err(group_title(&label, self.group_by, self.group_counts.get(&label).copied().unwrap_or(documents.len()))));
+                }
+                lines.extend(documents.into_iter().map(Ok));
+            }
+
+            let visible = (self.list_height / TABLE_ROW_HEIGHT).ceil() as usize + 2 * TABLE_ROW_BUFFER;
+            let first = ((self.list_offset / TABLE_ROW_HEIGHT) as usize).saturating_sub(TABLE_ROW_BUFFER).min(lines.len().saturating_sub(visible));
+            let last = (first + visible).min(lines.len());
+            let mut table = column![Space::new().height(Length::Fixed(first as f32 * TABLE_ROW_HEIGHT))];
+            for line in &lines[first..last] {
+                table = table.push(match line {
+                    Ok(document) => self.table_row(document),
+                    Err(title) => Container::new(Text::new(title.clone()).size(16)).height(Length::Fixed(TABLE_ROW_HEIGHT)).align_y(Center).into()
+                });
+            }
+            return table.push(Space::new().height(Length::Fixed((lines.len() - last) as f32 * TABLE_ROW_HEIGHT))).into()
+        }
+
+        /// One compact line with the columns of `table_header`.
+        fn table_row(&self, document: &Arc<Document>) -> Element<'_, Message> {
+            let document_id = document.get_document_id();
+            let selected = self.selected_documents.contains(&document_id);
+            let cells = [
+                document.get_document_number().to_string(),
+                document.get_document_type().to_string(),
+                document.get_comment().to_string(),
+                document.get_document_date().map(format_date).unwrap_or_default(),
+                document.get_attachment_count().to_string(),
+                document.get_page_count().to_string()
+            ];
+            let mut line = row![
+                checkbox(selected).on_toggle(move |_| Message::SelectDocument(document_id)).width(Length::Fixed(CHECKBOX_COLUMN_WIDTH))
+            ].align_y(Center);
+            for (index, cell) in cells.into_iter().enumerate() {
+                line = line.push(Text::new(cell).wrapping(iced::widget::text::Wrapping::None).width(Length::Fixed(self.column_widths[index] + COLUMN_HANDLE_WIDTH)));
+            }
+            return mouse_area(Container::new(line).height(Length::Fixed(TABLE_ROW_HEIGHT)).align_y(Center).width(Length::Fill).style(move |theme: &Theme| match selected {
+                true => container::Style { background: Some(theme.extended_palette().primary.weak.color.into()), ..Default::default() },
+                false => container::Style::default()
+            })).on_press(Message::OpenDocument(document.clone())).interaction(Interaction::Pointer).into()
+        }
+
+        /// The attachments of the open document that contain every plain word of the search. Field terms only
+        /// apply to documents.
+        fn visible_attachments(&self) -> Vec<Arc<Attachment>> {
+            let attachments = self.current_open_document.as_ref().and_then(|document| document.get_attachments()).unwrap_or_default();
+            let words = Query::parse(&self.search_text, &self.field_keys()).map(|query| query.words()).unwrap_or_default();
+            return attachments.into_iter().filter(|attachment| {
+                words.iter().all(|word| {
+                    attachment.get_reference_number().to_lowercase().contains(word) ||
+                    attachment.get_comment().to_lowercase().contains(word)
+                })
+            }).collect()
+        }
+
+        /// The query names of the custom fields of all document types.
+        fn field_keys(&self) -> Vec<String> {
+            let mut keys: Vec<String> = self.document_types.iter()
+                .flat_map(|document_type| document_type.get_fields())
+                .map(|field| field_key(&field.name))
+                .collect();
+            keys.sort();
+            keys.dedup();
+            return keys
+        }
+
+        /// Parses the search text for the list query. An invalid query keeps the previous one and shows why.
+        fn run_query(&mut self) {
+            self.queried_search = Some(self.search_text.clone());
+            if self.search_text.trim().is_empty() {
+                self.query = None;
+                self.query_error = None;
+                return
+            }
+            if self.search_text.ends_with(':') {
+                // The last term is still being typed; keep the previous query until it has a value.
+                self.query_error = None;
+                return
+            }
+            match Query::parse(&self.search_text, &self.field_keys()) {
+                Ok(query) => {
+                    self.query = Some(query);
+                    self.query_error = None;
+                },
+                Err(err) => self.query_error = Some(err.to_string())
+            }
+        }
+
+        /// The search input with the query error below it and completions for the term being typed.
+        fn search_box(&self) -> Element<'_, Message> {
+            let input = match self.query_error {
+                Some(_) => text_input("Search, e.g. type:invoice added:>2025-01-01 ref:PO-* -comment:void", &self.search_text).on_input(Message::SearchTextChange).id(Id::new("search")).style(|theme, _| empty_text_input_warning(theme)),
+                None => text_input("Search, e.g. type:invoice added:>2025-01-01 ref:PO-* -comment:void", &self.search_text).on_input(Message::SearchTextChange).id(Id::new("search"))
+            };
+            let completions = row(suggestions(&self.search_text, &self.field_keys()).into_iter().take(6).map(|(suggestion, description)| {
+                button(Text::new(format!("{}  {}", suggestion, description)).size(12)).style(button::secondary).on_press(Message::ApplySuggestion(suggestion)).into()
+            })).spacing(5);
+            let mut search = column![input].spacing(2).width(Length::Fill);
+            if self.query_error.is_some() {
+                search = search.push(error_text(&self.query_error).size(12));
+            }
+            return search.push(completions).into()
+        }
+
+        fn clear_selection(&mut self) {
+            self.selected_documents.clear();
+            self.selected_attachments.clear();
+            self.selection_anchor = None;
+        }
+
+        /// Starts working through the selection. Locked documents are left out of document actions.
+        fn start_bulk(&mut self, action: BulkAction) -> Task<Message> {
+            if self.bulk_job.as_ref().is_some_and(|job| !job.is_finished()) {
+                return Task::none()
+            }
+            let (ids, skipped): (Vec<u32>, usize) = match self.current_open_document {
+                Some(_) => (self.visible_attachments().iter().map(|attachment| attachment.get_attachment_id()).filter(|id| self.selected_attachments.contains(id)).collect(), 0),
+                None => {
+                    let selected: Vec<u32> = self.listed_document_ids().into_iter().filter(|id| self.selected_documents.contains(id)).collect();
+                    let locks = matches!(action, BulkAction::DeleteDocuments | BulkAction::TagDocuments(_) | BulkAction::ChangeDocumentType(_));
+                    let unlocked: Vec<u32> = selected.iter().copied().filter(|id| !locks || !self.is_document_locked(*id)).collect();
+                    let skipped = selected.len() - unlocked.len();
+                    (unlocked, skipped)
+                }
+            };
+            if ids.is_empty() && skipped == 0 {
+                self.error_message = Some(String::from("Nothing is selected."));
+                return Task::none()
+            }
+            self.error_message = None;
+            self.bulk_job = None;
+            // Attachment actions work on the open document, which is the only one with its attachments loaded.
+            // Document actions read what they need from the library, as the selection may not be loaded.
+            let documents: Vec<Arc<Document>> = match &self.current_open_document {
+                Some(document) => vec![document.clone()],
+                None => Vec::new()
+            };
+            let label = action.label();
+            let mut bulk_job = BulkJob::new(action, ids, skipped);
+            return self.start_job(&label, move |progress| {
+                let mut conn = DbConnection::new();
+                while !bulk_job.is_finished() {
+                    if progress.is_cancelled() {
+                        bulk_job.cancel();
+                        break
+                    }
+                    bulk_job.step(&mut conn, &documents);
+                    let (done, total) = bulk_job.counts();
+                    progress.report(done, total);
+                }
+                JobOutput::BulkFinished(bulk_job)
+            })
+        }
+
+        /// Runs `work` in the background, showing its progress until it finishes.
+        fn start_job(&mut self, label: &str, work: impl FnOnce(&Progress) -> JobOutput + Send + 'static) -> Task<Message> {
+            let (job, task) = background::run(label, work, Message::Job);
+            self.job = Some(job);
+            return task
+        }
+
+        /// Converts the pages of the open attachment and writes them to staging in the background. Saving goes on
+        /// in `finish_job` once the files are written.
+        fn start_staging(&mut self) -> Task<Message> {
+            let pages = self.current_file_bytes.clone().unwrap_or_default();
+            return self.start_job("Preparing pages", move |progress| {
+                let total = pages.len();
+                let mut prepared = Vec::new();
+                for (index, mut bytes) in pages.into_iter().enumerate() {
+                    if progress.is_cancelled() {
+                        return JobOutput::PagesStaged(Err(String::from("Cancelled")))
+                    }
+                    prepared.push(prepare_page_bytes(&mut bytes));
+                    progress.report(index + 1, total);
+                }
+                JobOutput::PagesStaged(StagedPages::stage(prepared).map(Arc::new).map_err(|err| err.to_string()))
+            })
+        }
+
+        /// Takes over the result of a background job. Results of cancelled jobs are dropped, except for bulk
+        /// actions, whose finished items stay done.
+        fn finish_job(&mut self, output: JobOutput, cancelled: bool) {
+            match output {
+                JobOutput::FilesRead(pages) => {
+                    if cancelled {
+                        return
+                    }
+                    self.files_changed = true;
+                    for bytes in pages {
+                        self.add_file_bytes(bytes);
+                    }
+                    self.update_file_handles();
+                    self.current_page_index = 0;
+                },
+                JobOutput::PagesStaged(staged) => {
+                    let staged = match staged.map(Arc::try_unwrap) {
+                        Ok(Ok(staged)) => staged,
+                        Ok(Err(_)) => {
+                            self.error_message = Some(String::from("Error writing page files"));
+                            return
+                        },
+                        Err(err) => {
+                            if !cancelled {
+                                println!("Error writing file to data folder: {}", err);
+                                self.error_message = Some(format!("Error writing page files: {}", err));
+                            }
+                            return
+                        }
+                    };
+                    if cancelled {
+                        staged.rollback();
+                        return
+                    }
+                    match self.create_new_attachment {
+                        true => self.save_new_attachment(staged),
+                        false => self.save_current_attachment(Some(staged))
+                    }
+                },
+                JobOutput::Exported(result) => {
+                    if let Err(err) = result && !cancelled {
+                        println!("Error exporting PDF: {}", err);
+                        self.error_message = Some(format!("Error exporting PDF: {}", err));
+                    }
+                },
+                JobOutput::BulkFinished(mut bulk_job) => {
+                    for edit in bulk_job.take_edits() {
+                        self.edit_history.push(edit);
+                    }
+                    self.bulk_job = Some(bulk_job);
+                    self.clear_selection();
+                    self.refresh_open_items();
                 }
             }
         }
 
-        pub(crate) fn subscription(&self) -> Subscription<Message> {
-            let kb_event = keyboard::listen().map(|event| {
-                match event {
-                    keyboard::Event::KeyPressed { key, ..} => {
-                        Message::KeyEvent(key)
+        fn save_new_attachment(&mut self, staged: StagedPages) {
+            let mut conn = DbConnection::new();
+            let current_document_id = self.current_open_document.clone().unwrap().get_document_id();
+
+            match conn.new_attachment(staged.pages(), self.current_attachment_reference_number.clone(), self.current_attachment_comment.clone(), current_document_id) {
+                Ok(_) => {
+                    self.edit_history.push(Edit::CreateAttachment { document_id: current_document_id, attachment_id: conn.last_rowid().unwrap() as u32 });
+                    staged.commit().unwrap_or_else(|err| {
+                        println!("Error moving page files into the data folder: {}", err);
+                    });
+                },
+                Err(err) => {
+                    println!("Error creating new attachment: {}", err);
+                    staged.rollback();
+                    self.error_message = Some(format!("Error creating new attachment: {}", err));
+                    return
+                }
+            }
+
+            self.reset_attachment_state();
+            self.refresh_documents();
+            self.current_open_document = self.load_document(current_document_id);
+            self.current_open_attachment = self.current_open_document.as_ref().unwrap().get_attachments().unwrap().iter().find(|attachment| attachment.get_attachment_id() == conn.last_rowid().unwrap() as u32).cloned();
+            self.current_attachment_reference_number = self.current_open_attachment.as_ref().unwrap().get_reference_number().to_string();
+            self.current_attachment_comment = self.current_open_attachment.as_ref().unwrap().get_comment().to_string();
+            self.current_file_bytes = Some(Vec::new());
+            for page in self.current_open_attachment.as_ref().unwrap().pages() {
+                self.current_file_bytes.as_mut().unwrap().push(page.image().to_vec());
+            }
+            self.update_file_handles();
+        }
+
+        /// Saves the open attachment, with `staged` replacing its pages if they were changed.
+        fn save_current_attachment(&mut self, staged: Option<StagedPages>) {
+            let mut conn = DbConnection::new();
+            let current_document_id = self.current_open_document.as_ref().unwrap().get_document_id();
+            let current_attachment = self.current_open_attachment.clone().unwrap();
+            let current_attachment_id = current_attachment.get_attachment_id();
+            let before = attachment_details(&current_attachment);
+
+            match conn.save_attachment(
+                current_attachment_id,
+                self.current_attachment_reference_number.clone(),
+                self.current_attachment_comment.clone(),
+                staged.as_ref().map(|staged| staged.pages()),
+                Some(current_attachment.get_version())
+            ) {
+                Ok(_) => {
+                    let after = AttachmentDetails {
+                        reference_number: self.current_attachment_reference_number.clone(),
+                        comment: self.current_attachment_comment.clone(),
+                        pages: staged.as_ref().map(|staged| staged.pages()).unwrap_or(before.pages.clone())
+                    };
+                    self.edit_history.push(Edit::ChangeAttachment { document_id: current_document_id, attachment_id: current_attachment_id, before: before, after: after });
+                    // The replaced pages are kept as a version of the attachment, so their files stay in the object store.
+                    if let Some(staged) = staged {
+                        staged.commit().unwrap_or_else(|err| {
+                            println!("Error moving page files into the data folder: {}", err);
+                        });
                     }
-                    _ => Message::None
+                },
+                Err(err) => {
+                    println!("Error editing attachment: {}", err);
+                    if let Some(staged) = staged {
+                        staged.rollback();
+                    }
+                    self.error_message = Some(if is_conflict(&err) { String::from(CHANGED_ELSEWHERE) } else { format!("Error saving attachment: {}", err) });
+                    return
                 }
+            }
+
+            let show_versions = self.versions.is_some();
+            self.reset_attachment_state();
+            self.refresh_documents();
+            self.current_open_document = self.load_document(current_document_id);
+            self.current_open_attachment = self.current_open_document.as_ref().unwrap().get_attachments().unwrap().iter().find(|attachment| attachment.get_attachment_id() == current_attachment_id).cloned();
+            self.current_attachment_reference_number = self.current_open_attachment.as_ref().unwrap().get_reference_number().to_string();
+            self.current_attachment_comment = self.current_open_attachment.as_ref().unwrap().get_comment().to_string();
+            self.current_file_bytes = Some(Vec::new());
+            self.current_file_handles = Some(Vec::new());
+            for page in self.current_open_attachment.as_ref().unwrap().pages() {
+                self.current_file_bytes.as_mut().unwrap().push(page.image().to_vec());
+            }
+            self.update_file_handles();
+            if show_versions {
+                self.load_versions();
+            }
+        }
+
+        /// Picks up changes other users made to the library since it was last read. The open document is reloaded
+        /// right away unless it has unsaved edits, which are kept until the user chooses to reload.
+        fn poll_changes(&mut self) {
+            let mut conn = DbConnection::new();
+            if let Some((attachment_id, _)) = self.attachment_lock {
+                let holder = conn.lock_attachment(attachment_id).unwrap_or_else(|err| {
+                    println!("Error renewing attachment lock: {}", err);
+                    None
+                });
+                self.attachment_lock = Some((attachment_id, holder));
+            }
+
+            let latest_audit_id = conn.last_audit_id().unwrap_or_else(|err| {
+                println!("Error reading audit log: {}", err);
+                self.last_audit_id
+            });
+            let changes = conn.read_changes_since(self.last_audit_id, latest_audit_id).unwrap_or_else(|err| {
+                println!("Error reading audit log: {}", err);
+                Vec::new()
+            });
+            self.last_audit_id = latest_audit_id;
+            if changes.is_empty() {
+                return
+            }
+            self.refresh_documents();
+            self.reload_tags();
+            let open_document_id = self.current_open_document.as_ref().map(|document| document.get_document_id());
+            let user_name = match changes.into_iter().find(|(document_id, _)| Some(*document_id) == open_document_id) {
+                Some((_, user_name)) => user_name,
+                None => return
+            };
+            if self.data_changed || self.files_changed || self.create_new_attachment {
+                self.remote_change = Some(format!("{} changed this document while you were editing it.", user_name));
+                return
+            }
+            self.reload_open_document();
+        }
+
+        /// Reads the open document and attachment again, dropping unsaved edits.
+        fn reload_open_document(&mut self) {
+            let document_id = self.current_open_document.as_ref().map(|document| document.get_document_id());
+            let attachment_id = self.current_open_attachment.as_ref().map(|attachment| attachment.get_attachment_id());
+            let tab = self.current_document_tab.clone();
+            self.refresh_documents();
+            self.reset_state();
+            self.reopen(document_id, attachment_id);
+            if self.current_open_document.is_none() && document_id.is_some() {
+                self.error_message = Some(String::from("The document was deleted by someone else."));
+            }
+            self.current_document_tab = tab;
+        }
+
+        /// Takes the advisory lock on the attachment now open and releases the one held before, so two users
+        /// do not edit the same attachment at once.
+        fn sync_attachment_lock(&mut self) {
+            let attachment_id = self.current_open_attachment.as_ref().map(|attachment| attachment.get_attachment_id());
+            if self.attachment_lock.as_ref().map(|(id, _)| *id) == attachment_id {
+                return
+            }
+            let mut conn = DbConnection::new();
+            if let Some((previous_id, None)) = self.attachment_lock.take() {
+                conn.unlock_attachment(previous_id).unwrap_or_else(|err| {
+                    println!("Error releasing attachment lock: {}", err);
+                    0
+                });
+            }
+            self.attachment_lock = attachment_id.map(|id| {
+                let holder = conn.lock_attachment(id).unwrap_or_else(|err| {
+                    println!("Error locking attachment: {}", err);
+                    None
+                });
+                (id, holder)
             });
+        }
 
-            let tick_event = if self.scanning {
-                iced::time::every(iced::time::Duration::from_millis(100)).map(|_| Message::ScanTick)
+        /// The other user editing the open attachment, if any.
+        fn attachment_holder(&self) -> Option<&String> {
+            return self.attachment_lock.as_ref().and_then(|(_, holder)| holder.as_ref())
+        }
+
+        /// Tells the user about other people's work on what is open here.
+        fn sharing_bar(&self) -> Element<'_, Message> {
+            let mut bar = column![].spacing(5);
+            if let Some(notice) = &self.remote_change {
+                bar = bar.push(row![
+                    Text::new(notice.as_str()).width(Length::Fill),
+                    button("Reload").on_press(Message::ReloadOpenDocument)
+                ].spacing(5).align_y(Center));
             }
-            else {
-                Subscription::none()
+            if let Some(user_name) = self.attachment_holder() {
+                bar = bar.push(Text::new(format!("{} is editing this attachment, so it is open read-only.", user_name)));
+            }
+            return Container::new(bar).padding(5).width(Length::Fill).style(container::bordered_box).into()
+        }
+
+        /// The running job's progress with a button to cancel it.
+        fn job_bar(&self) -> Element<'_, Message> {
+            let job = match &self.job {
+                Some(job) => job,
+                None => return column![].into()
+            };
+            return Container::new(row![
+                Text::new(job.status()).width(Length::Fill),
+                ProgressBar::new(0.0..=1.0, job.progress()).length(Length::Fixed(200.0)),
+                if job.is_cancelled() { button("Cancel") } else { button("Cancel").on_press(Message::CancelJob) }
+            ].spacing(5).align_y(Center)).padding(5).width(Length::Fill).style(container::bordered_box).into()
+        }
+
+        /// Actions for the selected cards, or the progress of the running bulk action.
+        fn bulk_bar(&self) -> Element<'_, Message> {
+            if let Some(job) = &self.bulk_job {
+                return row![
+                    Text::new(job.status()).width(Length::Fill),
+                    button("Close").on_press(Message::CloseBulk)
+                ].spacing(5).align_y(Center).into()
+            }
+
+            let selected = match self.current_open_document {
+                Some(_) => self.selected_attachments.len(),
+                None => self.selected_documents.len()
+            };
+            if selected == 0 {
+                return column![].into()
+            }
+
+            let mut actions = row![
+                Text::new(format!("{} selected", selected)),
+                button("Clear").on_press(Message::ClearSelection),
+                Space::new().width(Length::Fill),
+                text_input("Tag", &self.bulk_tag_name).on_input(Message::BulkTagNameChange).on_submit(Message::BulkTag).width(Length::Fixed(150.0)),
+                button("Tag").on_press(Message::BulkTag)
+            ].spacing(5).align_y(Center);
+            actions = match &self.current_open_document {
+                Some(document) => {
+                    let document_numbers = self.other_document_numbers(document.get_document_id());
+                    actions.push(PickList::new(document_numbers, self.bulk_move_target.clone(), Message::BulkMoveTargetChange).placeholder("Document"))
+                        .push(button("Move").on_press(Message::BulkMove))
+                },
+                None => actions
+                    .push(PickList::new(self.document_types.clone(), self.bulk_type.clone(), Message::BulkTypeChange).placeholder("Type"))
+                    .push(button("Change Type").on_press(Message::BulkChangeType))
+            };
+            actions = actions.push(button("Export").on_press(Message::BulkExport)).push(button("Export Archive").on_press(Message::BulkArchive));
+            actions = match self.show_confirm_delete {
+                true => actions.push(Text::from("Confirm deletion: ")).push(button("Confirm").on_press(Message::BulkDelete)),
+                false => actions.push(button("Delete").on_press(Message::ShowConfirmDelete))
+            };
+            return actions.into()
+        }
+
+        fn merge_panel(&self, document: &Document) -> Element<'_, Message> {
+            let document_numbers = self.other_document_numbers(document.get_document_id());
+            let source = self.merge_source.as_ref().and_then(|document_number| self.find_document(document_number));
+
+            let mut panel = column![
+                rule::horizontal(2),
+                row![
+                    Text::new("Merge into this").width(Length::FillPortion(1)),
+                    row![
+                        PickList::new(document_numbers, self.merge_source.clone(), Message::MergeSourceChange).placeholder("Document").width(Length::Fill),
+                        button("Cancel").on_press(Message::ToggleMerge)
+                    ].spacing(5).align_y(Center).width(Length::FillPortion(4))
+                ].spacing(5).align_y(Center)
+            ].spacing(5);
+            let source = match source {
+                Some(source) => source,
+                None => return panel.into()
+            };
+
+            for conflict in conflicts(document, &source, &self.document_types) {
+                let take_merged = self.merge_take.contains(&conflict.field);
+                panel = panel.push(row![
+                    Text::new(conflict.label).width(Length::FillPortion(1)),
+                    button(Text::new(conflict.kept_value)).style(if take_merged { button::secondary } else { button::primary }).on_press(Message::MergeChoice(conflict.field, false)).width(Length::FillPortion(2)),
+                    button(Text::new(conflict.merged_value)).style(if take_merged { button::primary } else { button::secondary }).on_press(Message::MergeChoice(conflict.field, true)).width(Length::FillPortion(2))
+                ].spacing(5).align_y(Center));
+            }
+            let attachment_count = source.get_attachment_count();
+            panel = panel.push(row![
+                Text::new(format!(
+                    "The {} attachments, tags and links of {} move to this document, and {} goes to the trash.",
+                    attachment_count, source.get_document_number(), source.get_document_number()
+                )).width(Length::Fill),
+                button("Merge").on_press(Message::MergeDocuments)
+            ].spacing(5).align_y(Center));
+            return panel.into()
+        }
+
+        fn split_panel(&self, document: &Document) -> Element<'_, Message> {
+            let mut attachment_rows = column![].spacing(5);
+            for attachment in document.get_attachments().unwrap_or_default() {
+                let attachment_id = attachment.get_attachment_id();
+                attachment_rows = attachment_rows.push(
+                    checkbox(self.split_selection.contains(&attachment_id))
+                        .label(format!("{}  {}", attachment.get_reference_number(), attachment.get_comment()))
+                        .on_toggle(move |selected| Message::SplitAttachmentChange(attachment_id, selected))
+                );
+            }
+
+            column![
+                rule::horizontal(2),
+                row![
+                    Text::new("Split off").width(Length::FillPortion(1)),
+                    attachment_rows.width(Length::FillPortion(4))
+                ].spacing(5),
+                row![
+                    Text::new("New Document").width(Length::FillPortion(1)),
+                    row![
+                        text_input("Document Number", &self.split_document_number).on_input(Message::SplitDocumentNumberChange).width(Length::Fill),
+                        button("Split").on_press(Message::SplitDocument),
+                        button("Cancel").on_press(Message::ToggleSplit)
+                    ].spacing(5).align_y(Center).width(Length::FillPortion(4))
+                ].spacing(5).align_y(Center)
+            ].spacing(5).into()
+        }
+
+        /// Checks the number for a document split off the open one, which gets the open document's type.
+        fn split_number_error(&self, document_number: &str) -> Option<String> {
+            if document_number.is_empty() {
+                return Some(String::from("Enter the number of the new document."))
+            }
+            let document_type = self.current_open_document.as_ref().map(|document| document.get_document_type().to_string()).unwrap_or_default();
+            let pattern = self.document_types.iter()
+                .find(|candidate| candidate.get_name().eq_ignore_ascii_case(document_type.trim()))
+                .and_then(|candidate| candidate.number_pattern());
+            if let Some(pattern) = pattern && !pattern.matches(document_number) {
+                return Some(format!("{} numbers have to look like {}.", document_type, pattern.as_str()))
+            }
+            return match DbConnection::new().find_document_number(document_number) {
+                Ok(Some(_)) => Some(format!("Document number {} is already used.", document_number)),
+                Ok(None) => None,
+                Err(err) => {
+                    println!("Error checking document number: {}", err);
+                    None
+                }
+            }
+        }
+
+        /// Whether the open document is approved or archived and has not been unlocked.
+        fn is_locked(&self) -> bool {
+            return self.current_open_document.as_ref().is_some_and(|document| self.is_document_locked(document.get_document_id()))
+        }
+
+        /// Whether the document is approved or archived and not unlocked. Documents not loaded are read from the
+        /// library.
+        fn is_document_locked(&self, document_id: u32) -> bool {
+            if self.unlocked_document_id == Some(document_id) {
+                return false
+            }
+            return match self.documents.iter().find(|document| document.get_document_id() == document_id) {
+                Some(document) => document.get_status().is_locked(),
+                None => DbConnection::new().read_document_summary(document_id).ok().flatten().is_some_and(|document| document.get_status().is_locked())
+            }
+        }
+
+        /// The first of the documents that is approved or archived and not unlocked. The status is read from the
+        /// library, as a document may not be loaded or may have been locked elsewhere.
+        fn locked_document(&self, conn: &DbConnection, document_ids: Vec<u32>) -> Option<Arc<Document>> {
+            return document_ids.into_iter()
+                .filter(|document_id| self.unlocked_document_id != Some(*document_id))
+                .filter_map(|document_id| conn.read_document_summary(document_id).ok().flatten())
+                .find(|document| document.get_status().is_locked())
+        }
+
+        fn locked_message(&self) -> String {
+            let status = self.current_open_document.as_ref().map(|document| document.get_status()).unwrap_or_default();
+            return format!("The document is {}. Unlock it to make changes.", status.to_string().to_lowercase())
+        }
+
+        fn status_bar(&self, document: &Document) -> Element<'_, Message> {
+            let status = document.get_status();
+            let mut controls = row![
+                Text::new(match document.get_status_changed_at() {
+                    Some(changed_at) => format!("{} since {}", status, format_timestamp(changed_at)),
+                    None => status.to_string()
+                }),
+                Space::new().width(Length::Fill)
+            ].spacing(5).align_y(Center);
+            for next_status in status.transitions() {
+                controls = controls.push(button(transition_label(status, *next_status)).on_press(Message::ChangeStatus(*next_status)));
+            }
+            if status.is_locked() {
+                controls = controls.push(match self.is_locked() {
+                    true => button("Unlock").on_press(Message::Unlock),
+                    false => button("Lock").on_press(Message::Lock)
+                });
+            }
+
+            let mut changes = column![].spacing(2);
+            for entry in self.status_changes.iter().rev() {
+                changes = changes.push(Text::new(format!(
+                    "{} \u{2192} {}  {}  {}",
+                    DocumentStatus::from_str(entry.old_value.as_deref().unwrap_or_default()),
+                    DocumentStatus::from_str(entry.new_value.as_deref().unwrap_or_default()),
+                    format_timestamp(entry.changed_at),
+                    entry.user_name
+                )).size(12));
+            }
+
+            row![
+                Text::new("Status").width(Length::FillPortion(1)),
+                column![controls, changes].spacing(5).width(Length::FillPortion(4))
+            ].spacing(5).into()
+        }
+
+        /// The entered document and due dates; empty inputs mean no date.
+        fn validate_dates(&self) -> Result<(Option<Date>, Option<Date>), String> {
+            let document_date = match self.current_document_date.trim() {
+                "" => None,
+                input => Some(parse_date(input).ok_or(String::from("Document Date must be a date like 2025-01-31."))?)
+            };
+            let due_date = match self.current_due_date.trim() {
+                "" => None,
+                input => Some(parse_date(input).ok_or(String::from("Due Date must be a date like 2025-01-31."))?)
+            };
+            return Ok((document_date, due_date))
+        }
+
+        /// Fills in the next number of the type's pattern when the type numbers its documents automatically.
+        fn fill_next_number(&mut self) {
+            if let Some(next_number) = self.next_document_number() {
+                self.current_document_number = next_number;
+            }
+        }
+
+        /// The next free number of the chosen type's pattern, if the type numbers its documents automatically.
+        fn next_document_number(&self) -> Option<String> {
+            let pattern = self.current_type().filter(|document_type| document_type.get_auto_number()).and_then(|document_type| document_type.number_pattern())?;
+            let used: Vec<String> = DbConnection::new().read_document_numbers().unwrap_or_else(|err| {
+                println!("Error reading document numbers: {}", err);
+                Vec::new()
+            }).into_iter().map(|(_, document_number)| document_number).collect();
+            let today = OffsetDateTime::now_local().unwrap_or_else(|_| OffsetDateTime::now_utc()).date();
+            return pattern.next_number(today, &used)
+        }
+
+        fn validate_fields(&self) -> Result<(), String> {
+            for field in self.current_type_fields() {
+                field.validate(self.current_field_values.get(&field.field_id).map(|value| value.as_str()).unwrap_or(""))?;
+            }
+            return Ok(())
+        }
+
+        fn field_values_to_save(&self) -> Vec<(u32, String)> {
+            return self.current_type_fields().iter()
+                .map(|field| (field.field_id, self.current_field_values.get(&field.field_id).cloned().unwrap_or_default()))
+                .collect()
+        }
+
+        fn filter_bar(&self) -> Element<'_, Message> {
+            let mut filters = row![
+                Text::new("Status:"),
+                PickList::new(DocumentStatus::ALL, self.status_filter, |status| Message::StatusFilterChange(Some(status))).placeholder("All statuses"),
+            ].spacing(5).align_y(Center);
+            if self.status_filter.is_some() {
+                filters = filters.push(button("Clear").on_press(Message::StatusFilterChange(None)));
+            }
+            if self.document_types.is_empty() {
+                return filters.into()
+            }
+            filters = filters.push(Text::new("Type:"));
+            filters = filters.push(PickList::new(self.document_types.clone(), self.type_filter.clone(), |document_type| Message::TypeFilterChange(Some(document_type))).placeholder("All types"));
+            if let Some(document_type) = &self.type_filter {
+                filters = filters.push(button("Clear").on_press(Message::TypeFilterChange(None)));
+                if !document_type.get_fields().is_empty() {
+                    filters = filters.push(PickList::new(document_type.get_fields().clone(), self.field_filter.clone(), Message::FieldFilterChange).placeholder("Field"));
+                    filters = filters.push(text_input("Value", &self.field_filter_value).on_input(Message::FieldFilterValueChange).width(Length::Fixed(150.0)));
+                }
+            }
+            return filters.into()
+        }
+
+        fn reload_tags(&mut self) {
+            self.all_tags = DbConnection::new().read_tags().unwrap_or_else(|err| {
+                println!("Error reading tags: {}", err);
+                Vec::new()
+            });
+            let all_tags = &self.all_tags;
+            self.tag_filter.retain(|tag_id| all_tags.iter().any(|tag| tag.get_tag_id() == *tag_id));
+        }
+
+        /// The attachment that tag changes apply to: the open one on the attachments tab, otherwise none,
+        /// meaning the open document.
+        fn tagged_attachment(&self) -> Option<u32> {
+            if self.current_document_tab != Tab::Attachments {
+                return None
+            }
+            return self.current_open_attachment.as_ref().map(|attachment| attachment.get_attachment_id())
+        }
+
+        /// Re-reads the open document and attachment without touching unsaved input.
+        fn refresh_open_items(&mut self) {
+            self.refresh_documents();
+            self.reload_tags();
+            let document_id = self.current_open_document.as_ref().map(|document| document.get_document_id());
+            let attachment_id = self.current_open_attachment.as_ref().map(|attachment| attachment.get_attachment_id());
+            self.current_open_document = document_id.and_then(|id| self.load_document(id));
+            self.current_open_attachment = match (&self.current_open_document, attachment_id) {
+                (Some(document), Some(id)) => document.get_attachments().unwrap_or_default().into_iter().find(|attachment| attachment.get_attachment_id() == id),
+                _ => None
+            };
+        }
+
+        fn tag_filter_bar(&self) -> Element<'_, Message> {
+            if self.all_tags.is_empty() {
+                return column![].into()
+            }
+            let mut chips = row![Text::new("Tags:")].spacing(5).align_y(Center);
+            for tag in &self.all_tags {
+                chips = chips.push(
+                    mouse_area(tag_chip(tag, !self.tag_filter.contains(&tag.get_tag_id()), None))
+                        .on_press(Message::ToggleTagFilter(tag.get_tag_id()))
+                        .interaction(Interaction::Pointer)
+                );
+            }
+            row![
+                chips.wrap(),
+                Space::new().width(Length::Fill),
+                button(if self.match_all_tags { "Match all" } else { "Match any" }).on_press(Message::ToggleTagMatchMode)
+            ].spacing(5).align_y(Center).into()
+        }
+
+        fn load_versions(&mut self) {
+            let attachment_id = match &self.current_open_attachment {
+                Some(attachment) => attachment.get_attachment_id(),
+                None => return
             };
+            self.versions = Some(DbConnection::new().read_attachment_versions(attachment_id).unwrap_or_else(|err| {
+                println!("Error reading attachment versions: {}", err);
+                Vec::new()
+            }));
+        }
 
-            Subscription::batch(vec![kb_event, tick_event])
+        /// The number of pages the viewer can page through; while comparing, the longer of the two sets.
+        fn viewer_page_count(&self) -> usize {
+            let current = self.current_file_handles.as_ref().map(|handles| handles.len()).unwrap_or(0);
+            let left = self.compare_left.as_ref().map(|(_, handles)| handles.len()).unwrap_or(current);
+            let right = self.compare_right.as_ref().map(|(_, handles)| handles.len()).unwrap_or(current);
+            return left.max(right)
         }
 
         fn add_file_bytes(&mut self, bytes: Vec<u8>) {
@@ -1012,9 +3087,27 @@ pub(crate) mod document_list {
 
         fn reset_state(&mut self) {
             self.current_open_document = None;
+            self.remote_change = None;
+            self.history = None;
+            self.current_field_values.clear();
+            self.number_error = None;
             self.current_document_number.clear();
             self.current_document_type.clear();
             self.current_comment.clear();
+            self.current_document_date.clear();
+            self.current_due_date.clear();
+            self.status_changes.clear();
+            self.links.clear();
+            self.link_target = None;
+            self.show_merge = false;
+            self.merge_source = None;
+            self.merge_take.clear();
+            self.show_split = false;
+            self.split_selection.clear();
+            self.split_document_number.clear();
+            self.selected_attachments.clear();
+            self.selection_anchor = None;
+            self.bulk_move_target = None;
             self.create_new_document = false;
             self.reset_attachment_state();
         }
@@ -1034,23 +3127,33 @@ pub(crate) mod document_list {
             self.create_new_attachment = false;
             self.show_confirm_delete = false;
             self.show_empty_field_warning = false;
+            self.error_message = None;
             self.current_page_index = 0;
             self.current_file_handles = None;
+            self.versions = None;
+            self.compare_left = None;
+            self.compare_right = None;
+            self.new_tag_name.clear();
+            self.show_transfer = false;
+            self.transfer_target = None;
+            self.transfer_reference_number.clear();
         }
     }
 
     struct DataCard {
         document: Option<Arc<Document>>,
         attachment: Option<Arc<Attachment>>,
-        theme: LocalTheme
+        theme: LocalTheme,
+        selected: bool
     }
 
     impl DataCard {
-        fn new(document: Option<Arc<Document>>, attachment: Option<Arc<Attachment>>, theme: LocalTheme) -> DataCard {
+        fn new(document: Option<Arc<Document>>, attachment: Option<Arc<Attachment>>, theme: LocalTheme, selected: bool) -> DataCard {
             DataCard {
                 document: document,
                 attachment: attachment,
-                theme: theme
+                theme: theme,
+                selected: selected
             }
         }
 
@@ -1062,12 +3165,19 @@ pub(crate) mod document_list {
                     "Error retrieving data from Document.").to_offset(OffsetDateTime::now_local().expect("Failed to acquire local offset.").offset())
             .format(datetime_format),
             "Error converting unix epoch to UtcDateTime");
+            let document_id = self.document.as_ref().unwrap().get_document_id();
 
             mouse_area(
-                Card::new(Text::new(self.document.as_ref().unwrap().get_document_number().to_string()), column![
+                Card::new(row![
+                    checkbox(self.selected).on_toggle(move |_| Message::SelectDocument(document_id)),
+                    Text::new(self.document.as_ref().unwrap().get_document_number().to_string())
+                ].spacing(5), column![
                     Text::new(self.document.as_ref().unwrap().get_document_type().to_string()),
-                    Text::new(self.document.as_ref().unwrap().get_comment().to_string())
-                ]).max_height(500.0).max_width(200.0).foot(Text::new(datetime)).style(|theme: &Theme, _| card_style(theme))
+                    Text::new(self.document.as_ref().unwrap().get_comment().to_string()),
+                    Text::new(self.document.as_ref().unwrap().get_status().to_string()).size(12),
+                    card_due_date(self.document.as_ref().unwrap()),
+                    card_tags(self.document.as_ref().unwrap().get_tags())
+                ].spacing(5)).max_height(500.0).max_width(200.0).foot(Text::new(datetime)).style(|theme: &Theme, _| card_style(theme))
             ).on_press(Message::OpenDocument(self.document.as_ref().unwrap().clone())).interaction(Interaction::Pointer)
         }
 
@@ -1079,11 +3189,16 @@ pub(crate) mod document_list {
                     "Error retrieving data from Attachment.").to_offset(OffsetDateTime::now_local().expect("Failed to acquire local offset.").offset())
             .format(datetime_format),
             "Error converting unix epoch to UtcDateTime");
+            let attachment_id = self.attachment.as_ref().unwrap().get_attachment_id();
 
             mouse_area(
-                Card::new(Text::new(self.attachment.as_ref().unwrap().get_reference_number().to_string()), column![
-                    Text::new(self.attachment.as_ref().unwrap().get_comment().to_string())
-                ]).max_height(500.0).max_width(200.0).foot(Text::new(datetime)).style(|theme, _| card_style(theme))
+                Card::new(row![
+                    checkbox(self.selected).on_toggle(move |_| Message::SelectAttachment(attachment_id)),
+                    Text::new(self.attachment.as_ref().unwrap().get_reference_number().to_string())
+                ].spacing(5), column![
+                    Text::new(self.attachment.as_ref().unwrap().get_comment().to_string()),
+                    card_tags(self.attachment.as_ref().unwrap().get_tags())
+                ].spacing(5)).max_height(500.0).max_width(200.0).foot(Text::new(datetime)).style(|theme, _| card_style(theme))
             ).on_press(Message::OpenAttachment(self.attachment.as_ref().unwrap().clone())).interaction(Interaction::Pointer)
         }
 
@@ -1112,6 +3227,273 @@ pub(crate) mod document_list {
         }
     }
 
+    fn dates_form(document_date: &str, due_date: &str) -> Element<'static, Message> {
+        let date_input = |value: &str, on_input: fn(String) -> Message| {
+            let input = text_input("YYYY-MM-DD", value).on_input(on_input).width(Length::Fill);
+            if !value.trim().is_empty() && parse_date(value).is_none() {
+                return input.style(|theme, _| empty_text_input_warning(theme))
+            }
+            return input
+        };
+        row![
+            Text::new("Document Date").width(Length::FillPortion(1)),
+            row![
+                date_input(document_date, Message::CurrentDocumentDateChange),
+                Text::new("Due Date"),
+                date_input(due_date, Message::CurrentDueDateChange)
+            ].spacing(5).align_y(Center).width(Length::FillPortion(4))
+        ].spacing(5).align_y(Center).into()
+    }
+
+    /// The due date line of a document card, flagged once the date has passed.
+    fn card_due_date(document: &Document) -> Element<'static, Message> {
+        let due_date = match document.get_due_date() {
+            Some(due_date) => due_date,
+            None => return column![].into()
+        };
+        let today = OffsetDateTime::now_local().unwrap_or_else(|_| OffsetDateTime::now_utc()).date();
+        if document.is_overdue(today) {
+            return Text::new(format!("Overdue since {}", format_date(due_date))).style(iced::widget::text::danger).into()
+        }
+        return Text::new(format!("Due {}", format_date(due_date))).into()
+    }
+
+    fn type_picker(document_types: &[DocumentType], current_document_type: &str) -> Element<'static, Message> {
+        let type_names: Vec<String> = document_types.iter().map(|document_type| document_type.get_name().to_string()).collect();
+        let selected = if current_document_type.is_empty() { None } else { Some(current_document_type.to_string()) };
+        row![
+            Text::new("Document Type").width(Length::FillPortion(1)),
+            row![
+                PickList::new(type_names, selected, Message::CurrentDocumentTypeChange).placeholder("No type").width(Length::Fill),
+                button("Clear").on_press(Message::CurrentDocumentTypeChange(String::new()))
+            ].spacing(5).width(Length::FillPortion(4))
+        ].spacing(5).align_y(Center).into()
+    }
+
+    /// Inputs for the custom fields of the document's type. Invalid values are marked as soon as they are typed;
+    /// empty required fields once a save was attempted.
+    fn field_form(fields: Vec<TypeField>, values: &HashMap<u32, String>, show_warnings: bool) -> Element<'static, Message> {
+        let mut form = column![].spacing(5);
+        for field in fields {
+            let value = values.get(&field.field_id).cloned().unwrap_or_default();
+            let error = match field.validate(&value) {
+                Err(error) if show_warnings || !value.trim().is_empty() => Some(error),
+                _ => None
+            };
+            let field_id = field.field_id;
+            let placeholder = match field.kind {
+                FieldKind::Number => "0",
+                FieldKind::Date => "YYYY-MM-DD",
+                FieldKind::Currency => "0.00",
+                _ => ""
+            };
+            let input: Element<'static, Message> = match field.kind {
+                FieldKind::Enum => PickList::new(field.options.clone(), if value.is_empty() { None } else { Some(value.clone()) }, move |option| Message::FieldValueChange(field_id, option))
+                    .width(Length::Fill).into(),
+                _ if error.is_some() => text_input(placeholder, &value).on_input(move |input| Message::FieldValueChange(field_id, input)).style(|theme, _| empty_text_input_warning(theme)).into(),
+                _ => text_input(placeholder, &value).on_input(move |input| Message::FieldValueChange(field_id, input)).into()
+            };
+            form = form.push(row![
+                row![
+                    Text::new(format!("{} ", field.name)),
+                    if field.required { Text::new("*").color(Color::from_rgb(1.0, 0.0, 0.0)) } else { Text::new("") }
+                ].width(Length::FillPortion(1)),
+                column![
+                    input,
+                    error_text(&error)
+                ].width(Length::FillPortion(4))
+            ].spacing(5).align_y(Center));
+        }
+        return form.into()
+    }
+
+    fn card_tags(tags: &[Tag]) -> Element<'static, Message> {
+        return row(tags.iter().map(|tag| tag_chip(tag, false, None).into())).spacing(3).wrap().into()
+    }
+
+    fn tag_editor(tags: &[Tag], new_tag_name: &str) -> Element<'static, Message> {
+        row![
+            Text::new("Tags").width(Length::FillPortion(1)),
+            row![
+                row(tags.iter().map(|tag| tag_chip(tag, false, Some(Message::RemoveTag(tag.get_tag_id()))).into())).spacing(3).wrap(),
+                text_input("Add tag", new_tag_name).on_input(Message::NewTagNameChange).on_submit(Message::AddTag).width(Length::Fixed(150.0)),
+                button("Add").on_press(Message::AddTag)
+            ].spacing(5).align_y(Center).width(Length::FillPortion(4))
+        ].spacing(5).align_y(Center).into()
+    }
+
+    fn page_viewer(pages: &[Handle], page_index: usize) -> Element<'static, Message> {
+        match pages.get(page_index) {
+            Some(page) => Viewer::new(page.clone()).width(Length::Fill).height(Length::Fill).into(),
+            None => Viewer::new(Handle::from_bytes(ERROR_FERRIS)).width(Length::Fill).height(Length::Fill).into()
+        }
+    }
+
+    fn version_label(version: &AttachmentVersion) -> String {
+        let datetime_format = format_description!("[year]-[month]-[day] [hour]:[minute]:[second]");
+        let replaced_at = UtcDateTime::from_unix_timestamp(version.replaced_at)
+            .ok()
+            .and_then(|datetime| datetime.to_offset(OffsetDateTime::now_local().map(|now| now.offset()).unwrap_or(time::UtcOffset::UTC)).format(datetime_format).ok())
+            .unwrap_or_default();
+        return format!("Replaced {} ({} pages)", replaced_at, version.pages.len())
+    }
+
+    fn versions_panel(versions: &[AttachmentVersion], comparing: bool) -> Element<'static, Message> {
+        let mut version_rows = column![
+            row![
+                Text::new("Current").width(Length::Fill),
+                button("Left").on_press(Message::CompareVersion(CompareSide::Left, None)),
+                button("Right").on_press(Message::CompareVersion(CompareSide::Right, None))
+            ].spacing(5).align_y(Center)
+        ].spacing(5);
+        for version in versions {
+            version_rows = version_rows.push(row![
+                Text::new(version_label(version)).width(Length::Fill),
+                button("Left").on_press(Message::CompareVersion(CompareSide::Left, Some(version.version_id))),
+                button("Right").on_press(Message::CompareVersion(CompareSide::Right, Some(version.version_id))),
+                button("Restore").on_press(Message::RestoreVersion(version.version_id))
+            ].spacing(5).align_y(Center));
+        }
+
+        column![
+            rule::horizontal(2),
+            row![
+                Text::new("Versions").size(16),
+                Space::new().width(Length::Fill),
+                if comparing {
+                    button("Close Compare").on_press(Message::CloseCompare)
+                }
+                else {
+                    button("Close Compare")
+                }
+            ].spacing(5).align_y(Center),
+            scrollable(version_rows).height(Length::Fill)
+        ].spacing(5).into()
+    }
+
+    fn attachment_details(attachment: &Attachment) -> AttachmentDetails {
+        return AttachmentDetails {
+            reference_number: attachment.get_reference_number().to_string(),
+            comment: attachment.get_comment().to_string(),
+            pages: attachment.pages().iter().map(|page| (PathBuf::from(page.file_path().as_str()), page.checksum().map(|checksum| checksum.to_string()).unwrap_or_default())).collect()
+        }
+    }
+
+    fn format_timestamp(timestamp: i64) -> String {
+        let datetime_format = format_description!("[year]-[month]-[day] [hour]:[minute]:[second]");
+        let offset = OffsetDateTime::now_local().map(|now| now.offset()).unwrap_or(time::UtcOffset::UTC);
+        return UtcDateTime::from_unix_timestamp(timestamp)
+            .ok()
+            .and_then(|datetime| datetime.to_offset(offset).format(datetime_format).ok())
+            .unwrap_or_default()
+    }
+
+    fn transition_label(from: DocumentStatus, to: DocumentStatus) -> &'static str {
+        match (from, to) {
+            (DocumentStatus::Review, DocumentStatus::Draft) => "Back to Draft",
+            (DocumentStatus::Draft, DocumentStatus::Review) => "Submit for Review",
+            (_, DocumentStatus::Review) => "Reopen for Review",
+            (DocumentStatus::Archived, DocumentStatus::Approved) => "Unarchive",
+            (_, DocumentStatus::Approved) => "Approve",
+            (_, DocumentStatus::Archived) => "Archive",
+            (_, DocumentStatus::Draft) => "Back to Draft"
+        }
+    }
+
+    /// Toggles `id`, or with shift selects every visible card from the anchor up to `id`.
+    fn select(selection: &mut HashSet<u32>, anchor: &mut Option<u32>, visible: &[u32], id: u32, shift: bool) {
+        let anchor_index = anchor.and_then(|anchor| visible.iter().position(|visible_id| *visible_id == anchor));
+        let index = visible.iter().position(|visible_id| *visible_id == id);
+        if let (true, Some(anchor_index), Some(index)) = (shift, anchor_index, index) {
+            selection.extend(&visible[anchor_index.min(index)..=anchor_index.max(index)]);
+            return
+        }
+        if !selection.remove(&id) {
+            selection.insert(id);
+        }
+        *anchor = Some(id);
+    }
+
+    /// Messages that change the open document or its attachments and are refused while it is locked.
+    fn edits_document(message: &Message) -> bool {
+        return matches!(message,
+            Message::CurrentDocumentNumberChange(_) | Message::CurrentDocumentTypeChange(_) | Message::CurrentCommentChange(_) |
+            Message::CurrentDocumentDateChange(_) | Message::CurrentDueDateChange(_) | Message::FieldValueChange(_, _) |
+            Message::NextDocumentNumber | Message::SaveCurrentDocument | Message::ShowConfirmDelete | Message::DeleteDocument |
+            Message::NewAttachment | Message::SaveNewAttachment | Message::SaveCurrentAttachment |
+            Message::CurrentAttachmentReferenceNumberChange(_) | Message::CurrentAttachmentCommentChange(_) | Message::DeleteAttachment |
+            Message::OpenFileDialog | Message::Scan | Message::ClearImageFiles | Message::RestoreVersion(_) |
+            Message::NewTagNameChange(_) | Message::AddTag | Message::RemoveTag(_) | Message::AddLink | Message::RemoveLink(_) |
+            Message::MoveAttachment | Message::CopyAttachment | Message::MergeDocuments | Message::SplitDocument |
+            Message::BulkDelete | Message::BulkTag | Message::BulkMove
+        )
+    }
+
+    /// Messages that change the open attachment, which wait while another user holds its lock.
+    fn edits_attachment(message: &Message) -> bool {
+        return matches!(message,
+            Message::SaveCurrentAttachment | Message::CurrentAttachmentReferenceNumberChange(_) | Message::CurrentAttachmentCommentChange(_) |
+            Message::DeleteAttachment | Message::OpenFileDialog | Message::Scan | Message::ClearImageFiles | Message::RestoreVersion(_) |
+            Message::MoveAttachment
+        )
+    }
+
+    /// Messages that have to wait while a background job runs, because they change or leave what the job works on.
+    fn waits_for_job(message: &Message) -> bool {
+        return edits_document(message) || matches!(message,
+            Message::OpenDocument(_) | Message::CloseDocument | Message::NewDocument | Message::SaveNewDocument |
+            Message::OpenAttachment(_) | Message::CloseAttachment | Message::Undo | Message::Redo | Message::OpenView(_) |
+            Message::ExportToPdf | Message::BulkExport | Message::BulkArchive | Message::BulkChangeType
+        )
+    }
+
+    fn history_panel(entries: &[AuditEntry]) -> Element<'static, Message> {
+        let mut entry_rows = column![].spacing(5);
+        if entries.is_empty() {
+            entry_rows = entry_rows.push(Text::new("No changes recorded yet."));
+        }
+        for entry in entries {
+            entry_rows = entry_rows.push(row![
+                Text::new(format_timestamp(entry.changed_at)).width(Length::FillPortion(1)),
+                Text::new(entry.user_name.clone()).width(Length::FillPortion(1)),
+                Text::new(entry.description()).width(Length::FillPortion(4))
+            ].spacing(5));
+        }
+
+        column![
+            rule::horizontal(2),
+            Text::new("History").size(16),
+            scrollable(entry_rows).height(Length::Fill)
+        ].spacing(5).into()
+    }
+
+    /// Explains a failed save; a UNIQUE violation can only come from the document number.
+    fn save_error_message(err: &rusqlite::Error, document_number: &str) -> String {
+        if is_conflict(err) {
+            return String::from(CHANGED_ELSEWHERE)
+        }
+        return match err.sqlite_error_code() {
+            Some(rusqlite::ErrorCode::ConstraintViolation) => format!("Document number {} is already used.", document_number),
+            _ => format!("Error saving document: {}", err)
+        }
+    }
+
+    /// The title of a group of documents, with how many it holds.
+    fn group_title(label: &str, group_by: GroupBy, count: usize) -> String {
+        return match (label.is_empty(), group_by) {
+            (false, _) => format!("{} ({})", label, count),
+            (true, GroupBy::DocumentType) => format!("No Type ({})", count),
+            (true, _) => format!("No Date ({})", count)
+        }
+    }
+
+    fn error_text(error_message: &Option<String>) -> Text<'static> {
+        match error_message {
+            Some(message) => Text::new(message.clone()).style(iced::widget::text::danger),
+            None => Text::new("")
+        }
+    }
+
     fn empty_text_input_warning(theme: &Theme) -> text_input::Style {
         text_input::Style {
             border: Border {
@@ -1136,6 +3518,46 @@ pub(crate) mod document_list {
         return compressed_bytes
     }
 
+    /// Converts the page to a compressed PNG, ready to be written to the object store.
+    fn prepare_page_bytes(bytes: &mut Vec<u8>) -> Vec<u8> {
+        if FileFormat::from_bytes(&bytes) != FileFormat::PortableNetworkGraphics {
+            let img = image::load_from_memory(&bytes);
+            match img.unwrap().write_to(&mut Cursor::new(&mut *bytes), image::ImageFormat::Png) {
+                Err(err) => println!("Error converting image format: {}", err),
+                _ => {}
+            }
+        }
+
+        return compress_image(bytes.to_vec())
+    }
+
+    /// Reads the chosen files as page images, rendering each page of a PDF to an image.
+    fn read_page_files(paths: Vec<PathBuf>, progress: &Progress) -> Vec<Vec<u8>> {
+        let mut pages: Vec<Vec<u8>> = Vec::new();
+        for (index, path) in paths.iter().enumerate() {
+            if progress.is_cancelled() {
+                break
+            }
+            match fs::read(&path) {
+                Ok(bytes) => {
+                    if file_format::FileFormat::from_bytes(&bytes) == FileFormat::PortableDocumentFormat {
+                        for image in pdf_to_png(bytes) {
+                            pages.push(image);
+                        }
+                    }
+                    else {
+                        pages.push(bytes);
+                    }
+                }
+                Err(err) => {
+                    println!("Error reading files from paths: {}", err);
+                }
+            }
+            progress.report(index + 1, paths.len());
+        }
+        return pages
+    }
+
     fn pdf_to_png(bytes: Vec<u8>) -> Vec<Vec<u8>> {
         let pdfium = Pdfium::default();
         let document = pdfium.load_pdf_from_byte_vec(bytes, None);
@@ -1157,11 +3579,23 @@ pub(crate) mod document_list {
         return bitmaps
     }
 
-    fn export_to_pdf(byte_vec: Vec<Vec<u8>>, path: PathBuf) {
+    /// Writes the pages to a PDF at `path`. With `progress`, reports each page and stops without writing the file
+    /// when cancelled.
+    pub(crate) fn export_to_pdf(byte_vec: Vec<Vec<u8>>, path: PathBuf, progress: Option<&Progress>) -> Result<(), String> {
+        let bytes = pdf_bytes(byte_vec, progress)?;
+        return fs::write(&path, bytes).map_err(|err| err.to_string())
+    }
+
+    /// The pages as a PDF, one page image per A4 page.
+    pub(crate) fn pdf_bytes(byte_vec: Vec<Vec<u8>>, progress: Option<&Progress>) -> Result<Vec<u8>, String> {
         let pdfium = Pdfium::default();
         let mut document = Result::expect(pdfium.create_new_pdf(), "Error creating new document");
-        
-        for bytes in byte_vec {
+        let total = byte_vec.len();
+
+        for (index, bytes) in byte_vec.into_iter().enumerate() {
+            if progress.is_some_and(|progress| progress.is_cancelled()) {
+                return Err(String::from("Cancelled"))
+            }
             let mut width: f32 = 0.0;
             let mut height: f32 = 0.0;
             let raw_bytes = match image::load_from_memory(&bytes) {
@@ -1186,9 +3620,23 @@ pub(crate) mod document_list {
 
             let mut page = document.pages_mut().create_page_at_end(PdfPagePaperSize::a4()).expect("Error creating document page");
             page.objects_mut().create_image_object((PdfPagePaperSize::a4().width() - PdfPoints::new(width)) / 2.0, (PdfPagePaperSize::a4().height() - PdfPoints::new(height)) / 2.0, raw_bytes.as_ref().unwrap(), Some(PdfPoints::new(width)), Some(PdfPoints::new(height))).expect("Error adding image to page");
+            if let Some(progress) = progress {
+                progress.report(index + 1, total);
+            }
         }
 
-        document.save_to_file(&path).expect("Error saving to PDF");
+        return document.save_to_bytes().map_err(|err| err.to_string())
+    }
+
+    /// What a background job of the document list hands back when it is done.
+    #[derive(Debug, Clone)]
+    pub(crate) enum JobOutput {
+        /// Page images read from the chosen files.
+        FilesRead(Vec<Vec<u8>>),
+        /// The pages of the attachment being saved, converted and written to staging.
+        PagesStaged(Result<Arc<StagedPages>, String>),
+        Exported(Result<(), String>),
+        BulkFinished(BulkJob)
     }
 
     #[derive(Debug, Clone)]
@@ -1198,6 +3646,59 @@ pub(crate) mod document_list {
         CurrentDocumentNumberChange(String),
         CurrentDocumentTypeChange(String),
         CurrentCommentChange(String),
+        CurrentDocumentDateChange(String),
+        CurrentDueDateChange(String),
+        ChangeStatus(DocumentStatus),
+        Unlock,
+        Lock,
+        StatusFilterChange(Option<DocumentStatus>),
+        LinkKindChange(LinkKind),
+        LinkTargetChange(String),
+        AddLink,
+        RemoveLink(u32),
+        SortKeyChange(SortKey),
+        LayoutChange(DocumentLayout),
+        /// Loads the next page of document summaries.
+        OpenLinkedDocument(u32),
+        PollChanges,
+        ReloadOpenDocument,
+        ListScrolled(scrollable::Viewport),
+        /// A table header was clicked.
+        SortColumn(SortKey),
+        ResizeColumnStart(usize),
+        ResizeColumnMove(iced::Point),
+        ResizeColumnEnd,
+        ToggleSortDirection,
+        GroupByChange(GroupBy),
+        ViewNameChange(String),
+        SaveView,
+        DeleteView(u32),
+        OpenView(u32),
+        ModifiersChanged(keyboard::Modifiers),
+        SelectDocument(u32),
+        SelectAttachment(u32),
+        SelectAll,
+        ClearSelection,
+        BulkTagNameChange(String),
+        BulkTypeChange(DocumentType),
+        BulkMoveTargetChange(String),
+        BulkDelete,
+        BulkTag,
+        BulkChangeType,
+        BulkMove,
+        BulkExport,
+        BulkArchive,
+        Job(JobEvent<JobOutput>),
+        CancelJob,
+        CloseBulk,
+        ToggleMerge,
+        MergeSourceChange(String),
+        MergeChoice(MergeField, bool),
+        MergeDocuments,
+        ToggleSplit,
+        SplitAttachmentChange(u32, bool),
+        SplitDocumentNumberChange(String),
+        SplitDocument,
         SaveCurrentDocument,
         SaveNewDocument,
         SwitchTab(Tab),
@@ -1213,6 +3714,8 @@ pub(crate) mod document_list {
         CloseAttachment,
         ShowConfirmDelete,
         SearchTextChange(String),
+        /// Replaces the search term being typed with a completion.
+        ApplySuggestion(String),
         OpenFileDialog,
         Back,
         KeyEvent(Key),
@@ -1225,9 +3728,38 @@ pub(crate) mod document_list {
         ExportToPdf,
         PrevPage,
         NextPage,
+        Undo,
+        Redo,
+        ToggleHistory,
+        ToggleVersions,
+        CompareVersion(CompareSide, Option<u32>),
+        CloseCompare,
+        RestoreVersion(u32),
+        ToggleTransfer,
+        TransferTargetChange(String),
+        TransferReferenceNumberChange(String),
+        MoveAttachment,
+        CopyAttachment,
+        ToggleTagFilter(u32),
+        ToggleTagMatchMode,
+        NewTagNameChange(String),
+        AddTag,
+        RemoveTag(u32),
+        NextDocumentNumber,
+        FieldValueChange(u32, String),
+        TypeFilterChange(Option<DocumentType>),
+        FieldFilterChange(TypeField),
+        FieldFilterValueChange(String),
         None,
     }
 
+    /// Which half of the viewer a version is shown in while comparing.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub(crate) enum CompareSide {
+        Left,
+        Right
+    }
+
     #[derive(Debug, Clone, PartialEq, Eq, Default)]
     pub(crate) enum Tab {
         #[default]
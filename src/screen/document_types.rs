@@ -0,0 +1,396 @@
+pub(crate) mod document_types {
+    use iced::{Alignment::Center, Element, Length, Task, widget::{Container, PickList, Space, Text, button, checkbox, column, container, row, rule, scrollable, text_input}};
+
+    use time::OffsetDateTime;
+
+    use crate::{db::db_module::DbConnection, document_type::document_type::{DocumentType, FieldKind, TypeField}, number_pattern::number_pattern::NumberPattern};
+
+    #[derive(Debug, Clone, Default)]
+    pub(crate) struct DocumentTypes {
+        document_types: Vec<DocumentType>,
+        new_type_name: String,
+        selected_type: Option<DocumentType>,
+        rename_text: String,
+        merge_target: Option<DocumentType>,
+        field_drafts: Vec<FieldDraft>,
+        number_pattern: String,
+        auto_number: bool,
+        show_confirm_delete: bool,
+        error_message: Option<String>
+    }
+
+    /// A field as edited in the form; the last draft is always the blank row for adding a field.
+    #[derive(Debug, Clone)]
+    struct FieldDraft {
+        field_id: Option<u32>,
+        name: String,
+        kind: FieldKind,
+        required: bool,
+        options: String
+    }
+
+    impl FieldDraft {
+        fn blank() -> FieldDraft {
+            FieldDraft {
+                field_id: None,
+                name: String::new(),
+                kind: FieldKind::Text,
+                required: false,
+                options: String::new()
+            }
+        }
+
+        fn from_field(field: &TypeField) -> FieldDraft {
+            FieldDraft {
+                field_id: Some(field.field_id),
+                name: field.name.clone(),
+                kind: field.kind,
+                required: field.required,
+                options: field.options.join(", ")
+            }
+        }
+
+        fn to_field(&self) -> TypeField {
+            TypeField {
+                field_id: self.field_id.unwrap_or_default(),
+                name: self.name.trim().to_string(),
+                kind: self.kind,
+                required: self.required,
+                options: self.options.split(',').map(|option| option.trim().to_string()).filter(|option| !option.is_empty()).collect()
+            }
+        }
+    }
+
+    impl DocumentTypes {
+        pub(crate) fn new() -> DocumentTypes {
+            let mut document_types = DocumentTypes::default();
+            document_types.reload();
+            document_types
+        }
+
+        pub(crate) fn update(&mut self, message: Message) -> Task<Message> {
+            let mut conn = DbConnection::new();
+            let result = match message {
+                Message::NewTypeNameChange(input) => {
+                    self.new_type_name = input;
+                    return Task::none()
+                },
+                Message::AddType => {
+                    if self.new_type_name.trim().is_empty() {
+                        return Task::none()
+                    }
+                    match conn.new_document_type(self.new_type_name.clone()) {
+                        Ok(_) => {
+                            self.new_type_name.clear();
+                            Ok(())
+                        },
+                        Err(_) => Err(format!("A type named \"{}\" already exists.", self.new_type_name.trim()))
+                    }
+                },
+                Message::Select(document_type) => {
+                    self.rename_text = document_type.get_name().to_string();
+                    self.field_drafts = document_type.get_fields().iter().map(FieldDraft::from_field).collect();
+                    self.field_drafts.push(FieldDraft::blank());
+                    self.number_pattern = document_type.get_number_pattern().to_string();
+                    self.auto_number = document_type.get_auto_number();
+                    self.selected_type = Some(document_type);
+                    self.merge_target = None;
+                    self.show_confirm_delete = false;
+                    Ok(())
+                },
+                Message::RenameChange(input) => {
+                    self.rename_text = input;
+                    return Task::none()
+                },
+                Message::Rename => {
+                    let type_id = self.selected_type.as_ref().unwrap().get_type_id();
+                    if self.rename_text.trim().is_empty() {
+                        Err(String::from("A type needs a name."))
+                    }
+                    else {
+                        conn.rename_document_type(type_id, self.rename_text.clone()).map_err(|err| {
+                            println!("Error renaming document type: {}", err);
+                            format!("A type named \"{}\" already exists. Merge the types instead.", self.rename_text.trim())
+                        })
+                    }
+                },
+                Message::NumberPatternChange(input) => {
+                    self.number_pattern = input;
+                    return Task::none()
+                },
+                Message::AutoNumberChange(auto_number) => {
+                    self.auto_number = auto_number;
+                    return Task::none()
+                },
+                Message::SaveNumberPattern => {
+                    let type_id = self.selected_type.as_ref().unwrap().get_type_id();
+                    let pattern = match self.number_pattern.trim().is_empty() {
+                        true => None,
+                        false => match NumberPattern::parse(&self.number_pattern) {
+                            Ok(pattern) => Some(pattern),
+                            Err(err) => {
+                                self.error_message = Some(err);
+                                return Task::none()
+                            }
+                        }
+                    };
+                    if self.auto_number && !pattern.as_ref().is_some_and(|pattern| pattern.can_generate()) {
+                        Err(String::from("Automatic numbering needs a template such as INV-{YYYY}-{seq:5}."))
+                    }
+                    else {
+                        conn.edit_number_pattern(type_id, self.number_pattern.clone(), self.auto_number).map(|_| ()).map_err(|err| format!("Error saving number pattern: {}", err))
+                    }
+                },
+                Message::MergeTargetChange(document_type) => {
+                    self.merge_target = Some(document_type);
+                    return Task::none()
+                },
+                Message::Merge => {
+                    let source = self.selected_type.take().unwrap();
+                    let target = self.merge_target.take().unwrap();
+                    conn.merge_document_types(source.get_type_id(), target.get_type_id()).map_err(|err| format!("Error merging document types: {}", err))
+                },
+                Message::ShowConfirmDelete => {
+                    self.show_confirm_delete = true;
+                    return Task::none()
+                },
+                Message::Delete => {
+                    let document_type = self.selected_type.take().unwrap();
+                    conn.delete_document_type(document_type.get_type_id()).map_err(|err| format!("Error deleting document type: {}", err))
+                },
+                Message::CloseType => {
+                    self.selected_type = None;
+                    Ok(())
+                },
+                Message::FieldNameChange(index, input) => {
+                    self.field_drafts[index].name = input;
+                    return Task::none()
+                },
+                Message::FieldKindChange(index, kind) => {
+                    self.field_drafts[index].kind = kind;
+                    return Task::none()
+                },
+                Message::FieldRequiredChange(index, required) => {
+                    self.field_drafts[index].required = required;
+                    return Task::none()
+                },
+                Message::FieldOptionsChange(index, input) => {
+                    self.field_drafts[index].options = input;
+                    return Task::none()
+                },
+                Message::SaveField(index) => {
+                    let draft = &self.field_drafts[index];
+                    let field = draft.to_field();
+                    if field.name.is_empty() {
+                        Err(String::from("A field needs a name."))
+                    }
+                    else if field.kind == FieldKind::Enum && field.options.is_empty() {
+                        Err(format!("List the choices for {}, separated by commas.", field.name))
+                    }
+                    else {
+                        let result = match draft.field_id {
+                            Some(_) => conn.edit_type_field(&field),
+                            None => conn.new_type_field(self.selected_type.as_ref().unwrap().get_type_id(), &field)
+                        };
+                        result.map(|_| ()).map_err(|err| format!("Error saving field: {}", err))
+                    }
+                },
+                Message::DeleteField(field_id) => {
+                    conn.delete_type_field(field_id).map_err(|err| format!("Error deleting field: {}", err))
+                },
+                Message::Back => Ok(())
+            };
+
+            match result {
+                Ok(_) => self.error_message = None,
+                Err(err) => {
+                    println!("{}", err);
+                    self.error_message = Some(err);
+                    return Task::none()
+                }
+            }
+            self.reload();
+            Task::none()
+        }
+
+        pub(crate) fn view(&self) -> Element<'_, Message> {
+            let mut type_rows = column![].spacing(5);
+            if self.document_types.is_empty() {
+                type_rows = type_rows.push(Text::new("No document types yet."));
+            }
+            for document_type in &self.document_types {
+                type_rows = type_rows.push(
+                    Container::new(row![
+                        Text::new(document_type.get_name().to_string()),
+                        Text::new(format!("{} fields", document_type.get_fields().len())).size(12),
+                        Space::new().width(Length::Fill),
+                        button("Edit").on_press(Message::Select(document_type.clone()))
+                    ].spacing(5).align_y(Center)).padding(5).style(container::bordered_box).width(Length::Fill)
+                );
+            }
+
+            Container::new(column![
+                Container::new(row![
+                    button("<").on_press(Message::Back),
+                    Space::new().width(Length::Fill),
+                    text_input("New type", &self.new_type_name).on_input(Message::NewTypeNameChange).on_submit(Message::AddType).width(Length::Fixed(200.0)),
+                    button("Add").on_press(Message::AddType)
+                ].spacing(5).align_y(Center)).padding(5).style(container::bordered_box).width(Length::Fill),
+                Container::new(column![
+                    row![
+                        Text::new("Document Types").size(20),
+                        Space::new().width(Length::Fill),
+                        match &self.error_message {
+                            Some(error) => Text::new(error.clone()).style(iced::widget::text::danger),
+                            None => Text::new("")
+                        }
+                    ].spacing(5).align_y(Center),
+                    rule::horizontal(2),
+                    match &self.selected_type {
+                        Some(document_type) => self.edit_panel(document_type),
+                        None => column![].into()
+                    },
+                    scrollable(type_rows).height(Length::Fill)
+                ].spacing(5)).padding(5).style(container::bordered_box).width(Length::Fill).height(Length::Fill),
+            ].spacing(5)).into()
+        }
+
+        /// Whether the message changed types or fields that the document list shows.
+        pub(crate) fn changes_library(message: &Message) -> bool {
+            return matches!(message, Message::AddType | Message::Rename | Message::Merge | Message::Delete | Message::SaveField(_) | Message::DeleteField(_) | Message::SaveNumberPattern)
+        }
+
+        pub(crate) fn reload(&mut self) {
+            self.document_types = DbConnection::new().read_document_types().unwrap_or_else(|err| {
+                println!("Error reading document types: {}", err);
+                Vec::new()
+            });
+            self.selected_type = self.selected_type.as_ref().and_then(|selected| self.document_types.iter().find(|document_type| document_type.get_type_id() == selected.get_type_id()).cloned());
+            if let Some(document_type) = &self.selected_type {
+                self.field_drafts = document_type.get_fields().iter().map(FieldDraft::from_field).collect();
+                self.field_drafts.push(FieldDraft::blank());
+            }
+        }
+
+        fn edit_panel(&self, document_type: &DocumentType) -> Element<'_, Message> {
+            let merge_targets: Vec<DocumentType> = self.document_types.iter().filter(|other| other.get_type_id() != document_type.get_type_id()).cloned().collect();
+
+            let mut field_rows = column![
+                row![
+                    Text::new("Field").width(Length::FillPortion(2)),
+                    Text::new("Kind").width(Length::FillPortion(1)),
+                    Text::new("Required").width(Length::Shrink),
+                    Text::new("Choices (comma separated)").width(Length::FillPortion(2)),
+                    Space::new().width(Length::Fixed(130.0))
+                ].spacing(5)
+            ].spacing(5);
+            for (index, draft) in self.field_drafts.iter().enumerate() {
+                field_rows = field_rows.push(row![
+                    text_input(if draft.field_id.is_none() { "New field" } else { "" }, &draft.name).on_input(move |input| Message::FieldNameChange(index, input)).width(Length::FillPortion(2)),
+                    PickList::new(FieldKind::ALL, Some(draft.kind), move |kind| Message::FieldKindChange(index, kind)).width(Length::FillPortion(1)),
+                    checkbox(draft.required).on_toggle(move |required| Message::FieldRequiredChange(index, required)),
+                    if draft.kind == FieldKind::Enum {
+                        text_input("", &draft.options).on_input(move |input| Message::FieldOptionsChange(index, input)).width(Length::FillPortion(2))
+                    }
+                    else {
+                        text_input("", &draft.options).width(Length::FillPortion(2))
+                    },
+                    match draft.field_id {
+                        Some(field_id) => row![
+                            button("Save").on_press(Message::SaveField(index)),
+                            button("Delete").on_press(Message::DeleteField(field_id))
+                        ].spacing(5).width(Length::Fixed(130.0)),
+                        None => row![
+                            button("Add Field").on_press(Message::SaveField(index))
+                        ].width(Length::Fixed(130.0))
+                    }
+                ].spacing(5).align_y(Center));
+            }
+
+            Container::new(column![
+                row![
+                    Text::new(document_type.get_name().to_string()).size(16),
+                    Space::new().width(Length::Fill),
+                    button("Close").on_press(Message::CloseType)
+                ].spacing(5).align_y(Center),
+                row![
+                    Text::new("Name").width(Length::FillPortion(1)),
+                    text_input("", &self.rename_text).on_input(Message::RenameChange).on_submit(Message::Rename).width(Length::FillPortion(3)),
+                    button("Rename").on_press(Message::Rename)
+                ].spacing(5).align_y(Center),
+                row![
+                    Text::new("Number pattern").width(Length::FillPortion(1)),
+                    text_input("INV-{YYYY}-{seq:5} or a regular expression", &self.number_pattern).on_input(Message::NumberPatternChange).on_submit(Message::SaveNumberPattern).width(Length::FillPortion(3)),
+                    checkbox(self.auto_number).label("Number automatically").on_toggle(Message::AutoNumberChange),
+                    button("Save").on_press(Message::SaveNumberPattern)
+                ].spacing(5).align_y(Center),
+                row![
+                    Space::new().width(Length::FillPortion(1)),
+                    Text::new(pattern_preview(&self.number_pattern)).size(12).width(Length::FillPortion(3))
+                ].spacing(5),
+                row![
+                    Text::new("Merge into").width(Length::FillPortion(1)),
+                    PickList::new(merge_targets, self.merge_target.clone(), Message::MergeTargetChange).width(Length::FillPortion(3)),
+                    if self.merge_target.is_some() {
+                        button("Merge").on_press(Message::Merge)
+                    }
+                    else {
+                        button("Merge")
+                    }
+                ].spacing(5).align_y(Center),
+                rule::horizontal(1),
+                field_rows,
+                row![
+                    Space::new().width(Length::Fill),
+                    if self.show_confirm_delete {
+                        row![
+                            Text::from("Delete the type and its field values; its documents keep no type: "),
+                            button("Confirm").on_press(Message::Delete),
+                            button("Cancel").on_press(Message::Select(document_type.clone()))
+                        ].spacing(5).align_y(Center)
+                    }
+                    else {
+                        row![button("Delete").on_press(Message::ShowConfirmDelete)]
+                    }
+                ].spacing(5).align_y(Center)
+            ].spacing(5)).padding(5).style(container::bordered_box).width(Length::Fill).into()
+        }
+    }
+
+    fn pattern_preview(number_pattern: &str) -> String {
+        if number_pattern.trim().is_empty() {
+            return String::from("Any document number is accepted.")
+        }
+        return match NumberPattern::parse(number_pattern) {
+            Ok(pattern) => match pattern.next_number(OffsetDateTime::now_local().unwrap_or_else(|_| OffsetDateTime::now_utc()).date(), &[]) {
+                Some(example) => format!("For example {}", example),
+                None => String::from("Numbers are checked against the regular expression.")
+            },
+            Err(err) => err
+        }
+    }
+
+    #[derive(Debug, Clone)]
+    pub(crate) enum Message {
+        NewTypeNameChange(String),
+        AddType,
+        Select(DocumentType),
+        RenameChange(String),
+        Rename,
+        NumberPatternChange(String),
+        AutoNumberChange(bool),
+        SaveNumberPattern,
+        MergeTargetChange(DocumentType),
+        Merge,
+        ShowConfirmDelete,
+        Delete,
+        CloseType,
+        FieldNameChange(usize, String),
+        FieldKindChange(usize, FieldKind),
+        FieldRequiredChange(usize, bool),
+        FieldOptionsChange(usize, String),
+        SaveField(usize),
+        DeleteField(u32),
+        Back
+    }
+}
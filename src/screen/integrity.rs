@@ -0,0 +1,156 @@
+pub(crate) mod integrity {
+    use iced::{Alignment::Center, Element, Length, Task, widget::{Container, Space, Text, button, column, container, row, rule, scrollable}};
+    use rfd::FileDialog;
+
+    use crate::{db::db_module::DbConnection, library_check::library_check::{self as checker, IntegrityReport, Issue}};
+
+    #[derive(Debug, Clone, Default)]
+    pub(crate) struct Integrity {
+        report: Option<IntegrityReport>,
+        last_error: Option<String>
+    }
+
+    impl Integrity {
+        pub(crate) fn new() -> Integrity {
+            Integrity {
+                report: None,
+                last_error: None
+            }
+        }
+
+        pub(crate) fn update(&mut self, message: Message) -> Task<Message> {
+            match message {
+                Message::Scan => {
+                    self.scan();
+                    Task::none()
+                },
+                Message::Repair(issue) => {
+                    let mut conn = DbConnection::new();
+                    let result = match &issue {
+                        Issue::MissingFile { page_id, .. } => {
+                            match FileDialog::new().set_title("Select Replacement Page")
+                                .add_filter("Image (.png, .jpg, .jpeg, .webp)", &["png", "jpg", "jpeg", "webp"])
+                                .pick_file() {
+                                Some(source) => checker::relink_page(&mut conn, *page_id, &source),
+                                None => return Task::none()
+                            }
+                        },
+                        Issue::OrphanFile { file_path } => checker::remove_orphan_file(file_path),
+                        Issue::OrphanDirectory { path } => checker::import_orphan_directory(&mut conn, path),
+                        Issue::ChecksumMismatch { page_id, actual, .. } => checker::accept_checksum(&mut conn, *page_id, actual),
+                        Issue::EmptyAttachment { attachment_id, .. } => checker::remove_empty_attachment(&mut conn, *attachment_id)
+                    };
+                    self.finish_repair(result);
+                    Task::none()
+                },
+                Message::RemoveDeadRow(page_id) => {
+                    let mut conn = DbConnection::new();
+                    let result = checker::remove_dead_page(&mut conn, page_id);
+                    self.finish_repair(result);
+                    Task::none()
+                },
+                Message::Back => Task::none()
+            }
+        }
+
+        pub(crate) fn view(&self) -> Element<'_, Message> {
+            let mut issue_rows = column![].spacing(5);
+            match &self.report {
+                None => {
+                    issue_rows = issue_rows.push(Text::new("Run a check to compare the database with the data folder."));
+                },
+                Some(report) => {
+                    issue_rows = issue_rows.push(Text::new(format!("{} pages and {} files checked, {} issues found.", report.pages_checked(), report.files_checked(), report.issues().len())));
+                    for issue in report.issues() {
+                        issue_rows = issue_rows.push(issue_row(issue));
+                    }
+                }
+            }
+
+            Container::new(column![
+                Container::new(row![
+                    button("<").on_press(Message::Back),
+                    button("Check").on_press(Message::Scan)
+                ].spacing(5)).padding(5).style(container::bordered_box).width(Length::Fill),
+                Container::new(column![
+                    row![
+                        Text::new("Library Integrity").size(20)
+                    ].spacing(5).align_y(Center),
+                    rule::horizontal(2),
+                    match &self.last_error {
+                        Some(error) => Text::new(error.clone()).style(iced::widget::text::danger),
+                        None => Text::new("")
+                    },
+                    scrollable(issue_rows).height(Length::Fill)
+                ].spacing(5)).padding(5).style(container::bordered_box).width(Length::Fill).height(Length::Fill),
+            ].spacing(5)).into()
+        }
+
+        /// Whether the last repair changed rows that other screens may have cached.
+        pub(crate) fn changes_library(message: &Message) -> bool {
+            match message {
+                Message::Repair(_) | Message::RemoveDeadRow(_) => true,
+                Message::Scan | Message::Back => false
+            }
+        }
+
+        fn scan(&mut self) {
+            match checker::scan(&DbConnection::new()) {
+                Ok(report) => {
+                    self.report = Some(report);
+                },
+                Err(err) => {
+                    println!("Error checking library integrity: {}", err);
+                    self.last_error = Some(format!("Error checking library integrity: {}", err));
+                }
+            }
+        }
+
+        fn finish_repair(&mut self, result: Result<(), String>) {
+            match result {
+                Ok(_) => self.last_error = None,
+                Err(err) => {
+                    println!("{}", err);
+                    self.last_error = Some(err);
+                }
+            }
+            self.scan();
+        }
+    }
+
+    fn issue_row(issue: &Issue) -> Element<'static, Message> {
+        let actions = match issue {
+            Issue::MissingFile { page_id, .. } => row![
+                button("Relink").on_press(Message::Repair(issue.clone())),
+                button("Remove Row").on_press(Message::RemoveDeadRow(*page_id))
+            ],
+            Issue::OrphanFile { .. } => row![
+                button("Delete File").on_press(Message::Repair(issue.clone()))
+            ],
+            Issue::OrphanDirectory { .. } => row![
+                button("Import").on_press(Message::Repair(issue.clone()))
+            ],
+            Issue::ChecksumMismatch { page_id, .. } => row![
+                button("Accept File").on_press(Message::Repair(issue.clone())),
+                button("Remove Row").on_press(Message::RemoveDeadRow(*page_id))
+            ],
+            Issue::EmptyAttachment { .. } => row![
+                button("Remove Attachment").on_press(Message::Repair(issue.clone()))
+            ]
+        };
+
+        Container::new(row![
+            Text::new(issue.description()),
+            Space::new().width(Length::Fill),
+            actions.spacing(5)
+        ].spacing(5).align_y(Center)).padding(5).style(container::bordered_box).width(Length::Fill).into()
+    }
+
+    #[derive(Debug, Clone)]
+    pub(crate) enum Message {
+        Scan,
+        Repair(Issue),
+        RemoveDeadRow(u32),
+        Back
+    }
+}
@@ -1,38 +1,110 @@
 pub(crate) mod main_menu {
-    use iced::{Alignment::Center, Element, Event, Length, Task, widget::{Container, Image, button, column, container, image::{Handle, Viewer}, row, text}};
+    use std::sync::Arc;
 
-    use crate::HOME_IMAGE;
+    use iced::{Alignment::Center, Element, Event, Length, Task, widget::{Container, Image, Space, Text, button, column, container, image::{Handle, Viewer}, row, rule, scrollable, text}};
+    use time::{Date, OffsetDateTime};
+
+    use crate::{HOME_IMAGE, db::db_module::DbConnection, document::document::{Document, DocumentStatus, format_date}};
+
+    /// How many days ahead the Upcoming list looks for due dates.
+    const UPCOMING_DAYS: i64 = 30;
 
     #[derive(Default, Debug, Clone)]
-    pub(crate) struct MainMenu;
-    
+    pub(crate) struct MainMenu {
+        upcoming: Vec<Arc<Document>>
+    }
+
     impl MainMenu {
         pub(crate) fn new() -> MainMenu {
-            MainMenu
+            let mut main_menu = MainMenu::default();
+            main_menu.reload();
+            main_menu
         }
         pub(crate) fn update(&mut self, message: Message) -> Task<Message> {
             match message {
                 Message::ToDocumentList => Task::none(),
                 Message::NewDocument => todo!(),
+                Message::OpenDocument(_) => Task::none(),
                 Message::KeyEvent(event) => Task::none(),
                 Message::None => Task::none(),
             }
         }
 
         pub(crate) fn view(&self) -> Element<Message> {
-            Container::new(
-                Image::new(Handle::from_bytes(HOME_IMAGE))
-                    .expand(true)
-                    .content_fit(iced::ContentFit::Cover)
-                    .border_radius(5.0)
-            ).align_x(Center).align_y(Center).width(Length::Fill).height(Length::Fill).style(container::bordered_box).into()
+            let today = today();
+            let mut upcoming_rows = column![].spacing(5);
+            if self.upcoming.is_empty() {
+                upcoming_rows = upcoming_rows.push(Text::new(format!("Nothing is due in the next {} days.", UPCOMING_DAYS)));
+            }
+            for document in &self.upcoming {
+                upcoming_rows = upcoming_rows.push(upcoming_row(document, today));
+            }
+
+            Container::new(row![
+                Container::new(
+                    Image::new(Handle::from_bytes(HOME_IMAGE))
+                        .expand(true)
+                        .content_fit(iced::ContentFit::Cover)
+                        .border_radius(5.0)
+                ).align_x(Center).align_y(Center).width(Length::FillPortion(3)).height(Length::Fill),
+                Container::new(column![
+                    Text::new("Upcoming").size(20),
+                    rule::horizontal(2),
+                    scrollable(upcoming_rows).height(Length::Fill)
+                ].spacing(5)).padding(5).width(Length::FillPortion(2)).height(Length::Fill)
+            ].spacing(5)).width(Length::Fill).height(Length::Fill).style(container::bordered_box).into()
+        }
+
+        /// Reads the documents that are overdue or due within `UPCOMING_DAYS`, soonest first.
+        pub(crate) fn reload(&mut self) {
+            let documents = DbConnection::new().read_document_table().unwrap_or_else(|err| {
+                println!("Error reading documents: {}", err);
+                Vec::new()
+            });
+            let horizon = today() + time::Duration::days(UPCOMING_DAYS);
+            self.upcoming = documents.into_iter()
+                .filter(|document| document.get_status() != DocumentStatus::Archived && document.get_due_date().is_some_and(|due_date| due_date <= horizon))
+                .collect();
+            self.upcoming.sort_by_key(|document| document.get_due_date());
         }
-        
+
+    }
+
+    fn today() -> Date {
+        return OffsetDateTime::now_local().unwrap_or_else(|_| OffsetDateTime::now_utc()).date()
+    }
+
+    fn upcoming_row(document: &Arc<Document>, today: Date) -> Element<'static, Message> {
+        let due_date = document.get_due_date().unwrap();
+        let days = (due_date - today).whole_days();
+        let due_text = match days {
+            0 => String::from("Due today"),
+            1 => String::from("Due tomorrow"),
+            days if days > 1 => format!("Due in {} days ({})", days, format_date(due_date)),
+            days => format!("Overdue by {} days ({})", -days, format_date(due_date))
+        };
+
+        Container::new(row![
+            column![
+                Text::new(document.get_document_number().to_string()),
+                Text::new(document.get_document_type().to_string()).size(12)
+            ],
+            Space::new().width(Length::Fill),
+            if days < 0 {
+                Text::new(due_text).style(text::danger)
+            }
+            else {
+                Text::new(due_text)
+            },
+            button("Open").on_press(Message::OpenDocument(document.clone()))
+        ].spacing(5).align_y(Center)).padding(5).style(container::bordered_box).width(Length::Fill).into()
     }
+
     #[derive(Debug, Clone)]
     pub(crate) enum Message {
         ToDocumentList,
         NewDocument,
+        OpenDocument(Arc<Document>),
         KeyEvent(Event),
         None,
     }
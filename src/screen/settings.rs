@@ -1,17 +1,52 @@
 pub(crate) mod settings {
-    use iced::{Alignment::Center, Element, Length, Task, Theme, alignment::Horizontal::Left, widget::{Container, Grid, PickList, Text, Toggler, button, column, container, pick_list, row, rule, toggler}};
+    use iced::{Alignment::Center, Element, Length, Task, Theme, alignment::Horizontal::Left, widget::{Container, Grid, PickList, ProgressBar, Text, Toggler, button, column, container, pick_list, row, rule, text_input, toggler}};
     use iced_aw::Card;
 
+    use crate::{background::background::{self, Job, JobEvent}, crypto::crypto::{hash_pin, verify_pin}, db::db_module::{DbConnection, change_passphrase, encrypt_library}, storage::storage::encrypt_page_files};
+
+    const MIN_PASSPHRASE_LENGTH: usize = 8;
+    const MIN_PIN_LENGTH: usize = 4;
+
     pub(crate) struct Settings {
         current_theme: Option<Theme>,
-        show_console: bool
+        show_console: bool,
+        trash_retention_days: String,
+        versions_kept: String,
+        encrypted: bool,
+        current_passphrase: String,
+        new_passphrase: String,
+        confirm_passphrase: String,
+        encryption_message: Option<String>,
+        /// Encrypting the library, which runs in the background as every page file is rewritten.
+        encryption_job: Option<Job>,
+        idle_lock_minutes: String,
+        /// The stored PIN hash, to check the current PIN against before it is changed or removed.
+        lock_pin: Option<String>,
+        current_pin: String,
+        new_pin: String,
+        confirm_pin: String,
+        lock_message: Option<String>
     }
 
     impl Settings {
         pub(crate) fn new() -> Settings {
             Settings {
                 current_theme: Some(Theme::CatppuccinMacchiato),
-                show_console: false
+                show_console: false,
+                trash_retention_days: String::from("30"),
+                versions_kept: String::from("10"),
+                encrypted: false,
+                current_passphrase: String::new(),
+                new_passphrase: String::new(),
+                confirm_passphrase: String::new(),
+                encryption_message: None,
+                encryption_job: None,
+                idle_lock_minutes: String::from("0"),
+                lock_pin: None,
+                current_pin: String::new(),
+                new_pin: String::new(),
+                confirm_pin: String::new(),
+                lock_message: None
             }
         }
 
@@ -26,6 +61,128 @@ pub(crate) mod settings {
                     println!("{}", show_console);
                     Task::none()
                 }
+                Message::TrashRetentionChange(input) => {
+                    self.trash_retention_days = input;
+                    Task::none()
+                },
+                Message::VersionsKeptChange(input) => {
+                    self.versions_kept = input;
+                    Task::none()
+                },
+                Message::CurrentPassphraseChange(input) => {
+                    self.current_passphrase = input;
+                    Task::none()
+                },
+                Message::NewPassphraseChange(input) => {
+                    self.new_passphrase = input;
+                    Task::none()
+                },
+                Message::ConfirmPassphraseChange(input) => {
+                    self.confirm_passphrase = input;
+                    Task::none()
+                },
+                Message::EncryptLibrary => {
+                    if let Err(err) = self.check_new_passphrase() {
+                        self.encryption_message = Some(err);
+                        return Task::none()
+                    }
+                    if self.encryption_job.is_some() {
+                        return Task::none()
+                    }
+                    let passphrase = self.new_passphrase.clone();
+                    self.clear_passphrases();
+                    self.encryption_message = None;
+                    let (job, task) = background::run("Encrypting library", move |progress| {
+                        encrypt_library(&passphrase)?;
+                        return Ok(encrypt_page_files(&mut DbConnection::new(), |done, total| progress.report(done, total)))
+                    }, Message::EncryptionJob);
+                    self.encryption_job = Some(job);
+                    task
+                },
+                Message::EncryptionJob(event) => {
+                    match event {
+                        JobEvent::Progress(done, total) => {
+                            if let Some(job) = self.encryption_job.as_mut() {
+                                job.set_progress(done, total);
+                            }
+                        },
+                        JobEvent::Finished(result) => {
+                            self.encryption_job = None;
+                            self.encrypted = result.is_ok();
+                            self.encryption_message = Some(match result {
+                                Ok(Ok(_)) => String::from("The library is encrypted. Keep the passphrase safe: without it the library cannot be opened."),
+                                Ok(Err(err)) => format!("The database is encrypted, but not all page files: {}. They are encrypted on the next start.", err),
+                                Err(err) => err
+                            });
+                        },
+                        JobEvent::Failed(err) => {
+                            self.encryption_job = None;
+                            self.encryption_message = Some(format!("Encrypting library failed: {}", err));
+                        }
+                    }
+                    Task::none()
+                },
+                Message::ChangePassphrase => {
+                    if let Err(err) = self.check_new_passphrase() {
+                        self.encryption_message = Some(err);
+                        return Task::none()
+                    }
+                    self.encryption_message = Some(match change_passphrase(&self.current_passphrase, &self.new_passphrase) {
+                        Ok(_) => String::from("The passphrase was changed."),
+                        Err(err) => err
+                    });
+                    self.clear_passphrases();
+                    Task::none()
+                },
+                Message::IdleLockChange(input) => {
+                    self.idle_lock_minutes = input;
+                    Task::none()
+                },
+                Message::CurrentPinChange(input) => {
+                    self.current_pin = input;
+                    Task::none()
+                },
+                Message::NewPinChange(input) => {
+                    self.new_pin = input;
+                    Task::none()
+                },
+                Message::ConfirmPinChange(input) => {
+                    self.confirm_pin = input;
+                    Task::none()
+                },
+                Message::SetLockPin => {
+                    if let Err(err) = self.check_current_pin() {
+                        self.lock_message = Some(err);
+                        return Task::none()
+                    }
+                    if self.new_pin.chars().count() < MIN_PIN_LENGTH {
+                        self.lock_message = Some(format!("The PIN needs at least {} characters.", MIN_PIN_LENGTH));
+                        return Task::none()
+                    }
+                    if self.new_pin != self.confirm_pin {
+                        self.lock_message = Some(String::from("The PINs do not match."));
+                        return Task::none()
+                    }
+                    let pin_hash = hash_pin(&self.new_pin);
+                    self.clear_pins();
+                    Task::done(Message::LockPinChanged(Some(pin_hash)))
+                },
+                Message::RemoveLockPin => {
+                    if let Err(err) = self.check_current_pin() {
+                        self.lock_message = Some(err);
+                        return Task::none()
+                    }
+                    self.clear_pins();
+                    Task::done(Message::LockPinChanged(None))
+                },
+                Message::LockPinChanged(pin_hash) => {
+                    self.lock_pin = pin_hash.clone();
+                    self.lock_message = Some(String::from(match pin_hash {
+                        Some(_) => "The PIN was saved.",
+                        None => "The PIN was removed."
+                    }));
+                    Task::none()
+                },
                 Message::Back => Task::none()
             }
         }
@@ -44,6 +201,18 @@ pub(crate) mod settings {
                         Text::new("Theme: ").align_y(Center),
                         PickList::new(Settings::available_themes(), self.current_theme.clone(), Message::ChangeTheme)
                     ].spacing(5).align_y(Center),
+                    row![
+                        Text::new("Keep deleted items for (days, 0 for no limit): ").align_y(Center),
+                        text_input("30", &self.trash_retention_days).on_input(Message::TrashRetentionChange).width(Length::Fixed(80.0))
+                    ].spacing(5).align_y(Center),
+                    row![
+                        Text::new("Page versions kept per attachment: ").align_y(Center),
+                        text_input("10", &self.versions_kept).on_input(Message::VersionsKeptChange).width(Length::Fixed(80.0))
+                    ].spacing(5).align_y(Center),
+                    rule::horizontal(2),
+                    self.lock_section(),
+                    rule::horizontal(2),
+                    self.encryption_section(),
                     // row![
                     //     Text::new("Show Console: "),
                     //     Toggler::new(self.show_console).on_toggle(Message::ShowConsole).size(18)
@@ -52,6 +221,99 @@ pub(crate) mod settings {
             ].spacing(5)).into()
         }
 
+        fn lock_section(&self) -> Element<'_, Message> {
+            let mut section = column![
+                Text::new("Application Lock").size(16),
+                row![
+                    Text::new("Lock after idle for (minutes, 0 for never): ").align_y(Center),
+                    text_input("0", &self.idle_lock_minutes).on_input(Message::IdleLockChange).width(Length::Fixed(80.0))
+                ].spacing(5).align_y(Center),
+                Text::new(match (self.lock_pin.is_some(), self.encrypted) {
+                    (true, _) => "The lock asks for the PIN.",
+                    (false, true) => "The lock asks for the library passphrase unless a PIN is set.",
+                    (false, false) => "Set a PIN to use the lock."
+                })
+            ].spacing(5);
+            if self.lock_pin.is_some() {
+                section = section.push(text_input("Current PIN", &self.current_pin).secure(true).on_input(Message::CurrentPinChange).width(Length::Fixed(300.0)));
+            }
+            section = section.push(text_input("New PIN", &self.new_pin).secure(true).on_input(Message::NewPinChange).width(Length::Fixed(300.0)));
+            section = section.push(text_input("Repeat new PIN", &self.confirm_pin).secure(true).on_input(Message::ConfirmPinChange).width(Length::Fixed(300.0)));
+            let mut buttons = row![button("Set PIN").on_press(Message::SetLockPin)].spacing(5);
+            if self.lock_pin.is_some() {
+                buttons = buttons.push(button("Remove PIN").on_press(Message::RemoveLockPin));
+            }
+            section = section.push(buttons);
+            if let Some(message) = &self.lock_message {
+                section = section.push(Text::new(message.as_str()));
+            }
+            return section.into()
+        }
+
+        fn encryption_section(&self) -> Element<'_, Message> {
+            let mut section = column![
+                Text::new("Encryption").size(16),
+                Text::new(if self.encrypted {
+                    "The library is encrypted and asks for its passphrase at startup."
+                }
+                else {
+                    "Encrypt the database and page files with a passphrase. Close the library on all other computers first."
+                })
+            ].spacing(5);
+            if self.encrypted {
+                section = section.push(text_input("Current passphrase", &self.current_passphrase).secure(true).on_input(Message::CurrentPassphraseChange).width(Length::Fixed(300.0)));
+            }
+            section = section.push(text_input("New passphrase", &self.new_passphrase).secure(true).on_input(Message::NewPassphraseChange).width(Length::Fixed(300.0)));
+            section = section.push(text_input("Repeat new passphrase", &self.confirm_passphrase).secure(true).on_input(Message::ConfirmPassphraseChange).width(Length::Fixed(300.0)));
+            let action: Element<'_, Message> = if self.encrypted {
+                button("Change Passphrase").on_press(Message::ChangePassphrase).into()
+            }
+            else if let Some(job) = &self.encryption_job {
+                row![
+                    Text::new(job.status()),
+                    ProgressBar::new(0.0..=1.0, job.progress()).length(Length::Fixed(200.0))
+                ].spacing(5).align_y(Center).into()
+            }
+            else {
+                button("Encrypt Library").on_press(Message::EncryptLibrary).into()
+            };
+            section = section.push(action);
+            if let Some(message) = &self.encryption_message {
+                section = section.push(Text::new(message.as_str()));
+            }
+            return section.into()
+        }
+
+        fn check_new_passphrase(&self) -> Result<(), String> {
+            if self.new_passphrase.chars().count() < MIN_PASSPHRASE_LENGTH {
+                return Err(format!("The passphrase needs at least {} characters.", MIN_PASSPHRASE_LENGTH))
+            }
+            if self.new_passphrase != self.confirm_passphrase {
+                return Err(String::from("The passphrases do not match."))
+            }
+            return Ok(())
+        }
+
+        /// Whether the current PIN was entered correctly, if one is set.
+        fn check_current_pin(&self) -> Result<(), String> {
+            return match &self.lock_pin {
+                Some(pin_hash) if !verify_pin(&self.current_pin, pin_hash) => Err(String::from("The current PIN is wrong.")),
+                _ => Ok(())
+            }
+        }
+
+        fn clear_pins(&mut self) {
+            self.current_pin.clear();
+            self.new_pin.clear();
+            self.confirm_pin.clear();
+        }
+
+        fn clear_passphrases(&mut self) {
+            self.current_passphrase.clear();
+            self.new_passphrase.clear();
+            self.confirm_passphrase.clear();
+        }
+
         fn available_themes() -> Vec<Theme> {
             let mut available_themes: Vec<Theme> = Vec::new();
 
@@ -66,6 +328,36 @@ pub(crate) mod settings {
             self.current_theme = Some(theme);
         }
 
+        pub(crate) fn set_trash_retention_days(&mut self, days: u32) {
+            self.trash_retention_days = days.to_string();
+        }
+
+        pub(crate) fn set_versions_kept(&mut self, versions_kept: u32) {
+            self.versions_kept = versions_kept.to_string();
+        }
+
+        pub(crate) fn set_encrypted(&mut self, encrypted: bool) {
+            self.encrypted = encrypted;
+        }
+
+        pub(crate) fn is_encrypted(&self) -> bool {
+            return self.encrypted
+        }
+
+        /// Whether page files are being encrypted, during which nothing else may change the library.
+        pub(crate) fn is_encrypting(&self) -> bool {
+            return self.encryption_job.is_some()
+        }
+
+        pub(crate) fn set_encryption_message(&mut self, message: String) {
+            self.encryption_message = Some(message);
+        }
+
+        pub(crate) fn set_lock(&mut self, idle_lock_minutes: u32, lock_pin: Option<String>) {
+            self.idle_lock_minutes = idle_lock_minutes.to_string();
+            self.lock_pin = lock_pin;
+        }
+
         
     }
 
@@ -73,6 +365,22 @@ pub(crate) mod settings {
     pub(crate) enum Message {
         ChangeTheme(Theme),
         ShowConsole(bool),
+        TrashRetentionChange(String),
+        VersionsKeptChange(String),
+        CurrentPassphraseChange(String),
+        NewPassphraseChange(String),
+        ConfirmPassphraseChange(String),
+        EncryptLibrary,
+        EncryptionJob(JobEvent<Result<Result<usize, String>, String>>),
+        ChangePassphrase,
+        IdleLockChange(String),
+        CurrentPinChange(String),
+        NewPinChange(String),
+        ConfirmPinChange(String),
+        SetLockPin,
+        RemoveLockPin,
+        /// The new PIN hash for the config, or `None` once the PIN was removed.
+        LockPinChanged(Option<String>),
         Back
     }
 
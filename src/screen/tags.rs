@@ -0,0 +1,246 @@
+pub(crate) mod tags {
+    use iced::{Alignment::Center, Border, Color, Element, Length, Task, Theme, widget::{Container, PickList, Space, Text, button, column, container, row, rule, scrollable, text_input}};
+
+    use crate::{db::db_module::DbConnection, tag::tag::{TAG_COLORS, Tag, parse_hex_color}};
+
+    #[derive(Debug, Clone, Default)]
+    pub(crate) struct Tags {
+        tags: Vec<Tag>,
+        selected_tag: Option<Tag>,
+        rename_text: String,
+        merge_target: Option<Tag>,
+        show_confirm_delete: bool,
+        error_message: Option<String>
+    }
+
+    impl Tags {
+        pub(crate) fn new() -> Tags {
+            let mut tags = Tags::default();
+            tags.reload();
+            tags
+        }
+
+        pub(crate) fn update(&mut self, message: Message) -> Task<Message> {
+            let mut conn = DbConnection::new();
+            match message {
+                Message::Select(tag) => {
+                    self.rename_text = tag.get_name().to_string();
+                    self.selected_tag = Some(tag);
+                    self.merge_target = None;
+                    self.show_confirm_delete = false;
+                    self.error_message = None;
+                },
+                Message::RenameChange(input) => {
+                    self.rename_text = input;
+                },
+                Message::Rename => {
+                    let tag_id = self.selected_tag.as_ref().unwrap().get_tag_id();
+                    if self.rename_text.trim().is_empty() {
+                        self.error_message = Some(String::from("A tag needs a name."));
+                        return Task::none()
+                    }
+                    if let Err(err) = conn.rename_tag(tag_id, self.rename_text.clone()) {
+                        println!("Error renaming tag: {}", err);
+                        self.error_message = Some(format!("A tag named \"{}\" already exists. Merge the tags instead.", self.rename_text.trim()));
+                        return Task::none()
+                    }
+                    self.error_message = None;
+                },
+                Message::ChangeColor(color) => {
+                    let tag_id = self.selected_tag.as_ref().unwrap().get_tag_id();
+                    conn.edit_tag_color(tag_id, color).unwrap_or_else(|err| {
+                        println!("Error changing tag color: {}", err);
+                        0
+                    });
+                },
+                Message::MergeTargetChange(tag) => {
+                    self.merge_target = Some(tag);
+                },
+                Message::Merge => {
+                    let source = self.selected_tag.take().unwrap();
+                    let target = self.merge_target.take().unwrap();
+                    conn.merge_tags(source.get_tag_id(), target.get_tag_id()).unwrap_or_else(|err| {
+                        println!("Error merging tags: {}", err);
+                    });
+                },
+                Message::ShowConfirmDelete => {
+                    self.show_confirm_delete = true;
+                },
+                Message::Delete => {
+                    let tag = self.selected_tag.take().unwrap();
+                    conn.delete_tag(tag.get_tag_id()).unwrap_or_else(|err| {
+                        println!("Error deleting tag: {}", err);
+                    });
+                },
+                Message::CloseTag => {
+                    self.selected_tag = None;
+                    self.error_message = None;
+                },
+                Message::Back => {}
+            }
+            self.reload();
+            Task::none()
+        }
+
+        pub(crate) fn view(&self) -> Element<'_, Message> {
+            let mut tag_rows = column![].spacing(5);
+            if self.tags.is_empty() {
+                tag_rows = tag_rows.push(Text::new("No tags yet. Tags are created by adding them to a document or attachment."));
+            }
+            for tag in &self.tags {
+                tag_rows = tag_rows.push(
+                    Container::new(row![
+                        tag_chip(tag, false, None),
+                        Space::new().width(Length::Fill),
+                        button("Edit").on_press(Message::Select(tag.clone()))
+                    ].spacing(5).align_y(Center)).padding(5).style(container::bordered_box).width(Length::Fill)
+                );
+            }
+
+            Container::new(column![
+                Container::new(row![
+                    button("<").on_press(Message::Back)
+                ].spacing(5)).padding(5).style(container::bordered_box).width(Length::Fill),
+                Container::new(column![
+                    row![
+                        Text::new("Tags").size(20)
+                    ].spacing(5).align_y(Center),
+                    rule::horizontal(2),
+                    match &self.selected_tag {
+                        Some(tag) => self.edit_panel(tag),
+                        None => column![].into()
+                    },
+                    scrollable(tag_rows).height(Length::Fill)
+                ].spacing(5)).padding(5).style(container::bordered_box).width(Length::Fill).height(Length::Fill),
+            ].spacing(5)).into()
+        }
+
+        /// Whether the message changed tags that the document list shows.
+        pub(crate) fn changes_library(message: &Message) -> bool {
+            return matches!(message, Message::Rename | Message::ChangeColor(_) | Message::Merge | Message::Delete)
+        }
+
+        pub(crate) fn reload(&mut self) {
+            self.tags = DbConnection::new().read_tags().unwrap_or_else(|err| {
+                println!("Error reading tags: {}", err);
+                Vec::new()
+            });
+            // Keep the selection pointing at the stored tag, so its new name and color show.
+            self.selected_tag = self.selected_tag.as_ref().and_then(|selected| self.tags.iter().find(|tag| tag.get_tag_id() == selected.get_tag_id()).cloned());
+        }
+
+        fn edit_panel(&self, tag: &Tag) -> Element<'_, Message> {
+            let mut swatches = row![].spacing(5);
+            for color in TAG_COLORS {
+                let selected = color == tag.get_color().as_str();
+                swatches = swatches.push(
+                    button(Space::new().width(Length::Fixed(16.0)).height(Length::Fixed(16.0)))
+                        .on_press(Message::ChangeColor(color.to_string()))
+                        .style(move |_: &Theme, _| swatch_style(color, selected))
+                );
+            }
+            let merge_targets: Vec<Tag> = self.tags.iter().filter(|other| other.get_tag_id() != tag.get_tag_id()).cloned().collect();
+
+            Container::new(column![
+                row![
+                    tag_chip(tag, false, None),
+                    Space::new().width(Length::Fill),
+                    match &self.error_message {
+                        Some(error) => Text::new(error.clone()).style(iced::widget::text::danger),
+                        None => Text::new("")
+                    },
+                    button("Close").on_press(Message::CloseTag)
+                ].spacing(5).align_y(Center),
+                row![
+                    Text::new("Name").width(Length::FillPortion(1)),
+                    text_input("", &self.rename_text).on_input(Message::RenameChange).on_submit(Message::Rename).width(Length::FillPortion(3)),
+                    button("Rename").on_press(Message::Rename)
+                ].spacing(5).align_y(Center),
+                row![
+                    Text::new("Color").width(Length::FillPortion(1)),
+                    Container::new(swatches).width(Length::FillPortion(3)),
+                    Space::new().width(Length::Shrink)
+                ].spacing(5).align_y(Center),
+                row![
+                    Text::new("Merge into").width(Length::FillPortion(1)),
+                    PickList::new(merge_targets, self.merge_target.clone(), Message::MergeTargetChange).width(Length::FillPortion(3)),
+                    if self.merge_target.is_some() {
+                        button("Merge").on_press(Message::Merge)
+                    }
+                    else {
+                        button("Merge")
+                    }
+                ].spacing(5).align_y(Center),
+                row![
+                    Space::new().width(Length::Fill),
+                    if self.show_confirm_delete {
+                        row![
+                            Text::from("Remove the tag from everything and delete it: "),
+                            button("Confirm").on_press(Message::Delete),
+                            button("Cancel").on_press(Message::Select(tag.clone()))
+                        ].spacing(5).align_y(Center)
+                    }
+                    else {
+                        row![button("Delete").on_press(Message::ShowConfirmDelete)]
+                    }
+                ].spacing(5).align_y(Center)
+            ].spacing(5)).padding(5).style(container::bordered_box).width(Length::Fill).into()
+        }
+    }
+
+    /// A small colored label showing the tag's name. Dimmed chips stand for tags that are not selected;
+    /// with `on_remove` the chip gets a button that sends it.
+    pub(crate) fn tag_chip<'a, M: Clone + 'a>(tag: &Tag, dimmed: bool, on_remove: Option<M>) -> Container<'a, M> {
+        let color = if dimmed { tag.color().scale_alpha(0.35) } else { tag.color() };
+        let text_color = chip_text_color(tag.color());
+        let mut content = row![Text::new(tag.get_name().to_string()).size(12).color(text_color)].spacing(4).align_y(Center);
+        if let Some(message) = on_remove {
+            content = content.push(
+                button(Text::new("x").size(12).color(text_color)).padding(0).style(button::text).on_press(message)
+            );
+        }
+        Container::new(content)
+            .padding([2, 6])
+            .style(move |_: &Theme| container::Style {
+                background: Some(color.into()),
+                border: Border {
+                    color: Color::TRANSPARENT,
+                    width: 0.0,
+                    radius: 8.0.into()
+                },
+                ..container::Style::default()
+            })
+    }
+
+    /// Black or white, whichever reads better on the chip color.
+    fn chip_text_color(background: Color) -> Color {
+        let luminance = 0.299 * background.r + 0.587 * background.g + 0.114 * background.b;
+        if luminance > 0.6 { Color::BLACK } else { Color::WHITE }
+    }
+
+    fn swatch_style(color: &str, selected: bool) -> button::Style {
+        button::Style {
+            background: parse_hex_color(color).map(|color| color.into()),
+            border: Border {
+                color: if selected { Color::WHITE } else { Color::TRANSPARENT },
+                width: 2.0,
+                radius: 4.0.into()
+            },
+            ..button::Style::default()
+        }
+    }
+
+    #[derive(Debug, Clone)]
+    pub(crate) enum Message {
+        Select(Tag),
+        RenameChange(String),
+        Rename,
+        ChangeColor(String),
+        MergeTargetChange(Tag),
+        Merge,
+        ShowConfirmDelete,
+        Delete,
+        CloseTag,
+        Back
+    }
+}
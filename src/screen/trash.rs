@@ -0,0 +1,188 @@
+pub(crate) mod trash {
+    use iced::{Alignment::Center, Element, Length, Task, widget::{Container, Space, Text, button, column, container, row, rule, scrollable}};
+    use time::{OffsetDateTime, UtcDateTime, macros::format_description};
+
+    use crate::{db::db_module::{DbConnection, TrashEntry, TrashKind, is_number_taken}, storage::storage::remove_unreferenced_objects};
+
+    #[derive(Debug, Clone, Default)]
+    pub(crate) struct Trash {
+        entries: Vec<TrashEntry>,
+        retention_days: u32,
+        confirm_empty: bool,
+        error_message: Option<String>
+    }
+
+    impl Trash {
+        pub(crate) fn new(retention_days: u32) -> Trash {
+            let mut trash = Trash {
+                entries: Vec::new(),
+                retention_days: retention_days,
+                confirm_empty: false,
+                error_message: None
+            };
+            trash.reload();
+            trash
+        }
+
+        pub(crate) fn update(&mut self, message: Message) -> Task<Message> {
+            let mut conn = DbConnection::new();
+            self.error_message = None;
+            match message {
+                Message::Restore(entry) => {
+                    let result = match entry.kind {
+                        TrashKind::Document => conn.restore_document(entry.id),
+                        TrashKind::Attachment => conn.restore_attachment(entry.id)
+                    };
+                    if let Err(err) = result {
+                        println!("Error restoring from trash: {}", err);
+                        self.error_message = Some(match is_number_taken(&err) {
+                            true => format!("{} cannot be restored: its number is now used by another item. Change that number first.", entry_name(&entry)),
+                            false => format!("Error restoring {}: {}", entry_name(&entry), err)
+                        });
+                    }
+                },
+                Message::Purge(entry) => {
+                    let result = match entry.kind {
+                        TrashKind::Document => conn.purge_document(entry.id),
+                        TrashKind::Attachment => conn.purge_attachment(entry.id)
+                    };
+                    result.unwrap_or_else(|err| {
+                        println!("Error purging from trash: {}", err);
+                    });
+                    remove_unused_files(&conn);
+                },
+                Message::ShowConfirmEmpty => {
+                    self.confirm_empty = true;
+                },
+                Message::CancelEmpty => {
+                    self.confirm_empty = false;
+                },
+                Message::EmptyTrash => {
+                    for entry in &self.entries {
+                        let result = match entry.kind {
+                            TrashKind::Document => conn.purge_document(entry.id),
+                            TrashKind::Attachment => conn.purge_attachment(entry.id)
+                        };
+                        result.unwrap_or_else(|err| {
+                            println!("Error purging from trash: {}", err);
+                        });
+                    }
+                    remove_unused_files(&conn);
+                    self.confirm_empty = false;
+                },
+                Message::Back => {}
+            }
+            self.reload();
+            Task::none()
+        }
+
+        pub(crate) fn view(&self) -> Element<'_, Message> {
+            let mut entry_rows = column![].spacing(5);
+            if self.entries.is_empty() {
+                entry_rows = entry_rows.push(Text::new("The trash is empty."));
+            }
+            for entry in &self.entries {
+                entry_rows = entry_rows.push(entry_row(entry));
+            }
+
+            Container::new(column![
+                Container::new(row![
+                    button("<").on_press(Message::Back),
+                    Space::new().width(Length::Fill),
+                    if self.confirm_empty {
+                        row![
+                            Text::from("Permanently delete everything in the trash: "),
+                            button("Confirm").on_press(Message::EmptyTrash),
+                            button("Cancel").on_press(Message::CancelEmpty)
+                        ].spacing(5).align_y(Center)
+                    }
+                    else if self.entries.is_empty() {
+                        row![button("Empty Trash")]
+                    }
+                    else {
+                        row![button("Empty Trash").on_press(Message::ShowConfirmEmpty)]
+                    }
+                ].spacing(5).align_y(Center)).padding(5).style(container::bordered_box).width(Length::Fill),
+                Container::new(column![
+                    row![
+                        Text::new("Trash").size(20),
+                        Space::new().width(Length::Fill),
+                        Text::new(match self.retention_days {
+                            0 => String::from("Items stay until they are deleted permanently."),
+                            days => format!("Items are deleted permanently after {} days.", days)
+                        })
+                    ].spacing(5).align_y(Center),
+                    rule::horizontal(2),
+                    self.error_message.as_ref().map(|err| Text::new(err.as_str())),
+                    scrollable(entry_rows).height(Length::Fill)
+                ].spacing(5)).padding(5).style(container::bordered_box).width(Length::Fill).height(Length::Fill),
+            ].spacing(5)).into()
+        }
+
+        /// Whether the message restored rows that the document list has to show again.
+        pub(crate) fn changes_library(message: &Message) -> bool {
+            return matches!(message, Message::Restore(_))
+        }
+
+        pub(crate) fn set_retention_days(&mut self, retention_days: u32) {
+            self.retention_days = retention_days;
+        }
+
+        pub(crate) fn reload(&mut self) {
+            self.entries = DbConnection::new().read_trash().unwrap_or_else(|err| {
+                println!("Error reading trash: {}", err);
+                Vec::new()
+            });
+        }
+    }
+
+    /// Purges trash entries older than the retention period and deletes page files nothing uses any more.
+    pub(crate) fn purge_expired(retention_days: u32) {
+        let mut conn = DbConnection::new();
+        match conn.purge_expired(retention_days) {
+            Ok(_) => remove_unused_files(&conn),
+            Err(err) => println!("Error purging expired trash: {}", err)
+        }
+    }
+
+    fn remove_unused_files(conn: &DbConnection) {
+        remove_unreferenced_objects(conn).unwrap_or_else(|err| {
+            println!("Error removing unused page files: {}", err);
+            0
+        });
+    }
+
+    fn entry_row(entry: &TrashEntry) -> Element<'static, Message> {
+        let datetime_format = format_description!("[year]-[month]-[day] [hour]:[minute]:[second]");
+        let deleted_at = UtcDateTime::from_unix_timestamp(entry.deleted_at)
+            .map(|datetime| datetime.to_offset(OffsetDateTime::now_local().map(|now| now.offset()).unwrap_or(time::UtcOffset::UTC)))
+            .ok()
+            .and_then(|datetime| datetime.format(datetime_format).ok())
+            .unwrap_or_default();
+
+        Container::new(row![
+            Text::new(entry_name(entry)),
+            Space::new().width(Length::Fill),
+            Text::new(format!("Deleted {}", deleted_at)),
+            button("Restore").on_press(Message::Restore(entry.clone())),
+            button("Delete Permanently").on_press(Message::Purge(entry.clone()))
+        ].spacing(5).align_y(Center)).padding(5).style(container::bordered_box).width(Length::Fill).into()
+    }
+
+    fn entry_name(entry: &TrashEntry) -> String {
+        return match entry.kind {
+            TrashKind::Document => format!("Document {}", entry.name),
+            TrashKind::Attachment => format!("Attachment {} of document {}", entry.name, entry.document_number)
+        }
+    }
+
+    #[derive(Debug, Clone)]
+    pub(crate) enum Message {
+        Restore(TrashEntry),
+        Purge(TrashEntry),
+        ShowConfirmEmpty,
+        CancelEmpty,
+        EmptyTrash,
+        Back
+    }
+}
@@ -0,0 +1,108 @@
+pub(crate) mod unlock {
+    use iced::{Alignment::Center, Element, Length, Task, widget::{Container, Text, button, center, column, container, text_input}};
+
+    use crate::{crypto::crypto::verify_pin, db::db_module::{is_library_passphrase, unlock_library}};
+
+    /// What the lock screen stands in front of.
+    #[derive(Debug, Clone, Default)]
+    pub(crate) enum Lock {
+        /// An encrypted library that is not open yet.
+        #[default]
+        Library,
+        /// The running application, locked by hand or after being idle. It opens with the PIN hash from the
+        /// config, or the library passphrase when no PIN is set.
+        Application(Option<String>)
+    }
+
+    /// Asks for the passphrase of an encrypted library before any of it is read, or for the PIN or passphrase
+    /// to get back into a locked application.
+    #[derive(Debug, Clone, Default)]
+    pub(crate) struct Unlock {
+        lock: Lock,
+        passphrase: String,
+        error_message: Option<String>
+    }
+
+    impl Unlock {
+        pub(crate) fn new() -> Unlock {
+            Unlock {
+                lock: Lock::Library,
+                passphrase: String::new(),
+                error_message: None
+            }
+        }
+
+        pub(crate) fn application(pin_hash: Option<String>) -> Unlock {
+            Unlock {
+                lock: Lock::Application(pin_hash),
+                passphrase: String::new(),
+                error_message: None
+            }
+        }
+
+        /// Whether unlocking opens the library, rather than returning to screens that are already loaded.
+        pub(crate) fn opens_library(&self) -> bool {
+            return matches!(self.lock, Lock::Library)
+        }
+
+        pub(crate) fn update(&mut self, message: Message) -> Task<Message> {
+            match message {
+                Message::PassphraseChange(input) => {
+                    self.passphrase = input;
+                    self.error_message = None;
+                    Task::none()
+                },
+                Message::Submit => {
+                    let result = match &self.lock {
+                        Lock::Library => unlock_library(&self.passphrase),
+                        Lock::Application(Some(pin_hash)) => match verify_pin(&self.passphrase, pin_hash) {
+                            true => Ok(()),
+                            false => Err(String::from("Wrong PIN."))
+                        },
+                        Lock::Application(None) => match is_library_passphrase(&self.passphrase) {
+                            true => Ok(()),
+                            false => Err(String::from("Wrong passphrase."))
+                        }
+                    };
+                    self.passphrase.clear();
+                    match result {
+                        Ok(_) => Task::done(Message::Unlocked),
+                        Err(err) => {
+                            self.error_message = Some(err);
+                            Task::none()
+                        }
+                    }
+                },
+                Message::Unlocked => Task::none()
+            }
+        }
+
+        pub(crate) fn view(&self) -> Element<'_, Message> {
+            let (title, prompt, placeholder) = match &self.lock {
+                Lock::Library => ("Library locked", "Enter the passphrase to open the library.", "Passphrase"),
+                Lock::Application(Some(_)) => ("Locked", "Enter the PIN to continue.", "PIN"),
+                Lock::Application(None) => ("Locked", "Enter the library passphrase to continue.", "Passphrase")
+            };
+            let mut content = column![
+                Text::new(title).size(20),
+                Text::new(prompt),
+                text_input(placeholder, &self.passphrase).secure(true).on_input(Message::PassphraseChange).on_submit(Message::Submit),
+                button("Unlock").on_press(Message::Submit)
+            ].spacing(10).align_x(Center);
+            if let Some(err) = &self.error_message {
+                content = content.push(Text::new(err.as_str()));
+            }
+
+            center(Container::new(content).padding(20).width(Length::Fixed(400.0)).style(container::bordered_box)).into()
+        }
+    }
+
+    #[derive(Debug, Clone)]
+    pub(crate) enum Message {
+        PassphraseChange(String),
+        Submit,
+        /// Sent once the passphrase or PIN was accepted; the application then opens the library or shows its
+        /// screens again.
+        Unlocked
+    }
+}
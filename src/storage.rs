@@ -0,0 +1,376 @@
+pub(crate) mod storage {
+    use std::{collections::HashSet, fs::{self, File}, io::{self, Write}, path::{Path, PathBuf}, time::{Duration, SystemTime, UNIX_EPOCH}};
+
+    use crate::{crypto::crypto, db::db_module::{DbConnection, session_id}, library_check::library_check::checksum};
+
+    /// The library folder that holds the object store and the staging folder.
+    const DATA_DIR: &str = "./data";
+    /// Page files are stored once per distinct content, named after their checksum from `object_name`.
+    pub(crate) const OBJECTS_DIR: &str = "./data/objects";
+    /// New page files are written here first and only moved into `OBJECTS_DIR` once the database commit succeeded.
+    pub(crate) const STAGING_DIR: &str = "./data/staging";
+    /// Objects written this recently are kept even when no row references them, as the save that writes the row may
+    /// still be running, in this session or another.
+    const OBJECT_GRACE_PERIOD: Duration = Duration::from_secs(60 * 60);
+
+    /// The checksum a page is stored and recorded under: its SHA-256, or in an encrypted library an HMAC keyed
+    /// with the page key, so file names do not show which pages the library holds.
+    pub(crate) fn object_name(bytes: &[u8]) -> String {
+        return crypto::keyed_checksum(bytes).unwrap_or_else(|| checksum(bytes))
+    }
+
+    pub(crate) fn object_path(checksum: &str) -> PathBuf {
+        return object_path_in(Path::new(DATA_DIR), checksum)
+    }
+
+    /// Where the object store under the library folder `root` keeps the page with this checksum.
+    fn object_path_in(root: &Path, checksum: &str) -> PathBuf {
+        return root.join("objects").join(&checksum[..2]).join(format!("{}.png", checksum))
+    }
+
+    /// The page bytes of a stored file, decrypted if the library is encrypted.
+    pub(crate) fn read_page_file(path: impl AsRef<Path>) -> Result<Vec<u8>, io::Error> {
+        return crypto::open(fs::read(path)?)
+    }
+
+    /// Writes the page bytes into the object store unless identical content is already there.
+    pub(crate) fn store_page(bytes: &[u8]) -> Result<(PathBuf, String), io::Error> {
+        let page_checksum = object_name(bytes);
+        let path = object_path(&page_checksum);
+
+        // An object that is already there is renewed, so the sweep leaves it alone until its row is written.
+        if touch(&path).is_err() {
+            fs::create_dir_all(path.parent().unwrap())?;
+            let temp_path = path.with_extension("tmp");
+            fs::write(&temp_path, crypto::seal(bytes))?;
+            fs::rename(&temp_path, &path)?;
+        }
+
+        return Ok((path, page_checksum))
+    }
+
+    pub(crate) fn is_object(path: &Path) -> bool {
+        return path.starts_with(OBJECTS_DIR)
+    }
+
+    pub(crate) fn read_objects() -> Vec<PathBuf> {
+        let mut objects: Vec<PathBuf> = Vec::new();
+        let prefixes = match fs::read_dir(OBJECTS_DIR) {
+            Ok(prefixes) => prefixes,
+            Err(_) => return objects
+        };
+
+        for prefix in prefixes.filter_map(|entry| entry.ok()) {
+            if let Ok(files) = fs::read_dir(prefix.path()) {
+                for file in files.filter_map(|entry| entry.ok()) {
+                    objects.push(file.path());
+                }
+            }
+        }
+
+        return objects
+    }
+
+    /// Deletes stored page files that no `page` row points to any more, unless they were written within
+    /// `OBJECT_GRACE_PERIOD`. Returns how many were removed.
+    pub(crate) fn remove_unreferenced_objects(conn: &DbConnection) -> Result<usize, rusqlite::Error> {
+        let mut referenced: HashSet<PathBuf> = conn.read_page_records()?.into_iter().map(|page| PathBuf::from(page.file_path)).collect();
+        referenced.extend(conn.read_version_file_paths()?.into_iter().map(PathBuf::from));
+        let mut removed = 0;
+
+        for object in read_objects() {
+            if !referenced.contains(&object) && !is_recent(&object) {
+                match fs::remove_file(&object) {
+                    Ok(_) => removed += 1,
+                    Err(err) => println!("Error removing unreferenced page file: {}", err)
+                }
+            }
+        }
+
+        return Ok(removed)
+    }
+
+    /// Moves pages saved under the old `./data/{document_number}/{reference_number}` layout into the object store.
+    pub(crate) fn migrate_legacy_pages(conn: &mut DbConnection) -> Result<(), rusqlite::Error> {
+        let mut legacy_dirs: HashSet<PathBuf> = HashSet::new();
+
+        for page in conn.read_page_records()? {
+            let legacy_path = PathBuf::from(&page.file_path);
+            if is_object(&legacy_path) {
+                continue;
+            }
+
+            match fs::read(&legacy_path) {
+                Ok(bytes) => {
+                    match store_page(&bytes) {
+                        Ok((path, page_checksum)) => {
+                            conn.edit_page_file(page.page_id, path, page_checksum)?;
+                            fs::remove_file(&legacy_path).unwrap_or_else(|err| {
+                                println!("Error removing migrated page file: {}", err);
+                            });
+                            if let Some(parent) = legacy_path.parent() {
+                                legacy_dirs.insert(parent.to_path_buf());
+                            }
+                        },
+                        Err(err) => println!("Error storing page file {}: {}", page.file_path, err)
+                    }
+                },
+                // Left in place so the integrity check can report it as missing.
+                Err(err) => println!("Error reading page file {}: {}", page.file_path, err)
+            }
+        }
+
+        for dir in legacy_dirs {
+            // Only empty folders are removed; anything left behind shows up as an orphan in the integrity check.
+            if fs::remove_dir(&dir).is_ok() && let Some(document_dir) = dir.parent() {
+                let _ = fs::remove_dir(document_dir);
+            }
+        }
+
+        return Ok(())
+    }
+
+    /// Page files written to a private staging folder, waiting for the database transaction that references them.
+    #[derive(Debug)]
+    pub(crate) struct StagedPages {
+        dir: PathBuf,
+        pages: Vec<(PathBuf, PathBuf, String)>
+    }
+
+    impl StagedPages {
+        pub(crate) fn stage(pages: Vec<Vec<u8>>) -> Result<StagedPages, io::Error> {
+            return StagedPages::stage_in(Path::new(DATA_DIR), pages)
+        }
+
+        /// Stages the pages for the object store under the library folder `root`.
+        fn stage_in(root: &Path, pages: Vec<Vec<u8>>) -> Result<StagedPages, io::Error> {
+            // Under a folder of the session, so other sessions can tell whether the save may still be running.
+            let time = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+            let dir = root.join("staging").join(session_id()).join(time.to_string());
+            fs::create_dir_all(&dir)?;
+            let mut staged = StagedPages {
+                dir: dir,
+                pages: Vec::new()
+            };
+
+            for bytes in pages {
+                let page_checksum = object_name(&bytes);
+                let staged_path = staged.dir.join(format!("{}.png", page_checksum));
+                // Identical pages within one save share a staged file.
+                if staged_path.exists() {
+                    staged.pages.push((staged_path, object_path_in(root, &page_checksum), page_checksum));
+                    continue;
+                }
+                if let Err(err) = write_synced(&staged_path, &crypto::seal(&bytes)) {
+                    staged.rollback();
+                    return Err(err)
+                }
+                staged.pages.push((staged_path, object_path_in(root, &page_checksum), page_checksum));
+            }
+
+            return Ok(staged)
+        }
+
+        /// The object paths and checksums to record in the `page` table.
+        pub(crate) fn pages(&self) -> Vec<(PathBuf, String)> {
+            return self.pages.iter().map(|(_, path, page_checksum)| (path.clone(), page_checksum.clone())).collect()
+        }
+
+        /// Moves the staged files into the object store. Call only after the database commit succeeded;
+        /// if this is interrupted, `recover_staged_pages` finishes the job on the next start.
+        pub(crate) fn commit(self) -> Result<(), io::Error> {
+            for (staged_path, path, _) in &self.pages {
+                move_into_store(staged_path, path)?;
+            }
+            return fs::remove_dir_all(&self.dir)
+        }
+
+        pub(crate) fn rollback(self) {
+            fs::remove_dir_all(&self.dir).unwrap_or_else(|err| {
+                println!("Error removing staged page files: {}", err);
+            });
+        }
+    }
+
+    /// Finishes or discards saves that were interrupted. Only the staging folders of sessions that stopped running
+    /// are touched, as the others may be in the middle of a save. Staged files whose checksum the database already
+    /// references belong to a committed save and are moved into place; everything else is discarded.
+    pub(crate) fn recover_staged_pages(conn: &DbConnection) -> Result<(), rusqlite::Error> {
+        let staging_dirs = match fs::read_dir(STAGING_DIR) {
+            Ok(dirs) => dirs,
+            Err(_) => return Ok(())
+        };
+        let live_sessions: HashSet<String> = conn.read_live_sessions()?.into_iter().map(|(session, _)| session).collect();
+        let referenced: HashSet<String> = conn.read_page_records()?.into_iter().filter_map(|page| page.checksum).collect();
+
+        for dir in staging_dirs.filter_map(|entry| entry.ok()).map(|entry| entry.path()) {
+            let session = dir.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+            if live_sessions.contains(&session) {
+                continue;
+            }
+            recover_staged_files(&dir, &referenced);
+            fs::remove_dir_all(&dir).unwrap_or_else(|err| {
+                println!("Error removing interrupted save: {}", err);
+            });
+        }
+
+        return Ok(())
+    }
+
+    /// Moves the staged files under `dir` that the database references into the object store.
+    fn recover_staged_files(dir: &Path, referenced: &HashSet<String>) {
+        let files = match fs::read_dir(dir) {
+            Ok(files) => files,
+            Err(_) => return
+        };
+        for file in files.filter_map(|entry| entry.ok()).map(|entry| entry.path()) {
+            if file.is_dir() {
+                recover_staged_files(&file, referenced);
+                continue;
+            }
+            let page_checksum = file.file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_default();
+            if referenced.contains(&page_checksum) && object_name(&read_page_file(&file).unwrap_or_default()) == page_checksum {
+                move_into_store(&file, &object_path(&page_checksum)).unwrap_or_else(|err| {
+                    println!("Error recovering staged page file: {}", err);
+                });
+            }
+        }
+    }
+
+    /// Encrypts the page files still stored in plain form once the library was encrypted, including those left
+    /// over when an earlier run was interrupted. Each is written again under its keyed name and the pages and
+    /// versions using it are pointed at the new file; plain files an interruption left behind are removed by
+    /// `remove_unreferenced_objects`. Returns how many were encrypted.
+    pub(crate) fn encrypt_page_files(conn: &mut DbConnection, mut report: impl FnMut(usize, usize)) -> Result<usize, String> {
+        let mut paths: Vec<String> = conn.read_page_records().map_err(|err| format!("Error reading pages: {}", err))?.into_iter().map(|page| page.file_path).collect();
+        paths.extend(conn.read_version_file_paths().map_err(|err| format!("Error reading versions: {}", err))?);
+        paths.retain(|path| is_object(Path::new(path)));
+        paths.sort();
+        paths.dedup();
+
+        let total = paths.len();
+        let mut encrypted = 0;
+        for (done, path) in paths.iter().enumerate() {
+            report(done, total);
+            let old_path = PathBuf::from(path);
+            let data = match fs::read(&old_path) {
+                Ok(data) => data,
+                // Missing files are reported by the library check.
+                Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+                Err(err) => return Err(format!("Error reading page file: {}", err))
+            };
+            if crypto::is_sealed(&data) {
+                continue;
+            }
+            let page_checksum = object_name(&data);
+            let new_path = object_path(&page_checksum);
+            if !new_path.exists() {
+                fs::create_dir_all(new_path.parent().unwrap()).map_err(|err| format!("Error creating page folder: {}", err))?;
+                let temp_path = new_path.with_extension("tmp");
+                write_synced(&temp_path, &crypto::seal(&data)).map_err(|err| format!("Error writing page file: {}", err))?;
+                fs::rename(&temp_path, &new_path).map_err(|err| format!("Error writing page file: {}", err))?;
+            }
+            conn.rename_page_object(path, &new_path, &page_checksum).map_err(|err| format!("Error updating pages: {}", err))?;
+            if new_path != old_path {
+                fs::remove_file(&old_path).map_err(|err| format!("Error removing plain page file: {}", err))?;
+            }
+            encrypted += 1;
+        }
+        report(total, total);
+        return Ok(encrypted)
+    }
+
+    fn move_into_store(staged_path: &Path, path: &Path) -> Result<(), io::Error> {
+        if touch(path).is_ok() {
+            if staged_path.exists() {
+                return fs::remove_file(staged_path)
+            }
+            return Ok(())
+        }
+        fs::create_dir_all(path.parent().unwrap())?;
+        return fs::rename(staged_path, path)
+    }
+
+    /// Marks an existing file as just written. Fails if there is no such file.
+    fn touch(path: &Path) -> Result<(), io::Error> {
+        return File::options().write(true).open(path)?.set_modified(SystemTime::now())
+    }
+
+    /// Whether the file was written within `OBJECT_GRACE_PERIOD`, or its age cannot be told.
+    fn is_recent(path: &Path) -> bool {
+        return fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .is_none_or(|age| age < OBJECT_GRACE_PERIOD)
+    }
+
+    fn write_synced(path: &Path, bytes: &[u8]) -> Result<(), io::Error> {
+        let mut file = File::create(path)?;
+        file.write_all(bytes)?;
+        return file.sync_all()
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use std::process;
+
+        use super::*;
+
+        /// A library folder of its own under the system temp folder, so tests never touch `./data`.
+        fn temp_root(name: &str) -> PathBuf {
+            let time = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+            return std::env::temp_dir().join(format!("doc_manager_{}_{}_{}", name, process::id(), time))
+        }
+
+        #[test]
+        fn test_staged_pages_commit() {
+            let root = temp_root("commit");
+            let first = b"first page".to_vec();
+            let second = b"second page".to_vec();
+            let staged = StagedPages::stage_in(&root, vec![first.clone(), second.clone(), first.clone()]).unwrap();
+            let pages = staged.pages();
+            assert_eq!(pages.len(), 3);
+            assert_eq!(pages[0], pages[2]);
+            assert_eq!(pages[0].1, checksum(&first));
+            // Nothing is in the object store until the database commit went through.
+            assert!(pages.iter().all(|(path, _)| !path.exists()));
+            let dir = staged.dir.clone();
+            assert!(dir.exists());
+
+            staged.commit().unwrap();
+            assert!(!dir.exists());
+            assert_eq!(read_page_file(&pages[0].0).unwrap(), first);
+            assert_eq!(read_page_file(&pages[1].0).unwrap(), second);
+            assert!(pages[0].0.starts_with(root.join("objects")));
+            fs::remove_dir_all(&root).unwrap();
+        }
+
+        #[test]
+        fn test_touch_renews_objects() {
+            let root = temp_root("touch");
+            fs::create_dir_all(&root).unwrap();
+            let path = root.join("object.png");
+            assert!(touch(&path).is_err());
+            fs::write(&path, b"page").unwrap();
+            File::options().write(true).open(&path).unwrap().set_modified(SystemTime::now() - OBJECT_GRACE_PERIOD * 2).unwrap();
+            assert!(!is_recent(&path));
+
+            touch(&path).unwrap();
+            assert!(is_recent(&path));
+            assert_eq!(fs::read(&path).unwrap(), b"page");
+            fs::remove_dir_all(&root).unwrap();
+        }
+
+        #[test]
+        fn test_staged_pages_rollback() {
+            let root = temp_root("rollback");
+            let staged = StagedPages::stage_in(&root, vec![b"rolled back page".to_vec()]).unwrap();
+            let pages = staged.pages();
+            let dir = staged.dir.clone();
+
+            staged.rollback();
+            assert!(!dir.exists());
+            assert!(!pages[0].0.exists());
+            fs::remove_dir_all(&root).unwrap();
+        }
+    }
+}
@@ -0,0 +1,57 @@
+pub(crate) mod tag {
+    use std::{fmt, sync::Arc};
+
+    use iced::Color;
+
+    /// Colors offered in the tag manager, as stored in the `tag.color` column.
+    pub(crate) const TAG_COLORS: [&str; 8] = ["#808080", "#e5484d", "#f76b15", "#ffc53d", "#46a758", "#12a594", "#0090ff", "#8e4ec6"];
+
+    #[derive(Debug, Clone, PartialEq)]
+    pub(crate) struct Tag {
+        tag_id: u32,
+        name: Arc<String>,
+        color: Arc<String>
+    }
+
+    impl Tag {
+        pub(crate) fn new(tag_id: u32, name: Arc<String>, color: Arc<String>) -> Tag {
+            Tag {
+                tag_id: tag_id,
+                name: name,
+                color: color
+            }
+        }
+
+        pub(crate) fn get_tag_id(&self) -> u32 {
+            return self.tag_id
+        }
+
+        pub(crate) fn get_name(&self) -> Arc<String> {
+            return self.name.clone()
+        }
+
+        pub(crate) fn get_color(&self) -> Arc<String> {
+            return self.color.clone()
+        }
+
+        pub(crate) fn color(&self) -> Color {
+            return parse_hex_color(&self.color).unwrap_or(Color::from_rgb8(0x80, 0x80, 0x80))
+        }
+    }
+
+    impl fmt::Display for Tag {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "{}", self.name)
+        }
+    }
+
+    /// Parses a `#rrggbb` color.
+    pub(crate) fn parse_hex_color(hex: &str) -> Option<Color> {
+        let hex = hex.strip_prefix('#')?;
+        if hex.len() != 6 {
+            return None
+        }
+        let channel = |index: usize| u8::from_str_radix(hex.get(index..index + 2)?, 16).ok();
+        return Some(Color::from_rgb8(channel(0)?, channel(2)?, channel(4)?))
+    }
+}